@@ -0,0 +1,451 @@
+//! Exports of a period's bookkeeping for external accounting software.
+//!
+//! The journal entries posted in the period become bookings (debit account, credit account,
+//! amount) in the base currency, exactly as the ledger holds them, reversals included. They
+//! are written as a DATEV booking batch (`EXTF` Buchungsstapel), a generic CSV journal or,
+//! for the bookings that move money alone, a QIF bank register.
+//!
+//! Account numbers come from the JSON in `settings.accounting_export` (`AccountMapping`);
+//! whatever is not set there falls back to the code of the ledger account. VAT is exported
+//! as booked, on the VAT accounts.
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, Local, NaiveDate};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::db_api::{open_state_db, DbApiError};
+use crate::money::currency_digits;
+use crate::reports::{ReportError, ReportScope};
+use crate::settings::read_setting;
+use crate::StateWrapper;
+
+#[derive(Debug, Error)]
+pub enum AccountingExportError {
+    #[error("[accounting_export.rs::unknown_format] Unknown export format {0:?}; use datev, csv or qif.")]
+    UnknownFormat(String),
+
+    #[error("[accounting_export.rs::missing_account] No account number for {0:?}; set it in `accounting_export`.")]
+    MissingAccount(String),
+
+    #[error("[accounting_export.rs::spans_fiscal_years] A DATEV batch must stay within one fiscal year.")]
+    SpansFiscalYears,
+
+    #[error("[accounting_export.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[accounting_export.rs::io] Could not write {0:?}: {1}")]
+    IoError(String, String),
+
+    #[error("[accounting_export.rs::reports] {0}")]
+    ReportError(#[from] ReportError),
+
+    #[error("[accounting_export.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[accounting_export.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for AccountingExportError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 🗂️ Account numbers of the external bookkeeping, stored as JSON in `settings.accounting_export`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct AccountMapping {
+    /// Account numbers by posting role: `bank`, `receivables`, `revenue`, `output_vat`,
+    /// `input_vat`, `payables`, `customer_credit` and `expenses` (expenses without a category)
+    pub accounts: HashMap<String, String>,
+    /// Account numbers by expense category name, for the ledger account of that category
+    pub categories: HashMap<String, String>,
+    /// DATEV consultant number (Beraternummer)
+    pub consultant_number: Option<String>,
+    /// DATEV client number (Mandantennummer)
+    pub client_number: Option<String>,
+    /// Length of general ledger account numbers in DATEV, 4 when not set
+    pub account_length: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Datev,
+    Csv,
+    Qif,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<ExportFormat> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "datev" => Some(ExportFormat::Datev),
+            "csv" => Some(ExportFormat::Csv),
+            "qif" => Some(ExportFormat::Qif),
+            _ => None,
+        }
+    }
+}
+
+/// One booking; `amount` is positive in minor units of the base currency
+#[derive(Debug, PartialEq, Eq)]
+pub struct Booking {
+    pub date: NaiveDate,
+    pub document: String,
+    pub text: String,
+    pub debit_account: String,
+    pub credit_account: String,
+    pub amount: i64,
+    /// Whether the booking moves money in or out of the bank account
+    pub cash: bool,
+}
+
+/// Translates ledger accounts into the account numbers of the external bookkeeping
+struct Accounts<'a> {
+    conn: &'a Connection,
+    mapping: AccountMapping,
+    cache: HashMap<i64, String>,
+}
+
+impl<'a> Accounts<'a> {
+    /// Account number of a posting role, from the mapping, else the code of the ledger account
+    fn role(&self, role: &str) -> Result<String, AccountingExportError> {
+        if let Some(code) = self.mapping.accounts.get(role).filter(|c| !c.trim().is_empty()) {
+            return Ok(code.trim().to_string());
+        }
+        self.conn
+            .query_row("SELECT code FROM accounts WHERE system_key = ?1", [role], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| AccountingExportError::MissingAccount(role.to_string()))
+    }
+
+    /// Account number of a ledger account: mapped by its posting role or by the expense
+    /// category booked to it, else its own code
+    fn code(&mut self, account_id: i64) -> Result<String, AccountingExportError> {
+        if let Some(code) = self.cache.get(&account_id) {
+            return Ok(code.clone());
+        }
+        let (code, system_key): (String, Option<String>) = self.conn.query_row(
+            "SELECT code, system_key FROM accounts WHERE id = ?1",
+            [account_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let by_role = system_key.and_then(|key| self.mapping.accounts.get(&key).cloned());
+        let by_category = {
+            let mut stmt = self.conn.prepare("SELECT name FROM expense_categories WHERE account_id = ?1 ORDER BY id")?;
+            let names = stmt.query_map([account_id], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+            names.iter().find_map(|name| {
+                self.mapping
+                    .categories
+                    .iter()
+                    .find(|(key, code)| key.eq_ignore_ascii_case(name) && !code.trim().is_empty())
+                    .map(|(_, code)| code.clone())
+            })
+        };
+        let code = by_role
+            .or(by_category)
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .unwrap_or(code);
+        self.cache.insert(account_id, code.clone());
+        Ok(code)
+    }
+}
+
+pub fn account_mapping(conn: &Connection) -> Result<AccountMapping, AccountingExportError> {
+    match read_setting(conn, "accounting_export")? {
+        Some(json) if !json.trim().is_empty() => {
+            serde_json::from_str(&json).map_err(|e| AccountingExportError::InvalidValue("accounting_export", e.to_string()))
+        }
+        _ => Ok(AccountMapping::default()),
+    }
+}
+
+/// Splits the lines of a balanced journal entry, debits positive, into bookings of one debit
+/// and one credit account each
+fn pair_lines(lines: &[(i64, i64)]) -> Vec<(i64, i64, i64)> {
+    let mut debits: Vec<(i64, i64)> = lines.iter().filter(|(_, amount)| *amount > 0).copied().collect();
+    let mut credits: Vec<(i64, i64)> = lines.iter().filter(|(_, amount)| *amount < 0).map(|&(a, amount)| (a, -amount)).collect();
+    let mut pairs = Vec::new();
+    let (mut d, mut c) = (0, 0);
+    while d < debits.len() && c < credits.len() {
+        let amount = debits[d].1.min(credits[c].1);
+        pairs.push((debits[d].0, credits[c].0, amount));
+        debits[d].1 -= amount;
+        credits[c].1 -= amount;
+        if debits[d].1 == 0 {
+            d += 1;
+        }
+        if credits[c].1 == 0 {
+            c += 1;
+        }
+    }
+    pairs
+}
+
+/// Bookings of the journal entries posted in the range, by date. Records still waiting for
+/// the ledger sync are not part of the journal yet and so not exported.
+pub fn bookings(conn: &Connection, scope: &ReportScope) -> Result<Vec<Booking>, AccountingExportError> {
+    let mut accounts = Accounts { conn, mapping: account_mapping(conn)?, cache: HashMap::new() };
+    let bank_id: Option<i64> =
+        conn.query_row("SELECT id FROM accounts WHERE system_key = 'bank'", [], |row| row.get(0)).optional()?;
+
+    let entries = {
+        let mut stmt = conn.prepare(
+            "SELECT e.id, e.date, e.description, e.currency, e.source_type, e.source_id,
+                    CASE e.source_type
+                        WHEN 'Invoice' THEN (SELECT number FROM invoices WHERE id = e.source_id)
+                        WHEN 'CreditNote' THEN (SELECT number FROM credit_notes WHERE id = e.source_id)
+                        WHEN 'Payment' THEN (SELECT COALESCE(i.number, p.reference) FROM payments p
+                                             LEFT JOIN invoices i ON i.id = p.invoice_id WHERE p.id = e.source_id)
+                    END
+             FROM journal_entries e
+             WHERE e.date BETWEEN ?1 AND ?2
+             ORDER BY e.date, e.id",
+        )?;
+        let rows = stmt
+            .query_map(scope.params(), |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    let mut bookings = Vec::new();
+    let mut stmt = conn.prepare("SELECT account_id, debit - credit FROM journal_lines WHERE entry_id = ?1 ORDER BY id")?;
+    for (entry_id, date, description, currency, source_type, source_id, number) in entries {
+        let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| AccountingExportError::InvalidValue("date", date))?;
+        let document = number.unwrap_or_else(|| {
+            let prefix = match source_type.as_str() {
+                "Invoice" => "I",
+                "CreditNote" => "C",
+                "Payment" => "P",
+                _ => "E",
+            };
+            format!("{}{}", prefix, source_id)
+        });
+        let lines = stmt.query_map([entry_id], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+        for (debit, credit, amount) in pair_lines(&lines) {
+            bookings.push(Booking {
+                date,
+                document: document.clone(),
+                text: description.clone(),
+                debit_account: accounts.code(debit)?,
+                credit_account: accounts.code(credit)?,
+                amount: scope.convert(conn, amount, &currency, date)?,
+                cash: bank_id.is_some_and(|bank| debit == bank || credit == bank),
+            });
+        }
+    }
+    Ok(bookings)
+}
+
+/// Formats minor units with `digits` decimals and the given separator, e.g. `1234,50`
+fn decimal(amount: i64, digits: u32, separator: char) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let scale = 10u64.pow(digits);
+    let value = amount.unsigned_abs();
+    if digits == 0 {
+        return format!("{}{}", sign, value);
+    }
+    format!("{}{}{}{:0width$}", sign, value / scale, separator, value % scale, width = digits as usize)
+}
+
+/// Quotes a CSV field when it contains the delimiter, quotes or line breaks
+fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// DATEV text field: quoted, without quotes inside, cut to `max` characters
+fn datev_text(value: &str, max: usize) -> String {
+    let text: String = value.chars().filter(|c| *c != '"' && !c.is_control()).take(max).collect();
+    format!("\"{}\"", text)
+}
+
+/// DATEV documents numbers allow letters, digits and `$%&*+-/` only, up to 36 characters
+fn datev_document(value: &str) -> String {
+    let document: String =
+        value.chars().filter(|c| c.is_ascii_alphanumeric() || "$%&*+-/".contains(*c)).take(36).collect();
+    format!("\"{}\"", document)
+}
+
+/// DATEV reads Windows-1252; characters it can't encode, including C1 controls, become `?`
+fn windows_1252(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\u{0}'..='\u{7f}' | '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            'ƒ' => 0x83,
+            '„' => 0x84,
+            '…' => 0x85,
+            '†' => 0x86,
+            '‡' => 0x87,
+            'ˆ' => 0x88,
+            '‰' => 0x89,
+            'Š' => 0x8a,
+            '‹' => 0x8b,
+            'Œ' => 0x8c,
+            'Ž' => 0x8e,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '˜' => 0x98,
+            '™' => 0x99,
+            'š' => 0x9a,
+            '›' => 0x9b,
+            'œ' => 0x9c,
+            'ž' => 0x9e,
+            'Ÿ' => 0x9f,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// DATEV `EXTF` booking batch (format 700, Buchungsstapel version 13)
+pub fn datev(scope: &ReportScope, mapping: &AccountMapping, bookings: &[Booking]) -> Result<Vec<u8>, AccountingExportError> {
+    if scope.from.year() != scope.to.year() {
+        return Err(AccountingExportError::SpansFiscalYears);
+    }
+    let fiscal_year_start = NaiveDate::from_ymd_opt(scope.from.year(), 1, 1).unwrap_or(scope.from);
+    let number = |value: &Option<String>, field: &'static str| -> Result<String, AccountingExportError> {
+        let value = value.as_deref().map(str::trim).unwrap_or_default();
+        if !value.chars().all(|c| c.is_ascii_digit()) {
+            return Err(AccountingExportError::InvalidValue(field, value.to_string()));
+        }
+        Ok(value.to_string())
+    };
+    let digits = currency_digits(&scope.currency);
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "\"EXTF\";700;21;\"Buchungsstapel\";13;{};;\"BM\";\"\";\"\";{};{};{};{};{};{};{};\"\";1;0;0;\"{}\"\r\n",
+        Local::now().format("%Y%m%d%H%M%S%3f"),
+        number(&mapping.consultant_number, "consultant_number")?,
+        number(&mapping.client_number, "client_number")?,
+        fiscal_year_start.format("%Y%m%d"),
+        mapping.account_length.unwrap_or(4),
+        scope.from.format("%Y%m%d"),
+        scope.to.format("%Y%m%d"),
+        datev_text(&format!("Export {} - {}", scope.from, scope.to), 30),
+        scope.currency
+    ));
+    out.push_str(
+        "Umsatz (ohne Soll/Haben-Kz);Soll/Haben-Kennzeichen;WKZ Umsatz;Kurs;Basis-Umsatz;WKZ Basis-Umsatz;Konto;\
+         Gegenkonto (ohne BU-Schlüssel);BU-Schlüssel;Belegdatum;Belegfeld 1;Belegfeld 2;Skonto;Buchungstext\r\n",
+    );
+    for booking in bookings {
+        out.push_str(&format!(
+            "{};\"S\";\"{}\";;;;{};{};;{};{};;;{}\r\n",
+            decimal(booking.amount, digits, ','),
+            scope.currency,
+            booking.debit_account,
+            booking.credit_account,
+            booking.date.format("%d%m"),
+            datev_document(&booking.document),
+            datev_text(&booking.text, 60)
+        ));
+    }
+    Ok(windows_1252(&out))
+}
+
+/// Generic CSV journal, one booking per row
+pub fn csv_journal(scope: &ReportScope, bookings: &[Booking]) -> Vec<u8> {
+    let digits = currency_digits(&scope.currency);
+    let mut out = String::from("date,document,text,debit_account,credit_account,amount,currency\n");
+    for booking in bookings {
+        let fields = [
+            booking.date.to_string(),
+            booking.document.clone(),
+            booking.text.clone(),
+            booking.debit_account.clone(),
+            booking.credit_account.clone(),
+            decimal(booking.amount, digits, '.'),
+            scope.currency.clone(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f, ',')).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out.into_bytes()
+}
+
+/// QIF bank register of the bookings that move money; the category is the other account
+pub fn qif(scope: &ReportScope, bank_account: &str, bookings: &[Booking]) -> Vec<u8> {
+    let digits = currency_digits(&scope.currency);
+    let mut out = String::from("!Type:Bank\n");
+    for booking in bookings.iter().filter(|b| b.cash) {
+        let (amount, category) = if booking.debit_account == bank_account {
+            (booking.amount, &booking.credit_account)
+        } else {
+            (-booking.amount, &booking.debit_account)
+        };
+        out.push_str(&format!(
+            "D{}\nT{}\nN{}\nP{}\nL{}\n^\n",
+            booking.date.format("%m/%d/%Y"),
+            decimal(amount, digits, '.'),
+            booking.document.replace('\n', " "),
+            booking.text.replace('\n', " "),
+            category
+        ));
+    }
+    out.into_bytes()
+}
+
+/// The export of a range in `format`
+pub fn export(conn: &Connection, scope: &ReportScope, format: ExportFormat) -> Result<(Vec<u8>, usize), AccountingExportError> {
+    let bookings = bookings(conn, scope)?;
+    let bytes = match format {
+        ExportFormat::Datev => datev(scope, &account_mapping(conn)?, &bookings)?,
+        ExportFormat::Csv => csv_journal(scope, &bookings),
+        ExportFormat::Qif => {
+            let accounts = Accounts { conn, mapping: account_mapping(conn)?, cache: HashMap::new() };
+            qif(scope, &accounts.role("bank")?, &bookings)
+        }
+    };
+    let count = match format {
+        ExportFormat::Qif => bookings.iter().filter(|b| b.cash).count(),
+        _ => bookings.len(),
+    };
+    Ok((bytes, count))
+}
+
+/// 📤 Write the journal of a period as `datev`, `csv` or `qif`.
+/// Returns the number of bookings written.
+#[tauri::command]
+pub fn export_accounting(
+    state: tauri::State<StateWrapper>,
+    path: String,
+    format: String,
+    from: String,
+    to: String,
+) -> Result<usize, AccountingExportError> {
+    let format = ExportFormat::parse(&format).ok_or(AccountingExportError::UnknownFormat(format))?;
+    let db_conn = open_state_db(&state)?;
+    let scope = ReportScope::new(&db_conn, &from, &to, None)?;
+    let (bytes, count) = export(&db_conn, &scope, format)?;
+    std::fs::write(&path, bytes).map_err(|e| AccountingExportError::IoError(path, e.to_string()))?;
+    Ok(count)
+}
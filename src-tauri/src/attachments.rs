@@ -0,0 +1,273 @@
+//! Files attached to records such as invoices.
+//!
+//! The bytes live in a folder next to the user's database (`<user_id>_attachments`) and the
+//! `attachments` table records which record each file belongs to. Stored paths are relative
+//! to that folder, so the data directory can be moved as a whole.
+//!
+//! Files are encrypted with AES-256-GCM under a key derived from the database key, so the
+//! folder is as private as the database next to it. The relative path is bound to the
+//! ciphertext as associated data; files written before encryption existed are read as is.
+
+use std::path::{Path, PathBuf};
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hkdf::{Salt, HKDF_SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::db_api::{open_state_db, DbApiError};
+use crate::StateWrapper;
+
+#[derive(Debug, Error)]
+pub enum AttachmentError {
+    #[error("[attachments.rs::not_found] Attachment {0} does not exist.")]
+    NotFound(i64),
+
+    #[error("[attachments.rs::io] Could not access {path:?}: {source}")]
+    IoError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("[attachments.rs::crypto] Could not encrypt or decrypt {0:?}.")]
+    CryptoError(String),
+
+    #[error("[attachments.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[attachments.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for AttachmentError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 📎 Attachment Struct
+#[derive(Serialize, Clone, Debug)]
+pub struct Attachment {
+    pub id: i64,
+    /// Kind of record the file belongs to, e.g. `invoice`
+    pub owner_type: String,
+    pub owner_id: i64,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub created_at: String,
+    #[serde(skip)]
+    path: String,
+    #[serde(skip)]
+    encrypted: bool,
+}
+
+const ATTACHMENT_COLUMNS: &str = "id, owner_type, owner_id, file_name, mime_type, size, created_at, path, encrypted";
+
+fn attachment_from_row(row: &rusqlite::Row) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        owner_type: row.get(1)?,
+        owner_id: row.get(2)?,
+        file_name: row.get(3)?,
+        mime_type: row.get(4)?,
+        size: row.get(5)?,
+        created_at: row.get(6)?,
+        path: row.get(7)?,
+        encrypted: row.get(8)?,
+    })
+}
+
+/// Folder holding the attachments of the database at `db_path`
+pub fn attachments_dir(db_path: &Path) -> PathBuf {
+    let stem = db_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    db_path.with_file_name(format!("{}_attachments", stem))
+}
+
+/// Marks an encrypted file: magic bytes, then the nonce, then ciphertext and tag
+const ENCRYPTED_MAGIC: &[u8] = b"BMATT1";
+
+/// Attachments folder and the key its files are encrypted with
+pub struct AttachmentFiles {
+    pub dir: PathBuf,
+    key: LessSafeKey,
+}
+
+impl AttachmentFiles {
+    /// Derives the file key from the (base64) database key with HKDF-SHA256
+    pub fn new(dir: PathBuf, db_key: &str) -> Result<Self, AttachmentError> {
+        let crypto_error = |_| AttachmentError::CryptoError("key".to_string());
+        let prk = Salt::new(HKDF_SHA256, b"buffmod-attachments").extract(db_key.as_bytes());
+        let okm = prk.expand(&[b"aes-256-gcm"], &AES_256_GCM).map_err(crypto_error)?;
+        Ok(AttachmentFiles { dir, key: LessSafeKey::new(UnboundKey::from(okm)) })
+    }
+
+    fn encrypt(&self, relative: &str, bytes: &[u8]) -> Result<Vec<u8>, AttachmentError> {
+        let crypto_error = |_| AttachmentError::CryptoError(relative.to_string());
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).map_err(crypto_error)?;
+        let mut sealed = bytes.to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(relative.as_bytes()), &mut sealed)
+            .map_err(crypto_error)?;
+
+        let mut out = Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + sealed.len());
+        out.extend_from_slice(ENCRYPTED_MAGIC);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    fn decrypt(&self, relative: &str, bytes: &[u8]) -> Result<Vec<u8>, AttachmentError> {
+        let crypto_error = || AttachmentError::CryptoError(relative.to_string());
+        let rest = bytes.strip_prefix(ENCRYPTED_MAGIC).ok_or_else(crypto_error)?;
+        if rest.len() < NONCE_LEN {
+            return Err(crypto_error());
+        }
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| crypto_error())?;
+        let mut sealed = sealed.to_vec();
+        let plain_len = self
+            .key
+            .open_in_place(nonce, Aad::from(relative.as_bytes()), &mut sealed)
+            .map_err(|_| crypto_error())?
+            .len();
+        sealed.truncate(plain_len);
+        Ok(sealed)
+    }
+}
+
+/// Attachments folder and file key of the signed-in user
+pub fn state_attachment_files(state: &StateWrapper) -> Result<AttachmentFiles, AttachmentError> {
+    let loc_state = state.lock().map_err(|_| DbApiError::DatabaseLockError)?;
+    let app_state = loc_state.as_ref().ok_or(DbApiError::DatabaseLocked)?;
+    let db_key = app_state.db_key.as_ref().ok_or(DbApiError::DatabaseLocked)?;
+    let db_path = app_state.db_path.as_ref().ok_or(DbApiError::DatabaseConnectionNotFound)?;
+    AttachmentFiles::new(attachments_dir(db_path), db_key)
+}
+
+/// Keeps letters, digits, dots, dashes and underscores so the name is safe on every file system
+fn safe_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    cleaned.trim_start_matches('.').to_string()
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> AttachmentError + '_ {
+    move |source| AttachmentError::IoError { path: path.to_path_buf(), source }
+}
+
+/// Encrypts `bytes` into the attachments folder and records it against its owner
+pub fn store(
+    conn: &Connection,
+    files: &AttachmentFiles,
+    owner_type: &str,
+    owner_id: i64,
+    file_name: &str,
+    mime_type: &str,
+    bytes: &[u8],
+) -> Result<Attachment, AttachmentError> {
+    let dir = files.dir.as_path();
+    std::fs::create_dir_all(dir).map_err(io_error(dir))?;
+    let relative = format!("{}-{}", Uuid::new_v4(), safe_file_name(file_name));
+    let full_path = dir.join(&relative);
+    let sealed = files.encrypt(&relative, bytes)?;
+    std::fs::write(&full_path, sealed).map_err(io_error(&full_path))?;
+
+    let inserted = conn.execute(
+        "INSERT INTO attachments (owner_type, owner_id, file_name, mime_type, path, size, encrypted)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1)",
+        params![owner_type, owner_id, file_name, mime_type, relative, bytes.len() as i64],
+    );
+    if let Err(e) = inserted {
+        // Do not leave a file behind that no row points to
+        let _ = std::fs::remove_file(&full_path);
+        return Err(e.into());
+    }
+    get(conn, conn.last_insert_rowid())
+}
+
+pub fn get(conn: &Connection, attachment_id: i64) -> Result<Attachment, AttachmentError> {
+    conn.query_row(
+        &format!("SELECT {} FROM attachments WHERE id = ?1", ATTACHMENT_COLUMNS),
+        [attachment_id],
+        attachment_from_row,
+    )
+    .optional()?
+    .ok_or(AttachmentError::NotFound(attachment_id))
+}
+
+/// Attachments of one record, newest first
+pub fn list(conn: &Connection, owner_type: &str, owner_id: i64) -> Result<Vec<Attachment>, AttachmentError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM attachments WHERE owner_type = ?1 AND owner_id = ?2 ORDER BY created_at DESC, id DESC",
+        ATTACHMENT_COLUMNS
+    ))?;
+    let attachments = stmt
+        .query_map(params![owner_type, owner_id], attachment_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(attachments)
+}
+
+/// Plain bytes of an attachment
+pub fn read(conn: &Connection, files: &AttachmentFiles, attachment_id: i64) -> Result<Vec<u8>, AttachmentError> {
+    let attachment = get(conn, attachment_id)?;
+    let full_path = files.dir.join(&attachment.path);
+    let bytes = std::fs::read(&full_path).map_err(io_error(&full_path))?;
+    if attachment.encrypted {
+        files.decrypt(&attachment.path, &bytes)
+    } else {
+        Ok(bytes)
+    }
+}
+
+pub fn delete(conn: &Connection, files: &AttachmentFiles, attachment_id: i64) -> Result<(), AttachmentError> {
+    let path = delete_row(conn, attachment_id)?;
+    remove_file(files, &path)
+}
+
+/// Deletes the row of an attachment and returns the path of its file. Within a transaction,
+/// remove the file with `remove_file` only once the transaction is committed.
+pub fn delete_row(conn: &Connection, attachment_id: i64) -> Result<String, AttachmentError> {
+    let attachment = get(conn, attachment_id)?;
+    conn.execute("DELETE FROM attachments WHERE id = ?1", [attachment_id])?;
+    Ok(attachment.path)
+}
+
+/// Removes a stored attachment file; a file that is already gone is fine
+pub fn remove_file(files: &AttachmentFiles, path: &str) -> Result<(), AttachmentError> {
+    let full_path = files.dir.join(path);
+    match std::fs::remove_file(&full_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_error(&full_path)(e)),
+        _ => Ok(()),
+    }
+}
+
+/// 📋 List the attachments of a record, e.g. `("invoice", 12)`
+#[tauri::command]
+pub fn list_attachments(state: tauri::State<StateWrapper>, owner_type: String, owner_id: i64) -> Result<Vec<Attachment>, AttachmentError> {
+    let db_conn = open_state_db(&state)?;
+    list(&db_conn, &owner_type, owner_id)
+}
+
+/// 📥 Read the bytes of an attachment
+#[tauri::command]
+pub fn read_attachment(state: tauri::State<StateWrapper>, attachment_id: i64) -> Result<Vec<u8>, AttachmentError> {
+    let db_conn = open_state_db(&state)?;
+    read(&db_conn, &state_attachment_files(&state)?, attachment_id)
+}
+
+/// 🗑️ Delete an attachment and its file
+#[tauri::command]
+pub fn delete_attachment(state: tauri::State<StateWrapper>, attachment_id: i64) -> Result<(), AttachmentError> {
+    let db_conn = open_state_db(&state)?;
+    delete(&db_conn, &state_attachment_files(&state)?, attachment_id)
+}
@@ -0,0 +1,160 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::db_api::{open_state_db, DbApiError, Event};
+use crate::members::current_member_id;
+use crate::StateWrapper;
+
+/// ✉️ RSVP status of an attendee, stored as text in `event_attendees.rsvp_status`
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum RsvpStatus {
+    NeedsAction,
+    Accepted,
+    Declined,
+    Tentative,
+}
+
+impl RsvpStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RsvpStatus::NeedsAction => "NeedsAction",
+            RsvpStatus::Accepted => "Accepted",
+            RsvpStatus::Declined => "Declined",
+            RsvpStatus::Tentative => "Tentative",
+        }
+    }
+}
+
+/// 🧑‍🤝‍🧑 Attendee Struct: either a client contact or an org member
+#[derive(Serialize, Deserialize)]
+pub struct Attendee {
+    pub id: Option<i32>,
+    pub event_id: i32,
+    pub contact_id: Option<i32>,
+    pub member_id: Option<i32>,
+    /// Resolved from the contact or member when listing
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub rsvp_status: String,
+}
+
+/// ➕ Add a client contact or a member to an event
+#[tauri::command]
+pub fn add_event_attendee(
+    state: tauri::State<StateWrapper>,
+    event_id: i32,
+    contact_id: Option<i32>,
+    member_id: Option<i32>,
+) -> Result<i64, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute(
+        "INSERT INTO event_attendees (event_id, contact_id, member_id) VALUES (?1, ?2, ?3)",
+        params![event_id, contact_id, member_id]
+    )?;
+    Ok(db_conn.last_insert_rowid())
+}
+
+/// ✅ Record an attendee's answer
+#[tauri::command]
+pub fn set_attendee_rsvp(state: tauri::State<StateWrapper>, attendee_id: i32, status: RsvpStatus) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    let updated = db_conn.execute(
+        "UPDATE event_attendees SET rsvp_status = ?1 WHERE id = ?2",
+        params![status.as_str(), attendee_id]
+    )?;
+    if updated == 0 {
+        return Err(DbApiError::AttendeeNotFound(attendee_id));
+    }
+    Ok(())
+}
+
+/// 🗑️ Remove an attendee from an event
+#[tauri::command]
+pub fn remove_event_attendee(state: tauri::State<StateWrapper>, attendee_id: i32) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute("DELETE FROM event_attendees WHERE id = ?1", [attendee_id])?;
+    Ok(())
+}
+
+/// 📋 List the attendees of an event
+#[tauri::command]
+pub fn list_event_attendees(state: tauri::State<StateWrapper>, event_id: i32) -> Result<Vec<Attendee>, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT a.id, a.event_id, a.contact_id, a.member_id,
+                COALESCE(c.name, m.name), COALESCE(c.email, m.email), a.rsvp_status
+         FROM event_attendees a
+         LEFT JOIN client_contacts c ON c.id = a.contact_id
+         LEFT JOIN members m ON m.id = a.member_id
+         WHERE a.event_id = ?1
+         ORDER BY a.id"
+    )?;
+
+    let attendees = stmt
+        .query_map([event_id], |row| {
+            Ok(Attendee {
+                id: row.get(0)?,
+                event_id: row.get(1)?,
+                contact_id: row.get(2)?,
+                member_id: row.get(3)?,
+                name: row.get(4)?,
+                email: row.get(5)?,
+                rsvp_status: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(attendees)
+}
+
+/// 👷 Assign a staff member to an event, or clear the assignment with `null`
+#[tauri::command]
+pub fn assign_event_staff(state: tauri::State<StateWrapper>, event_id: i32, member_id: Option<i32>) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute(
+        "UPDATE events SET assigned_member_id = ?1, caldav_dirty = 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        params![member_id, event_id]
+    )?;
+    Ok(())
+}
+
+fn member_schedule(conn: &Connection, member_id: i64, from: &str, to: &str) -> Result<Vec<Event>, DbApiError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, start_date, end_date, client_id, assigned_member_id
+         FROM events
+         WHERE assigned_member_id = ?1
+           AND datetime(end_date) >= datetime(?2)
+           AND datetime(start_date) <= datetime(?3)
+         ORDER BY datetime(start_date)"
+    )?;
+
+    let events = stmt
+        .query_map(params![member_id, from, to], |row| {
+            Ok(Event {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                start_date: row.get(2)?,
+                end_date: row.get(3)?,
+                client_id: row.get(4)?,
+                assigned_member_id: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+/// 🗓️ Events assigned to a member between `from` and `to`.
+/// Without `member_id` this is "my schedule" for the signed-in user.
+#[tauri::command]
+pub fn list_schedule(
+    state: tauri::State<StateWrapper>,
+    member_id: Option<i32>,
+    from: String,
+    to: String,
+) -> Result<Vec<Event>, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    let member_id = match member_id {
+        Some(id) => id as i64,
+        None => current_member_id(&state, &db_conn)?,
+    };
+    member_schedule(&db_conn, member_id, &from, &to)
+}
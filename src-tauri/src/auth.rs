@@ -1,3 +1,4 @@
+use crate::members::ensure_member;
 use crate::secure_db_access::SecureDbError;
 use crate::storage::{get_database_path, new_db, StorageError};
 use crate::supabase::{Supabase, SupabaseError};
@@ -78,12 +79,13 @@ pub async fn sign_in(
             println!("Storing db_key");
             s.db_key = Some(str_db_key.to_owned());
         } else {
-            *loc_state = Some(AppState { db_key: Some(str_db_key.to_owned()), db_path: None });
+            *loc_state = Some(AppState { db_key: Some(str_db_key.to_owned()), db_path: None, user_id: Some(user_id.to_string()) });
         }
     } // Lock is released here when loc_state is dropped
 
     println!("New db starting...");
-    new_db(state.to_owned(), &app_handle, &user_id)?;
+    let db_conn = new_db(state.to_owned(), &app_handle, &user_id)?;
+    ensure_member(&db_conn, &user_id, &email, &email).map_err(StorageError::from)?;
     println!("New db created...");
 
     {
@@ -96,7 +98,7 @@ pub async fn sign_in(
         if let Some(ref mut s) = *loc_state {
             s.db_path = Some(db_path.clone());
         } else {
-            *loc_state = Some(AppState { db_key: Some(str_db_key.to_owned()), db_path: Some(db_path.clone()) });
+            *loc_state = Some(AppState { db_key: Some(str_db_key.to_owned()), db_path: Some(db_path.clone()), user_id: Some(user_id.to_string()) });
         }
     }
 
@@ -143,11 +145,12 @@ pub async fn initial_sign_up(
             println!("Storing db_key");
             s.db_key = Some(str_db_key.to_owned());
         } else {
-            *loc_state = Some(AppState { db_key: Some(str_db_key.to_owned()), db_path: None });
+            *loc_state = Some(AppState { db_key: Some(str_db_key.to_owned()), db_path: None, user_id: Some(user_id.to_string()) });
         }
     } // Lock is released here when loc_state is dropped
     println!("Createding db...");
-    new_db(state.to_owned(), &app_handle, &user_id)?;
+    let db_conn = new_db(state.to_owned(), &app_handle, &user_id)?;
+    ensure_member(&db_conn, &user_id, &user_name, &email).map_err(StorageError::from)?;
 
     Ok(vec![
         Entry {
@@ -196,10 +199,11 @@ async fn invite_sign_up(
     if let Some(ref mut s) = *loc_state {
         s.db_key = Some(str_db_key.to_owned());
     } else {
-        *loc_state = Some(AppState { db_key: Some(str_db_key.to_owned()), db_path: None });
+        *loc_state = Some(AppState { db_key: Some(str_db_key.to_owned()), db_path: None, user_id: Some(user_id.to_string()) });
     }
 
-    new_db(state.to_owned(), &app_handle, user_id)?;
+    let db_conn = new_db(state.to_owned(), &app_handle, user_id)?;
+    ensure_member(&db_conn, user_id, &user_name, &email).map_err(StorageError::from)?;
 
     Ok(vec![
         user_id.to_string(),
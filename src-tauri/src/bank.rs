@@ -0,0 +1,1454 @@
+//! Bank statement import into `bank_transactions`.
+//!
+//! Supported formats are ISO 20022 CAMT.053 XML, SWIFT MT940, OFX (SGML 1.x and XML 2.x) and
+//! CSV with configurable columns (`BankCsvFormat`, defaulting to the JSON in
+//! `settings.bank_csv_format`). Each statement is recorded with its account, period and
+//! opening/closing balances. Transactions are unique per account by their import key: the
+//! id the bank assigns to each booking where the format has one (CAMT `AcctSvcrRef`, OFX
+//! `FITID`), otherwise a hash of reference, date, amount, counterparty and text, so overlapping
+//! exports can be imported again without duplicates. Payer references such as MT940 customer
+//! references or CSV reference columns repeat across bookings and are never a key on their own.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate};
+use ring::digest::{digest, SHA256};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::currencies::{currency_or_base, normalize_currency, parse_date, CurrencyError};
+use crate::db_api::{open_state_db, DbApiError};
+use crate::money::{currency_digits, parse_minor};
+use crate::qrbill::{validate_creditor_reference, validate_qr_reference};
+use crate::settings::read_setting;
+use crate::xml::XmlNode;
+use crate::StateWrapper;
+
+#[derive(Debug, Error)]
+pub enum BankError {
+    #[error("[bank.rs::invalid_file] Could not read the statement: {0}")]
+    InvalidFile(String),
+
+    #[error("[bank.rs::invalid_amount] Invalid amount {0:?}.")]
+    InvalidAmount(String),
+
+    #[error("[bank.rs::invalid_date] Invalid date {0:?}.")]
+    InvalidDate(String),
+
+    #[error("[bank.rs::missing_account] The statement names no account; pass the account to import it into.")]
+    MissingAccount,
+
+    #[error("[bank.rs::account_not_found] Bank account {0} does not exist.")]
+    AccountNotFound(i64),
+
+    #[error("[bank.rs::statement_not_found] Bank statement {0} does not exist.")]
+    StatementNotFound(i64),
+
+    #[error("[bank.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[bank.rs::io] Could not read {0:?}: {1}")]
+    IoError(String, std::io::Error),
+
+    #[error("[bank.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[bank.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[bank.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for BankError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 🏦 File format of a bank statement, stored as text in `bank_statements.format`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum StatementFormat {
+    Camt053,
+    Mt940,
+    Ofx,
+    Csv,
+}
+
+impl StatementFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatementFormat::Camt053 => "Camt053",
+            StatementFormat::Mt940 => "Mt940",
+            StatementFormat::Ofx => "Ofx",
+            StatementFormat::Csv => "Csv",
+        }
+    }
+
+    /// Guesses the format from the content; anything unrecognised is read as CSV
+    pub fn detect(text: &str) -> StatementFormat {
+        let head: String = text.chars().take(4096).collect();
+        if head.contains("OFXHEADER") || head.contains("<OFX>") {
+            StatementFormat::Ofx
+        } else if head.trim_start().starts_with('<') {
+            StatementFormat::Camt053
+        } else if head.contains(":20:") && (head.contains(":60F:") || head.contains(":61:")) {
+            StatementFormat::Mt940
+        } else {
+            StatementFormat::Csv
+        }
+    }
+}
+
+/// 🧮 Columns of a bank CSV export. Column names are matched case-insensitively.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BankCsvFormat {
+    /// Detected from the header (`;`, tab or `,`) when not set
+    pub delimiter: Option<char>,
+    /// Decimal separator of amounts; the other of `.` and `,` is taken as thousands separator
+    pub decimal_separator: char,
+    /// chrono format such as `%m/%d/%Y`; `YYYY-MM-DD` and `DD.MM.YYYY` are read when not set
+    pub date_format: Option<String>,
+    /// Lines before the header row, e.g. an account summary
+    pub skip_lines: usize,
+    pub date_column: String,
+    pub value_date_column: Option<String>,
+    /// Signed amount; alternatively `credit_column` and `debit_column`
+    pub amount_column: Option<String>,
+    pub credit_column: Option<String>,
+    pub debit_column: Option<String>,
+    pub currency_column: Option<String>,
+    /// Balance after each transaction, used for the opening and closing balance
+    pub balance_column: Option<String>,
+    pub reference_column: Option<String>,
+    pub description_column: Option<String>,
+    pub counterparty_column: Option<String>,
+    pub counterparty_iban_column: Option<String>,
+}
+
+impl Default for BankCsvFormat {
+    fn default() -> Self {
+        BankCsvFormat {
+            delimiter: None,
+            decimal_separator: '.',
+            date_format: None,
+            skip_lines: 0,
+            date_column: "Date".to_string(),
+            value_date_column: None,
+            amount_column: Some("Amount".to_string()),
+            credit_column: None,
+            debit_column: None,
+            currency_column: None,
+            balance_column: None,
+            reference_column: Some("Reference".to_string()),
+            description_column: Some("Description".to_string()),
+            counterparty_column: None,
+            counterparty_iban_column: None,
+        }
+    }
+}
+
+/// 🏦 Bank Account Struct, with the closing balance of its latest statement
+#[derive(Serialize, Deserialize)]
+pub struct BankAccount {
+    pub id: i64,
+    /// IBAN or account number as named in the statements
+    pub identifier: String,
+    pub name: Option<String>,
+    pub currency: String,
+    #[serde(default)]
+    pub balance: Option<i64>,
+    #[serde(default)]
+    pub balance_date: Option<String>,
+    #[serde(default)]
+    pub transaction_count: i64,
+}
+
+/// 📄 Bank Statement Struct. Balances are in minor units.
+#[derive(Serialize, Deserialize)]
+pub struct BankStatement {
+    pub id: i64,
+    pub account_id: i64,
+    pub format: String,
+    pub statement_ref: Option<String>,
+    pub period_from: Option<String>,
+    pub period_to: Option<String>,
+    pub opening_balance: Option<i64>,
+    pub closing_balance: Option<i64>,
+    pub file_name: Option<String>,
+    pub imported_at: String,
+}
+
+/// 💳 Bank Transaction Struct. `amount` is in minor units, positive for incoming money.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BankTransaction {
+    pub id: i64,
+    pub account_id: i64,
+    pub statement_id: Option<i64>,
+    pub booking_date: String,
+    pub value_date: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+    /// Reference from the statement: the payer's reference, else the bank's
+    pub reference: Option<String>,
+    pub remittance: Option<String>,
+    /// QR reference or ISO 11649 creditor reference, when one was found
+    pub structured_reference: Option<String>,
+    pub counterparty_name: Option<String>,
+    pub counterparty_iban: Option<String>,
+}
+
+/// 🔎 Filters for `list_bank_transactions`; dates are inclusive
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct BankTransactionFilter {
+    pub account_id: Option<i64>,
+    pub statement_id: Option<i64>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// 📥 Outcome of importing one statement of a file
+#[derive(Serialize, Debug)]
+pub struct ImportSummary {
+    pub account_id: i64,
+    pub statement_id: i64,
+    pub format: StatementFormat,
+    pub imported: usize,
+    /// Transactions that were already known from an earlier import
+    pub duplicates: usize,
+    pub opening_balance: Option<i64>,
+    pub closing_balance: Option<i64>,
+}
+
+/// Options of `import`; everything is optional
+#[derive(Default, Debug)]
+pub struct ImportOptions {
+    /// Account to import into, for files that do not name one (CSV) or to override it
+    pub account: Option<String>,
+    pub csv_format: Option<BankCsvFormat>,
+    pub file_name: Option<String>,
+}
+
+#[derive(Default, Debug)]
+struct ParsedStatement {
+    account: Option<String>,
+    currency: Option<String>,
+    statement_ref: Option<String>,
+    period_from: Option<NaiveDate>,
+    period_to: Option<NaiveDate>,
+    opening_balance: Option<i64>,
+    closing_balance: Option<i64>,
+    transactions: Vec<ParsedTransaction>,
+}
+
+#[derive(Default, Debug)]
+struct ParsedTransaction {
+    booking_date: Option<NaiveDate>,
+    value_date: Option<NaiveDate>,
+    amount: i64,
+    currency: Option<String>,
+    /// Id the bank assigned to this booking, unique per account
+    bank_reference: Option<String>,
+    reference: Option<String>,
+    remittance: Option<String>,
+    structured_reference: Option<String>,
+    counterparty_name: Option<String>,
+    counterparty_iban: Option<String>,
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Account identifiers are compared without spaces and in upper case
+fn normalize_account(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase()
+}
+
+fn amount_in(value: &str, currency: &str) -> Result<i64, BankError> {
+    parse_minor(value, currency_digits(currency)).ok_or_else(|| BankError::InvalidAmount(value.to_string()))
+}
+
+/// Finds a QR reference (27 digits) or an ISO 11649 creditor reference in free text,
+/// also when it is printed in groups
+pub fn find_structured_reference(text: &str) -> Option<String> {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+    let chars: Vec<char> = compact.chars().collect();
+
+    let mut start = 0;
+    while start < chars.len() {
+        if chars[start].is_ascii_digit() {
+            let end = (start..chars.len()).find(|&i| !chars[i].is_ascii_digit()).unwrap_or(chars.len());
+            if end - start == 27 {
+                let candidate: String = chars[start..end].iter().collect();
+                if validate_qr_reference(&candidate).is_ok() {
+                    return Some(candidate);
+                }
+            }
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+
+    for (index, _) in compact.match_indices("RF") {
+        let run = compact[index..].chars().take_while(char::is_ascii_alphanumeric).count().min(25);
+        for len in (5..=run).rev() {
+            let candidate = &compact[index..index + len];
+            if validate_creditor_reference(candidate).is_ok() {
+                return Some(candidate.to_string());
+            }
+        }
+    }
+    None
+}
+
+// ---------------------------------------------------------------------------
+// CAMT.053
+// ---------------------------------------------------------------------------
+
+fn camt_date(node: Option<&XmlNode>) -> Result<Option<NaiveDate>, BankError> {
+    let Some(value) = node.and_then(|n| n.text_at(&["Dt"]).or_else(|| n.text_at(&["DtTm"]))) else {
+        return Ok(None);
+    };
+    let date = value.get(..10).unwrap_or(&value);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map(Some).map_err(|_| BankError::InvalidDate(value.clone()))
+}
+
+/// Signed amount and currency of a node holding `Amt` and `CdtDbtInd`
+fn camt_amount(node: &XmlNode, amount: &XmlNode, indicator: Option<&str>) -> Result<(i64, String), BankError> {
+    let currency = normalize_currency(amount.attribute("Ccy").unwrap_or_default())?;
+    let value = amount_in(amount.text.trim(), &currency)?;
+    let debit = node.text_at(&["CdtDbtInd"]).as_deref().or(indicator) == Some("DBIT");
+    Ok((if debit { -value } else { value }, currency))
+}
+
+/// Amount of a single payment within a batch entry
+fn camt_detail_amount(details: &XmlNode) -> Option<&XmlNode> {
+    details.find(&["AmtDtls", "TxAmt", "Amt"]).or_else(|| details.child("Amt"))
+}
+
+/// References, remittance and counterparty from a `TxDtls` element
+fn camt_details(details: &XmlNode, transaction: &mut ParsedTransaction) {
+    if let Some(bank_reference) = details.text_at(&["Refs", "AcctSvcrRef"]) {
+        transaction.bank_reference = Some(bank_reference);
+    }
+    transaction.reference = ["EndToEndId", "TxId", "InstrId"]
+        .iter()
+        .filter_map(|name| details.text_at(&["Refs", name]))
+        .find(|value| value != "NOTPROVIDED");
+
+    if let Some(remittance) = details.child("RmtInf") {
+        let text: Vec<String> = remittance.children_named("Ustrd").filter_map(|u| non_empty(&u.text)).collect();
+        if !text.is_empty() {
+            transaction.remittance = Some(text.join(" "));
+        }
+        transaction.structured_reference = remittance
+            .children_named("Strd")
+            .find_map(|s| s.text_at(&["CdtrRefInf", "Ref"]))
+            .map(|r| normalize_account(&r));
+    }
+
+    // The counterparty is the debtor of incoming and the creditor of outgoing money
+    let (party, account) = if transaction.amount >= 0 { ("Dbtr", "DbtrAcct") } else { ("Cdtr", "CdtrAcct") };
+    if let Some(parties) = details.child("RltdPties") {
+        transaction.counterparty_name =
+            parties.text_at(&[party, "Nm"]).or_else(|| parties.text_at(&[party, "Pty", "Nm"]));
+        transaction.counterparty_iban = parties.text_at(&[account, "Id", "IBAN"]);
+    }
+}
+
+fn parse_camt053(text: &str) -> Result<Vec<ParsedStatement>, BankError> {
+    let root = XmlNode::parse(text).map_err(BankError::InvalidFile)?;
+    let report = root
+        .child("BkToCstmrStmt")
+        .ok_or_else(|| BankError::InvalidFile("no BkToCstmrStmt element, not a camt.053 file".to_string()))?;
+
+    let mut statements = Vec::new();
+    for stmt in report.children_named("Stmt") {
+        let mut statement = ParsedStatement {
+            account: stmt.text_at(&["Acct", "Id", "IBAN"]).or_else(|| stmt.text_at(&["Acct", "Id", "Othr", "Id"])),
+            currency: stmt.text_at(&["Acct", "Ccy"]),
+            statement_ref: stmt.text_at(&["Id"]),
+            ..Default::default()
+        };
+        if let Some(period) = stmt.child("FrToDt") {
+            let day = |name: &str| period.text_at(&[name]).and_then(|v| v.get(..10).and_then(parse_date));
+            statement.period_from = day("FrDtTm");
+            statement.period_to = day("ToDtTm");
+        }
+
+        for balance in stmt.children_named("Bal") {
+            let Some(amount) = balance.child("Amt") else { continue };
+            let (value, _) = camt_amount(balance, amount, None)?;
+            match balance.text_at(&["Tp", "CdOrPrtry", "Cd"]).as_deref() {
+                Some("OPBD") | Some("PRCD") => statement.opening_balance = statement.opening_balance.or(Some(value)),
+                Some("CLBD") => statement.closing_balance = Some(value),
+                _ => {}
+            }
+        }
+
+        for entry in stmt.children_named("Ntry") {
+            let status = entry.text_at(&["Sts"]).or_else(|| entry.text_at(&["Sts", "Cd"]));
+            if matches!(status.as_deref(), Some("PDNG") | Some("INFO")) {
+                continue;
+            }
+            let amount = entry.child("Amt").ok_or_else(|| BankError::InvalidFile("entry without amount".to_string()))?;
+            let (value, currency) = camt_amount(entry, amount, None)?;
+            let indicator = entry.text_at(&["CdtDbtInd"]);
+            let booking_date = camt_date(entry.child("BookgDt"))?;
+            let value_date = camt_date(entry.child("ValDt"))?;
+            let entry_ref = entry.text_at(&["AcctSvcrRef"]);
+            let entry_info = entry.text_at(&["AddtlNtryInf"]);
+
+            let details: Vec<&XmlNode> =
+                entry.children_named("NtryDtls").flat_map(|d| d.children_named("TxDtls")).collect();
+            // A batch booking lists its single payments; import those so each can be matched
+            if details.len() > 1 && details.iter().all(|d| camt_detail_amount(d).is_some()) {
+                for (index, detail) in details.iter().enumerate() {
+                    let (value, currency) = camt_amount(detail, camt_detail_amount(detail).unwrap(), indicator.as_deref())?;
+                    let mut transaction = ParsedTransaction {
+                        booking_date,
+                        value_date,
+                        amount: value,
+                        currency: Some(currency),
+                        ..Default::default()
+                    };
+                    camt_details(detail, &mut transaction);
+                    if transaction.bank_reference.is_none() {
+                        transaction.bank_reference = entry_ref.as_ref().map(|r| format!("{}/{}", r, index + 1));
+                    }
+                    transaction.remittance = transaction.remittance.or_else(|| entry_info.clone());
+                    statement.transactions.push(transaction);
+                }
+                continue;
+            }
+
+            let mut transaction = ParsedTransaction {
+                booking_date,
+                value_date,
+                amount: value,
+                currency: Some(currency),
+                bank_reference: entry_ref,
+                ..Default::default()
+            };
+            if let Some(detail) = details.first() {
+                camt_details(detail, &mut transaction);
+            }
+            transaction.remittance = transaction.remittance.or(entry_info);
+            statement.transactions.push(transaction);
+        }
+        statements.push(statement);
+    }
+    if statements.is_empty() {
+        return Err(BankError::InvalidFile("no Stmt element".to_string()));
+    }
+    Ok(statements)
+}
+
+// ---------------------------------------------------------------------------
+// MT940
+// ---------------------------------------------------------------------------
+
+/// Splits an MT940 message into `(tag, value)` fields; continuation lines stay in the value
+fn mt940_fields(text: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with('{') || line.trim() == "-" || line.starts_with("-}") {
+            continue;
+        }
+        let tag = line
+            .strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(tag, _)| (2..=3).contains(&tag.len()) && tag.chars().all(|c| c.is_ascii_alphanumeric()));
+        match (tag, fields.last_mut()) {
+            (Some((tag, value)), _) => fields.push((tag.to_string(), value.to_string())),
+            (None, Some((_, value))) => {
+                value.push('\n');
+                value.push_str(line);
+            }
+            (None, None) => {}
+        }
+    }
+    fields
+}
+
+/// `YYMMDD`
+fn mt940_date(value: &str) -> Result<NaiveDate, BankError> {
+    let invalid = || BankError::InvalidDate(value.to_string());
+    let number = |range: std::ops::Range<usize>| value.get(range).and_then(|v| v.parse::<u32>().ok()).ok_or_else(invalid);
+    NaiveDate::from_ymd_opt(2000 + number(0..2)? as i32, number(2..4)?, number(4..6)?).ok_or_else(invalid)
+}
+
+/// Balance such as `C250131CHF1234,56`: mark, date, currency and amount
+fn mt940_balance(value: &str) -> Result<(NaiveDate, String, i64), BankError> {
+    let value = value.trim();
+    let invalid = || BankError::InvalidFile(format!("invalid balance {:?}", value));
+    let (mark, rest) = (value.get(..1).ok_or_else(invalid)?, value.get(1..).ok_or_else(invalid)?);
+    let date = mt940_date(rest.get(..6).ok_or_else(invalid)?)?;
+    let currency = normalize_currency(rest.get(6..9).ok_or_else(invalid)?)?;
+    let amount = amount_in(&rest.get(9..).ok_or_else(invalid)?.replace(',', "."), &currency)?;
+    Ok((date, currency, if mark == "D" { -amount } else { amount }))
+}
+
+/// Statement line `:61:`, e.g. `2501050105C1500,00NTRFNONREF//BANKREF123`
+fn mt940_statement_line(value: &str, currency: &str) -> Result<ParsedTransaction, BankError> {
+    let mut lines = value.lines();
+    let first = lines.next().unwrap_or_default().trim();
+    let invalid = || BankError::InvalidFile(format!("invalid statement line {:?}", first));
+    let value_date = mt940_date(first.get(..6).ok_or_else(invalid)?)?;
+    let mut rest = &first[6..];
+
+    // Optional booking date MMDD in the year of the value date
+    let mut booking_date = value_date;
+    if rest.get(..4).is_some_and(|d| d.chars().all(|c| c.is_ascii_digit())) {
+        let (month, day) = (rest[..2].parse().unwrap_or(0), rest[2..4].parse().unwrap_or(0));
+        booking_date = NaiveDate::from_ymd_opt(value_date.year(), month, day).ok_or_else(invalid)?;
+        rest = &rest[4..];
+    }
+
+    let (negative, after_mark) = if let Some(r) = rest.strip_prefix("RC") {
+        (true, r)
+    } else if let Some(r) = rest.strip_prefix("RD") {
+        (false, r)
+    } else if let Some(r) = rest.strip_prefix('C') {
+        (false, r)
+    } else if let Some(r) = rest.strip_prefix('D') {
+        (true, r)
+    } else {
+        return Err(invalid());
+    };
+    // Optional funds code: the third letter of the currency
+    rest = after_mark.strip_prefix(|c: char| c.is_ascii_alphabetic()).unwrap_or(after_mark);
+
+    let amount_len = rest.find(|c: char| !(c.is_ascii_digit() || c == ',')).unwrap_or(rest.len());
+    let amount = amount_in(&rest[..amount_len].replace(',', "."), currency)?;
+    rest = &rest[amount_len..];
+    // Transaction type: N, F or S and three characters
+    rest = rest.get(4..).unwrap_or_default();
+
+    let (customer_ref, bank_ref) = rest.split_once("//").unwrap_or((rest, ""));
+    let reference = non_empty(bank_ref)
+        .or_else(|| non_empty(customer_ref).filter(|r| r != "NONREF"))
+        .map(|r| r.split_whitespace().next().unwrap_or_default().to_string());
+
+    Ok(ParsedTransaction {
+        booking_date: Some(booking_date),
+        value_date: Some(value_date),
+        amount: if negative { -amount } else { amount },
+        currency: Some(currency.to_string()),
+        reference,
+        remittance: non_empty(&lines.collect::<Vec<_>>().join(" ")),
+        ..Default::default()
+    })
+}
+
+/// Information field `:86:`, either free text or German `?nn` subfields
+fn mt940_information(value: &str, transaction: &mut ParsedTransaction) {
+    let joined: String = value.lines().map(str::trim_end).collect();
+    let structured = joined.get(..4).is_some_and(|head| head[..3].chars().all(|c| c.is_ascii_digit()) && &head[3..] == "?");
+    if !structured {
+        let text = value.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+        transaction.remittance = non_empty(&text).or(transaction.remittance.take());
+        return;
+    }
+
+    let (mut remittance, mut name) = (String::new(), String::new());
+    for part in joined.split('?').skip(1) {
+        let (code, content) = (part.get(..2).unwrap_or(part), part.get(2..).unwrap_or_default());
+        match code.parse::<u32>().unwrap_or(0) {
+            20..=29 | 60..=63 => remittance.push_str(content),
+            31 => transaction.counterparty_iban = non_empty(content),
+            32 | 33 => name.push_str(content),
+            _ => {}
+        }
+    }
+    transaction.remittance = non_empty(&remittance).or(transaction.remittance.take());
+    transaction.counterparty_name = non_empty(&name);
+}
+
+fn parse_mt940(text: &str, default_currency: &str) -> Result<Vec<ParsedStatement>, BankError> {
+    let mut statements: Vec<ParsedStatement> = Vec::new();
+    let mut currency = default_currency.to_string();
+    for (tag, value) in mt940_fields(text) {
+        if tag == "20" || statements.is_empty() {
+            statements.push(ParsedStatement { statement_ref: non_empty(&value), ..Default::default() });
+        }
+        let statement = statements.last_mut().unwrap();
+        match tag.as_str() {
+            "25" => statement.account = non_empty(&value),
+            "28C" => {
+                let number = value.trim();
+                statement.statement_ref = Some(match &statement.statement_ref {
+                    Some(reference) => format!("{} {}", reference, number),
+                    None => number.to_string(),
+                });
+            }
+            "60F" | "60M" => {
+                let (date, code, amount) = mt940_balance(&value)?;
+                currency = code;
+                statement.currency = Some(currency.clone());
+                if statement.opening_balance.is_none() {
+                    statement.opening_balance = Some(amount);
+                    statement.period_from = Some(date);
+                }
+            }
+            "62F" | "62M" => {
+                let (date, _, amount) = mt940_balance(&value)?;
+                statement.closing_balance = Some(amount);
+                statement.period_to = Some(date);
+            }
+            "61" => statement.transactions.push(mt940_statement_line(&value, &currency)?),
+            "86" => {
+                if let Some(transaction) = statement.transactions.last_mut() {
+                    mt940_information(&value, transaction);
+                }
+            }
+            _ => {}
+        }
+    }
+    if statements.iter().all(|s| s.transactions.is_empty() && s.closing_balance.is_none()) {
+        return Err(BankError::InvalidFile("no MT940 statement found".to_string()));
+    }
+    Ok(statements)
+}
+
+// ---------------------------------------------------------------------------
+// OFX
+// ---------------------------------------------------------------------------
+
+/// `(TAG, value)` pairs of an OFX body; closing tags come as `/TAG` with an empty value.
+/// Works for SGML (unclosed leaf elements) and XML alike.
+fn ofx_tokens(text: &str) -> Vec<(String, String)> {
+    let body = text.find("<OFX>").map(|start| &text[start..]).unwrap_or(text);
+    body.split('<')
+        .skip(1)
+        .filter_map(|chunk| {
+            let (tag, value) = chunk.split_once('>')?;
+            Some((tag.trim().to_uppercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// `YYYYMMDD[HHMMSS[.XXX][[-5:EST]]]`
+fn ofx_date(value: &str) -> Result<NaiveDate, BankError> {
+    value
+        .get(..8)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        .ok_or_else(|| BankError::InvalidDate(value.to_string()))
+}
+
+fn parse_ofx(text: &str, default_currency: &str) -> Result<Vec<ParsedStatement>, BankError> {
+    let mut statements: Vec<ParsedStatement> = Vec::new();
+    let mut transaction: Option<ParsedTransaction> = None;
+    let mut in_ledger_balance = false;
+    let mut currency = default_currency.to_string();
+
+    for (tag, value) in ofx_tokens(text) {
+        match tag.as_str() {
+            "STMTRS" | "CCSTMTRS" => statements.push(ParsedStatement::default()),
+            "LEDGERBAL" => in_ledger_balance = true,
+            "/LEDGERBAL" => in_ledger_balance = false,
+            "STMTTRN" => transaction = Some(ParsedTransaction::default()),
+            "/STMTTRN" => {
+                let statement = statements.last_mut().ok_or_else(|| BankError::InvalidFile("transaction outside a statement".into()))?;
+                let mut done = transaction.take().unwrap_or_default();
+                done.currency = Some(currency.clone());
+                statement.transactions.push(done);
+            }
+            _ => {}
+        }
+        let Some(statement) = statements.last_mut() else { continue };
+        if value.is_empty() {
+            continue;
+        }
+        if let Some(transaction) = transaction.as_mut() {
+            match tag.as_str() {
+                "DTPOSTED" => transaction.booking_date = Some(ofx_date(&value)?),
+                "DTAVAIL" => transaction.value_date = Some(ofx_date(&value)?),
+                "TRNAMT" => transaction.amount = amount_in(&value.replace(',', "."), &currency)?,
+                "FITID" => transaction.bank_reference = Some(value),
+                "NAME" | "PAYEE" => transaction.counterparty_name = Some(value),
+                "MEMO" => transaction.remittance = Some(value),
+                _ => {}
+            }
+            continue;
+        }
+        match tag.as_str() {
+            "CURDEF" => {
+                currency = normalize_currency(&value)?;
+                statement.currency = Some(currency.clone());
+            }
+            "ACCTID" => statement.account = statement.account.take().or(Some(value)),
+            "DTSTART" => statement.period_from = Some(ofx_date(&value)?),
+            "DTEND" => statement.period_to = Some(ofx_date(&value)?),
+            "BALAMT" if in_ledger_balance => statement.closing_balance = Some(amount_in(&value.replace(',', "."), &currency)?),
+            _ => {}
+        }
+    }
+    if statements.is_empty() {
+        return Err(BankError::InvalidFile("no STMTRS element".to_string()));
+    }
+    Ok(statements)
+}
+
+// ---------------------------------------------------------------------------
+// CSV
+// ---------------------------------------------------------------------------
+
+/// Splits one CSV line, honouring double quotes (`""` inside quotes is a quote)
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+fn csv_amount(value: &str, decimal_separator: char, currency: &str) -> Result<i64, BankError> {
+    let thousands = if decimal_separator == ',' { '.' } else { ',' };
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '\'' | '’') && *c != thousands)
+        .map(|c| if c == decimal_separator { '.' } else { c })
+        .collect();
+    // Some banks write the sign last, e.g. `12.50-`
+    let cleaned = match cleaned.strip_suffix('-') {
+        Some(rest) => format!("-{}", rest),
+        None => cleaned,
+    };
+    amount_in(&cleaned, currency).map_err(|_| BankError::InvalidAmount(value.to_string()))
+}
+
+fn parse_csv(text: &str, format: &BankCsvFormat, default_currency: &str) -> Result<ParsedStatement, BankError> {
+    let mut lines = text.lines().skip(format.skip_lines).filter(|line| !line.trim().is_empty());
+    let header = lines.next().ok_or_else(|| BankError::InvalidFile("the file is empty".to_string()))?;
+    let header = header.trim_start_matches('\u{feff}');
+    let delimiter = format
+        .delimiter
+        .unwrap_or_else(|| [';', '\t', ','].into_iter().find(|d| header.contains(*d)).unwrap_or(','));
+    let columns = split_csv_line(header, delimiter);
+    let find = |name: &Option<String>| -> Result<Option<usize>, BankError> {
+        let Some(name) = name.as_deref().map(str::trim).filter(|n| !n.is_empty()) else {
+            return Ok(None);
+        };
+        columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(name))
+            .map(Some)
+            .ok_or_else(|| BankError::InvalidFile(format!("no {:?} column in the header", name)))
+    };
+
+    let date_column = find(&Some(format.date_column.clone()))?.unwrap();
+    let value_date_column = find(&format.value_date_column)?;
+    let amount_column = find(&format.amount_column)?;
+    let credit_column = find(&format.credit_column)?;
+    let debit_column = find(&format.debit_column)?;
+    if amount_column.is_none() && credit_column.is_none() && debit_column.is_none() {
+        return Err(BankError::InvalidFile("set an amount column or credit and debit columns".to_string()));
+    }
+    let currency_column = find(&format.currency_column)?;
+    let balance_column = find(&format.balance_column)?;
+    let reference_column = find(&format.reference_column)?;
+    let description_column = find(&format.description_column)?;
+    let counterparty_column = find(&format.counterparty_column)?;
+    let counterparty_iban_column = find(&format.counterparty_iban_column)?;
+
+    let parse_day = |value: &str| -> Result<NaiveDate, BankError> {
+        match &format.date_format {
+            Some(pattern) => NaiveDate::parse_from_str(value.trim(), pattern).ok(),
+            None => parse_date(value),
+        }
+        .ok_or_else(|| BankError::InvalidDate(value.to_string()))
+    };
+
+    let mut statement = ParsedStatement::default();
+    let mut balances = Vec::new();
+    for line in lines {
+        let fields = split_csv_line(line, delimiter);
+        let field = |column: Option<usize>| column.and_then(|c| fields.get(c)).map(String::as_str).unwrap_or_default();
+        let currency = match non_empty(field(currency_column)) {
+            Some(code) => normalize_currency(&code)?,
+            None => default_currency.to_string(),
+        };
+        let amount = match amount_column {
+            Some(_) => csv_amount(field(amount_column), format.decimal_separator, &currency)?,
+            None => {
+                let (credit, debit) = (field(credit_column), field(debit_column));
+                if credit.trim().is_empty() && debit.trim().is_empty() {
+                    continue;
+                }
+                let part = |value: &str| match value.trim() {
+                    "" => Ok(0),
+                    value => csv_amount(value, format.decimal_separator, &currency).map(i64::abs),
+                };
+                part(credit)? - part(debit)?
+            }
+        };
+        let booking_date = parse_day(field(Some(date_column)))?;
+        if let Some(balance) = non_empty(field(balance_column)) {
+            balances.push((booking_date, csv_amount(&balance, format.decimal_separator, &currency)?, amount));
+        }
+        statement.transactions.push(ParsedTransaction {
+            booking_date: Some(booking_date),
+            value_date: non_empty(field(value_date_column)).map(|v| parse_day(&v)).transpose()?,
+            amount,
+            currency: Some(currency),
+            reference: non_empty(field(reference_column)),
+            remittance: non_empty(field(description_column)),
+            counterparty_name: non_empty(field(counterparty_column)),
+            counterparty_iban: non_empty(field(counterparty_iban_column)),
+            ..Default::default()
+        });
+    }
+
+    let dates = statement.transactions.iter().filter_map(|t| t.booking_date);
+    statement.period_from = dates.clone().min();
+    statement.period_to = dates.max();
+    // Exports are in date order either way; the running balance tells the ends apart
+    if let (Some(first), Some(last)) = (balances.first(), balances.last()) {
+        let (oldest, newest) = if first.0 > last.0 { (last, first) } else { (first, last) };
+        statement.opening_balance = Some(oldest.1 - oldest.2);
+        statement.closing_balance = Some(newest.1);
+    }
+    Ok(statement)
+}
+
+// ---------------------------------------------------------------------------
+// Storage
+// ---------------------------------------------------------------------------
+
+/// Stable import key of a transaction without a bank-assigned id; `occurrence` counts
+/// identical transactions within one statement so they are not merged
+fn synthetic_key(transaction: &ParsedTransaction, currency: &str, occurrence: usize) -> String {
+    let key = format!(
+        "{:?}|{}|{}|{}|{}|{}|{}|{}",
+        transaction.booking_date,
+        transaction.amount,
+        currency,
+        transaction.reference.as_deref().unwrap_or_default(),
+        transaction.counterparty_name.as_deref().unwrap_or_default(),
+        transaction.counterparty_iban.as_deref().unwrap_or_default(),
+        transaction.remittance.as_deref().unwrap_or_default(),
+        occurrence
+    );
+    let hash = digest(&SHA256, key.as_bytes());
+    let hex: String = hash.as_ref()[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("h:{}", hex)
+}
+
+/// Id and currency of the account, created on first import
+fn resolve_account(conn: &Connection, identifier: &str, currency: Option<&str>) -> Result<(i64, String), BankError> {
+    let existing: Option<(i64, String)> = conn
+        .query_row("SELECT id, currency FROM bank_accounts WHERE identifier = ?1", [identifier], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()?;
+    if let Some(account) = existing {
+        return Ok(account);
+    }
+    let currency = currency_or_base(conn, currency)?;
+    conn.execute("INSERT INTO bank_accounts (identifier, currency) VALUES (?1, ?2)", params![identifier, currency])?;
+    Ok((conn.last_insert_rowid(), currency))
+}
+
+fn day(date: Option<NaiveDate>) -> Option<String> {
+    date.map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+fn store_statement(
+    conn: &Connection,
+    statement: ParsedStatement,
+    format: StatementFormat,
+    options: &ImportOptions,
+) -> Result<ImportSummary, BankError> {
+    let identifier = options
+        .account
+        .as_deref()
+        .or(statement.account.as_deref())
+        .map(normalize_account)
+        .filter(|a| !a.is_empty())
+        .ok_or(BankError::MissingAccount)?;
+    let (account_id, account_currency) = resolve_account(conn, &identifier, statement.currency.as_deref())?;
+
+    // Importing the same statement again reuses its record
+    let (period_from, period_to) = (day(statement.period_from), day(statement.period_to));
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM bank_statements
+             WHERE account_id = ?1 AND format = ?2 AND statement_ref IS ?3 AND period_from IS ?4 AND period_to IS ?5
+               AND opening_balance IS ?6 AND closing_balance IS ?7",
+            params![
+                account_id,
+                format.as_str(),
+                statement.statement_ref,
+                period_from,
+                period_to,
+                statement.opening_balance,
+                statement.closing_balance
+            ],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let statement_id = match existing {
+        Some(id) => id,
+        None => {
+            conn.execute(
+                "INSERT INTO bank_statements (account_id, format, statement_ref, period_from, period_to,
+                                              opening_balance, closing_balance, file_name)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    account_id,
+                    format.as_str(),
+                    statement.statement_ref,
+                    period_from,
+                    period_to,
+                    statement.opening_balance,
+                    statement.closing_balance,
+                    options.file_name
+                ],
+            )?;
+            conn.last_insert_rowid()
+        }
+    };
+
+    let mut summary = ImportSummary {
+        account_id,
+        statement_id,
+        format,
+        imported: 0,
+        duplicates: 0,
+        opening_balance: statement.opening_balance,
+        closing_balance: statement.closing_balance,
+    };
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    for mut transaction in statement.transactions {
+        let booking_date = transaction
+            .booking_date
+            .or(transaction.value_date)
+            .ok_or_else(|| BankError::InvalidFile("transaction without a date".to_string()))?;
+        let currency = transaction.currency.take().unwrap_or_else(|| account_currency.clone());
+        if transaction.structured_reference.is_none() {
+            transaction.structured_reference = transaction.remittance.as_deref().and_then(find_structured_reference);
+        }
+        transaction.reference = transaction.reference.as_deref().and_then(non_empty);
+        let bank_reference = transaction.bank_reference.as_deref().and_then(non_empty);
+        let import_key = match &bank_reference {
+            Some(bank_reference) => bank_reference.clone(),
+            None => {
+                let base = synthetic_key(&transaction, &currency, 0);
+                let occurrence = occurrences.entry(base).or_insert(0);
+                *occurrence += 1;
+                synthetic_key(&transaction, &currency, *occurrence - 1)
+            }
+        };
+
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO bank_transactions (account_id, statement_id, booking_date, value_date, amount, currency,
+                                                      reference, import_key, remittance, structured_reference,
+                                                      counterparty_name, counterparty_iban)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                account_id,
+                statement_id,
+                booking_date.format("%Y-%m-%d").to_string(),
+                day(transaction.value_date),
+                transaction.amount,
+                currency,
+                transaction.reference.or(bank_reference),
+                import_key,
+                transaction.remittance,
+                transaction.structured_reference,
+                transaction.counterparty_name,
+                transaction.counterparty_iban.as_deref().map(normalize_account)
+            ],
+        )?;
+        if inserted == 0 {
+            summary.duplicates += 1;
+        } else {
+            summary.imported += 1;
+        }
+    }
+    Ok(summary)
+}
+
+/// CSV columns from `settings.bank_csv_format` (JSON), or the defaults
+fn csv_format_setting(conn: &Connection) -> Result<BankCsvFormat, BankError> {
+    match read_setting(conn, "bank_csv_format")? {
+        Some(json) if !json.trim().is_empty() => {
+            serde_json::from_str(&json).map_err(|e| BankError::InvalidValue("bank_csv_format", e.to_string()))
+        }
+        _ => Ok(BankCsvFormat::default()),
+    }
+}
+
+/// Imports every statement of a file; transactions already known by import key are skipped
+pub fn import(conn: &mut Connection, text: &str, options: &ImportOptions) -> Result<Vec<ImportSummary>, BankError> {
+    let format = StatementFormat::detect(text);
+    // Amounts without a currency of their own are in the currency of the target account
+    let default_currency: String = match options.account.as_deref().map(normalize_account) {
+        Some(identifier) => conn
+            .query_row("SELECT currency FROM bank_accounts WHERE identifier = ?1", [identifier], |row| row.get(0))
+            .optional()?
+            .map(Ok)
+            .unwrap_or_else(|| currency_or_base(conn, None))?,
+        None => currency_or_base(conn, None)?,
+    };
+    let statements = match format {
+        StatementFormat::Camt053 => parse_camt053(text)?,
+        StatementFormat::Mt940 => parse_mt940(text, &default_currency)?,
+        StatementFormat::Ofx => parse_ofx(text, &default_currency)?,
+        StatementFormat::Csv => {
+            let csv_format = match &options.csv_format {
+                Some(csv_format) => csv_format.clone(),
+                None => csv_format_setting(conn)?,
+            };
+            vec![parse_csv(text, &csv_format, &default_currency)?]
+        }
+    };
+
+    let tx = conn.transaction()?;
+    let summaries = statements
+        .into_iter()
+        .map(|statement| store_statement(&tx, statement, format, options))
+        .collect::<Result<Vec<_>, _>>()?;
+    tx.commit()?;
+    Ok(summaries)
+}
+
+const TRANSACTION_COLUMNS: &str = "id, account_id, statement_id, booking_date, value_date, amount, currency, reference,
+     remittance, structured_reference, counterparty_name, counterparty_iban";
+
+fn transaction_from_row(row: &rusqlite::Row) -> rusqlite::Result<BankTransaction> {
+    Ok(BankTransaction {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        statement_id: row.get(2)?,
+        booking_date: row.get(3)?,
+        value_date: row.get(4)?,
+        amount: row.get(5)?,
+        currency: row.get(6)?,
+        reference: row.get(7)?,
+        remittance: row.get(8)?,
+        structured_reference: row.get(9)?,
+        counterparty_name: row.get(10)?,
+        counterparty_iban: row.get(11)?,
+    })
+}
+
+/// Transactions matching `filter`, newest first
+pub fn list_transactions(conn: &Connection, filter: &BankTransactionFilter) -> Result<Vec<BankTransaction>, BankError> {
+    let day = |field: &'static str, value: &Option<String>| {
+        value
+            .as_deref()
+            .map(|v| parse_date(v).map(|d| d.format("%Y-%m-%d").to_string()).ok_or_else(|| BankError::InvalidValue(field, v.to_string())))
+            .transpose()
+    };
+    let (from, to) = (day("from", &filter.from)?, day("to", &filter.to)?);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM bank_transactions
+         WHERE (?1 IS NULL OR account_id = ?1) AND (?2 IS NULL OR statement_id = ?2)
+           AND (?3 IS NULL OR booking_date >= ?3) AND (?4 IS NULL OR booking_date <= ?4)
+         ORDER BY booking_date DESC, id DESC",
+        TRANSACTION_COLUMNS
+    ))?;
+    let transactions = stmt
+        .query_map(params![filter.account_id, filter.statement_id, from, to], transaction_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(transactions)
+}
+
+pub fn list_accounts(conn: &Connection) -> Result<Vec<BankAccount>, BankError> {
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.identifier, a.name, a.currency,
+                (SELECT closing_balance FROM bank_statements s
+                 WHERE s.account_id = a.id AND s.closing_balance IS NOT NULL ORDER BY s.period_to DESC, s.id DESC LIMIT 1),
+                (SELECT period_to FROM bank_statements s
+                 WHERE s.account_id = a.id AND s.closing_balance IS NOT NULL ORDER BY s.period_to DESC, s.id DESC LIMIT 1),
+                (SELECT COUNT(*) FROM bank_transactions t WHERE t.account_id = a.id)
+         FROM bank_accounts a ORDER BY a.name, a.identifier",
+    )?;
+    let accounts = stmt
+        .query_map([], |row| {
+            Ok(BankAccount {
+                id: row.get(0)?,
+                identifier: row.get(1)?,
+                name: row.get(2)?,
+                currency: row.get(3)?,
+                balance: row.get(4)?,
+                balance_date: row.get(5)?,
+                transaction_count: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(accounts)
+}
+
+/// Deletes an imported statement with its transactions
+pub fn delete_statement(conn: &mut Connection, statement_id: i64) -> Result<(), BankError> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM bank_transactions WHERE statement_id = ?1", [statement_id])?;
+    if tx.execute("DELETE FROM bank_statements WHERE id = ?1", [statement_id])? == 0 {
+        return Err(BankError::StatementNotFound(statement_id));
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// 📥 Import a CAMT.053, MT940, OFX or CSV statement file. `account` names the account for
+/// files that do not (CSV); `csv_format` overrides `settings.bank_csv_format`.
+#[tauri::command]
+pub fn import_bank_statement(
+    state: tauri::State<StateWrapper>,
+    path: String,
+    account: Option<String>,
+    csv_format: Option<BankCsvFormat>,
+) -> Result<Vec<ImportSummary>, BankError> {
+    let text = std::fs::read(&path).map_err(|e| BankError::IoError(path.clone(), e))?;
+    // Older exports are often Latin-1 rather than UTF-8
+    let text = match String::from_utf8(text) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    };
+    let options = ImportOptions {
+        account,
+        csv_format,
+        file_name: Path::new(&path).file_name().map(|n| n.to_string_lossy().into_owned()),
+    };
+    let mut db_conn = open_state_db(&state)?;
+    import(&mut db_conn, &text, &options)
+}
+
+/// 🏦 List bank accounts with their latest statement balance
+#[tauri::command]
+pub fn list_bank_accounts(state: tauri::State<StateWrapper>) -> Result<Vec<BankAccount>, BankError> {
+    let db_conn = open_state_db(&state)?;
+    list_accounts(&db_conn)
+}
+
+/// ✏️ Name a bank account
+#[tauri::command]
+pub fn rename_bank_account(state: tauri::State<StateWrapper>, account_id: i64, name: Option<String>) -> Result<(), BankError> {
+    let db_conn = open_state_db(&state)?;
+    let name = name.as_deref().and_then(non_empty);
+    if db_conn.execute("UPDATE bank_accounts SET name = ?1 WHERE id = ?2", params![name, account_id])? == 0 {
+        return Err(BankError::AccountNotFound(account_id));
+    }
+    Ok(())
+}
+
+/// 📄 List the imported statements of an account, newest first
+#[tauri::command]
+pub fn list_bank_statements(state: tauri::State<StateWrapper>, account_id: i64) -> Result<Vec<BankStatement>, BankError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, account_id, format, statement_ref, period_from, period_to, opening_balance, closing_balance,
+                file_name, imported_at
+         FROM bank_statements WHERE account_id = ?1 ORDER BY period_to DESC, id DESC",
+    )?;
+    let statements = stmt
+        .query_map([account_id], |row| {
+            Ok(BankStatement {
+                id: row.get(0)?,
+                account_id: row.get(1)?,
+                format: row.get(2)?,
+                statement_ref: row.get(3)?,
+                period_from: row.get(4)?,
+                period_to: row.get(5)?,
+                opening_balance: row.get(6)?,
+                closing_balance: row.get(7)?,
+                file_name: row.get(8)?,
+                imported_at: row.get(9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(statements)
+}
+
+/// 🗑️ Delete an imported statement and its transactions
+#[tauri::command]
+pub fn delete_bank_statement(state: tauri::State<StateWrapper>, statement_id: i64) -> Result<(), BankError> {
+    let mut db_conn = open_state_db(&state)?;
+    delete_statement(&mut db_conn, statement_id)
+}
+
+/// 📋 List bank transactions by account, statement and date range
+#[tauri::command]
+pub fn list_bank_transactions(
+    state: tauri::State<StateWrapper>,
+    filter: Option<BankTransactionFilter>,
+) -> Result<Vec<BankTransaction>, BankError> {
+    let db_conn = open_state_db(&state)?;
+    list_transactions(&db_conn, &filter.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    const CAMT053: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.04">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Id>STMT-2025-01</Id>
+      <FrToDt><FrDtTm>2025-01-01T00:00:00</FrDtTm><ToDtTm>2025-01-31T23:59:59</ToDtTm></FrToDt>
+      <Acct><Id><IBAN>CH9300762011623852957</IBAN></Id><Ccy>CHF</Ccy></Acct>
+      <Bal><Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp><Amt Ccy="CHF">1000.00</Amt><CdtDbtInd>CRDT</CdtDbtInd></Bal>
+      <Bal><Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp><Amt Ccy="CHF">1450.50</Amt><CdtDbtInd>CRDT</CdtDbtInd></Bal>
+      <Ntry>
+        <Amt Ccy="CHF">250.50</Amt><CdtDbtInd>CRDT</CdtDbtInd><Sts>BOOK</Sts>
+        <BookgDt><Dt>2025-01-10</Dt></BookgDt><ValDt><Dt>2025-01-11</Dt></ValDt>
+        <AcctSvcrRef>ENTRY-1</AcctSvcrRef>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>NOTPROVIDED</EndToEndId><TxId>TX-1</TxId></Refs>
+          <RltdPties>
+            <Dbtr><Nm>Muster AG</Nm></Dbtr>
+            <DbtrAcct><Id><IBAN>CH5800791123000889012</IBAN></Id></DbtrAcct>
+          </RltdPties>
+          <RmtInf><Strd><CdtrRefInf><Ref>RF18 5390 0754 7034</Ref></CdtrRefInf></Strd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="CHF">100.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>BOOK</Sts>
+        <BookgDt><Dt>2025-01-20</Dt></BookgDt>
+        <AcctSvcrRef>ENTRY-2</AcctSvcrRef>
+        <AddtlNtryInf>Office rent</AddtlNtryInf>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="CHF">300.00</Amt><CdtDbtInd>CRDT</CdtDbtInd><Sts>BOOK</Sts>
+        <BookgDt><Dt>2025-01-25</Dt></BookgDt>
+        <AcctSvcrRef>ENTRY-3</AcctSvcrRef>
+        <NtryDtls>
+          <TxDtls><AmtDtls><TxAmt><Amt Ccy="CHF">100.00</Amt></TxAmt></AmtDtls><Refs><EndToEndId>E2E-A</EndToEndId></Refs></TxDtls>
+          <TxDtls><AmtDtls><TxAmt><Amt Ccy="CHF">200.00</Amt></TxAmt></AmtDtls><Refs><EndToEndId>E2E-B</EndToEndId></Refs></TxDtls>
+        </NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="CHF">5.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>PDNG</Sts>
+        <AcctSvcrRef>ENTRY-4</AcctSvcrRef>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    const MT940: &str = ":20:STMT1
+:25:CH9300762011623852957
+:28C:1/1
+:60F:C250101CHF1000,00
+:61:2501050105C1500,00NTRFNONREF//BANKREF123
+:86:166?00GUTSCHRIFT?20Invoice 2025-0042?31CH5800791123000889012
+?32Muster AG
+:61:250110D200,50NMSCREF-OUT
+:86:Office supplies
+:62F:C250131CHF2299,50
+-";
+
+    const OFX: &str = "OFXHEADER:100
+DATA:OFXSGML
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>EUR
+<BANKACCTFROM><BANKID>37040044<ACCTID>DE89370400440532013000<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST><DTSTART>20250101<DTEND>20250131
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20250115120000[+1:CET]<TRNAMT>1234.56<FITID>FIT-1<NAME>Muster GmbH<MEMO>Invoice 42</STMTTRN>
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20250120<TRNAMT>-45,00<FITID>FIT-2<NAME>Telco</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>5000.00<DTASOF>20250131</LEDGERBAL>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>";
+
+    const CSV: &str = "Account summary
+Date;Value date;Amount;Currency;Reference;Description;Balance
+31.01.2025;31.01.2025;-1'250,50;CHF;REF-2;\"Rent; \"\"January\"\"\";8749,50
+15.01.2025;16.01.2025;10000,00;CHF;REF-1;Invoice 42;10000,00
+";
+
+    #[test]
+    fn detect_recognises_each_format() {
+        assert_eq!(StatementFormat::detect(CAMT053), StatementFormat::Camt053);
+        assert_eq!(StatementFormat::detect(MT940), StatementFormat::Mt940);
+        assert_eq!(StatementFormat::detect(OFX), StatementFormat::Ofx);
+        assert_eq!(StatementFormat::detect(CSV), StatementFormat::Csv);
+    }
+
+    #[test]
+    fn parse_camt053_reads_entries_batches_and_balances() {
+        let statements = parse_camt053(CAMT053).unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.account.as_deref(), Some("CH9300762011623852957"));
+        assert_eq!(statement.statement_ref.as_deref(), Some("STMT-2025-01"));
+        assert_eq!((statement.period_from, statement.period_to), (date(2025, 1, 1), date(2025, 1, 31)));
+        assert_eq!((statement.opening_balance, statement.closing_balance), (Some(100000), Some(145050)));
+
+        // The pending entry is skipped and the batch is split into its payments
+        let transactions = &statement.transactions;
+        assert_eq!(transactions.iter().map(|t| t.amount).collect::<Vec<_>>(), vec![25050, -10000, 10000, 20000]);
+
+        let incoming = &transactions[0];
+        assert_eq!((incoming.booking_date, incoming.value_date), (date(2025, 1, 10), date(2025, 1, 11)));
+        assert_eq!(incoming.bank_reference.as_deref(), Some("ENTRY-1"));
+        assert_eq!(incoming.reference.as_deref(), Some("TX-1"));
+        assert_eq!(incoming.structured_reference.as_deref(), Some("RF18539007547034"));
+        assert_eq!(incoming.counterparty_name.as_deref(), Some("Muster AG"));
+        assert_eq!(incoming.counterparty_iban.as_deref(), Some("CH5800791123000889012"));
+
+        let rent = &transactions[1];
+        assert_eq!(rent.bank_reference.as_deref(), Some("ENTRY-2"));
+        assert_eq!(rent.reference, None);
+        assert_eq!(rent.remittance.as_deref(), Some("Office rent"));
+
+        assert_eq!(transactions[2].bank_reference.as_deref(), Some("ENTRY-3/1"));
+        assert_eq!(transactions[2].reference.as_deref(), Some("E2E-A"));
+        assert_eq!(transactions[3].bank_reference.as_deref(), Some("ENTRY-3/2"));
+    }
+
+    #[test]
+    fn parse_camt053_rejects_other_xml() {
+        assert!(matches!(parse_camt053("<Document><Other/></Document>"), Err(BankError::InvalidFile(_))));
+    }
+
+    #[test]
+    fn parse_mt940_reads_lines_and_information() {
+        let statements = parse_mt940(MT940, "EUR").unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.account.as_deref(), Some("CH9300762011623852957"));
+        assert_eq!(statement.statement_ref.as_deref(), Some("STMT1 1/1"));
+        assert_eq!(statement.currency.as_deref(), Some("CHF"));
+        assert_eq!((statement.opening_balance, statement.closing_balance), (Some(100000), Some(229950)));
+        assert_eq!((statement.period_from, statement.period_to), (date(2025, 1, 1), date(2025, 1, 31)));
+
+        let incoming = &statement.transactions[0];
+        assert_eq!(incoming.amount, 150000);
+        assert_eq!(incoming.currency.as_deref(), Some("CHF"));
+        assert_eq!((incoming.booking_date, incoming.value_date), (date(2025, 1, 5), date(2025, 1, 5)));
+        assert_eq!(incoming.reference.as_deref(), Some("BANKREF123"));
+        assert_eq!(incoming.remittance.as_deref(), Some("Invoice 2025-0042"));
+        assert_eq!(incoming.counterparty_name.as_deref(), Some("Muster AG"));
+        assert_eq!(incoming.counterparty_iban.as_deref(), Some("CH5800791123000889012"));
+
+        let outgoing = &statement.transactions[1];
+        assert_eq!(outgoing.amount, -20050);
+        assert_eq!(outgoing.booking_date, date(2025, 1, 10));
+        assert_eq!(outgoing.reference.as_deref(), Some("REF-OUT"));
+        assert_eq!(outgoing.remittance.as_deref(), Some("Office supplies"));
+    }
+
+    #[test]
+    fn parse_ofx_reads_sgml_statements() {
+        let statements = parse_ofx(OFX, "CHF").unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.account.as_deref(), Some("DE89370400440532013000"));
+        assert_eq!(statement.currency.as_deref(), Some("EUR"));
+        assert_eq!((statement.period_from, statement.period_to), (date(2025, 1, 1), date(2025, 1, 31)));
+        assert_eq!(statement.closing_balance, Some(500000));
+
+        let credit = &statement.transactions[0];
+        assert_eq!(credit.amount, 123456);
+        assert_eq!(credit.currency.as_deref(), Some("EUR"));
+        assert_eq!(credit.booking_date, date(2025, 1, 15));
+        assert_eq!(credit.bank_reference.as_deref(), Some("FIT-1"));
+        assert_eq!(credit.counterparty_name.as_deref(), Some("Muster GmbH"));
+        assert_eq!(credit.remittance.as_deref(), Some("Invoice 42"));
+
+        let debit = &statement.transactions[1];
+        assert_eq!(debit.amount, -4500);
+        assert_eq!(debit.bank_reference.as_deref(), Some("FIT-2"));
+    }
+
+    #[test]
+    fn parse_csv_reads_configured_columns_and_balances() {
+        let format = BankCsvFormat {
+            decimal_separator: ',',
+            skip_lines: 1,
+            value_date_column: Some("Value date".to_string()),
+            currency_column: Some("Currency".to_string()),
+            description_column: Some("Description".to_string()),
+            balance_column: Some("Balance".to_string()),
+            ..Default::default()
+        };
+        let statement = parse_csv(CSV, &format, "EUR").unwrap();
+        assert_eq!((statement.period_from, statement.period_to), (date(2025, 1, 15), date(2025, 1, 31)));
+        // Newest first in the file: the oldest row's balance less its amount opens the statement
+        assert_eq!((statement.opening_balance, statement.closing_balance), (Some(0), Some(874950)));
+
+        let rent = &statement.transactions[0];
+        assert_eq!(rent.amount, -125050);
+        assert_eq!(rent.currency.as_deref(), Some("CHF"));
+        assert_eq!(rent.reference.as_deref(), Some("REF-2"));
+        assert_eq!(rent.remittance.as_deref(), Some("Rent; \"January\""));
+        assert_eq!(statement.transactions[1].value_date, date(2025, 1, 16));
+    }
+
+    #[test]
+    fn parse_csv_combines_credit_and_debit_columns() {
+        let format = BankCsvFormat {
+            amount_column: None,
+            credit_column: Some("In".to_string()),
+            debit_column: Some("Out".to_string()),
+            description_column: None,
+            ..Default::default()
+        };
+        let text = "Date,In,Out,Reference\n2025-02-01,12.50,,A\n2025-02-02,,7.25-,B\n2025-02-03,,,\n";
+        let statement = parse_csv(text, &format, "CHF").unwrap();
+        assert_eq!(statement.transactions.iter().map(|t| t.amount).collect::<Vec<_>>(), vec![1250, -725]);
+    }
+
+    #[test]
+    fn parse_csv_reports_missing_columns() {
+        assert!(matches!(parse_csv("Day;Amount\n", &BankCsvFormat::default(), "CHF"), Err(BankError::InvalidFile(_))));
+    }
+
+    #[test]
+    fn find_structured_reference_finds_grouped_references() {
+        assert_eq!(
+            find_structured_reference("Payment 21 00000 00003 13947 14300 09017, thank you").as_deref(),
+            Some("210000000003139471430009017")
+        );
+        assert_eq!(find_structured_reference("Ref: rf18 5390 0754 7034").as_deref(), Some("RF18539007547034"));
+        assert_eq!(find_structured_reference("Invoice 2025-0042"), None);
+    }
+
+    #[test]
+    fn synthetic_key_is_stable_and_counts_occurrences() {
+        let transaction = ParsedTransaction {
+            booking_date: date(2025, 1, 10),
+            amount: 5000,
+            reference: Some("REF-1".to_string()),
+            ..Default::default()
+        };
+        let key = synthetic_key(&transaction, "CHF", 0);
+        assert!(key.starts_with("h:"));
+        assert_eq!(key, synthetic_key(&transaction, "CHF", 0));
+        assert_ne!(key, synthetic_key(&transaction, "CHF", 1));
+        assert_ne!(key, synthetic_key(&transaction, "EUR", 0));
+    }
+}
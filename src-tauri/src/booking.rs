@@ -0,0 +1,441 @@
+//! Inbox of appointment requests from clients.
+//!
+//! Requests arrive through a drop folder (`<data dir>/buffmod/inbox`, or the `booking_inbox_dir`
+//! setting) that the scheduler scans every tick, so they can be produced offline by a web form
+//! export, a mail filter or by hand. Supported files:
+//! - `.json`: one object or an array of objects with `start` (required), `end` or
+//!   `duration_minutes`, `title`, `name`, `email`, `phone`, `message` and an optional unique `id`
+//! - `.ics`: every VEVENT becomes a request; the ORGANIZER is the requester
+//!
+//! Imported files are moved to `processed/`, unreadable ones to `failed/`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use thiserror::Error;
+
+use crate::db_api::{insert_event, open_state_db, parse_db_timestamp, DbApiError, Event, DB_TIMESTAMP_FORMAT};
+use crate::ical;
+use crate::settings::read_setting;
+use crate::storage::{data_subdir, StorageError};
+use crate::StateWrapper;
+
+/// Name of the app event emitted when the importer adds requests to the inbox
+pub const BOOKING_INBOX_EVENT: &str = "booking-requests-imported";
+
+/// Length of a requested slot when the file gives neither `end` nor `duration_minutes`
+const DEFAULT_DURATION_MINUTES: i64 = 60;
+
+#[derive(Debug, Error)]
+pub enum BookingError {
+    #[error("[booking.rs::not_found] Booking request {0} does not exist.")]
+    NotFound(i64),
+
+    #[error("[booking.rs::invalid_state] Booking request {0} is already {1}.")]
+    AlreadyClosed(i64, String),
+
+    #[error("[booking.rs::missing_contact] The request has no email address to create a client from.")]
+    MissingContact,
+
+    #[error("[booking.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[booking.rs::io] Could not read the inbox: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("[booking.rs::json] Invalid booking file: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("[booking.rs::storage] {0}")]
+    StorageError(#[from] StorageError),
+
+    #[error("[booking.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[booking.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for BookingError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 📥 Booking Request Struct
+#[derive(Serialize, Deserialize)]
+pub struct BookingRequest {
+    pub id: Option<i64>,
+    pub source: String,
+    pub requester_name: Option<String>,
+    pub requester_email: Option<String>,
+    pub requester_phone: Option<String>,
+    pub client_id: Option<i64>,
+    pub title: String,
+    pub message: Option<String>,
+    pub requested_start: String,
+    pub requested_end: String,
+    pub proposed_start: Option<String>,
+    pub proposed_end: Option<String>,
+    /// `Pending`, `Proposed`, `Accepted` or `Declined`
+    pub status: String,
+    pub decline_reason: Option<String>,
+    pub event_id: Option<i64>,
+    pub created_at: Option<String>,
+}
+
+/// 📊 Outcome of an inbox import
+#[derive(Serialize, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Requests already in the inbox from an earlier import
+    pub duplicates: usize,
+    /// Files that could not be read, with the reason
+    pub failed: Vec<String>,
+}
+
+/// A request read from a drop-folder file, before it is stored
+struct IncomingRequest {
+    external_id: String,
+    source: &'static str,
+    name: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    title: String,
+    message: Option<String>,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonBookingFile {
+    Many(Vec<JsonBooking>),
+    One(JsonBooking),
+}
+
+#[derive(Deserialize)]
+struct JsonBooking {
+    id: Option<String>,
+    name: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    title: Option<String>,
+    message: Option<String>,
+    start: String,
+    end: Option<String>,
+    duration_minutes: Option<i64>,
+}
+
+const REQUEST_COLUMNS: &str = "id, source, requester_name, requester_email, requester_phone, client_id, title, message,
+     requested_start, requested_end, proposed_start, proposed_end, status, decline_reason, event_id, created_at";
+
+fn request_from_row(row: &rusqlite::Row) -> rusqlite::Result<BookingRequest> {
+    Ok(BookingRequest {
+        id: row.get(0)?,
+        source: row.get(1)?,
+        requester_name: row.get(2)?,
+        requester_email: row.get(3)?,
+        requester_phone: row.get(4)?,
+        client_id: row.get(5)?,
+        title: row.get(6)?,
+        message: row.get(7)?,
+        requested_start: row.get(8)?,
+        requested_end: row.get(9)?,
+        proposed_start: row.get(10)?,
+        proposed_end: row.get(11)?,
+        status: row.get(12)?,
+        decline_reason: row.get(13)?,
+        event_id: row.get(14)?,
+        created_at: row.get(15)?,
+    })
+}
+
+fn load_open_request(conn: &Connection, request_id: i64) -> Result<BookingRequest, BookingError> {
+    let sql = format!("SELECT {} FROM booking_requests WHERE id = ?1", REQUEST_COLUMNS);
+    let request = conn
+        .query_row(&sql, [request_id], request_from_row)
+        .optional()?
+        .ok_or(BookingError::NotFound(request_id))?;
+
+    match request.status.as_str() {
+        "Pending" | "Proposed" => Ok(request),
+        other => Err(BookingError::AlreadyClosed(request_id, other.to_string())),
+    }
+}
+
+fn normalize_time(field: &'static str, value: &str) -> Result<String, BookingError> {
+    parse_db_timestamp(value)
+        .map(|t| t.format(DB_TIMESTAMP_FORMAT).to_string())
+        .ok_or_else(|| BookingError::InvalidValue(field, value.to_string()))
+}
+
+/// Finds the client with the requester's email, or creates one
+fn link_or_create_client(conn: &Connection, request: &BookingRequest) -> Result<i64, BookingError> {
+    if let Some(client_id) = request.client_id {
+        return Ok(client_id);
+    }
+    let email = request
+        .requester_email
+        .as_deref()
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .ok_or(BookingError::MissingContact)?;
+
+    let existing: Option<i64> = conn
+        .query_row("SELECT id FROM clients WHERE lower(email) = lower(?1)", [email], |row| row.get(0))
+        .optional()?;
+    if let Some(client_id) = existing {
+        return Ok(client_id);
+    }
+
+    conn.execute(
+        "INSERT INTO clients (name, email, phone) VALUES (?1, ?2, ?3)",
+        params![request.requester_name.as_deref().unwrap_or(email), email, request.requester_phone],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Accepts a request: links or creates the client and books the event at the proposed
+/// time when one was offered, otherwise at the requested time
+pub fn accept_request(conn: &mut Connection, request_id: i64) -> Result<i64, BookingError> {
+    let tx = conn.transaction()?;
+    let request = load_open_request(&tx, request_id)?;
+    let client_id = link_or_create_client(&tx, &request)?;
+
+    let (start, end) = match (&request.proposed_start, &request.proposed_end) {
+        (Some(start), Some(end)) => (start.clone(), end.clone()),
+        _ => (request.requested_start.clone(), request.requested_end.clone()),
+    };
+    let event_id = insert_event(&tx, &Event {
+        id: None,
+        title: request.title.clone(),
+        start_date: start,
+        end_date: end,
+        client_id: Some(client_id as i32),
+        assigned_member_id: None,
+    })?;
+
+    tx.execute(
+        "UPDATE booking_requests SET status = 'Accepted', client_id = ?1, event_id = ?2, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?3",
+        params![client_id, event_id, request_id],
+    )?;
+    tx.commit()?;
+    Ok(event_id)
+}
+
+fn parse_json_file(name: &str, text: &str) -> Result<Vec<IncomingRequest>, BookingError> {
+    let bookings = match serde_json::from_str::<JsonBookingFile>(text)? {
+        JsonBookingFile::Many(bookings) => bookings,
+        JsonBookingFile::One(booking) => vec![booking],
+    };
+
+    bookings
+        .into_iter()
+        .enumerate()
+        .map(|(index, booking)| {
+            let start = parse_db_timestamp(&booking.start)
+                .ok_or_else(|| BookingError::InvalidValue("start", booking.start.clone()))?;
+            let end = match (&booking.end, booking.duration_minutes) {
+                (Some(end), _) => parse_db_timestamp(end).ok_or_else(|| BookingError::InvalidValue("end", end.clone()))?,
+                (None, Some(minutes)) => start + Duration::minutes(minutes),
+                (None, None) => start + Duration::minutes(DEFAULT_DURATION_MINUTES),
+            };
+            if end <= start {
+                return Err(BookingError::InvalidValue("end", booking.end.unwrap_or_default()));
+            }
+            Ok(IncomingRequest {
+                external_id: format!("json:{}", booking.id.unwrap_or_else(|| format!("{}#{}", name, index))),
+                source: "json",
+                title: booking
+                    .title
+                    .unwrap_or_else(|| format!("Appointment with {}", booking.name.as_deref().unwrap_or("client"))),
+                name: booking.name,
+                email: booking.email,
+                phone: booking.phone,
+                message: booking.message,
+                start,
+                end,
+            })
+        })
+        .collect()
+}
+
+fn parse_ics_file(text: &str) -> Result<Vec<IncomingRequest>, BookingError> {
+    ical::parse_events(text)
+        .into_iter()
+        .map(|event| {
+            let event = event.map_err(|e| BookingError::InvalidValue("VEVENT", e.to_string()))?;
+            Ok(IncomingRequest {
+                external_id: format!("ics:{}", event.uid),
+                source: "ics",
+                name: event.organizer.as_ref().and_then(|o| o.name.clone()),
+                email: event.organizer.as_ref().map(|o| o.email.clone()),
+                phone: None,
+                title: event.summary,
+                message: event.description,
+                start: event.start,
+                end: event.end,
+            })
+        })
+        .collect()
+}
+
+/// Stores incoming requests, skipping those already imported. Returns `(imported, duplicates)`.
+fn store_incoming(conn: &Connection, requests: &[IncomingRequest]) -> Result<(usize, usize), BookingError> {
+    let mut imported = 0;
+    for request in requests {
+        imported += conn.execute(
+            "INSERT OR IGNORE INTO booking_requests
+                (external_id, source, requester_name, requester_email, requester_phone, title, message,
+                 requested_start, requested_end)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                request.external_id,
+                request.source,
+                request.name,
+                request.email,
+                request.phone,
+                request.title,
+                request.message,
+                request.start.format(DB_TIMESTAMP_FORMAT).to_string(),
+                request.end.format(DB_TIMESTAMP_FORMAT).to_string(),
+            ],
+        )?;
+    }
+    Ok((imported, requests.len() - imported))
+}
+
+fn move_into(file: &Path, folder: &Path) -> Result<(), std::io::Error> {
+    fs::create_dir_all(folder)?;
+    let target = folder.join(file.file_name().unwrap_or_default());
+    fs::rename(file, target)
+}
+
+/// Imports every `.json` and `.ics` file found directly in `inbox`
+pub fn import_folder(conn: &Connection, inbox: &Path) -> Result<ImportReport, BookingError> {
+    let mut report = ImportReport::default();
+    let mut files: Vec<PathBuf> = fs::read_dir(inbox)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+
+    for file in files {
+        let extension = file.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let name = file.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+        let parsed = match extension.as_deref() {
+            Some("json") => fs::read_to_string(&file).map_err(BookingError::from).and_then(|t| parse_json_file(&name, &t)),
+            Some("ics") => fs::read_to_string(&file).map_err(BookingError::from).and_then(|t| parse_ics_file(&t)),
+            _ => continue,
+        };
+
+        match parsed {
+            Ok(requests) => {
+                let (imported, duplicates) = store_incoming(conn, &requests)?;
+                report.imported += imported;
+                report.duplicates += duplicates;
+                move_into(&file, &inbox.join("processed"))?;
+            }
+            Err(err) => {
+                report.failed.push(format!("{}: {}", name, err));
+                move_into(&file, &inbox.join("failed"))?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn inbox_dir(app: &AppHandle, conn: &Connection) -> Result<PathBuf, BookingError> {
+    match read_setting(conn, "booking_inbox_dir")? {
+        Some(dir) if !dir.trim().is_empty() => {
+            let dir = PathBuf::from(dir.trim());
+            fs::create_dir_all(&dir)?;
+            Ok(dir)
+        }
+        _ => Ok(data_subdir(app, "inbox")?),
+    }
+}
+
+/// Scheduler job: feeds the inbox from the drop folder and tells the UI when something arrived
+pub fn import_inbox(app: &AppHandle, conn: &Connection) -> Result<(), BookingError> {
+    let report = import_folder(conn, &inbox_dir(app, conn)?)?;
+    if report.imported > 0 || !report.failed.is_empty() {
+        if let Err(err) = app.emit(BOOKING_INBOX_EVENT, &report) {
+            println!("[booking.rs::import_inbox] Could not emit inbox event: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// 📥 Import the drop folder right away instead of waiting for the scheduler
+#[tauri::command]
+pub fn import_booking_requests(state: tauri::State<StateWrapper>, app_handle: AppHandle) -> Result<ImportReport, BookingError> {
+    let db_conn = open_state_db(&state)?;
+    import_folder(&db_conn, &inbox_dir(&app_handle, &db_conn)?)
+}
+
+/// 📋 List booking requests, optionally filtered by status
+#[tauri::command]
+pub fn list_booking_requests(state: tauri::State<StateWrapper>, status: Option<String>) -> Result<Vec<BookingRequest>, BookingError> {
+    let db_conn = open_state_db(&state)?;
+    let sql = format!(
+        "SELECT {} FROM booking_requests WHERE ?1 IS NULL OR status = ?1 ORDER BY requested_start",
+        REQUEST_COLUMNS
+    );
+    let mut stmt = db_conn.prepare(&sql)?;
+    let requests = stmt
+        .query_map([status], request_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(requests)
+}
+
+/// ✅ Accept a request, creating its event and linking (or creating) the client
+#[tauri::command]
+pub fn accept_booking_request(state: tauri::State<StateWrapper>, request_id: i64) -> Result<i64, BookingError> {
+    let mut db_conn = open_state_db(&state)?;
+    accept_request(&mut db_conn, request_id)
+}
+
+/// 🔄 Offer the requester another time
+#[tauri::command]
+pub fn propose_booking_time(
+    state: tauri::State<StateWrapper>,
+    request_id: i64,
+    start: String,
+    end: String,
+) -> Result<(), BookingError> {
+    let db_conn = open_state_db(&state)?;
+    load_open_request(&db_conn, request_id)?;
+    let (start, end) = (normalize_time("start", &start)?, normalize_time("end", &end)?);
+    if end <= start {
+        return Err(BookingError::InvalidValue("end", end));
+    }
+
+    db_conn.execute(
+        "UPDATE booking_requests SET status = 'Proposed', proposed_start = ?1, proposed_end = ?2, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?3",
+        params![start, end, request_id],
+    )?;
+    Ok(())
+}
+
+/// ❌ Decline a request
+#[tauri::command]
+pub fn decline_booking_request(state: tauri::State<StateWrapper>, request_id: i64, reason: Option<String>) -> Result<(), BookingError> {
+    let db_conn = open_state_db(&state)?;
+    load_open_request(&db_conn, request_id)?;
+    db_conn.execute(
+        "UPDATE booking_requests SET status = 'Declined', decline_reason = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        params![reason, request_id],
+    )?;
+    Ok(())
+}
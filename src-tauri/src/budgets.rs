@@ -0,0 +1,316 @@
+//! Monthly and yearly spending budgets per expense category.
+//!
+//! Spending is the net amount (without input tax) of the category's expenses dated in the
+//! current month or year, converted into the budget currency at each expense date, as in the
+//! profit and loss report. The scheduler compares it against the thresholds from
+//! `settings.budget_alert_thresholds` (percentages, `80,100` when not set) and emits a
+//! `budget-alert` app event the first time a budget crosses one of them in a period.
+
+use chrono::{Datelike, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use thiserror::Error;
+
+use crate::currencies::{convert, currency_or_base, parse_date, CurrencyError};
+use crate::db_api::{open_state_db, DbApiError};
+use crate::money::parse_percent_bp;
+use crate::settings::read_setting;
+use crate::StateWrapper;
+
+/// Name of the app event emitted when a budget crosses an alert threshold
+pub const BUDGET_ALERT_EVENT: &str = "budget-alert";
+
+/// Thresholds in basis points used when `settings.budget_alert_thresholds` is not set
+const DEFAULT_ALERT_THRESHOLDS_BP: [i64; 2] = [8_000, 10_000];
+
+#[derive(Debug, Error)]
+pub enum BudgetError {
+    #[error("[budgets.rs::budget_not_found] Budget {0} does not exist.")]
+    BudgetNotFound(i64),
+
+    #[error("[budgets.rs::category_not_found] Expense category {0} does not exist.")]
+    CategoryNotFound(i64),
+
+    #[error("[budgets.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[budgets.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[budgets.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[budgets.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for BudgetError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 📅 Period a budget applies to, stored as text in `budgets.period`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum BudgetPeriod {
+    Monthly,
+    Yearly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Monthly => "Monthly",
+            BudgetPeriod::Yearly => "Yearly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<BudgetPeriod> {
+        match value {
+            "Monthly" => Some(BudgetPeriod::Monthly),
+            "Yearly" => Some(BudgetPeriod::Yearly),
+            _ => None,
+        }
+    }
+
+    /// First and last day of the month or year containing `date`
+    pub fn range(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            BudgetPeriod::Monthly => {
+                let start = date.with_day(1).unwrap_or(date);
+                let next = if date.month() == 12 {
+                    NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1)
+                };
+                (start, next.and_then(|d| d.pred_opt()).unwrap_or(date))
+            }
+            BudgetPeriod::Yearly => (
+                NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date),
+                NaiveDate::from_ymd_opt(date.year(), 12, 31).unwrap_or(date),
+            ),
+        }
+    }
+}
+
+/// 🎯 Budget Struct. `amount` is in minor units of `currency`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Budget {
+    pub id: Option<i64>,
+    pub category_id: i64,
+    /// Name of the category, filled in when reading
+    #[serde(default)]
+    pub category: Option<String>,
+    pub period: BudgetPeriod,
+    pub amount: i64,
+    /// ISO 4217 code; defaults to `settings.base_currency`
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// 📊 Spending of a category against its budget in the current period
+#[derive(Serialize, Clone, Debug)]
+pub struct BudgetStatus {
+    pub budget_id: i64,
+    pub category_id: i64,
+    pub category: String,
+    pub period: BudgetPeriod,
+    pub period_start: String,
+    pub period_end: String,
+    pub currency: String,
+    pub budget: i64,
+    /// Net expenses of the category in the period
+    pub actual: i64,
+    /// `budget - actual`; negative once the budget is exceeded
+    pub remaining: i64,
+    /// Share of the budget spent in basis points (80 % = 8000)
+    pub used_bp: i64,
+}
+
+/// 🚨 Payload of the `budget-alert` app event
+#[derive(Serialize, Clone, Debug)]
+pub struct BudgetAlert {
+    /// Highest threshold crossed, in basis points (100 % = 10000)
+    pub threshold_bp: i64,
+    pub status: BudgetStatus,
+}
+
+fn budget_from_row(row: &rusqlite::Row) -> rusqlite::Result<Budget> {
+    let period: String = row.get(3)?;
+    Ok(Budget {
+        id: row.get(0)?,
+        category_id: row.get(1)?,
+        category: row.get(2)?,
+        period: BudgetPeriod::parse(&period)
+            .ok_or_else(|| rusqlite::Error::InvalidColumnType(3, period, rusqlite::types::Type::Text))?,
+        amount: row.get(4)?,
+        currency: row.get(5)?,
+    })
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<Budget>, BudgetError> {
+    let mut stmt = conn.prepare(
+        "SELECT b.id, b.category_id, c.name, b.period, b.amount, b.currency
+         FROM budgets b JOIN expense_categories c ON c.id = b.category_id
+         ORDER BY c.name COLLATE NOCASE, b.period",
+    )?;
+    let budgets = stmt.query_map([], budget_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(budgets)
+}
+
+/// Sets the budget of a category for a period, replacing the one it had; returns its id
+pub fn set(conn: &Connection, budget: &Budget) -> Result<i64, BudgetError> {
+    if budget.amount <= 0 {
+        return Err(BudgetError::InvalidValue("amount", budget.amount.to_string()));
+    }
+    let category: Option<i64> = conn
+        .query_row("SELECT id FROM expense_categories WHERE id = ?1", [budget.category_id], |row| row.get(0))
+        .optional()?;
+    if category.is_none() {
+        return Err(BudgetError::CategoryNotFound(budget.category_id));
+    }
+    let currency = currency_or_base(conn, budget.currency.as_deref())?;
+    let budget_id = conn.query_row(
+        "INSERT INTO budgets (category_id, period, amount, currency) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (category_id, period) DO UPDATE SET amount = excluded.amount, currency = excluded.currency
+         RETURNING id",
+        params![budget.category_id, budget.period.as_str(), budget.amount, currency],
+        |row| row.get(0),
+    )?;
+    Ok(budget_id)
+}
+
+pub fn delete(conn: &Connection, budget_id: i64) -> Result<(), BudgetError> {
+    let deleted = conn.execute("DELETE FROM budgets WHERE id = ?1", [budget_id])?;
+    if deleted == 0 {
+        return Err(BudgetError::BudgetNotFound(budget_id));
+    }
+    Ok(())
+}
+
+/// Net expenses of a category between two dates in `currency`
+fn spent(conn: &Connection, category_id: i64, (from, to): (NaiveDate, NaiveDate), currency: &str) -> Result<i64, BudgetError> {
+    let mut stmt = conn.prepare(
+        "SELECT amount - tax_amount, currency, date(date) FROM expenses
+         WHERE category_id = ?1 AND date(date) BETWEEN ?2 AND ?3",
+    )?;
+    let expenses = stmt
+        .query_map(params![category_id, from.to_string(), to.to_string()], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut total = 0;
+    for (amount, expense_currency, date) in expenses {
+        let date = parse_date(&date).ok_or(BudgetError::InvalidValue("date", date))?;
+        total += convert(conn, amount, &expense_currency, currency, date)?;
+    }
+    Ok(total)
+}
+
+/// Spending against every budget in the period that contains `date`
+pub fn status(conn: &Connection, date: NaiveDate) -> Result<Vec<BudgetStatus>, BudgetError> {
+    let mut report = Vec::new();
+    for budget in list(conn)? {
+        let (start, end) = budget.period.range(date);
+        let currency = currency_or_base(conn, budget.currency.as_deref())?;
+        let actual = spent(conn, budget.category_id, (start, end), &currency)?;
+        report.push(BudgetStatus {
+            budget_id: budget.id.unwrap_or_default(),
+            category_id: budget.category_id,
+            category: budget.category.unwrap_or_default(),
+            period: budget.period,
+            period_start: start.to_string(),
+            period_end: end.to_string(),
+            currency,
+            budget: budget.amount,
+            actual,
+            remaining: budget.amount - actual,
+            used_bp: ((actual as i128 * 10_000) / budget.amount as i128) as i64,
+        });
+    }
+    Ok(report)
+}
+
+/// Alert thresholds in basis points from `settings.budget_alert_thresholds`, e.g. `80, 100`
+pub fn alert_thresholds(conn: &Connection) -> Result<Vec<i64>, BudgetError> {
+    let Some(value) = read_setting(conn, "budget_alert_thresholds")?.filter(|v| !v.trim().is_empty()) else {
+        return Ok(DEFAULT_ALERT_THRESHOLDS_BP.to_vec());
+    };
+    let mut thresholds = value
+        .split(',')
+        .map(|part| parse_percent_bp(part).filter(|bp| *bp > 0))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| BudgetError::InvalidValue("budget_alert_thresholds", value.clone()))?;
+    thresholds.sort_unstable();
+    thresholds.dedup();
+    Ok(thresholds)
+}
+
+/// Records the thresholds each budget has newly crossed in the period containing `date` and
+/// returns one alert per budget for the highest of them
+pub fn new_alerts(conn: &Connection, date: NaiveDate) -> Result<Vec<BudgetAlert>, BudgetError> {
+    let thresholds = alert_thresholds(conn)?;
+    let mut alerts = Vec::new();
+    for status in status(conn, date)? {
+        let mut crossed = None;
+        for threshold_bp in thresholds.iter().filter(|t| status.used_bp >= **t) {
+            let inserted = conn.execute(
+                "INSERT OR IGNORE INTO budget_alerts (budget_id, period_start, threshold_bp) VALUES (?1, ?2, ?3)",
+                params![status.budget_id, status.period_start, threshold_bp],
+            )?;
+            if inserted > 0 {
+                crossed = Some(*threshold_bp);
+            }
+        }
+        if let Some(threshold_bp) = crossed {
+            alerts.push(BudgetAlert { threshold_bp, status });
+        }
+    }
+    Ok(alerts)
+}
+
+/// Emits a `budget-alert` event for every budget that crossed a threshold since the last run
+pub fn raise_budget_alerts(app: &AppHandle, conn: &Connection) -> Result<(), BudgetError> {
+    for alert in new_alerts(conn, Utc::now().date_naive())? {
+        if let Err(err) = app.emit(BUDGET_ALERT_EVENT, &alert) {
+            println!("[budgets.rs::raise_budget_alerts] Could not emit budget alert: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// 🎯 List the budgets of all expense categories
+#[tauri::command]
+pub fn list_budgets(state: tauri::State<StateWrapper>) -> Result<Vec<Budget>, BudgetError> {
+    let db_conn = open_state_db(&state)?;
+    list(&db_conn)
+}
+
+/// ✏️ Set the monthly or yearly budget of a category, returns its id
+#[tauri::command]
+pub fn set_budget(state: tauri::State<StateWrapper>, budget: Budget) -> Result<i64, BudgetError> {
+    let db_conn = open_state_db(&state)?;
+    set(&db_conn, &budget)
+}
+
+/// 🗑️ Delete a budget
+#[tauri::command]
+pub fn delete_budget(state: tauri::State<StateWrapper>, budget_id: i64) -> Result<(), BudgetError> {
+    let db_conn = open_state_db(&state)?;
+    delete(&db_conn, budget_id)
+}
+
+/// 📊 Actual spending against each budget in the period containing `date` (today by default)
+#[tauri::command]
+pub fn get_budget_report(state: tauri::State<StateWrapper>, date: Option<String>) -> Result<Vec<BudgetStatus>, BudgetError> {
+    let date = match date.filter(|d| !d.trim().is_empty()) {
+        Some(value) => parse_date(&value).ok_or(BudgetError::InvalidValue("date", value))?,
+        None => Utc::now().date_naive(),
+    };
+    let db_conn = open_state_db(&state)?;
+    status(&db_conn, date)
+}
@@ -0,0 +1,1188 @@
+//! Two-way CalDAV (RFC 4791) sync of the `events` table.
+//!
+//! Connection details live in `settings`:
+//! - `caldav_url`: server or principal URL, e.g. `http://localhost:5232/` for a local Radicale
+//! - `caldav_username` / `caldav_password`: HTTP basic auth credentials
+//! - `caldav_calendar_url` (optional): the collection to sync; discovered and saved when missing
+//! - `caldav_calendar_name` (optional): display name to pick during discovery
+//! - `caldav_conflict_policy` (optional): `newest` (default), `server` or `client`
+//!
+//! Changes are pulled with `sync-collection` (RFC 6578) when the server supports it and with a
+//! full ETag listing otherwise. Local edits are tracked with `events.caldav_dirty` and deletions
+//! with `caldav_tombstones`, then pushed with `If-Match` / `If-None-Match` preconditions so
+//! concurrent remote edits surface as conflicts instead of being overwritten.
+//!
+//! To try it locally: `pip install radicale && python -m radicale --storage-filesystem-folder=/tmp/radicale`
+//! (any username/password is accepted by the default config), create a calendar from the web UI
+//! at `http://localhost:5232/` and point `caldav_url` at that address.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDateTime;
+use reqwest::{header, Method, StatusCode, Url};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::db_api::{open_state_db, parse_db_timestamp, DbApiError, DB_TIMESTAMP_FORMAT};
+use crate::ical::{self, IcalError, VEvent};
+use crate::settings::{read_setting, write_setting};
+use crate::xml::XmlNode;
+use crate::StateWrapper;
+
+/// Number of resources fetched per `calendar-multiget` request
+const MULTIGET_BATCH: usize = 50;
+
+#[derive(Debug, Error)]
+pub enum CalDavError {
+    #[error("[caldav.rs::not_configured] Missing setting: {0}")]
+    NotConfigured(&'static str),
+
+    #[error("[caldav.rs::invalid_url] Invalid CalDAV URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("[caldav.rs::network_request] Network issue: {0}")]
+    NetworkError(#[from] reqwest::Error),
+
+    #[error("[caldav.rs::http_status] {method} {url} failed with status {status}")]
+    HttpStatus { method: String, url: String, status: u16 },
+
+    #[error("[caldav.rs::xml] Could not parse the server response: {0}")]
+    XmlError(String),
+
+    #[error("[caldav.rs::no_calendar] No calendar collection supporting events was found")]
+    NoCalendar,
+
+    #[error("[caldav.rs::ical] {0}")]
+    IcalError(#[from] IcalError),
+
+    #[error("[caldav.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[caldav.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for CalDavError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// How a conflicting edit (changed both locally and on the server) is resolved
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ConflictPolicy {
+    /// The most recently modified side wins
+    Newest,
+    Server,
+    Client,
+}
+
+struct CalDavConfig {
+    base_url: Url,
+    username: String,
+    password: String,
+    calendar_url: Option<Url>,
+    calendar_name: Option<String>,
+    conflict_policy: ConflictPolicy,
+}
+
+impl CalDavConfig {
+    fn load(conn: &Connection) -> Result<Self, CalDavError> {
+        let base = read_setting(conn, "caldav_url")?.ok_or(CalDavError::NotConfigured("caldav_url"))?;
+        let calendar_url = read_setting(conn, "caldav_calendar_url")?
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| parse_collection_url(&url))
+            .transpose()?;
+
+        Ok(CalDavConfig {
+            base_url: parse_collection_url(&base)?,
+            username: read_setting(conn, "caldav_username")?.unwrap_or_default(),
+            password: read_setting(conn, "caldav_password")?.unwrap_or_default(),
+            calendar_url,
+            calendar_name: read_setting(conn, "caldav_calendar_name")?,
+            conflict_policy: match read_setting(conn, "caldav_conflict_policy")?.as_deref() {
+                Some("server") => ConflictPolicy::Server,
+                Some("client") => ConflictPolicy::Client,
+                _ => ConflictPolicy::Newest,
+            },
+        })
+    }
+}
+
+/// Collection URLs must end with `/` so member hrefs resolve inside them
+fn parse_collection_url(url: &str) -> Result<Url, CalDavError> {
+    let url = url.trim();
+    let url = if url.ends_with('/') { url.to_string() } else { format!("{}/", url) };
+    Url::parse(&url).map_err(|_| CalDavError::InvalidUrl(url))
+}
+
+/// 📅 A calendar collection found during discovery
+#[derive(Serialize)]
+pub struct CalendarInfo {
+    pub url: String,
+    pub display_name: Option<String>,
+}
+
+/// 🔁 Outcome of a sync run
+#[derive(Serialize, Default)]
+pub struct SyncReport {
+    pub calendar_url: String,
+    pub pulled: usize,
+    pub pushed: usize,
+    pub deleted_locally: usize,
+    pub deleted_remotely: usize,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// ⚠️ An event that was changed on both sides since the last sync
+#[derive(Serialize)]
+pub struct SyncConflict {
+    pub uid: String,
+    pub title: String,
+    /// `server` or `client`: which version was kept
+    pub kept: String,
+}
+
+/// One `<response>` of a multistatus, flattened to the properties found with a 2xx status
+struct DavResponse<'a> {
+    href: String,
+    status: Option<u16>,
+    props: Vec<&'a XmlNode>,
+}
+
+impl<'a> DavResponse<'a> {
+    fn prop(&self, name: &str) -> Option<&'a XmlNode> {
+        self.props.iter().find_map(|p| p.child(name))
+    }
+}
+
+fn parse_status(line: &str) -> Option<u16> {
+    // "HTTP/1.1 404 Not Found"
+    line.split_whitespace().nth(1).and_then(|code| code.parse().ok())
+}
+
+fn dav_responses(multistatus: &XmlNode) -> Vec<DavResponse<'_>> {
+    multistatus
+        .children_named("response")
+        .filter_map(|response| {
+            let href = response.child("href")?.text.trim().to_string();
+            let props = response
+                .children_named("propstat")
+                .filter(|ps| ps.child("status").and_then(|s| parse_status(&s.text)).map_or(true, |s| s < 300))
+                .filter_map(|ps| ps.child("prop"))
+                .collect();
+            Some(DavResponse {
+                href,
+                status: response.child("status").and_then(|s| parse_status(&s.text)),
+                props,
+            })
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// HTTP client
+// ---------------------------------------------------------------------------
+
+struct CalDavClient {
+    http: reqwest::Client,
+    username: String,
+    password: String,
+}
+
+enum PutOutcome {
+    Stored { etag: Option<String> },
+    PreconditionFailed,
+}
+
+/// Condition a PUT is sent with, so a concurrent remote edit is not overwritten
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PutPrecondition<'a> {
+    /// New resource: `If-None-Match: *`
+    Create,
+    /// The version with this ETag: `If-Match`
+    Replace(&'a str),
+    /// A stored resource whose ETag the server never returned: no precondition
+    Overwrite,
+}
+
+impl CalDavClient {
+    fn new(config: &CalDavConfig) -> Self {
+        CalDavClient {
+            http: reqwest::Client::new(),
+            username: config.username.clone(),
+            password: config.password.clone(),
+        }
+    }
+
+    fn request(&self, method: Method, url: &Url) -> reqwest::RequestBuilder {
+        let builder = self.http.request(method, url.clone());
+        if self.username.is_empty() {
+            builder
+        } else {
+            builder.basic_auth(&self.username, Some(&self.password))
+        }
+    }
+
+    /// Sends a PROPFIND/REPORT and returns the status with the parsed multistatus body
+    async fn dav(&self, method: &str, url: &Url, depth: &str, body: String) -> Result<(StatusCode, Option<XmlNode>), CalDavError> {
+        let method = Method::from_bytes(method.as_bytes()).expect("valid WebDAV method");
+        let response = self
+            .request(method, url)
+            .header("Depth", depth)
+            .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await?;
+        let status = response.status();
+        let text = response.text().await?;
+        let xml = if status == StatusCode::MULTI_STATUS { Some(XmlNode::parse(&text).map_err(CalDavError::XmlError)?) } else { None };
+        Ok((status, xml))
+    }
+
+    async fn multistatus(&self, method: &str, url: &Url, depth: &str, body: String) -> Result<XmlNode, CalDavError> {
+        match self.dav(method, url, depth, body).await? {
+            (_, Some(xml)) => Ok(xml),
+            (status, None) => Err(CalDavError::HttpStatus {
+                method: method.to_string(),
+                url: url.to_string(),
+                status: status.as_u16(),
+            }),
+        }
+    }
+
+    async fn put(&self, url: &Url, ics: String, precondition: PutPrecondition<'_>) -> Result<PutOutcome, CalDavError> {
+        let builder = self
+            .request(Method::PUT, url)
+            .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(ics);
+        let builder = match precondition {
+            PutPrecondition::Create => builder.header(header::IF_NONE_MATCH, "*"),
+            PutPrecondition::Replace(etag) => builder.header(header::IF_MATCH, etag),
+            PutPrecondition::Overwrite => builder,
+        };
+        let response = builder.send().await?;
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(PutOutcome::PreconditionFailed),
+            status if status.is_success() => Ok(PutOutcome::Stored {
+                etag: response
+                    .headers()
+                    .get(header::ETAG)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string),
+            }),
+            status => Err(CalDavError::HttpStatus {
+                method: "PUT".into(),
+                url: url.to_string(),
+                status: status.as_u16(),
+            }),
+        }
+    }
+
+    /// Current ETag of a resource, for servers that return none on PUT
+    async fn etag(&self, url: &Url) -> Result<Option<String>, CalDavError> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>"#;
+        let root = self.multistatus("PROPFIND", url, "0", body.into()).await?;
+        Ok(dav_responses(&root).first().and_then(|r| r.prop("getetag")).map(|etag| etag.text.trim().to_string()))
+    }
+
+    /// Deletes a resource. Returns `false` when the server copy changed since `etag`.
+    async fn delete(&self, url: &Url, etag: Option<&str>) -> Result<bool, CalDavError> {
+        let mut builder = self.request(Method::DELETE, url);
+        if let Some(etag) = etag {
+            builder = builder.header(header::IF_MATCH, etag);
+        }
+        match builder.send().await?.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(false),
+            status if status.is_success() || status == StatusCode::NOT_FOUND => Ok(true),
+            status => Err(CalDavError::HttpStatus {
+                method: "DELETE".into(),
+                url: url.to_string(),
+                status: status.as_u16(),
+            }),
+        }
+    }
+
+    /// RFC 6764 / 4791 discovery: principal → calendar home → calendar collections
+    async fn discover(&self, base: &Url) -> Result<Vec<CalendarInfo>, CalDavError> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:resourcetype/><d:current-user-principal/><c:calendar-home-set/></d:prop>
+</d:propfind>"#;
+        let root = self.multistatus("PROPFIND", base, "0", body.into()).await?;
+        let responses = dav_responses(&root);
+        let first = responses.first().ok_or(CalDavError::NoCalendar)?;
+
+        // The configured URL may already be a calendar
+        if first.prop("resourcetype").and_then(|r| r.child("calendar")).is_some() {
+            return Ok(vec![CalendarInfo { url: base.to_string(), display_name: None }]);
+        }
+
+        let home = match first.prop("calendar-home-set").and_then(|h| h.child("href")) {
+            Some(href) => join(base, &href.text)?,
+            None => {
+                let principal = first
+                    .prop("current-user-principal")
+                    .and_then(|p| p.child("href"))
+                    .ok_or(CalDavError::NoCalendar)?;
+                let principal_url = join(base, &principal.text)?;
+                let root = self.multistatus("PROPFIND", &principal_url, "0", body.into()).await?;
+                let href = dav_responses(&root)
+                    .first()
+                    .and_then(|r| r.prop("calendar-home-set"))
+                    .and_then(|h| h.child("href"))
+                    .map(|h| h.text.clone())
+                    .ok_or(CalDavError::NoCalendar)?;
+                join(base, &href)?
+            }
+        };
+
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:resourcetype/><d:displayname/><c:supported-calendar-component-set/></d:prop>
+</d:propfind>"#;
+        let root = self.multistatus("PROPFIND", &home, "1", body.into()).await?;
+        let mut calendars = Vec::new();
+        for response in dav_responses(&root) {
+            let is_calendar = response.prop("resourcetype").and_then(|r| r.child("calendar")).is_some();
+            // Servers that omit the component set accept events
+            let supports_events = response
+                .prop("supported-calendar-component-set")
+                .map_or(true, |set| {
+                    set.children.is_empty()
+                        || set.children.iter().any(|c| c.name == "comp" && c.attribute("name") == Some("VEVENT"))
+                });
+            if is_calendar && supports_events {
+                calendars.push(CalendarInfo {
+                    url: join(base, &response.href)?.to_string(),
+                    display_name: response.prop("displayname").map(|d| d.text.clone()).filter(|d| !d.is_empty()),
+                });
+            }
+        }
+        Ok(calendars)
+    }
+
+    /// Lists what changed since `sync_token`. Falls back to a full ETag listing when the
+    /// server has no `sync-collection` support or no longer accepts the token.
+    async fn changes(&self, calendar: &Url, sync_token: Option<&str>) -> Result<RemoteChanges, CalDavError> {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<d:sync-collection xmlns:d="DAV:">
+  <d:sync-token>{}</d:sync-token>
+  <d:sync-level>1</d:sync-level>
+  <d:prop><d:getetag/></d:prop>
+</d:sync-collection>"#,
+            sync_token.map(xml_escape).unwrap_or_default()
+        );
+        let (status, root) = self.dav("REPORT", calendar, "1", body).await?;
+
+        let root = match root {
+            Some(root) => root,
+            // Expired token (403/409 valid-sync-token): start over with a full sync
+            None if sync_token.is_some() && matches!(status, StatusCode::FORBIDDEN | StatusCode::CONFLICT) => {
+                return Box::pin(self.changes(calendar, None)).await;
+            }
+            None => return self.full_listing(calendar).await,
+        };
+
+        let mut changes = RemoteChanges {
+            full: sync_token.is_none(),
+            new_token: root.child("sync-token").map(|t| t.text.trim().to_string()),
+            ..Default::default()
+        };
+        for response in dav_responses(&root) {
+            if is_collection_href(calendar, &response.href) {
+                continue;
+            }
+            match (response.status, response.prop("getetag")) {
+                (Some(404), _) => changes.deleted.push(response.href),
+                (_, Some(etag)) => changes.changed.push((response.href, etag.text.trim().to_string())),
+                _ => {}
+            }
+        }
+        Ok(changes)
+    }
+
+    async fn full_listing(&self, calendar: &Url) -> Result<RemoteChanges, CalDavError> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/><d:resourcetype/></d:prop></d:propfind>"#;
+        let root = self.multistatus("PROPFIND", calendar, "1", body.into()).await?;
+        let mut changes = RemoteChanges { full: true, ..Default::default() };
+        for response in dav_responses(&root) {
+            if is_collection_href(calendar, &response.href) {
+                continue;
+            }
+            if let Some(etag) = response.prop("getetag") {
+                changes.changed.push((response.href, etag.text.trim().to_string()));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Fetches calendar data for the given hrefs with `calendar-multiget`
+    async fn fetch(&self, calendar: &Url, hrefs: &[String]) -> Result<Vec<RemoteResource>, CalDavError> {
+        let mut resources = Vec::new();
+        for batch in hrefs.chunks(MULTIGET_BATCH) {
+            let href_list: String = batch.iter().map(|h| format!("<d:href>{}</d:href>", xml_escape(h))).collect();
+            let body = format!(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  {}
+</c:calendar-multiget>"#,
+                href_list
+            );
+            let root = self.multistatus("REPORT", calendar, "1", body).await?;
+            for response in dav_responses(&root) {
+                if let (Some(etag), Some(data)) = (response.prop("getetag"), response.prop("calendar-data")) {
+                    resources.push(RemoteResource {
+                        href: response.href.clone(),
+                        etag: etag.text.trim().to_string(),
+                        ics: data.text.clone(),
+                    });
+                }
+            }
+        }
+        Ok(resources)
+    }
+}
+
+#[derive(Default)]
+struct RemoteChanges {
+    /// `true` when `changed` lists every resource, so anything missing was deleted
+    full: bool,
+    changed: Vec<(String, String)>,
+    deleted: Vec<String>,
+    new_token: Option<String>,
+}
+
+struct RemoteResource {
+    href: String,
+    etag: String,
+    ics: String,
+}
+
+fn join(base: &Url, href: &str) -> Result<Url, CalDavError> {
+    base.join(href.trim()).map_err(|_| CalDavError::InvalidUrl(href.to_string()))
+}
+
+fn is_collection_href(calendar: &Url, href: &str) -> bool {
+    href.trim_end_matches('/') == calendar.path().trim_end_matches('/')
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// ---------------------------------------------------------------------------
+// Local state
+// ---------------------------------------------------------------------------
+
+struct LocalEvent {
+    id: i64,
+    uid: String,
+    title: String,
+    start_date: String,
+    end_date: String,
+    updated_at: Option<String>,
+    href: Option<String>,
+    etag: Option<String>,
+    ics: Option<String>,
+    dirty: bool,
+}
+
+const LOCAL_EVENT_COLUMNS: &str =
+    "id, uid, title, start_date, end_date, updated_at, caldav_href, caldav_etag, caldav_ics, caldav_dirty";
+
+impl LocalEvent {
+    fn put_precondition(&self) -> PutPrecondition<'_> {
+        match (&self.href, &self.etag) {
+            (None, _) => PutPrecondition::Create,
+            (Some(_), Some(etag)) => PutPrecondition::Replace(etag),
+            (Some(_), None) => PutPrecondition::Overwrite,
+        }
+    }
+}
+
+fn local_event_from_row(row: &rusqlite::Row) -> rusqlite::Result<LocalEvent> {
+    Ok(LocalEvent {
+        id: row.get(0)?,
+        uid: row.get(1)?,
+        title: row.get(2)?,
+        start_date: row.get(3)?,
+        end_date: row.get(4)?,
+        updated_at: row.get(5)?,
+        href: row.get(6)?,
+        etag: row.get(7)?,
+        ics: row.get(8)?,
+        dirty: row.get(9)?,
+    })
+}
+
+fn find_local(conn: &Connection, href: &str, uid: Option<&str>) -> rusqlite::Result<Option<LocalEvent>> {
+    let sql = format!("SELECT {} FROM events WHERE caldav_href = ?1 OR uid = ?2", LOCAL_EVENT_COLUMNS);
+    conn.query_row(&sql, params![href, uid], local_event_from_row).optional()
+}
+
+fn known_etags(conn: &Connection) -> rusqlite::Result<HashMap<String, Option<String>>> {
+    let mut stmt = conn.prepare("SELECT caldav_href, caldav_etag FROM events WHERE caldav_href IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+fn load_sync_token(conn: &Connection, calendar: &Url) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT sync_token FROM caldav_state WHERE calendar_url = ?1",
+        [calendar.as_str()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(Option::flatten)
+}
+
+fn save_sync_token(conn: &Connection, calendar: &Url, token: Option<&str>) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO caldav_state (calendar_url, sync_token, last_synced_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)
+         ON CONFLICT(calendar_url) DO UPDATE SET sync_token = excluded.sync_token, last_synced_at = excluded.last_synced_at",
+        params![calendar.as_str(), token],
+    )?;
+    Ok(())
+}
+
+fn format_db(value: &NaiveDateTime) -> String {
+    value.format(DB_TIMESTAMP_FORMAT).to_string()
+}
+
+fn store_remote(conn: &Connection, local_id: Option<i64>, resource: &RemoteResource, event: &VEvent) -> rusqlite::Result<()> {
+    match local_id {
+        Some(id) => conn.execute(
+            "UPDATE events SET title = ?1, start_date = ?2, end_date = ?3, uid = ?4, caldav_href = ?5,
+                    caldav_etag = ?6, caldav_ics = ?7, caldav_dirty = 0, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?8",
+            params![event.summary, format_db(&event.start), format_db(&event.end), event.uid,
+                    resource.href, resource.etag, resource.ics, id],
+        )?,
+        None => conn.execute(
+            "INSERT INTO events (title, start_date, end_date, uid, caldav_href, caldav_etag, caldav_ics, caldav_dirty, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, CURRENT_TIMESTAMP)",
+            params![event.summary, format_db(&event.start), format_db(&event.end), event.uid,
+                    resource.href, resource.etag, resource.ics],
+        )?,
+    };
+    Ok(())
+}
+
+/// Applies pulled changes inside one transaction, resolving conflicts with `policy`
+fn apply_remote(
+    conn: &mut Connection,
+    policy: ConflictPolicy,
+    changes: &RemoteChanges,
+    resources: &[RemoteResource],
+    report: &mut SyncReport,
+) -> Result<(), CalDavError> {
+    let tx = conn.transaction()?;
+
+    for resource in resources {
+        let event = match ical::parse_event(&resource.ics) {
+            Ok(event) => event,
+            Err(err) => {
+                // A single unsupported resource (e.g. a VTODO) must not block the sync
+                println!("[caldav.rs::apply_remote] Skipping {}: {}", resource.href, err);
+                continue;
+            }
+        };
+        let local = find_local(&tx, &resource.href, Some(&event.uid))?;
+
+        match local {
+            Some(local) if local.dirty && local.etag.as_deref() != Some(resource.etag.as_str()) => {
+                let local_modified = local.updated_at.as_deref().and_then(parse_db_timestamp);
+                let server_wins = match policy {
+                    ConflictPolicy::Server => true,
+                    ConflictPolicy::Client => false,
+                    ConflictPolicy::Newest => match (event.last_modified, local_modified) {
+                        (Some(remote), Some(local)) => remote > local,
+                        _ => true,
+                    },
+                };
+                if server_wins {
+                    store_remote(&tx, Some(local.id), resource, &event)?;
+                    report.pulled += 1;
+                } else {
+                    // Keep the local edit but adopt the server ETag/data so the push overwrites it
+                    tx.execute(
+                        "UPDATE events SET caldav_href = ?1, caldav_etag = ?2, caldav_ics = ?3 WHERE id = ?4",
+                        params![resource.href, resource.etag, resource.ics, local.id],
+                    )?;
+                }
+                report.conflicts.push(SyncConflict {
+                    uid: local.uid,
+                    title: local.title,
+                    kept: if server_wins { "server" } else { "client" }.to_string(),
+                });
+            }
+            // Local edits on top of the current server version are pushed later
+            Some(local) if local.dirty => {}
+            Some(local) => {
+                store_remote(&tx, Some(local.id), resource, &event)?;
+                report.pulled += 1;
+            }
+            None => {
+                store_remote(&tx, None, resource, &event)?;
+                report.pulled += 1;
+            }
+        }
+    }
+
+    let mut deleted: Vec<String> = changes.deleted.clone();
+    if changes.full {
+        let listed: HashSet<&str> = changes.changed.iter().map(|(href, _)| href.as_str()).collect();
+        deleted.extend(known_etags(&tx)?.into_keys().filter(|href| !listed.contains(href.as_str())));
+    }
+    for href in deleted {
+        let Some(local) = find_local(&tx, &href, None)? else { continue };
+        if local.dirty {
+            // Edited here but deleted on the server: keep it and upload it as a new resource
+            tx.execute(
+                "UPDATE events SET caldav_href = NULL, caldav_etag = NULL WHERE id = ?1",
+                [local.id],
+            )?;
+        } else {
+            tx.execute("DELETE FROM events WHERE id = ?1", [local.id])?;
+            report.deleted_locally += 1;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+fn dirty_events(conn: &Connection) -> rusqlite::Result<Vec<LocalEvent>> {
+    let sql = format!("SELECT {} FROM events WHERE caldav_dirty = 1", LOCAL_EVENT_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], local_event_from_row)?;
+    rows.collect()
+}
+
+fn tombstones(conn: &Connection) -> rusqlite::Result<Vec<(i64, String, Option<String>)>> {
+    let mut stmt = conn.prepare("SELECT id, href, etag FROM caldav_tombstones")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    rows.collect()
+}
+
+/// Builds the VEVENT for a local row, carrying over the sequence and all-day flag of the
+/// last server copy when there is one
+fn to_vevent(local: &LocalEvent) -> Result<VEvent, CalDavError> {
+    let previous = local.ics.as_deref().and_then(|ics| ical::parse_event(ics).ok());
+    let invalid = |value: &str| CalDavError::IcalError(IcalError::InvalidValue("DTSTART", value.to_string()));
+    Ok(VEvent {
+        uid: local.uid.clone(),
+        summary: local.title.clone(),
+        start: parse_db_timestamp(&local.start_date).ok_or_else(|| invalid(&local.start_date))?,
+        end: parse_db_timestamp(&local.end_date).ok_or_else(|| invalid(&local.end_date))?,
+        all_day: previous.as_ref().is_some_and(|p| p.all_day),
+        last_modified: None,
+        sequence: previous.as_ref().map_or(0, |p| p.sequence + 1),
+        description: previous.as_ref().and_then(|p| p.description.clone()),
+        organizer: previous.and_then(|p| p.organizer),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Sync
+// ---------------------------------------------------------------------------
+
+/// Pulls the remote changes, then pushes local deletions, creations and edits
+async fn sync(conn: &mut Connection, config: &CalDavConfig) -> Result<SyncReport, CalDavError> {
+    let client = CalDavClient::new(config);
+
+    let calendar = match config.calendar_url.clone() {
+        Some(url) => url,
+        None => {
+            let calendars = client.discover(&config.base_url).await?;
+            let chosen = calendars
+                .iter()
+                .find(|c| config.calendar_name.is_some() && c.display_name == config.calendar_name)
+                .or_else(|| calendars.first())
+                .ok_or(CalDavError::NoCalendar)?;
+            let url = parse_collection_url(&chosen.url)?;
+            write_setting(conn, "caldav_calendar_url", Some(url.as_str()))?;
+            url
+        }
+    };
+    let mut report = SyncReport { calendar_url: calendar.to_string(), ..Default::default() };
+
+    // 1. Pull remote changes
+    let token = load_sync_token(conn, &calendar)?;
+    let changes = client.changes(&calendar, token.as_deref()).await?;
+    let known = known_etags(conn)?;
+    let to_fetch: Vec<String> = changes
+        .changed
+        .iter()
+        .filter(|(href, etag)| known.get(href).map_or(true, |known| known.as_deref() != Some(etag.as_str())))
+        .map(|(href, _)| href.clone())
+        .collect();
+    let resources = client.fetch(&calendar, &to_fetch).await?;
+    apply_remote(conn, config.conflict_policy, &changes, &resources, &mut report)?;
+
+    // 2. Push local deletions
+    for (id, href, etag) in tombstones(conn)? {
+        let url = join(&calendar, &href)?;
+        if client.delete(&url, etag.as_deref()).await? {
+            report.deleted_remotely += 1;
+        }
+        // On a precondition failure the server copy changed; it comes back on the next pull
+        conn.execute("DELETE FROM caldav_tombstones WHERE id = ?1", [id])?;
+    }
+
+    // 3. Push local creations and edits
+    for local in dirty_events(conn)? {
+        let event = to_vevent(&local)?;
+        let ics = ical::write_event(&event, local.ics.as_deref());
+        let url = match &local.href {
+            Some(href) => join(&calendar, href)?,
+            None => join(&calendar, &format!("{}.ics", local.uid))?,
+        };
+        match client.put(&url, ics.clone(), local.put_precondition()).await? {
+            PutOutcome::Stored { etag } => {
+                let etag = match etag {
+                    Some(etag) => Some(etag),
+                    // Without it the next pull refetches the resource, which is harmless
+                    None => client.etag(&url).await.unwrap_or_else(|err| {
+                        println!("[caldav.rs::sync] Could not read the ETag of {}: {}", url, err);
+                        None
+                    }),
+                };
+                conn.execute(
+                    "UPDATE events SET caldav_href = ?1, caldav_etag = ?2, caldav_ics = ?3, caldav_dirty = 0 WHERE id = ?4",
+                    params![url.path(), etag, ics, local.id],
+                )?;
+                report.pushed += 1;
+            }
+            // Changed remotely since our last pull: left dirty and resolved on the next sync
+            PutOutcome::PreconditionFailed => report.conflicts.push(SyncConflict {
+                uid: local.uid,
+                title: local.title,
+                kept: "pending".to_string(),
+            }),
+        }
+    }
+
+    save_sync_token(conn, &calendar, changes.new_token.as_deref())?;
+    Ok(report)
+}
+
+// ---------------------------------------------------------------------------
+// Commands
+// ---------------------------------------------------------------------------
+
+/// 🔍 Discover the calendars available for the configured account
+#[tauri::command]
+pub async fn caldav_list_calendars(state: tauri::State<'_, StateWrapper>) -> Result<Vec<CalendarInfo>, CalDavError> {
+    let config = {
+        let db_conn = open_state_db(&state)?;
+        CalDavConfig::load(&db_conn)?
+    };
+    CalDavClient::new(&config).discover(&config.base_url).await
+}
+
+/// 🔁 Run a two-way sync between the `events` table and the configured calendar
+#[tauri::command]
+pub async fn caldav_sync(state: tauri::State<'_, StateWrapper>) -> Result<SyncReport, CalDavError> {
+    let mut db_conn = open_state_db(&state)?;
+    let config = CalDavConfig::load(&db_conn)?;
+    sync(&mut db_conn, &config).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                start_date TIMESTAMP NOT NULL,
+                end_date TIMESTAMP NOT NULL,
+                uid TEXT UNIQUE,
+                updated_at TIMESTAMP,
+                caldav_href TEXT UNIQUE,
+                caldav_etag TEXT,
+                caldav_ics TEXT,
+                caldav_dirty INTEGER NOT NULL DEFAULT 1
+            );
+            CREATE TABLE caldav_tombstones (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                href TEXT NOT NULL,
+                etag TEXT,
+                deleted_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE caldav_state (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                calendar_url TEXT UNIQUE NOT NULL,
+                sync_token TEXT,
+                last_synced_at TIMESTAMP
+            );
+            CREATE TABLE settings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                key TEXT UNIQUE NOT NULL,
+                value TEXT
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn ics(uid: &str, summary: &str, last_modified: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Test//EN\r\nBEGIN:VEVENT\r\nUID:{}\r\n\
+             DTSTAMP:20250101T000000Z\r\nLAST-MODIFIED:{}\r\nSUMMARY:{}\r\n\
+             DTSTART:20250110T090000Z\r\nDTEND:20250110T100000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            uid, last_modified, summary
+        )
+    }
+
+    /// Inserts an event as the sync left it: `href`/`etag` of its server copy, if any
+    fn insert_event(conn: &Connection, uid: &str, href: Option<&str>, etag: Option<&str>, dirty: bool) -> i64 {
+        conn.execute(
+            "INSERT INTO events (title, start_date, end_date, uid, updated_at, caldav_href, caldav_etag, caldav_dirty)
+             VALUES (?1, '2025-01-10 09:00:00', '2025-01-10 10:00:00', ?2, '2025-01-03 12:00:00', ?3, ?4, ?5)",
+            params![format!("Local {}", uid), uid, href, etag, dirty],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn local(conn: &Connection, uid: &str) -> Option<LocalEvent> {
+        let sql = format!("SELECT {} FROM events WHERE uid = ?1", LOCAL_EVENT_COLUMNS);
+        conn.query_row(&sql, [uid], local_event_from_row).optional().unwrap()
+    }
+
+    fn resource(href: &str, etag: &str, ics: String) -> RemoteResource {
+        RemoteResource { href: href.to_string(), etag: etag.to_string(), ics }
+    }
+
+    /// A request received by `serve`
+    struct Received {
+        method: String,
+        path: String,
+        headers: HashMap<String, String>,
+        body: String,
+    }
+
+    /// A canned response: status, headers and body
+    type Canned = (u16, Vec<(&'static str, &'static str)>, String);
+
+    /// Answers one connection per canned response, in order, and returns the requests it received
+    fn serve(responses: Vec<Canned>) -> (Url, JoinHandle<Vec<Received>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let server = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            for (status, headers, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut request_line = line.split_whitespace();
+                let (method, path) = (request_line.next().unwrap().to_string(), request_line.next().unwrap().to_string());
+
+                let mut request_headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(':') else { break };
+                    request_headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+                let length = request_headers.get("content-length").map_or(0, |l| l.parse().unwrap());
+                let mut request_body = vec![0; length];
+                reader.read_exact(&mut request_body).unwrap();
+
+                let extra: String = headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect();
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Canned\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    extra,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+                received.push(Received {
+                    method,
+                    path,
+                    headers: request_headers,
+                    body: String::from_utf8(request_body).unwrap(),
+                });
+            }
+            received
+        });
+        (url, server)
+    }
+
+    fn multistatus(responses: &str) -> String {
+        format!(r#"<?xml version="1.0" encoding="utf-8"?><d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#, responses)
+    }
+
+    fn etag_response(href: &str, etag: &str) -> String {
+        format!(
+            "<d:response><d:href>{}</d:href><d:propstat><d:prop><d:getetag>{}</d:getetag></d:prop>\
+             <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            href,
+            xml_escape(etag)
+        )
+    }
+
+    fn config(base_url: Url, calendar_url: Option<Url>, conflict_policy: ConflictPolicy) -> CalDavConfig {
+        CalDavConfig {
+            base_url,
+            username: String::new(),
+            password: String::new(),
+            calendar_url,
+            calendar_name: None,
+            conflict_policy,
+        }
+    }
+
+    #[test]
+    fn to_vevent_round_trips_through_ics() {
+        let conn = connection();
+        insert_event(&conn, "new", None, None, true);
+        let event = to_vevent(&local(&conn, "new").unwrap()).unwrap();
+        let parsed = ical::parse_event(&ical::write_event(&event, None)).unwrap();
+        assert_eq!((parsed.uid.as_str(), parsed.summary.as_str(), parsed.sequence), ("new", "Local new", 0));
+        assert_eq!(parsed.start, parse_db_timestamp("2025-01-10 09:00:00").unwrap());
+        assert_eq!(parsed.end, parse_db_timestamp("2025-01-10 10:00:00").unwrap());
+
+        // An edit keeps what only the server copy has and bumps its sequence
+        let server_copy = ics("synced", "Old title", "20250101T000000Z")
+            .replace("SUMMARY:", "SEQUENCE:3\r\nDESCRIPTION:Bring slides\r\nSUMMARY:");
+        let id = insert_event(&conn, "synced", Some("/cal/synced.ics"), Some("\"1\""), true);
+        conn.execute("UPDATE events SET caldav_ics = ?1 WHERE id = ?2", params![server_copy, id]).unwrap();
+        let previous = local(&conn, "synced").unwrap();
+        let event = to_vevent(&previous).unwrap();
+        let parsed = ical::parse_event(&ical::write_event(&event, previous.ics.as_deref())).unwrap();
+        assert_eq!(parsed.summary, "Local synced");
+        assert_eq!(parsed.sequence, 4);
+        assert_eq!(parsed.description.as_deref(), Some("Bring slides"));
+    }
+
+    #[test]
+    fn load_reads_the_conflict_policy() {
+        let conn = connection();
+        write_setting(&conn, "caldav_url", Some("http://localhost:5232/user")).unwrap();
+        let policy = |value: Option<&str>| {
+            write_setting(&conn, "caldav_conflict_policy", value).unwrap();
+            CalDavConfig::load(&conn).unwrap().conflict_policy
+        };
+        assert_eq!(policy(None), ConflictPolicy::Newest);
+        assert_eq!(policy(Some("server")), ConflictPolicy::Server);
+        assert_eq!(policy(Some("client")), ConflictPolicy::Client);
+        assert_eq!(policy(Some("unknown")), ConflictPolicy::Newest);
+        assert_eq!(CalDavConfig::load(&conn).unwrap().base_url.as_str(), "http://localhost:5232/user/");
+    }
+
+    #[test]
+    fn apply_remote_resolves_conflicts_by_policy() {
+        // The local edit is from 2025-01-03 12:00; the server edit before or after it
+        let cases = [
+            (ConflictPolicy::Server, "20250101T000000Z", "server"),
+            (ConflictPolicy::Client, "20250105T000000Z", "client"),
+            (ConflictPolicy::Newest, "20250102T000000Z", "client"),
+            (ConflictPolicy::Newest, "20250104T000000Z", "server"),
+        ];
+        for (policy, last_modified, kept) in cases {
+            let mut conn = connection();
+            insert_event(&conn, "a", Some("/cal/a.ics"), Some("\"1\""), true);
+            let resources = [resource("/cal/a.ics", "\"2\"", ics("a", "Server title", last_modified))];
+            let mut report = SyncReport::default();
+            apply_remote(&mut conn, policy, &RemoteChanges::default(), &resources, &mut report).unwrap();
+
+            let event = local(&conn, "a").unwrap();
+            assert_eq!(report.conflicts.len(), 1);
+            assert_eq!(report.conflicts[0].kept, kept, "{:?} at {}", policy, last_modified);
+            // Either way the server ETag is adopted, so a kept local edit replaces that version
+            assert_eq!(event.etag.as_deref(), Some("\"2\""));
+            if kept == "server" {
+                assert_eq!((event.title.as_str(), event.dirty, report.pulled), ("Server title", false, 1));
+            } else {
+                assert_eq!((event.title.as_str(), event.dirty, report.pulled), ("Local a", true, 0));
+                assert_eq!(event.put_precondition(), PutPrecondition::Replace("\"2\""));
+            }
+        }
+    }
+
+    #[test]
+    fn apply_remote_pulls_clean_events_and_keeps_edits_on_the_current_version() {
+        let mut conn = connection();
+        insert_event(&conn, "clean", Some("/cal/clean.ics"), Some("\"1\""), false);
+        insert_event(&conn, "edited", Some("/cal/edited.ics"), Some("\"1\""), true);
+        let resources = [
+            resource("/cal/clean.ics", "\"2\"", ics("clean", "Moved", "20250101T000000Z")),
+            resource("/cal/edited.ics", "\"1\"", ics("edited", "Unchanged", "20250101T000000Z")),
+            resource("/cal/remote.ics", "\"1\"", ics("remote", "New on the server", "20250101T000000Z")),
+        ];
+        let mut report = SyncReport::default();
+        apply_remote(&mut conn, ConflictPolicy::Server, &RemoteChanges::default(), &resources, &mut report).unwrap();
+
+        assert_eq!((report.pulled, report.conflicts.len()), (2, 0));
+        assert_eq!(local(&conn, "clean").unwrap().title, "Moved");
+        assert_eq!(local(&conn, "edited").unwrap().title, "Local edited");
+        let remote = local(&conn, "remote").unwrap();
+        assert_eq!((remote.href.as_deref(), remote.etag.as_deref(), remote.dirty), (Some("/cal/remote.ics"), Some("\"1\""), false));
+    }
+
+    #[test]
+    fn apply_remote_deletes_what_a_full_listing_no_longer_has() {
+        let mut conn = connection();
+        insert_event(&conn, "listed", Some("/cal/listed.ics"), Some("\"1\""), false);
+        insert_event(&conn, "gone", Some("/cal/gone.ics"), Some("\"1\""), false);
+        insert_event(&conn, "gone-edited", Some("/cal/gone-edited.ics"), Some("\"1\""), true);
+        insert_event(&conn, "unsynced", None, None, true);
+        let changes = RemoteChanges {
+            full: true,
+            changed: vec![("/cal/listed.ics".to_string(), "\"1\"".to_string())],
+            ..Default::default()
+        };
+        let mut report = SyncReport::default();
+        apply_remote(&mut conn, ConflictPolicy::Newest, &changes, &[], &mut report).unwrap();
+
+        assert_eq!(report.deleted_locally, 1);
+        assert!(local(&conn, "listed").is_some());
+        assert!(local(&conn, "gone").is_none());
+        // Edited here but deleted there: kept and uploaded again as a new resource
+        let edited = local(&conn, "gone-edited").unwrap();
+        assert_eq!((edited.href.as_deref(), edited.etag.as_deref()), (None, None));
+        assert_eq!(edited.put_precondition(), PutPrecondition::Create);
+        assert_eq!(local(&conn, "unsynced").unwrap().put_precondition(), PutPrecondition::Create);
+    }
+
+    #[test]
+    fn put_precondition_never_guards_a_stored_resource_with_if_none_match() {
+        let conn = connection();
+        insert_event(&conn, "new", None, None, true);
+        insert_event(&conn, "known", Some("/cal/known.ics"), Some("\"7\""), true);
+        insert_event(&conn, "no-etag", Some("/cal/no-etag.ics"), None, true);
+        assert_eq!(local(&conn, "new").unwrap().put_precondition(), PutPrecondition::Create);
+        assert_eq!(local(&conn, "known").unwrap().put_precondition(), PutPrecondition::Replace("\"7\""));
+        assert_eq!(local(&conn, "no-etag").unwrap().put_precondition(), PutPrecondition::Overwrite);
+    }
+
+    #[tokio::test]
+    async fn changes_falls_back_to_a_full_listing() {
+        let listing = multistatus(&format!(
+            "<d:response><d:href>/cal/</d:href><d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>\
+             <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>{}{}",
+            etag_response("/cal/a.ics", "\"1\""),
+            etag_response("/cal/b.ics", "\"2\"")
+        ));
+        // No sync-collection support, then an expired token that is retried without it
+        let (url, server) = serve(vec![
+            (501, vec![], String::new()),
+            (207, vec![], listing),
+            (403, vec![], String::new()),
+            (207, vec![], multistatus(&format!("{}<d:sync-token>tok-2</d:sync-token>", etag_response("/cal/a.ics", "\"1\"")))),
+        ]);
+        let calendar = url.join("/cal/").unwrap();
+        let client = CalDavClient::new(&config(url, None, ConflictPolicy::Newest));
+
+        let changes = client.changes(&calendar, None).await.unwrap();
+        assert!(changes.full);
+        assert_eq!(changes.changed.iter().map(|(href, _)| href.as_str()).collect::<Vec<_>>(), ["/cal/a.ics", "/cal/b.ics"]);
+        assert_eq!(changes.new_token, None);
+
+        let changes = client.changes(&calendar, Some("tok-1")).await.unwrap();
+        assert!(changes.full);
+        assert_eq!(changes.new_token.as_deref(), Some("tok-2"));
+
+        let received = server.join().unwrap();
+        let methods: Vec<_> = received.iter().map(|r| r.method.as_str()).collect();
+        assert_eq!(methods, ["REPORT", "PROPFIND", "REPORT", "REPORT"]);
+        assert_eq!(received[1].headers["depth"], "1");
+        assert!(received[2].body.contains("<d:sync-token>tok-1</d:sync-token>"));
+        assert!(received[3].body.contains("<d:sync-token></d:sync-token>"));
+    }
+
+    #[tokio::test]
+    async fn sync_pulls_then_pushes_deletions_and_edits() {
+        let mut conn = connection();
+        insert_event(&conn, "a", Some("/cal/a.ics"), Some("\"a1\""), false);
+        insert_event(&conn, "b", Some("/cal/b.ics"), Some("\"b1\""), false);
+        // Stored by a server that returned no ETag on PUT, then edited again
+        insert_event(&conn, "c", Some("/cal/c.ics"), None, true);
+        insert_event(&conn, "d", None, None, true);
+        conn.execute("INSERT INTO caldav_tombstones (href, etag) VALUES ('/cal/e.ics', '\"e1\"')", []).unwrap();
+
+        let changed = multistatus(&format!(
+            "{}<d:response><d:href>/cal/b.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>\
+             <d:sync-token>tok-2</d:sync-token>",
+            etag_response("/cal/a.ics", "\"a2\"")
+        ));
+        let fetched = multistatus(&format!(
+            "<d:response><d:href>/cal/a.ics</d:href><d:propstat><d:prop><d:getetag>\"a2\"</d:getetag>\
+             <c:calendar-data>{}</c:calendar-data></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            ics("a", "Moved on the phone", "20250105T000000Z")
+        ));
+        let (url, server) = serve(vec![
+            (207, vec![], changed),
+            (207, vec![], fetched),
+            (204, vec![], String::new()),
+            (204, vec![], String::new()),
+            (207, vec![], multistatus(&etag_response("/cal/c.ics", "\"c2\""))),
+            (201, vec![("ETag", "\"d1\"")], String::new()),
+        ]);
+        let calendar = url.join("/cal/").unwrap();
+        save_sync_token(&conn, &calendar, Some("tok-1")).unwrap();
+
+        let report = sync(&mut conn, &config(url, Some(calendar.clone()), ConflictPolicy::Newest)).await.unwrap();
+        assert_eq!(
+            (report.pulled, report.deleted_locally, report.deleted_remotely, report.pushed, report.conflicts.len()),
+            (1, 1, 1, 2, 0)
+        );
+
+        let received = server.join().unwrap();
+        let requests: Vec<_> = received.iter().map(|r| (r.method.as_str(), r.path.as_str())).collect();
+        assert_eq!(
+            requests,
+            [
+                ("REPORT", "/cal/"),
+                ("REPORT", "/cal/"),
+                ("DELETE", "/cal/e.ics"),
+                ("PUT", "/cal/c.ics"),
+                ("PROPFIND", "/cal/c.ics"),
+                ("PUT", "/cal/d.ics"),
+            ]
+        );
+        assert!(received[0].body.contains("<d:sync-token>tok-1</d:sync-token>"));
+        assert!(received[1].body.contains("<d:href>/cal/a.ics</d:href>"));
+        assert_eq!(received[2].headers.get("if-match").map(String::as_str), Some("\"e1\""));
+        // A stored resource without a known ETag is sent without a precondition
+        assert!(!received[3].headers.contains_key("if-match") && !received[3].headers.contains_key("if-none-match"));
+        assert_eq!(received[4].headers["depth"], "0");
+        assert_eq!(received[5].headers.get("if-none-match").map(String::as_str), Some("*"));
+
+        let a = local(&conn, "a").unwrap();
+        assert_eq!((a.title.as_str(), a.etag.as_deref()), ("Moved on the phone", Some("\"a2\"")));
+        assert!(local(&conn, "b").is_none());
+        let c = local(&conn, "c").unwrap();
+        assert_eq!((c.etag.as_deref(), c.dirty), (Some("\"c2\""), false));
+        let d = local(&conn, "d").unwrap();
+        assert_eq!((d.href.as_deref(), d.etag.as_deref(), d.dirty), (Some("/cal/d.ics"), Some("\"d1\""), false));
+        assert!(tombstones(&conn).unwrap().is_empty());
+        assert_eq!(load_sync_token(&conn, &calendar).unwrap().as_deref(), Some("tok-2"));
+    }
+}
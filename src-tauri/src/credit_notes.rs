@@ -0,0 +1,730 @@
+//! Credit notes (Gutschriften) against finalized invoices.
+//!
+//! A finalized invoice can't be changed, so whatever is taken back is booked on a credit
+//! note: a document with its own number from `settings.credit_note_number_pattern` whose
+//! lines mirror invoice lines with negated amounts. A credit note takes back whole lines,
+//! part of their quantity or a fixed amount, never more than is left on a line. It is issued
+//! when it is created and is immutable from then on.
+//!
+//! Tax is rounded like on the invoice, except that a credit note taking back the last net of a
+//! rate takes back exactly the tax still left on it, so an invoice credited in part after part
+//! still nets to zero. Credit notes lower what is due on their invoice; money already paid
+//! beyond that goes to the client's credit. Voiding an invoice issues a credit note for
+//! everything still open.
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::db_api::{open_state_db, DbApiError, DB_TIMESTAMP_FORMAT};
+use crate::invoices::{invoice_totals, tax_rounding_name, transition, InvoiceError, InvoiceStatus};
+use crate::ledger::{post_invoice, LedgerError};
+use crate::members::current_member_id;
+use crate::money::{
+    document_totals, line_amounts, round_div, DocumentTotals, LineAmounts, TaxBreakdown, TaxRounding, FULL_RATE_BP,
+};
+use crate::numbering::{next_number, NumberingError};
+use crate::payments::{release_excess_to_credit, release_to_credit, sync_invoice_status, PaymentError};
+use crate::settings::read_setting;
+use crate::StateWrapper;
+
+/// Numbering pattern used when `settings.credit_note_number_pattern` is not set
+pub const DEFAULT_CREDIT_NOTE_NUMBER_PATTERN: &str = "CN-{YYYY}-{seq:04}";
+
+#[derive(Debug, Error)]
+pub enum CreditNoteError {
+    #[error("[credit_notes.rs::invoice_not_found] Invoice {0} does not exist.")]
+    InvoiceNotFound(i64),
+
+    #[error("[credit_notes.rs::not_found] Credit note {0} does not exist.")]
+    NotFound(i64),
+
+    #[error("[credit_notes.rs::not_creditable] Invoice {0} is {1}; only finalized invoices can be credited.")]
+    NotCreditable(i64, String),
+
+    #[error("[credit_notes.rs::line_not_found] Invoice {0} has no line {1}.")]
+    LineNotFound(i64, i64),
+
+    #[error("[credit_notes.rs::exceeds_line] Only {1} is left to credit on invoice line {0}.")]
+    ExceedsLine(i64, i64),
+
+    #[error("[credit_notes.rs::nothing_to_credit] Nothing is left to credit on invoice {0}.")]
+    NothingToCredit(i64),
+
+    #[error("[credit_notes.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[credit_notes.rs::invoice] {0}")]
+    InvoiceError(#[from] InvoiceError),
+
+    #[error("[credit_notes.rs::payments] {0}")]
+    PaymentError(#[from] PaymentError),
+
+    #[error("[credit_notes.rs::ledger] {0}")]
+    LedgerError(#[from] LedgerError),
+
+    #[error("[credit_notes.rs::numbering] {0}")]
+    NumberingError(#[from] NumberingError),
+
+    #[error("[credit_notes.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[credit_notes.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for CreditNoteError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 🧾 Credit Note Struct. Amounts are in minor units and negative.
+#[derive(Serialize)]
+pub struct CreditNote {
+    pub id: i64,
+    pub number: String,
+    pub invoice_id: i64,
+    pub invoice_number: Option<String>,
+    pub client_id: Option<i64>,
+    pub reason: Option<String>,
+    pub subtotal: i64,
+    pub tax_total: i64,
+    pub total: i64,
+    /// Currency of the invoice
+    pub currency: String,
+    pub member_id: Option<i64>,
+    pub issued_at: String,
+}
+
+/// 🧾 Credit Note Line Struct. The quantity is negative, so net and tax are too.
+#[derive(Serialize)]
+pub struct CreditNoteLine {
+    pub id: i64,
+    pub credit_note_id: i64,
+    pub invoice_line_id: Option<i64>,
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: i64,
+    pub discount_bp: i64,
+    pub tax_rate_bp: i64,
+    pub net_amount: i64,
+    pub tax_amount: i64,
+}
+
+/// ↩️ What to take back from one invoice line: part of its quantity, a fixed net amount
+/// (e.g. a price reduction), or with neither everything still left on the line
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CreditLine {
+    pub invoice_line_id: i64,
+    #[serde(default)]
+    pub quantity: Option<f64>,
+    /// Net amount in minor units, positive
+    #[serde(default)]
+    pub amount: Option<i64>,
+}
+
+struct InvoiceLineToCredit {
+    id: i64,
+    description: String,
+    quantity: f64,
+    unit_price: i64,
+    discount_bp: i64,
+    tax_rate_bp: i64,
+    net_amount: i64,
+    /// Net already taken back by earlier credit notes, with the sign of `net_amount`
+    credited: i64,
+}
+
+impl InvoiceLineToCredit {
+    fn remaining(&self) -> i64 {
+        self.net_amount - self.credited
+    }
+}
+
+struct NewLine {
+    invoice_line_id: i64,
+    description: String,
+    quantity: f64,
+    unit_price: i64,
+    discount_bp: i64,
+    tax_rate_bp: i64,
+    amounts: LineAmounts,
+}
+
+const CREDIT_NOTE_COLUMNS: &str =
+    "n.id, n.number, n.invoice_id, i.number, n.client_id, n.reason, n.subtotal, n.tax_total, n.total, i.currency, n.member_id, n.issued_at";
+
+fn credit_note_from_row(row: &Row) -> rusqlite::Result<CreditNote> {
+    Ok(CreditNote {
+        id: row.get(0)?,
+        number: row.get(1)?,
+        invoice_id: row.get(2)?,
+        invoice_number: row.get(3)?,
+        client_id: row.get(4)?,
+        reason: row.get(5)?,
+        subtotal: row.get(6)?,
+        tax_total: row.get(7)?,
+        total: row.get(8)?,
+        currency: row.get(9)?,
+        member_id: row.get(10)?,
+        issued_at: row.get(11)?,
+    })
+}
+
+fn number_pattern(conn: &Connection) -> Result<String, CreditNoteError> {
+    Ok(read_setting(conn, "credit_note_number_pattern")?
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| p.trim().to_string())
+        .unwrap_or_else(|| DEFAULT_CREDIT_NOTE_NUMBER_PATTERN.to_string()))
+}
+
+fn invoice_lines(conn: &Connection, invoice_id: i64) -> Result<Vec<InvoiceLineToCredit>, CreditNoteError> {
+    let mut stmt = conn.prepare(
+        "SELECT l.id, l.description, l.quantity, l.unit_price, l.discount_bp, l.tax_rate_bp, l.net_amount,
+                -COALESCE((SELECT SUM(c.net_amount) FROM credit_note_lines c WHERE c.invoice_line_id = l.id), 0)
+         FROM invoice_lines l WHERE l.invoice_id = ?1 ORDER BY l.position, l.id"
+    )?;
+    let lines = stmt
+        .query_map([invoice_id], |row| {
+            Ok(InvoiceLineToCredit {
+                id: row.get(0)?,
+                description: row.get(1)?,
+                quantity: row.get(2)?,
+                unit_price: row.get(3)?,
+                discount_bp: row.get(4)?,
+                tax_rate_bp: row.get(5)?,
+                net_amount: row.get(6)?,
+                credited: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+/// Negated line for a fixed net amount taken back from `line`
+fn amount_line(line: &InvoiceLineToCredit, net: i64) -> NewLine {
+    NewLine {
+        invoice_line_id: line.id,
+        description: line.description.clone(),
+        quantity: -1.0,
+        unit_price: net,
+        discount_bp: 0,
+        tax_rate_bp: line.tax_rate_bp,
+        amounts: LineAmounts {
+            net: -net,
+            tax: round_div(-net as i128 * line.tax_rate_bp as i128, FULL_RATE_BP as i128),
+        },
+    }
+}
+
+fn credit_line(line: &InvoiceLineToCredit, credit: &CreditLine) -> Result<NewLine, CreditNoteError> {
+    let new_line = match (credit.quantity, credit.amount) {
+        (Some(_), Some(_)) => {
+            return Err(CreditNoteError::InvalidValue("amount", "give either a quantity or an amount".to_string()))
+        }
+        (Some(quantity), None) => {
+            if !quantity.is_finite() || quantity <= 0.0 {
+                return Err(CreditNoteError::InvalidValue("quantity", quantity.to_string()));
+            }
+            NewLine {
+                invoice_line_id: line.id,
+                description: line.description.clone(),
+                quantity: -quantity,
+                unit_price: line.unit_price,
+                discount_bp: line.discount_bp,
+                tax_rate_bp: line.tax_rate_bp,
+                amounts: line_amounts(-quantity, line.unit_price, line.discount_bp, line.tax_rate_bp),
+            }
+        }
+        (None, Some(amount)) => {
+            if amount <= 0 {
+                return Err(CreditNoteError::InvalidValue("amount", amount.to_string()));
+            }
+            amount_line(line, amount * line.net_amount.signum())
+        }
+        // Untouched lines are copied as they are, so a full credit note mirrors the invoice
+        (None, None) if line.credited == 0 => NewLine {
+            invoice_line_id: line.id,
+            description: line.description.clone(),
+            quantity: -line.quantity,
+            unit_price: line.unit_price,
+            discount_bp: line.discount_bp,
+            tax_rate_bp: line.tax_rate_bp,
+            amounts: line_amounts(-line.quantity, line.unit_price, line.discount_bp, line.tax_rate_bp),
+        },
+        (None, None) => amount_line(line, line.remaining()),
+    };
+
+    // Taken back has the sign of the line and never goes past zero
+    let taken = -new_line.amounts.net;
+    let remaining = line.remaining();
+    if taken == 0 || taken.signum() != line.net_amount.signum() || taken.abs() > remaining.abs() {
+        return Err(CreditNoteError::ExceedsLine(line.id, remaining));
+    }
+    Ok(new_line)
+}
+
+/// Issues a credit note on a finalized invoice inside the caller's transaction and returns
+/// its id. Without `lines` everything still left on the invoice is taken back.
+/// Payments and invoice status are left to the caller.
+pub fn create(
+    conn: &Connection,
+    invoice_id: i64,
+    lines: &[CreditLine],
+    reason: Option<&str>,
+    member_id: Option<i64>,
+) -> Result<i64, CreditNoteError> {
+    let (client_id, status, rounding): (Option<i64>, String, String) = conn
+        .query_row("SELECT client_id, status, tax_rounding FROM invoices WHERE id = ?1", [invoice_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .optional()?
+        .ok_or(CreditNoteError::InvoiceNotFound(invoice_id))?;
+    if matches!(InvoiceStatus::parse(&status), Some(InvoiceStatus::Draft) | Some(InvoiceStatus::Void) | None) {
+        return Err(CreditNoteError::NotCreditable(invoice_id, status));
+    }
+    let rounding = if rounding == "PerLine" { TaxRounding::PerLine } else { TaxRounding::PerRate };
+
+    let mut invoice_lines = invoice_lines(conn, invoice_id)?;
+    let mut new_lines = Vec::new();
+    if lines.is_empty() {
+        for line in invoice_lines.iter().filter(|l| l.remaining() != 0) {
+            new_lines.push(credit_line(line, &CreditLine { invoice_line_id: line.id, quantity: None, amount: None })?);
+        }
+    } else {
+        for credit in lines {
+            let line = invoice_lines
+                .iter_mut()
+                .find(|l| l.id == credit.invoice_line_id)
+                .ok_or(CreditNoteError::LineNotFound(invoice_id, credit.invoice_line_id))?;
+            let new_line = credit_line(line, credit)?;
+            // A line listed twice can't be taken back twice
+            line.credited -= new_line.amounts.net;
+            new_lines.push(new_line);
+        }
+    }
+    if new_lines.is_empty() {
+        return Err(CreditNoteError::NothingToCredit(invoice_id));
+    }
+
+    let amounts: Vec<_> = new_lines.iter().map(|l| (l.amounts, l.tax_rate_bp)).collect();
+    let mut totals = document_totals(&amounts, rounding);
+    settle_last_rates(conn, invoice_id, None, &mut totals)?;
+    if rounding == TaxRounding::PerLine {
+        // Line taxes are what the totals add up, so the last line of a rate takes the difference
+        for rate in &totals.taxes {
+            let of_rate = || new_lines.iter().filter(|l| l.tax_rate_bp == rate.tax_rate_bp);
+            let difference = rate.tax - of_rate().map(|l| l.amounts.tax).sum::<i64>();
+            if let Some(last) = new_lines.iter_mut().rev().find(|l| l.tax_rate_bp == rate.tax_rate_bp) {
+                last.amounts.tax += difference;
+            }
+        }
+    }
+    let now = Utc::now().naive_utc();
+    let number = next_number(conn, "credit_note", &number_pattern(conn)?, now.date())?;
+    conn.execute(
+        "INSERT INTO credit_notes (number, invoice_id, client_id, reason, subtotal, tax_total, total, tax_rounding, member_id, issued_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            number,
+            invoice_id,
+            client_id,
+            reason.map(str::trim).filter(|r| !r.is_empty()),
+            totals.subtotal,
+            totals.tax_total,
+            totals.total,
+            tax_rounding_name(rounding),
+            member_id,
+            now.format(DB_TIMESTAMP_FORMAT).to_string()
+        ],
+    )?;
+    let credit_note_id = conn.last_insert_rowid();
+
+    for (position, line) in new_lines.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO credit_note_lines (credit_note_id, invoice_line_id, position, description, quantity, unit_price,
+                                            discount_bp, tax_rate_bp, net_amount, tax_amount)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                credit_note_id,
+                line.invoice_line_id,
+                position as i64,
+                line.description,
+                line.quantity,
+                line.unit_price,
+                line.discount_bp,
+                line.tax_rate_bp,
+                line.amounts.net,
+                line.amounts.tax
+            ],
+        )?;
+    }
+    Ok(credit_note_id)
+}
+
+/// Issues a credit note and settles the invoice: whatever was paid beyond the new balance
+/// becomes client credit and the status follows the balance, so an invoice credited in full
+/// ends up void rather than paid
+pub fn issue(
+    conn: &mut Connection,
+    invoice_id: i64,
+    lines: &[CreditLine],
+    reason: Option<&str>,
+    member_id: Option<i64>,
+) -> Result<i64, CreditNoteError> {
+    let tx = conn.transaction()?;
+    let credit_note_id = create(&tx, invoice_id, lines, reason, member_id)?;
+    release_excess_to_credit(&tx, invoice_id)?;
+    sync_invoice_status(&tx, invoice_id, member_id)?;
+    post_invoice(&tx, invoice_id)?;
+    tx.commit()?;
+    Ok(credit_note_id)
+}
+
+/// Voids an invoice: a credit note takes back everything still open, the number stays taken
+/// and any payments become client credit. Returns the credit note, `None` when earlier credit
+/// notes already took everything back.
+pub fn void(
+    conn: &mut Connection,
+    invoice_id: i64,
+    reason: Option<&str>,
+    member_id: Option<i64>,
+) -> Result<Option<i64>, CreditNoteError> {
+    let tx = conn.transaction()?;
+    let credit_note_id = match create(&tx, invoice_id, &[], reason, member_id) {
+        Ok(id) => Some(id),
+        Err(CreditNoteError::NothingToCredit(_)) => None,
+        Err(err) => return Err(err),
+    };
+    transition(&tx, invoice_id, InvoiceStatus::Void, member_id, reason)?;
+    release_to_credit(&tx, invoice_id)?;
+    post_invoice(&tx, invoice_id)?;
+    tx.commit()?;
+    Ok(credit_note_id)
+}
+
+/// A credit note that takes back the last net of a rate takes back the tax still left on
+/// it instead of rounding again. `before` limits the earlier credit notes to those issued
+/// before that one; `None` counts all of them.
+fn settle_last_rates(
+    conn: &Connection,
+    invoice_id: i64,
+    before: Option<i64>,
+    totals: &mut DocumentTotals,
+) -> Result<(), CreditNoteError> {
+    let invoiced = invoice_totals(conn, invoice_id)?;
+    let mut credited: Vec<TaxBreakdown> = Vec::new();
+    let mut stmt = conn.prepare("SELECT id FROM credit_notes WHERE invoice_id = ?1 AND (?2 IS NULL OR id < ?2)")?;
+    let earlier = stmt.query_map(params![invoice_id, before], |row| row.get(0))?.collect::<Result<Vec<i64>, _>>()?;
+    for credit_note_id in earlier {
+        // A rate an earlier note cleared has no net left for this one, so rounding is all it saw
+        for tax in rounded_totals(conn, credit_note_id)?.taxes {
+            match credited.iter_mut().find(|t| t.tax_rate_bp == tax.tax_rate_bp) {
+                Some(entry) => {
+                    entry.net += tax.net;
+                    entry.tax += tax.tax;
+                }
+                None => credited.push(tax),
+            }
+        }
+    }
+
+    for entry in totals.taxes.iter_mut() {
+        let Some(invoiced) = invoiced.taxes.iter().find(|t| t.tax_rate_bp == entry.tax_rate_bp) else { continue };
+        let (net, tax) = credited
+            .iter()
+            .find(|t| t.tax_rate_bp == entry.tax_rate_bp)
+            .map_or((0, 0), |t| (t.net, t.tax));
+        if invoiced.net + net + entry.net == 0 {
+            entry.tax = -(invoiced.tax + tax);
+        }
+    }
+    totals.tax_total = totals.taxes.iter().map(|t| t.tax).sum();
+    totals.total = totals.subtotal + totals.tax_total;
+    Ok(())
+}
+
+/// Totals of a credit note from its stored line amounts, negative
+pub fn credit_note_totals(conn: &Connection, credit_note_id: i64) -> Result<DocumentTotals, CreditNoteError> {
+    let invoice_id: i64 = conn
+        .query_row("SELECT invoice_id FROM credit_notes WHERE id = ?1", [credit_note_id], |row| row.get(0))
+        .optional()?
+        .ok_or(CreditNoteError::NotFound(credit_note_id))?;
+    let mut totals = rounded_totals(conn, credit_note_id)?;
+    settle_last_rates(conn, invoice_id, Some(credit_note_id), &mut totals)?;
+    Ok(totals)
+}
+
+/// Totals of a credit note rounded like its invoice, before `settle_last_rates`
+fn rounded_totals(conn: &Connection, credit_note_id: i64) -> Result<DocumentTotals, CreditNoteError> {
+    let rounding: String = conn
+        .query_row("SELECT tax_rounding FROM credit_notes WHERE id = ?1", [credit_note_id], |row| row.get(0))
+        .optional()?
+        .ok_or(CreditNoteError::NotFound(credit_note_id))?;
+    let rounding = if rounding == "PerLine" { TaxRounding::PerLine } else { TaxRounding::PerRate };
+
+    let mut stmt =
+        conn.prepare("SELECT net_amount, tax_amount, tax_rate_bp FROM credit_note_lines WHERE credit_note_id = ?1")?;
+    let lines = stmt
+        .query_map([credit_note_id], |row| {
+            Ok((LineAmounts { net: row.get(0)?, tax: row.get(1)? }, row.get(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(document_totals(&lines, rounding))
+}
+
+/// ↩️ Issue a credit note on a finalized invoice; without lines everything still open is taken back
+#[tauri::command]
+pub fn issue_credit_note(
+    state: tauri::State<StateWrapper>,
+    invoice_id: i64,
+    lines: Option<Vec<CreditLine>>,
+    reason: Option<String>,
+) -> Result<i64, CreditNoteError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    issue(&mut db_conn, invoice_id, &lines.unwrap_or_default(), reason.as_deref(), member_id)
+}
+
+/// 🚫 Void an issued invoice with a credit note for what is still open. Its number stays taken
+/// and any payments become client credit.
+#[tauri::command]
+pub fn void_invoice(
+    state: tauri::State<StateWrapper>,
+    invoice_id: i64,
+    reason: Option<String>,
+) -> Result<Option<i64>, CreditNoteError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    void(&mut db_conn, invoice_id, reason.as_deref(), member_id)
+}
+
+/// 📋 List credit notes, optionally of one client or one invoice
+#[tauri::command]
+pub fn list_credit_notes(
+    state: tauri::State<StateWrapper>,
+    client_id: Option<i64>,
+    invoice_id: Option<i64>,
+) -> Result<Vec<CreditNote>, CreditNoteError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {} FROM credit_notes n JOIN invoices i ON i.id = n.invoice_id
+         WHERE (?1 IS NULL OR n.client_id = ?1) AND (?2 IS NULL OR n.invoice_id = ?2)
+         ORDER BY n.id DESC",
+        CREDIT_NOTE_COLUMNS
+    ))?;
+    let notes = stmt
+        .query_map(params![client_id, invoice_id], credit_note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+/// 🔍 Get one credit note
+#[tauri::command]
+pub fn get_credit_note(state: tauri::State<StateWrapper>, credit_note_id: i64) -> Result<CreditNote, CreditNoteError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM credit_notes n JOIN invoices i ON i.id = n.invoice_id WHERE n.id = ?1",
+                CREDIT_NOTE_COLUMNS
+            ),
+            [credit_note_id],
+            credit_note_from_row,
+        )
+        .optional()?
+        .ok_or(CreditNoteError::NotFound(credit_note_id))
+}
+
+/// 📄 List the lines of a credit note
+#[tauri::command]
+pub fn list_credit_note_lines(
+    state: tauri::State<StateWrapper>,
+    credit_note_id: i64,
+) -> Result<Vec<CreditNoteLine>, CreditNoteError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, credit_note_id, invoice_line_id, description, quantity, unit_price, discount_bp, tax_rate_bp,
+                net_amount, tax_amount
+         FROM credit_note_lines WHERE credit_note_id = ?1 ORDER BY position, id"
+    )?;
+    let lines = stmt
+        .query_map([credit_note_id], |row| {
+            Ok(CreditNoteLine {
+                id: row.get(0)?,
+                credit_note_id: row.get(1)?,
+                invoice_line_id: row.get(2)?,
+                description: row.get(3)?,
+                quantity: row.get(4)?,
+                unit_price: row.get(5)?,
+                discount_bp: row.get(6)?,
+                tax_rate_bp: row.get(7)?,
+                net_amount: row.get(8)?,
+                tax_amount: row.get(9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+/// 🧮 Subtotal, tax per rate and total of a credit note, negative minor units
+#[tauri::command]
+pub fn get_credit_note_totals(state: tauri::State<StateWrapper>, credit_note_id: i64) -> Result<DocumentTotals, CreditNoteError> {
+    let db_conn = open_state_db(&state)?;
+    credit_note_totals(&db_conn, credit_note_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT);
+            CREATE TABLE number_sequences (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                scope TEXT NOT NULL,
+                period TEXT NOT NULL,
+                last_value INTEGER NOT NULL,
+                UNIQUE (scope, period)
+            );
+            CREATE TABLE invoices (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                client_id INTEGER,
+                status TEXT NOT NULL,
+                tax_rounding TEXT NOT NULL,
+                subtotal INTEGER NOT NULL,
+                tax_total INTEGER NOT NULL,
+                total INTEGER NOT NULL
+            );
+            CREATE TABLE invoice_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                invoice_id INTEGER NOT NULL,
+                position INTEGER NOT NULL DEFAULT 0,
+                description TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                unit_price INTEGER NOT NULL,
+                discount_bp INTEGER NOT NULL DEFAULT 0,
+                tax_rate_bp INTEGER NOT NULL DEFAULT 0,
+                net_amount INTEGER NOT NULL,
+                tax_amount INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE credit_notes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                number TEXT NOT NULL UNIQUE,
+                invoice_id INTEGER NOT NULL,
+                client_id INTEGER,
+                reason TEXT,
+                subtotal INTEGER NOT NULL,
+                tax_total INTEGER NOT NULL,
+                total INTEGER NOT NULL,
+                tax_rounding TEXT NOT NULL,
+                member_id INTEGER,
+                issued_at TIMESTAMP NOT NULL
+            );
+            CREATE TABLE credit_note_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                credit_note_id INTEGER NOT NULL,
+                invoice_line_id INTEGER,
+                position INTEGER NOT NULL DEFAULT 0,
+                description TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                unit_price INTEGER NOT NULL,
+                discount_bp INTEGER NOT NULL DEFAULT 0,
+                tax_rate_bp INTEGER NOT NULL DEFAULT 0,
+                net_amount INTEGER NOT NULL,
+                tax_amount INTEGER NOT NULL DEFAULT 0
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// An issued invoice with one line of `quantity` × `unit_price` at `tax_rate_bp`
+    fn invoice(conn: &Connection, rounding: TaxRounding, quantity: f64, unit_price: i64, tax_rate_bp: i64) -> (i64, i64) {
+        let amounts = line_amounts(quantity, unit_price, 0, tax_rate_bp);
+        let totals = document_totals(&[(amounts, tax_rate_bp)], rounding);
+        conn.execute(
+            "INSERT INTO invoices (status, tax_rounding, subtotal, tax_total, total) VALUES ('Issued', ?1, ?2, ?3, ?4)",
+            params![tax_rounding_name(rounding), totals.subtotal, totals.tax_total, totals.total],
+        )
+        .unwrap();
+        let invoice_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO invoice_lines (invoice_id, description, quantity, unit_price, tax_rate_bp, net_amount, tax_amount)
+             VALUES (?1, 'Consulting', ?2, ?3, ?4, ?5, ?6)",
+            params![invoice_id, quantity, unit_price, tax_rate_bp, amounts.net, amounts.tax],
+        )
+        .unwrap();
+        (invoice_id, conn.last_insert_rowid())
+    }
+
+    fn stored(conn: &Connection, credit_note_id: i64) -> (i64, i64, i64) {
+        conn.query_row(
+            "SELECT subtotal, tax_total, total FROM credit_notes WHERE id = ?1",
+            [credit_note_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap()
+    }
+
+    fn half(line_id: i64) -> CreditLine {
+        CreditLine { invoice_line_id: line_id, quantity: Some(1.0), amount: None }
+    }
+
+    #[test]
+    fn two_half_credits_take_back_the_whole_tax() {
+        let conn = connection();
+        // 2 × 10.05 at 8.1%: net 20.10, tax 1.6281 rounds to 1.63, each half's 0.81405 to 0.81
+        let (invoice_id, line_id) = invoice(&conn, TaxRounding::PerRate, 2.0, 1005, 810);
+
+        let first = create(&conn, invoice_id, &[half(line_id)], None, None).unwrap();
+        let second = create(&conn, invoice_id, &[half(line_id)], None, None).unwrap();
+        assert_eq!(stored(&conn, first), (-1005, -81, -1086));
+        assert_eq!(stored(&conn, second), (-1005, -82, -1087));
+
+        for credit_note_id in [first, second] {
+            let totals = credit_note_totals(&conn, credit_note_id).unwrap();
+            assert_eq!((totals.subtotal, totals.tax_total, totals.total), stored(&conn, credit_note_id));
+        }
+        assert_eq!(
+            credit_note_totals(&conn, second).unwrap().taxes,
+            vec![TaxBreakdown { tax_rate_bp: 810, net: -1005, tax: -82 }]
+        );
+    }
+
+    #[test]
+    fn last_credit_line_takes_the_remaining_tax_per_line() {
+        let conn = connection();
+        let (invoice_id, line_id) = invoice(&conn, TaxRounding::PerLine, 2.0, 1005, 810);
+
+        create(&conn, invoice_id, &[half(line_id)], None, None).unwrap();
+        let last = create(&conn, invoice_id, &[], None, None).unwrap();
+        assert_eq!(stored(&conn, last), (-1005, -82, -1087));
+        let line_tax: i64 = conn
+            .query_row("SELECT tax_amount FROM credit_note_lines WHERE credit_note_id = ?1", [last], |row| row.get(0))
+            .unwrap();
+        assert_eq!(line_tax, -82);
+        assert_eq!(credit_note_totals(&conn, last).unwrap().tax_total, -82);
+    }
+
+    #[test]
+    fn partial_credits_round_as_usual() {
+        let conn = connection();
+        let (invoice_id, line_id) = invoice(&conn, TaxRounding::PerRate, 3.0, 1005, 810);
+
+        let first = create(&conn, invoice_id, &[half(line_id)], None, None).unwrap();
+        let second = create(&conn, invoice_id, &[half(line_id)], None, None).unwrap();
+        assert_eq!(stored(&conn, first), (-1005, -81, -1086));
+        assert_eq!(stored(&conn, second), (-1005, -81, -1086));
+        let last = create(&conn, invoice_id, &[], None, None).unwrap();
+        // 3 × 10.05 at 8.1% is 2.44 of tax, 1.62 of it taken back already
+        assert_eq!(stored(&conn, last), (-1005, -82, -1087));
+    }
+}
@@ -0,0 +1,350 @@
+//! Currencies and exchange rates.
+//!
+//! Every monetary record carries an ISO 4217 code; `settings.base_currency` is the currency
+//! the books are kept in. Rates are maintained locally in `exchange_rates`, entered by hand
+//! or imported from a CSV file or the ECB reference rates XML. A row means one unit of
+//! `base_currency` is worth `rate` units of `quote_currency` on `date`.
+//!
+//! A conversion uses the most recent rate on or before the transaction date: the pair as
+//! stored, its inverse, or a cross rate through a currency both sides are quoted against.
+
+use std::fs;
+
+use chrono::NaiveDate;
+use quick_xml::events::Event as XmlEvent;
+use quick_xml::Reader;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::db_api::{open_state_db, parse_db_timestamp, DbApiError};
+use crate::money::{convert_minor, format_exchange_rate, is_currency_code, parse_rate, DEFAULT_CURRENCY, RATE_SCALE};
+use crate::settings::read_setting;
+use crate::StateWrapper;
+
+#[derive(Debug, Error)]
+pub enum CurrencyError {
+    #[error("[currencies.rs::invalid_currency] {0:?} is not an ISO 4217 currency code.")]
+    InvalidCurrency(String),
+
+    #[error("[currencies.rs::invalid_rate] Invalid exchange rate: {0}")]
+    InvalidRate(String),
+
+    #[error("[currencies.rs::invalid_date] Invalid date: {0}")]
+    InvalidDate(String),
+
+    #[error("[currencies.rs::missing_rate] No exchange rate from {0} to {1} on or before {2}.")]
+    MissingRate(String, String, NaiveDate),
+
+    #[error("[currencies.rs::not_found] Exchange rate {0} does not exist.")]
+    NotFound(i64),
+
+    #[error("[currencies.rs::invalid_file] Could not read the rates file: {0}")]
+    InvalidFile(String),
+
+    #[error("[currencies.rs::io] File error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("[currencies.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[currencies.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for CurrencyError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 💱 Exchange Rate Struct: 1 `base_currency` = `rate` `quote_currency` on `date`.
+/// The rate is a decimal string so no precision is lost on the way.
+#[derive(Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub id: Option<i64>,
+    pub date: String,
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub rate: String,
+    /// `Manual`, `Csv` or `Ecb`; set by the app
+    #[serde(default)]
+    pub source: String,
+}
+
+/// One parsed rate, before it is stored
+struct RateRow {
+    date: NaiveDate,
+    base: String,
+    quote: String,
+    rate: i64,
+}
+
+/// Trims and upper-cases a currency code and checks that it is ISO 4217 shaped
+pub fn normalize_currency(code: &str) -> Result<String, CurrencyError> {
+    let code = code.trim().to_ascii_uppercase();
+    if !is_currency_code(&code) {
+        return Err(CurrencyError::InvalidCurrency(code));
+    }
+    Ok(code)
+}
+
+/// Currency the books are kept in, from `settings.base_currency`
+pub fn base_currency(conn: &Connection) -> Result<String, CurrencyError> {
+    match read_setting(conn, "base_currency")?.filter(|v| !v.trim().is_empty()) {
+        Some(code) => normalize_currency(&code),
+        None => Ok(DEFAULT_CURRENCY.to_string()),
+    }
+}
+
+/// The given currency, normalized, or the base currency when none is given
+pub fn currency_or_base(conn: &Connection, code: Option<&str>) -> Result<String, CurrencyError> {
+    match code.filter(|c| !c.trim().is_empty()) {
+        Some(code) => normalize_currency(code),
+        None => base_currency(conn),
+    }
+}
+
+/// Parses a date as `YYYY-MM-DD`, `DD.MM.YYYY` or a stored timestamp
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d.%m.%Y"))
+        .ok()
+        .or_else(|| parse_db_timestamp(value).map(|t| t.date()))
+}
+
+/// Most recent rate of a stored pair on or before `date`
+fn latest_rate(conn: &Connection, base: &str, quote: &str, date: NaiveDate) -> Result<Option<i64>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT rate FROM exchange_rates
+         WHERE base_currency = ?1 AND quote_currency = ?2 AND date <= ?3
+         ORDER BY date DESC LIMIT 1",
+        params![base, quote, date.to_string()],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Exact rate from `from` to `to` on `date` as `(numerator, denominator)`
+pub fn rate_on(conn: &Connection, from: &str, to: &str, date: NaiveDate) -> Result<(i128, i128), CurrencyError> {
+    if from == to {
+        return Ok((1, 1));
+    }
+    if let Some(rate) = latest_rate(conn, from, to, date)? {
+        return Ok((rate as i128, RATE_SCALE as i128));
+    }
+    if let Some(rate) = latest_rate(conn, to, from, date)? {
+        return Ok((RATE_SCALE as i128, rate as i128));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT base_currency FROM exchange_rates WHERE quote_currency IN (?1, ?2) ORDER BY base_currency"
+    )?;
+    let bases = stmt.query_map(params![from, to], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+    for base in bases {
+        if let (Some(from_rate), Some(to_rate)) = (latest_rate(conn, &base, from, date)?, latest_rate(conn, &base, to, date)?) {
+            return Ok((to_rate as i128, from_rate as i128));
+        }
+    }
+    Err(CurrencyError::MissingRate(from.to_string(), to.to_string(), date))
+}
+
+/// Converts minor units of `from` into minor units of `to` at the rate on `date`
+pub fn convert(conn: &Connection, amount: i64, from: &str, to: &str, date: NaiveDate) -> Result<i64, CurrencyError> {
+    let (numerator, denominator) = rate_on(conn, from, to, date)?;
+    Ok(convert_minor(amount, from, to, numerator, denominator))
+}
+
+fn store_rate(conn: &Connection, row: &RateRow, source: &str) -> Result<i64, CurrencyError> {
+    if row.base == row.quote {
+        return Err(CurrencyError::InvalidRate(format!("{} against itself", row.base)));
+    }
+    conn.execute(
+        "INSERT INTO exchange_rates (date, base_currency, quote_currency, rate, source) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (date, base_currency, quote_currency) DO UPDATE SET rate = excluded.rate, source = excluded.source",
+        params![row.date.to_string(), row.base, row.quote, row.rate, source],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM exchange_rates WHERE date = ?1 AND base_currency = ?2 AND quote_currency = ?3",
+        params![row.date.to_string(), row.base, row.quote],
+        |r| r.get(0),
+    )?)
+}
+
+/// Rates of the ECB reference rates XML (daily, 90 days or full history), all against EUR
+fn parse_ecb_xml(text: &str) -> Result<Vec<RateRow>, CurrencyError> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let mut rows = Vec::new();
+    let mut date = None;
+    loop {
+        match reader.read_event().map_err(|e| CurrencyError::InvalidFile(e.to_string()))? {
+            XmlEvent::Start(tag) | XmlEvent::Empty(tag) if tag.local_name().as_ref() == b"Cube" => {
+                let mut currency = None;
+                let mut rate = None;
+                for attribute in tag.attributes().flatten() {
+                    let value = String::from_utf8_lossy(&attribute.value).into_owned();
+                    match attribute.key.local_name().as_ref() {
+                        b"time" => {
+                            date = Some(
+                                NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                                    .map_err(|_| CurrencyError::InvalidDate(value.clone()))?,
+                            )
+                        }
+                        b"currency" => currency = Some(value),
+                        b"rate" => rate = Some(value),
+                        _ => {}
+                    }
+                }
+                if let (Some(currency), Some(rate)) = (currency, rate) {
+                    rows.push(RateRow {
+                        date: date.ok_or_else(|| CurrencyError::InvalidFile("rate outside a dated Cube".to_string()))?,
+                        base: "EUR".to_string(),
+                        quote: normalize_currency(&currency)?,
+                        rate: parse_rate(&rate).ok_or(CurrencyError::InvalidRate(rate))?,
+                    });
+                }
+            }
+            XmlEvent::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(rows)
+}
+
+/// Rates of a CSV file with a header row naming the columns `date`, `quote` (or `currency`),
+/// `rate` and optionally `base`, which defaults to `default_base`. Fields are separated by
+/// commas, semicolons or tabs.
+fn parse_csv(text: &str, default_base: &str) -> Result<Vec<RateRow>, CurrencyError> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or_else(|| CurrencyError::InvalidFile("the file is empty".to_string()))?;
+    let delimiter = [';', '\t', ','].into_iter().find(|d| header.contains(*d)).unwrap_or(',');
+    let split = |line: &str| -> Vec<String> {
+        line.split(delimiter).map(|f| f.trim().trim_matches('"').trim().to_string()).collect()
+    };
+
+    let columns = split(header.trim_start_matches('\u{feff}'));
+    let column = |names: &[&str]| columns.iter().position(|c| names.contains(&c.to_ascii_lowercase().as_str()));
+    let missing = |name: &str| CurrencyError::InvalidFile(format!("no {} column in the header", name));
+    let date_column = column(&["date"]).ok_or_else(|| missing("date"))?;
+    let quote_column = column(&["quote", "quote_currency", "currency"]).ok_or_else(|| missing("quote"))?;
+    let rate_column = column(&["rate"]).ok_or_else(|| missing("rate"))?;
+    let base_column = column(&["base", "base_currency"]);
+
+    let mut rows = Vec::new();
+    for (index, line) in lines {
+        let fields = split(line);
+        let field = |column: usize| {
+            fields
+                .get(column)
+                .map(String::as_str)
+                .ok_or_else(|| CurrencyError::InvalidFile(format!("line {} has too few fields", index + 1)))
+        };
+        let date = field(date_column)?;
+        let rate = field(rate_column)?;
+        rows.push(RateRow {
+            date: parse_date(date).ok_or_else(|| CurrencyError::InvalidDate(date.to_string()))?,
+            base: match base_column {
+                Some(column) => normalize_currency(field(column)?)?,
+                None => default_base.to_string(),
+            },
+            quote: normalize_currency(field(quote_column)?)?,
+            rate: parse_rate(rate).ok_or_else(|| CurrencyError::InvalidRate(rate.to_string()))?,
+        });
+    }
+    Ok(rows)
+}
+
+/// Imports a CSV or ECB XML rates file; existing rates of the same day and pair are replaced
+pub fn import_rates(conn: &mut Connection, text: &str) -> Result<usize, CurrencyError> {
+    let (rows, source) = if text.trim_start().starts_with('<') {
+        (parse_ecb_xml(text)?, "Ecb")
+    } else {
+        (parse_csv(text, &base_currency(conn)?)?, "Csv")
+    };
+    let tx = conn.transaction()?;
+    for row in &rows {
+        store_rate(&tx, row, source)?;
+    }
+    tx.commit()?;
+    Ok(rows.len())
+}
+
+/// 📋 List exchange rates, newest first, optionally those involving one currency
+#[tauri::command]
+pub fn list_exchange_rates(
+    state: tauri::State<StateWrapper>,
+    currency: Option<String>,
+) -> Result<Vec<ExchangeRate>, CurrencyError> {
+    let db_conn = open_state_db(&state)?;
+    let currency = currency.as_deref().map(normalize_currency).transpose()?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, date, base_currency, quote_currency, rate, source FROM exchange_rates
+         WHERE ?1 IS NULL OR base_currency = ?1 OR quote_currency = ?1
+         ORDER BY date DESC, base_currency, quote_currency"
+    )?;
+    let rates = stmt
+        .query_map([currency], |row| {
+            Ok(ExchangeRate {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                base_currency: row.get(2)?,
+                quote_currency: row.get(3)?,
+                rate: format_exchange_rate(row.get(4)?),
+                source: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rates)
+}
+
+/// 💱 Enter an exchange rate by hand, replacing the rate of the same day and pair
+#[tauri::command]
+pub fn save_exchange_rate(state: tauri::State<StateWrapper>, rate: ExchangeRate) -> Result<i64, CurrencyError> {
+    let db_conn = open_state_db(&state)?;
+    let row = RateRow {
+        date: parse_date(&rate.date).ok_or(CurrencyError::InvalidDate(rate.date))?,
+        base: normalize_currency(&rate.base_currency)?,
+        quote: normalize_currency(&rate.quote_currency)?,
+        rate: parse_rate(&rate.rate).ok_or(CurrencyError::InvalidRate(rate.rate))?,
+    };
+    store_rate(&db_conn, &row, "Manual")
+}
+
+/// 🗑️ Delete an exchange rate
+#[tauri::command]
+pub fn delete_exchange_rate(state: tauri::State<StateWrapper>, rate_id: i64) -> Result<(), CurrencyError> {
+    let db_conn = open_state_db(&state)?;
+    if db_conn.execute("DELETE FROM exchange_rates WHERE id = ?1", [rate_id])? == 0 {
+        return Err(CurrencyError::NotFound(rate_id));
+    }
+    Ok(())
+}
+
+/// 📥 Import exchange rates from a CSV or ECB XML file and return how many were stored
+#[tauri::command]
+pub fn import_exchange_rates(state: tauri::State<StateWrapper>, path: String) -> Result<usize, CurrencyError> {
+    let text = fs::read_to_string(&path)?;
+    let mut db_conn = open_state_db(&state)?;
+    import_rates(&mut db_conn, &text)
+}
+
+/// 🔄 Convert minor units between currencies at the rate on a date; into the base currency by default
+#[tauri::command]
+pub fn convert_amount(
+    state: tauri::State<StateWrapper>,
+    amount: i64,
+    from: String,
+    to: Option<String>,
+    date: String,
+) -> Result<i64, CurrencyError> {
+    let db_conn = open_state_db(&state)?;
+    let from = normalize_currency(&from)?;
+    let to = currency_or_base(&db_conn, to.as_deref())?;
+    let date = parse_date(&date).ok_or(CurrencyError::InvalidDate(date))?;
+    convert(&db_conn, amount, &from, &to, date)
+}
@@ -1,8 +1,11 @@
 use std::path::PathBuf;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use uuid::Uuid;
 
+use crate::currencies::currency_or_base;
 use crate::StateWrapper;
 
 /// Define a custom DbApiError enum for improved error handling
@@ -22,6 +25,15 @@ pub enum DbApiError {
 
     #[error("Encryption error: {0}")]
     EncryptionError(String),
+
+    #[error("👤 No member profile exists for the signed-in user.")]
+    MemberNotFound,
+
+    #[error("👥 Attendee {0} does not exist.")]
+    AttendeeNotFound(i32),
+
+    #[error("💱 {0}")]
+    InvalidCurrency(String),
 }
 
 // Implement serialization so we can return errors in Tauri commands
@@ -41,6 +53,12 @@ pub struct Client {
     pub name: String,
     pub email: String,
     pub phone: Option<String>,
+    /// Rate used when invoicing this client's events, in minor units of the base currency;
+    /// falls back to `settings.default_hourly_rate`
+    pub hourly_rate: Option<i64>,
+    /// Postal address printed on invoices, one line per address line
+    #[serde(default)]
+    pub billing_address: Option<String>,
 }
 
 
@@ -56,9 +74,53 @@ pub fn open_encrypted_db(db_path: &PathBuf, encryption_key: &str) -> Result<Conn
     //    Err(err) => return Err(DbApiError::EncryptionError(err.to_string()))
     //}
 
+    // SQLite leaves foreign keys off per connection; the ON DELETE rules rely on them
+    conn.pragma_update(None, "foreign_keys", true)?;
+
     Ok(conn)
 }
 
+/// Opens the signed-in user's database using the key and path held in the app state
+pub fn open_state_db(state: &StateWrapper) -> Result<Connection, DbApiError> {
+    let loc_state = state.lock().map_err(|_| DbApiError::DatabaseLockError)?;
+    let app_state = loc_state.as_ref().ok_or(DbApiError::DatabaseLocked)?;
+    let db_key = app_state.db_key.clone().ok_or(DbApiError::DatabaseLocked)?;
+    let db_path = app_state.db_path.clone().ok_or(DbApiError::DatabaseConnectionNotFound)?;
+    drop(loc_state);
+
+    open_encrypted_db(&db_path, &db_key)
+}
+
+/// Format used for every timestamp written to the database (UTC, same as `CURRENT_TIMESTAMP`)
+pub const DB_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Date part of a stored timestamp
+pub fn date_only(value: &str) -> String {
+    value.get(..10).unwrap_or(value).to_string()
+}
+
+/// Parses a stored timestamp. Accepts the SQLite format, `T`-separated ISO 8601,
+/// RFC 3339 with an offset (converted to UTC) and plain dates (midnight).
+pub fn parse_db_timestamp(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc).naive_utc());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), format) {
+            return Some(dt);
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+}
+
+/// Current UTC time in the database timestamp format
+pub fn db_now() -> String {
+    Utc::now().naive_utc().format(DB_TIMESTAMP_FORMAT).to_string()
+}
+
 /// 🏷️ Create a new client
 #[tauri::command]
 pub fn create_client(state: tauri::State<StateWrapper>, client: Client) -> Result<(), DbApiError> {
@@ -68,8 +130,8 @@ pub fn create_client(state: tauri::State<StateWrapper>, client: Client) -> Resul
 
     let db_conn = open_encrypted_db(&db_path, &db_key)?;
     db_conn.execute(
-        "INSERT INTO clients (name, email, phone) VALUES (?1, ?2, ?3)",
-        params![client.name, client.email, client.phone]
+        "INSERT INTO clients (name, email, phone, hourly_rate, billing_address) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![client.name, client.email, client.phone, client.hourly_rate, client.billing_address]
     )?;
 
     Ok(())
@@ -82,7 +144,7 @@ pub fn list_clients(state: tauri::State<StateWrapper>) -> Result<Vec<Client>, Db
     let db_key = loc_state.as_ref().and_then(|s| s.db_key.clone()).unwrap();
     let db_path = loc_state.as_ref().and_then(|s| s.db_path.clone()).unwrap();
     let db_conn = open_encrypted_db(&db_path, &db_key)?;
-    let mut stmt = db_conn.prepare("SELECT id, name, email, phone FROM clients")?;
+    let mut stmt = db_conn.prepare("SELECT id, name, email, phone, hourly_rate, billing_address FROM clients")?;
     
     let clients_iter = stmt.query_map([], |row| {
         Ok(Client {
@@ -90,6 +152,8 @@ pub fn list_clients(state: tauri::State<StateWrapper>) -> Result<Vec<Client>, Db
             name: row.get(1)?,
             email: row.get(2)?,
             phone: row.get(3)?,
+            hourly_rate: row.get(4)?,
+            billing_address: row.get(5)?,
         })
     })?;
 
@@ -105,13 +169,15 @@ pub fn get_client_by_id(state: tauri::State<StateWrapper>, client_id: i32) -> Re
     let db_path = loc_state.as_ref().and_then(|s| s.db_path.clone()).unwrap();
     let db_conn = open_encrypted_db(&db_path, &db_key)?;
 
-    let mut stmt = db_conn.prepare("SELECT id, name, email, phone FROM clients WHERE id = ?1")?;
+    let mut stmt = db_conn.prepare("SELECT id, name, email, phone, hourly_rate, billing_address FROM clients WHERE id = ?1")?;
     let client_result = stmt.query_row([client_id], |row| {
         Ok(Client {
             id: row.get(0)?,
             name: row.get(1)?,
             email: row.get(2)?,
             phone: row.get(3)?,
+            hourly_rate: row.get(4)?,
+            billing_address: row.get(5)?,
         })
     });
 
@@ -121,6 +187,79 @@ pub fn get_client_by_id(state: tauri::State<StateWrapper>, client_id: i32) -> Re
     }
 }
 
+/// 💲 Set (or clear, with `null`) the hourly rate of a client, in minor units of the base currency
+#[tauri::command]
+pub fn set_client_hourly_rate(state: tauri::State<StateWrapper>, client_id: i32, hourly_rate: Option<i64>) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute(
+        "UPDATE clients SET hourly_rate = ?1 WHERE id = ?2",
+        params![hourly_rate, client_id]
+    )?;
+    Ok(())
+}
+
+/// 📮 Set (or clear, with `null`) the billing address of a client
+#[tauri::command]
+pub fn set_client_billing_address(state: tauri::State<StateWrapper>, client_id: i32, billing_address: Option<String>) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute(
+        "UPDATE clients SET billing_address = ?1 WHERE id = ?2",
+        params![billing_address, client_id]
+    )?;
+    Ok(())
+}
+
+/// 📇 Client Contact Struct: a person at a client who can attend events
+#[derive(Serialize, Deserialize)]
+pub struct ClientContact {
+    pub id: Option<i32>,
+    pub client_id: i32,
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+/// 🏷️ Create a contact for a client
+#[tauri::command]
+pub fn create_client_contact(state: tauri::State<StateWrapper>, contact: ClientContact) -> Result<i64, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute(
+        "INSERT INTO client_contacts (client_id, name, email, phone) VALUES (?1, ?2, ?3, ?4)",
+        params![contact.client_id, contact.name, contact.email, contact.phone]
+    )?;
+    Ok(db_conn.last_insert_rowid())
+}
+
+/// 📋 List the contacts of a client
+#[tauri::command]
+pub fn list_client_contacts(state: tauri::State<StateWrapper>, client_id: i32) -> Result<Vec<ClientContact>, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, client_id, name, email, phone FROM client_contacts WHERE client_id = ?1 ORDER BY name"
+    )?;
+
+    let contacts = stmt
+        .query_map([client_id], |row| {
+            Ok(ClientContact {
+                id: row.get(0)?,
+                client_id: row.get(1)?,
+                name: row.get(2)?,
+                email: row.get(3)?,
+                phone: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(contacts)
+}
+
+/// 🗑️ Delete a client contact (also removes it from event attendee lists)
+#[tauri::command]
+pub fn delete_client_contact(state: tauri::State<StateWrapper>, contact_id: i32) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute("DELETE FROM client_contacts WHERE id = ?1", [contact_id])?;
+    Ok(())
+}
+
 /// 📌 Event Struct
 #[derive(Serialize, Deserialize)]
 pub struct Event {
@@ -129,6 +268,8 @@ pub struct Event {
     pub start_date: String,  
     pub end_date: String,
     pub client_id: Option<i32>,
+    /// Staff member responsible for the event
+    pub assigned_member_id: Option<i32>,
 }
 
 /// 🗓️ Create an event
@@ -138,14 +279,20 @@ pub fn create_event(state: tauri::State<StateWrapper>, event: Event) -> Result<(
     let db_key = loc_state.as_ref().and_then(|s| s.db_key.clone()).unwrap();
     let db_path = loc_state.as_ref().and_then(|s| s.db_path.clone()).unwrap();
     let db_conn = open_encrypted_db(&db_path, &db_key)?;
-    db_conn.execute(
-        "INSERT INTO events (title, start_date, end_date, client_id) VALUES (?1, ?2, ?3, ?4)",
-        params![event.title, event.start_date, event.end_date, event.client_id]
-    )?;
+    insert_event(&db_conn, &event)?;
 
     Ok(())
 }
 
+/// Inserts an event with a fresh calendar UID and returns its id
+pub fn insert_event(conn: &Connection, event: &Event) -> Result<i64, rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO events (title, start_date, end_date, client_id, assigned_member_id, uid) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![event.title, event.start_date, event.end_date, event.client_id, event.assigned_member_id, Uuid::new_v4().to_string()]
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
 /// ⏳ List all events
 #[tauri::command]
 pub fn list_events(state: tauri::State<StateWrapper>) -> Result<Vec<Event>, DbApiError> {
@@ -153,7 +300,7 @@ pub fn list_events(state: tauri::State<StateWrapper>) -> Result<Vec<Event>, DbAp
     let db_key = loc_state.as_ref().and_then(|s| s.db_key.clone()).unwrap();
     let db_path = loc_state.as_ref().and_then(|s| s.db_path.clone()).unwrap();
     let db_conn = open_encrypted_db(&db_path, &db_key)?;
-    let mut stmt = db_conn.prepare("SELECT id, title, start_date, end_date, client_id FROM events")?;
+    let mut stmt = db_conn.prepare("SELECT id, title, start_date, end_date, client_id, assigned_member_id FROM events")?;
     
     let events_iter = stmt.query_map([], |row| {
         Ok(Event {
@@ -162,6 +309,7 @@ pub fn list_events(state: tauri::State<StateWrapper>) -> Result<Vec<Event>, DbAp
             start_date: row.get(2)?,
             end_date: row.get(3)?,
             client_id: row.get(4)?,
+            assigned_member_id: row.get(5)?,
         })
     })?;
 
@@ -169,27 +317,81 @@ pub fn list_events(state: tauri::State<StateWrapper>) -> Result<Vec<Event>, DbAp
     Ok(events)
 }
 
+/// ✏️ Update an event and flag it for the next calendar sync
+#[tauri::command]
+pub fn update_event(state: tauri::State<StateWrapper>, event: Event) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    let event_id = event.id.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    let updated = db_conn.execute(
+        "UPDATE events
+         SET title = ?1, start_date = ?2, end_date = ?3, client_id = ?4, assigned_member_id = ?5,
+             caldav_dirty = 1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?6",
+        params![event.title, event.start_date, event.end_date, event.client_id, event.assigned_member_id, event_id]
+    )?;
+    if updated == 0 {
+        return Err(DbApiError::SqliteError(rusqlite::Error::QueryReturnedNoRows));
+    }
+
+    Ok(())
+}
+
+/// 🗑️ Delete an event, remembering its calendar resource so the sync can remove it remotely
+#[tauri::command]
+pub fn delete_event(state: tauri::State<StateWrapper>, event_id: i32) -> Result<(), DbApiError> {
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    tx.execute(
+        "INSERT INTO caldav_tombstones (href, etag)
+         SELECT caldav_href, caldav_etag FROM events WHERE id = ?1 AND caldav_href IS NOT NULL",
+        [event_id]
+    )?;
+    tx.execute("DELETE FROM events WHERE id = ?1", [event_id])?;
+    tx.commit()?;
+
+    Ok(())
+}
+
 /// 🧾 Invoice Struct
 #[derive(Serialize, Deserialize)]
 pub struct Invoice {
     pub id: Option<i32>,
+    /// Assigned when the invoice is finalized
+    #[serde(default)]
+    pub number: Option<String>,
     pub client_id: i32,
-    pub amount: f64,
+    /// Totals in minor units, computed from the invoice lines
+    #[serde(default)]
+    pub subtotal: i64,
+    #[serde(default)]
+    pub tax_total: i64,
+    #[serde(default)]
+    pub total: i64,
     pub due_date: String,
     pub status: String,  
     pub event_id: Option<i32>,
+    #[serde(default)]
+    pub issued_at: Option<String>,
+    /// Quote the invoice was converted from
+    #[serde(default)]
+    pub quote_id: Option<i64>,
+    /// ISO 4217 code; defaults to `settings.base_currency`
+    #[serde(default)]
+    pub currency: Option<String>,
 }
 
-/// 💵 Create an invoice
+/// 💵 Create an empty draft invoice; add lines with `add_invoice_line`, then finalize it
 #[tauri::command]
 pub fn create_invoice(state: tauri::State<StateWrapper>, invoice: Invoice) -> Result<(), DbApiError> {
     let loc_state = state.lock().unwrap(); 
     let db_key = loc_state.as_ref().and_then(|s| s.db_key.clone()).unwrap();
     let db_path = loc_state.as_ref().and_then(|s| s.db_path.clone()).unwrap();
     let db_conn = open_encrypted_db(&db_path, &db_key)?;
+    let currency = currency_or_base(&db_conn, invoice.currency.as_deref())
+        .map_err(|e| DbApiError::InvalidCurrency(e.to_string()))?;
     db_conn.execute(
-        "INSERT INTO invoices (client_id, amount, due_date, status, event_id) VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![invoice.client_id, invoice.amount, invoice.due_date, invoice.status, invoice.event_id]
+        "INSERT INTO invoices (client_id, due_date, status, event_id, currency) VALUES (?1, ?2, 'Draft', ?3, ?4)",
+        params![invoice.client_id, invoice.due_date, invoice.event_id, currency]
     )?;
     Ok(())
 
@@ -0,0 +1,592 @@
+//! Payment reminders (dunning) for overdue invoices.
+//!
+//! `dunning_levels` configures when each reminder is due (days past the due date), its title,
+//! an optional template and an optional fee and yearly interest rate. Fees and interest are
+//! charged on the reminder as extra lines; the finalized invoice itself never changes.
+//! Sending a reminder books its charges as a separate invoice to the client, so they are a
+//! receivable like any other; charges still unpaid are repeated on the next reminder, and
+//! interest is only charged for the days no earlier reminder covered.
+//! An invoice is due for the highest level it has reached that was not sent yet, so levels
+//! skipped while the app was not used are not sent one after another.
+
+use chrono::{Duration, NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::currencies::{convert, currency_or_base, CurrencyError};
+use crate::db_api::{date_only, open_state_db, parse_db_timestamp, DbApiError};
+use crate::invoice_pdf::{business_profile, format_rate};
+use crate::invoices::{finalize_draft, payment_terms_days, recompute_invoice_totals, InvoiceError};
+use crate::members::current_member_id;
+use crate::money::{currency_digits, format_minor, round_div, FULL_RATE_BP};
+use crate::payments::{invoice_due, invoice_paid};
+use crate::StateWrapper;
+
+/// Used by levels without a template of their own
+pub const DEFAULT_REMINDER_TEMPLATE: &str = r#"Dear {{ client.name }},
+
+{% if reminder.level == 1 %}
+According to our records, invoice {{ invoice.number }} of {{ invoice.issued_at }} was due on {{ invoice.due_date }} and has not been paid yet. This may simply have been overlooked; we would be grateful if you could settle it in the next few days.
+{% elif reminder.final %}
+Despite our previous reminders, invoice {{ invoice.number }} of {{ invoice.issued_at }} is still unpaid, {{ reminder.days_overdue }} days after its due date. Please pay the amount below within 10 days. Otherwise we will have to hand the matter over for collection.
+{% else %}
+We have not yet received payment for invoice {{ invoice.number }} of {{ invoice.issued_at }}, which was due on {{ invoice.due_date }}. Please transfer the amount below as soon as possible.
+{% endif %}
+
+Outstanding on invoice {{ invoice.number }}: {{ currency }} {{ reminder.outstanding }}
+{% for line in reminder.lines %}
+{{ line.description }}: {{ currency }} {{ line.amount }}
+{% endfor %}
+Total due: {{ currency }} {{ reminder.total }}
+{% if business.iban %}
+
+Account: {{ business.iban }}{% if business.bank_name %} ({{ business.bank_name }}){% endif %}
+
+Reference: {{ invoice.number }}
+{% endif %}
+
+If you have paid in the meantime, please disregard this reminder.
+
+Kind regards
+{{ business.name }}
+"#;
+
+#[derive(Debug, Error)]
+pub enum DunningError {
+    #[error("[dunning.rs::level_not_found] Reminder level {0} does not exist.")]
+    LevelNotFound(i64),
+
+    #[error("[dunning.rs::not_due] Reminder level {1} is not due for invoice {0}.")]
+    NotDue(i64, i64),
+
+    #[error("[dunning.rs::template] Reminder template error: {0}")]
+    TemplateError(String),
+
+    #[error("[dunning.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[dunning.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[dunning.rs::invoice] {0}")]
+    InvoiceError(#[from] InvoiceError),
+
+    #[error("[dunning.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[dunning.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for DunningError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 📣 Reminder Level Struct. The fee is in minor units, the interest a yearly rate in basis points.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DunningLevel {
+    pub id: Option<i64>,
+    pub level: i64,
+    pub days_overdue: i64,
+    pub title: String,
+    /// Jinja template of the reminder text; the default template when empty
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub fee: i64,
+    /// ISO 4217 code of the fee, converted to the invoice currency when they differ;
+    /// defaults to `settings.base_currency`
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub interest_bp: i64,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ReminderCharge {
+    Fee,
+    Interest,
+    /// Charges of earlier reminders still unpaid; already booked, so not charged again
+    Unpaid,
+}
+
+/// ➕ Extra amount charged on a reminder, a fee or late interest
+#[derive(Serialize, Clone)]
+pub struct ReminderLine {
+    pub kind: ReminderCharge,
+    pub description: String,
+    pub amount: i64,
+}
+
+/// 📣 Due Reminder Struct, the reminder an overdue invoice should get now
+#[derive(Serialize)]
+pub struct DueReminder {
+    pub invoice_id: i64,
+    pub invoice_number: String,
+    pub client_id: Option<i64>,
+    pub client_name: String,
+    pub client_email: Option<String>,
+    pub currency: String,
+    pub level: i64,
+    pub days_overdue: i64,
+    pub outstanding: i64,
+    pub lines: Vec<ReminderLine>,
+    /// Outstanding plus the reminder lines
+    pub total: i64,
+    pub subject: String,
+    pub text: String,
+}
+
+/// 📨 Sent Reminder Struct
+#[derive(Serialize)]
+pub struct SentReminder {
+    pub id: i64,
+    pub invoice_id: i64,
+    pub level: i64,
+    pub days_overdue: i64,
+    pub outstanding: i64,
+    pub fee: i64,
+    pub interest: i64,
+    /// Invoice that books the fee and interest
+    pub charge_invoice_id: Option<i64>,
+    pub subject: String,
+    pub text: String,
+    pub member_id: Option<i64>,
+    pub sent_at: String,
+}
+
+struct OverdueInvoice {
+    id: i64,
+    number: String,
+    client_id: Option<i64>,
+    client_name: String,
+    client_email: Option<String>,
+    issued_at: String,
+    due_date: String,
+    total: i64,
+    currency: String,
+    last_level: i64,
+    /// Days overdue up to which earlier reminders charged interest
+    interest_days: i64,
+}
+
+#[derive(Serialize)]
+struct ReminderContext {
+    level: i64,
+    title: String,
+    /// True for the last configured level
+    #[serde(rename = "final")]
+    is_final: bool,
+    days_overdue: i64,
+    outstanding: String,
+    lines: Vec<LineContext>,
+    total: String,
+}
+
+#[derive(Serialize)]
+struct LineContext {
+    description: String,
+    amount: String,
+}
+
+/// Configured levels, earliest first
+pub fn levels(conn: &Connection) -> Result<Vec<DunningLevel>, DunningError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, level, days_overdue, title, template, fee, currency, interest_bp FROM dunning_levels ORDER BY level"
+    )?;
+    let levels = stmt
+        .query_map([], |row| {
+            Ok(DunningLevel {
+                id: row.get(0)?,
+                level: row.get(1)?,
+                days_overdue: row.get(2)?,
+                title: row.get(3)?,
+                template: row.get(4)?,
+                fee: row.get(5)?,
+                currency: row.get(6)?,
+                interest_bp: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(levels)
+}
+
+fn validate_level(level: &DunningLevel) -> Result<(), DunningError> {
+    if level.level < 1 {
+        return Err(DunningError::InvalidValue("level", level.level.to_string()));
+    }
+    if level.days_overdue < 0 {
+        return Err(DunningError::InvalidValue("days_overdue", level.days_overdue.to_string()));
+    }
+    if level.title.trim().is_empty() {
+        return Err(DunningError::InvalidValue("title", level.title.clone()));
+    }
+    if level.fee < 0 {
+        return Err(DunningError::InvalidValue("fee", level.fee.to_string()));
+    }
+    if level.interest_bp < 0 {
+        return Err(DunningError::InvalidValue("interest_bp", level.interest_bp.to_string()));
+    }
+    if let Some(template) = level.template.as_deref().filter(|t| !t.trim().is_empty()) {
+        minijinja::Environment::new()
+            .template_from_str(template)
+            .map_err(|e| DunningError::TemplateError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Issued invoices past their due date with money still open, and the last level sent.
+/// Invoices booking reminder charges are left out; their charges are repeated on the
+/// reminders of the invoice they belong to.
+fn overdue_invoices(conn: &Connection, today: NaiveDate, invoice_id: Option<i64>) -> Result<Vec<OverdueInvoice>, DunningError> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.number, i.client_id, c.name, c.email, i.issued_at, i.due_date, i.total, i.currency,
+                COALESCE((SELECT MAX(n.level) FROM dunning_notices n WHERE n.invoice_id = i.id), 0),
+                COALESCE((SELECT MAX(n.days_overdue) FROM dunning_notices n WHERE n.invoice_id = i.id AND n.interest > 0), 0)
+         FROM invoices i LEFT JOIN clients c ON c.id = i.client_id
+         WHERE i.status IN ('Issued', 'PartiallyPaid', 'Overdue') AND i.number IS NOT NULL
+           AND date(i.due_date) < ?1 AND (?2 IS NULL OR i.id = ?2)
+           AND NOT EXISTS (SELECT 1 FROM dunning_notices n WHERE n.charge_invoice_id = i.id)
+         ORDER BY i.due_date, i.id"
+    )?;
+    let invoices = stmt
+        .query_map(params![today.format("%Y-%m-%d").to_string(), invoice_id], |row| {
+            Ok(OverdueInvoice {
+                id: row.get(0)?,
+                number: row.get(1)?,
+                client_id: row.get(2)?,
+                client_name: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                client_email: row.get(4)?,
+                issued_at: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                due_date: row.get(6)?,
+                total: row.get(7)?,
+                currency: row.get(8)?,
+                last_level: row.get(9)?,
+                interest_days: row.get(10)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(invoices)
+}
+
+/// Charges of earlier reminders for an invoice that are still unpaid
+fn unpaid_charges(conn: &Connection, invoice_id: i64) -> Result<i64, DunningError> {
+    let mut stmt = conn.prepare(
+        "SELECT n.charge_invoice_id FROM dunning_notices n JOIN invoices i ON i.id = n.charge_invoice_id
+         WHERE n.invoice_id = ?1 AND i.status NOT IN ('Paid', 'Void')"
+    )?;
+    let charge_invoices = stmt.query_map([invoice_id], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>, _>>()?;
+    let mut unpaid = 0;
+    for charge_invoice_id in charge_invoices {
+        unpaid += (invoice_due(conn, charge_invoice_id)? - invoice_paid(conn, charge_invoice_id)?).max(0);
+    }
+    Ok(unpaid)
+}
+
+/// Reminder lines of a level: its fee in the invoice currency, interest on the outstanding
+/// amount for the days overdue not charged before, and the unpaid charges of earlier reminders
+fn reminder_lines(
+    conn: &Connection,
+    invoice: &OverdueInvoice,
+    level: &DunningLevel,
+    outstanding: i64,
+    days_overdue: i64,
+    today: NaiveDate,
+) -> Result<Vec<ReminderLine>, DunningError> {
+    let mut lines = Vec::new();
+    if level.fee > 0 {
+        let fee_currency = currency_or_base(conn, level.currency.as_deref())?;
+        let fee = if fee_currency == invoice.currency {
+            level.fee
+        } else {
+            convert(conn, level.fee, &fee_currency, &invoice.currency, today)?
+        };
+        lines.push(ReminderLine { kind: ReminderCharge::Fee, description: "Reminder fee".to_string(), amount: fee });
+    }
+    let interest_days = days_overdue - invoice.interest_days;
+    if level.interest_bp > 0 && interest_days > 0 {
+        let interest = round_div(
+            outstanding as i128 * level.interest_bp as i128 * interest_days as i128,
+            FULL_RATE_BP as i128 * 365,
+        );
+        if interest > 0 {
+            lines.push(ReminderLine {
+                kind: ReminderCharge::Interest,
+                description: format!("Late interest {} p.a. for {} days", format_rate(level.interest_bp), interest_days),
+                amount: interest,
+            });
+        }
+    }
+    let unpaid = unpaid_charges(conn, invoice.id)?;
+    if unpaid > 0 {
+        lines.push(ReminderLine {
+            kind: ReminderCharge::Unpaid,
+            description: "Unpaid charges of earlier reminders".to_string(),
+            amount: unpaid,
+        });
+    }
+    Ok(lines)
+}
+
+fn build_reminder(
+    conn: &Connection,
+    invoice: &OverdueInvoice,
+    level: &DunningLevel,
+    is_final: bool,
+    days_overdue: i64,
+    outstanding: i64,
+    today: NaiveDate,
+) -> Result<DueReminder, DunningError> {
+    let lines = reminder_lines(conn, invoice, level, outstanding, days_overdue, today)?;
+    let total = outstanding + lines.iter().map(|l| l.amount).sum::<i64>();
+    let digits = currency_digits(&invoice.currency);
+
+    let template = level
+        .template
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_REMINDER_TEMPLATE.to_string());
+    let mut env = minijinja::Environment::new();
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    let text = env
+        .render_str(
+            &template,
+            minijinja::context! {
+                business => business_profile(conn)?,
+                currency => invoice.currency,
+                client => minijinja::context! { name => invoice.client_name, email => invoice.client_email },
+                invoice => minijinja::context! {
+                    number => invoice.number,
+                    issued_at => date_only(&invoice.issued_at),
+                    due_date => date_only(&invoice.due_date),
+                    total => format_minor(invoice.total, digits),
+                },
+                reminder => ReminderContext {
+                    level: level.level,
+                    title: level.title.clone(),
+                    is_final,
+                    days_overdue,
+                    outstanding: format_minor(outstanding, digits),
+                    lines: lines
+                        .iter()
+                        .map(|l| LineContext { description: l.description.clone(), amount: format_minor(l.amount, digits) })
+                        .collect(),
+                    total: format_minor(total, digits),
+                },
+            },
+        )
+        .map_err(|e| DunningError::TemplateError(e.to_string()))?;
+
+    Ok(DueReminder {
+        invoice_id: invoice.id,
+        invoice_number: invoice.number.clone(),
+        client_id: invoice.client_id,
+        client_name: invoice.client_name.clone(),
+        client_email: invoice.client_email.clone(),
+        currency: invoice.currency.clone(),
+        level: level.level,
+        days_overdue,
+        outstanding,
+        lines,
+        total,
+        subject: format!("{}: invoice {}", level.title, invoice.number),
+        text,
+    })
+}
+
+/// Reminders due on `today`, optionally for a single invoice
+pub fn due_reminders(conn: &Connection, today: NaiveDate, invoice_id: Option<i64>) -> Result<Vec<DueReminder>, DunningError> {
+    let levels = levels(conn)?;
+    let last_level = levels.iter().map(|l| l.level).max().unwrap_or_default();
+
+    let mut reminders = Vec::new();
+    for invoice in overdue_invoices(conn, today, invoice_id)? {
+        let due_date = parse_db_timestamp(&invoice.due_date)
+            .ok_or_else(|| DunningError::InvalidValue("due_date", invoice.due_date.clone()))?
+            .date();
+        let days_overdue = (today - due_date).num_days();
+        let outstanding = invoice_due(conn, invoice.id)? - invoice_paid(conn, invoice.id)?;
+        if outstanding <= 0 {
+            continue;
+        }
+        // The highest level reached that was not sent yet
+        let level = levels
+            .iter()
+            .filter(|l| l.level > invoice.last_level && days_overdue >= l.days_overdue)
+            .max_by_key(|l| l.level);
+        if let Some(level) = level {
+            reminders.push(build_reminder(conn, &invoice, level, level.level == last_level, days_overdue, outstanding, today)?);
+        }
+    }
+    Ok(reminders)
+}
+
+/// Books the fee and interest of a reminder as a finalized invoice to the client, due after
+/// the usual payment term. Returns `None` when the reminder charges nothing new.
+fn book_charges(conn: &Connection, reminder: &DueReminder, member_id: Option<i64>, today: NaiveDate) -> Result<Option<i64>, DunningError> {
+    let charges: Vec<_> = reminder.lines.iter().filter(|l| l.kind != ReminderCharge::Unpaid).collect();
+    if charges.is_empty() {
+        return Ok(None);
+    }
+
+    let due_date = format!("{} 00:00:00", (today + Duration::days(payment_terms_days(conn)?)).format("%Y-%m-%d"));
+    conn.execute(
+        "INSERT INTO invoices (client_id, due_date, status, currency) VALUES (?1, ?2, 'Draft', ?3)",
+        params![reminder.client_id, due_date, reminder.currency],
+    )?;
+    let charge_invoice_id = conn.last_insert_rowid();
+    for (position, line) in charges.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO invoice_lines (invoice_id, position, description, quantity, unit_price, tax_rate_bp, net_amount)
+             VALUES (?1, ?2, ?3, 1, ?4, 0, 0)",
+            params![
+                charge_invoice_id,
+                position as i64,
+                format!("{}, invoice {}", line.description, reminder.invoice_number),
+                line.amount
+            ],
+        )?;
+    }
+    recompute_invoice_totals(conn, charge_invoice_id)?;
+    finalize_draft(conn, charge_invoice_id, member_id)?;
+    Ok(Some(charge_invoice_id))
+}
+
+/// Records that the reminder of `level` was sent for an invoice, with the text and amounts
+/// it had at that moment, and books its charges
+pub fn record_sent(conn: &mut Connection, invoice_id: i64, level: i64, member_id: Option<i64>, today: NaiveDate) -> Result<i64, DunningError> {
+    let tx = conn.transaction()?;
+    let reminder = due_reminders(&tx, today, Some(invoice_id))?
+        .into_iter()
+        .find(|r| r.level == level)
+        .ok_or(DunningError::NotDue(invoice_id, level))?;
+    let amount_of = |kind| reminder.lines.iter().filter(|l| l.kind == kind).map(|l| l.amount).sum::<i64>();
+    let charge_invoice_id = book_charges(&tx, &reminder, member_id, today)?;
+
+    tx.execute(
+        "INSERT INTO dunning_notices (invoice_id, level, days_overdue, outstanding, fee, interest, charge_invoice_id, subject, text, member_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            invoice_id,
+            level,
+            reminder.days_overdue,
+            reminder.outstanding,
+            amount_of(ReminderCharge::Fee),
+            amount_of(ReminderCharge::Interest),
+            charge_invoice_id,
+            reminder.subject,
+            reminder.text,
+            member_id
+        ],
+    )?;
+    let notice_id = tx.last_insert_rowid();
+    tx.commit()?;
+    Ok(notice_id)
+}
+
+/// 📣 List the reminder levels
+#[tauri::command]
+pub fn list_dunning_levels(state: tauri::State<StateWrapper>) -> Result<Vec<DunningLevel>, DunningError> {
+    let db_conn = open_state_db(&state)?;
+    levels(&db_conn)
+}
+
+/// 💾 Create a reminder level, or update it when it has an id; returns its id
+#[tauri::command]
+pub fn save_dunning_level(state: tauri::State<StateWrapper>, level: DunningLevel) -> Result<i64, DunningError> {
+    validate_level(&level)?;
+    let db_conn = open_state_db(&state)?;
+    let template = level.template.as_deref().filter(|t| !t.trim().is_empty());
+    let currency = currency_or_base(&db_conn, level.currency.as_deref())?;
+    match level.id {
+        Some(id) => {
+            let updated = db_conn.execute(
+                "UPDATE dunning_levels SET level = ?1, days_overdue = ?2, title = ?3, template = ?4, fee = ?5, currency = ?6,
+                                           interest_bp = ?7
+                 WHERE id = ?8",
+                params![level.level, level.days_overdue, level.title.trim(), template, level.fee, currency, level.interest_bp, id],
+            )?;
+            if updated == 0 {
+                return Err(DunningError::LevelNotFound(id));
+            }
+            Ok(id)
+        }
+        None => {
+            db_conn.execute(
+                "INSERT INTO dunning_levels (level, days_overdue, title, template, fee, currency, interest_bp)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![level.level, level.days_overdue, level.title.trim(), template, level.fee, currency, level.interest_bp],
+            )?;
+            Ok(db_conn.last_insert_rowid())
+        }
+    }
+}
+
+/// 🗑️ Delete a reminder level. Reminders already sent are kept.
+#[tauri::command]
+pub fn delete_dunning_level(state: tauri::State<StateWrapper>, level_id: i64) -> Result<(), DunningError> {
+    let db_conn = open_state_db(&state)?;
+    let deleted = db_conn.execute("DELETE FROM dunning_levels WHERE id = ?1", [level_id])?;
+    if deleted == 0 {
+        return Err(DunningError::LevelNotFound(level_id));
+    }
+    Ok(())
+}
+
+/// 📄 Get the built-in reminder template, as a starting point for custom ones
+#[tauri::command]
+pub fn get_default_reminder_template() -> String {
+    DEFAULT_REMINDER_TEMPLATE.to_string()
+}
+
+/// ⏰ List the reminders due today, one per overdue invoice, with their rendered text
+#[tauri::command]
+pub fn list_due_reminders(state: tauri::State<StateWrapper>) -> Result<Vec<DueReminder>, DunningError> {
+    let db_conn = open_state_db(&state)?;
+    due_reminders(&db_conn, Utc::now().date_naive(), None)
+}
+
+/// 📨 Record that the due reminder of `level` was sent for an invoice and book its charges;
+/// returns its id
+#[tauri::command]
+pub fn record_reminder_sent(state: tauri::State<StateWrapper>, invoice_id: i64, level: i64) -> Result<i64, DunningError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    record_sent(&mut db_conn, invoice_id, level, member_id, Utc::now().date_naive())
+}
+
+/// 📜 Reminders sent for an invoice, oldest first
+#[tauri::command]
+pub fn list_sent_reminders(state: tauri::State<StateWrapper>, invoice_id: i64) -> Result<Vec<SentReminder>, DunningError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, invoice_id, level, days_overdue, outstanding, fee, interest, charge_invoice_id, subject, text, member_id, sent_at
+         FROM dunning_notices WHERE invoice_id = ?1 ORDER BY sent_at, id"
+    )?;
+    let reminders = stmt
+        .query_map([invoice_id], |row| {
+            Ok(SentReminder {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                level: row.get(2)?,
+                days_overdue: row.get(3)?,
+                outstanding: row.get(4)?,
+                fee: row.get(5)?,
+                interest: row.get(6)?,
+                charge_invoice_id: row.get(7)?,
+                subject: row.get(8)?,
+                text: row.get(9)?,
+                member_id: row.get(10)?,
+                sent_at: row.get(11)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(reminders)
+}
@@ -0,0 +1,781 @@
+//! Structured e-invoices: UBL 2.1 following Peppol BIS Billing 3.0, and Factur-X/ZUGFeRD,
+//! a PDF/A-3 invoice with the same data embedded as UN/CEFACT CII XML (EN 16931 profile).
+//!
+//! Both are built from one EN 16931 view of the invoice. That view is first checked against
+//! the business rules most likely to fail on user data, so the messages can name the setting
+//! or field to fix. The generated XML is then validated like a receiving access point would:
+//! against the UBL 2.1 or CII D16B XSD with libxml2, and against the EN 16931 Schematron
+//! (plus the Peppol BIS rules for UBL) with the runner in `schematron.rs`. Those official files
+//! ship as app resources (see `assets/einvoice/README.md`); if any is missing, or anything
+//! fails, nothing is written.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use libxml::error::StructuredError;
+use libxml::parser::Parser;
+use libxml::schemas::{SchemaParserContext, SchemaValidationContext};
+use quick_xml::escape::escape;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::Manager;
+use thiserror::Error;
+
+use crate::attachments::{state_attachment_files, store, Attachment, AttachmentError};
+use crate::db_api::{date_only, open_state_db, DbApiError};
+use crate::invoice_pdf::{layout_invoice, InvoicePdfError};
+use crate::invoices::{invoice_totals, InvoiceError};
+use crate::money::{currency_digits, format_minor, line_amounts, round_div, FULL_RATE_BP};
+use crate::pdf::{PdfBuilder, PdfError};
+use crate::qrbill::Address;
+use crate::schematron::{Schema, SchematronError};
+use crate::settings::{read_setting, text_setting};
+use crate::StateWrapper;
+
+const PEPPOL_CUSTOMIZATION_ID: &str = "urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:billing:3.0";
+const PEPPOL_PROFILE_ID: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
+const FACTUR_X_GUIDELINE_ID: &str = "urn:cen.eu:en16931:2017";
+const FACTUR_X_FILE_NAME: &str = "factur-x.xml";
+
+/// Resource directory of the official validation files, laid out as in `assets/einvoice/README.md`
+const ARTEFACTS_DIR: &str = "einvoice";
+const UBL_XSD: &str = "ubl/xsd/maindoc/UBL-Invoice-2.1.xsd";
+const CII_XSD: &str = "cii/xsd/CrossIndustryInvoice_100pD16B.xsd";
+const EN16931_UBL_RULES: &str = "en16931/ubl/EN16931-UBL-validation.sch";
+const EN16931_CII_RULES: &str = "en16931/cii/EN16931-CII-validation.sch";
+const PEPPOL_UBL_RULES: &str = "peppol/PEPPOL-EN16931-UBL.sch";
+
+/// libxml2 keeps global parser state, so schemas are loaded and run one at a time
+static LIBXML: Mutex<()> = Mutex::new(());
+
+/// XMP of the Factur-X extension schema, required in the PDF/A metadata
+const FACTUR_X_METADATA: &str = r#"<rdf:Description rdf:about="" xmlns:fx="urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#">
+<fx:DocumentType>INVOICE</fx:DocumentType>
+<fx:DocumentFileName>factur-x.xml</fx:DocumentFileName>
+<fx:Version>1.0</fx:Version>
+<fx:ConformanceLevel>EN 16931</fx:ConformanceLevel>
+</rdf:Description>
+<rdf:Description rdf:about="" xmlns:pdfaExtension="http://www.aiim.org/pdfa/ns/extension/" xmlns:pdfaSchema="http://www.aiim.org/pdfa/ns/schema#" xmlns:pdfaProperty="http://www.aiim.org/pdfa/ns/property#">
+<pdfaExtension:schemas><rdf:Bag><rdf:li rdf:parseType="Resource">
+<pdfaSchema:schema>Factur-X PDFA Extension Schema</pdfaSchema:schema>
+<pdfaSchema:namespaceURI>urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#</pdfaSchema:namespaceURI>
+<pdfaSchema:prefix>fx</pdfaSchema:prefix>
+<pdfaSchema:property><rdf:Seq>
+<rdf:li rdf:parseType="Resource"><pdfaProperty:name>DocumentFileName</pdfaProperty:name><pdfaProperty:valueType>Text</pdfaProperty:valueType><pdfaProperty:category>external</pdfaProperty:category><pdfaProperty:description>The name of the embedded XML document</pdfaProperty:description></rdf:li>
+<rdf:li rdf:parseType="Resource"><pdfaProperty:name>DocumentType</pdfaProperty:name><pdfaProperty:valueType>Text</pdfaProperty:valueType><pdfaProperty:category>external</pdfaProperty:category><pdfaProperty:description>The type of the hybrid document in capital letters, e.g. INVOICE or ORDER</pdfaProperty:description></rdf:li>
+<rdf:li rdf:parseType="Resource"><pdfaProperty:name>Version</pdfaProperty:name><pdfaProperty:valueType>Text</pdfaProperty:valueType><pdfaProperty:category>external</pdfaProperty:category><pdfaProperty:description>The actual version of the standard applying to the embedded XML document</pdfaProperty:description></rdf:li>
+<rdf:li rdf:parseType="Resource"><pdfaProperty:name>ConformanceLevel</pdfaProperty:name><pdfaProperty:valueType>Text</pdfaProperty:valueType><pdfaProperty:category>external</pdfaProperty:category><pdfaProperty:description>The conformance level of the embedded XML document</pdfaProperty:description></rdf:li>
+</rdf:Seq></pdfaSchema:property>
+</rdf:li></rdf:Bag></pdfaExtension:schemas>
+</rdf:Description>"#;
+
+#[derive(Debug, Error)]
+pub enum EInvoiceError {
+    #[error("[einvoice.rs::invoice_not_found] Invoice {0} does not exist.")]
+    InvoiceNotFound(i64),
+
+    #[error("[einvoice.rs::not_issued] Invoice {0} has not been issued yet.")]
+    NotIssued(i64),
+
+    #[error("[einvoice.rs::unknown_format] Unknown e-invoice format {0:?}; use `ubl` or `facturx`.")]
+    UnknownFormat(String),
+
+    #[error("[einvoice.rs::invalid] The invoice breaks e-invoicing rules: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+
+    #[error("[einvoice.rs::missing_artefact] {0} is missing; e-invoices are only exported once the official XSD and Schematron files are installed.")]
+    MissingArtefact(String),
+
+    #[error("[einvoice.rs::artefact] Could not load {0}: {1}")]
+    ArtefactError(String, String),
+
+    #[error("[einvoice.rs::schema_invalid] The generated XML does not validate against its XSD: {}", .0.join("; "))]
+    SchemaInvalid(Vec<String>),
+
+    #[error("[einvoice.rs::rules_failed] The generated XML breaks EN 16931/Peppol rules: {}", .0.join("; "))]
+    RulesFailed(Vec<String>),
+
+    #[error("[einvoice.rs::schematron] {0}")]
+    SchematronError(#[from] SchematronError),
+
+    #[error("[einvoice.rs::tauri] {0}")]
+    TauriError(#[from] tauri::Error),
+
+    #[error("[einvoice.rs::invoice_pdf] {0}")]
+    InvoicePdfError(#[from] InvoicePdfError),
+
+    #[error("[einvoice.rs::pdf] {0}")]
+    PdfError(#[from] PdfError),
+
+    #[error("[einvoice.rs::invoice] {0}")]
+    InvoiceError(#[from] InvoiceError),
+
+    #[error("[einvoice.rs::attachment] {0}")]
+    AttachmentError(#[from] AttachmentError),
+
+    #[error("[einvoice.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[einvoice.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for EInvoiceError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EInvoiceFormat {
+    /// UBL 2.1 invoice, Peppol BIS Billing 3.0
+    Ubl,
+    /// PDF/A-3 with embedded CII XML, EN 16931 profile
+    FacturX,
+}
+
+impl EInvoiceFormat {
+    pub fn parse(value: &str) -> Option<EInvoiceFormat> {
+        match value.trim().to_lowercase().as_str() {
+            "ubl" | "peppol" => Some(EInvoiceFormat::Ubl),
+            "facturx" | "factur-x" | "zugferd" => Some(EInvoiceFormat::FacturX),
+            _ => None,
+        }
+    }
+}
+
+struct Party {
+    name: String,
+    address: Option<Address>,
+    vat_id: Option<String>,
+    email: Option<String>,
+    /// Electronic address as (scheme, identifier), e.g. `("0088", "7300010000001")`
+    endpoint: Option<(String, String)>,
+}
+
+struct Line {
+    description: String,
+    quantity: f64,
+    unit_code: &'static str,
+    unit_price: i64,
+    /// Discount in minor units, an EN 16931 line allowance
+    allowance: i64,
+    net: i64,
+    category: &'static str,
+    rate_bp: i64,
+}
+
+struct TaxSubtotal {
+    category: &'static str,
+    rate_bp: i64,
+    net: i64,
+    tax: i64,
+}
+
+/// The invoice as EN 16931 sees it
+struct Document {
+    number: String,
+    /// `YYYY-MM-DD`
+    issue_date: String,
+    due_date: String,
+    currency: String,
+    seller: Party,
+    buyer: Party,
+    lines: Vec<Line>,
+    taxes: Vec<TaxSubtotal>,
+    subtotal: i64,
+    tax_total: i64,
+    total: i64,
+    iban: Option<String>,
+    bic: Option<String>,
+}
+
+/// VAT category: standard rated, zero rated, or not subject to VAT for sellers without a VAT id
+fn tax_category(rate_bp: i64, seller_vat_id: bool) -> &'static str {
+    match (rate_bp > 0, seller_vat_id) {
+        (true, _) => "S",
+        (false, true) => "Z",
+        (false, false) => "O",
+    }
+}
+
+fn load(conn: &Connection, invoice_id: i64) -> Result<Document, EInvoiceError> {
+    let row = conn
+        .query_row(
+            "SELECT i.number, i.issued_at, i.due_date, i.subtotal, i.tax_total, i.total,
+                    c.name, c.email, c.billing_address, i.currency
+             FROM invoices i LEFT JOIN clients c ON c.id = i.client_id
+             WHERE i.id = ?1",
+            [invoice_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                    (row.get::<_, i64>(3)?, row.get::<_, i64>(4)?, row.get::<_, i64>(5)?),
+                    row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+                    row.get::<_, String>(9)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or(EInvoiceError::InvoiceNotFound(invoice_id))?;
+    let (number, issued_at, due_date, (subtotal, tax_total, total), client_name, client_email, billing_address, currency) = row;
+    let (number, issued_at) = match (number, issued_at) {
+        (Some(number), Some(issued_at)) => (number, issued_at),
+        _ => return Err(EInvoiceError::NotIssued(invoice_id)),
+    };
+
+    let seller_name = text_setting(conn, "business_name")?.unwrap_or_default();
+    let seller_email = text_setting(conn, "business_email")?;
+    let seller_endpoint = match text_setting(conn, "peppol_endpoint_id")? {
+        Some(value) => value.split_once(':').map(|(scheme, id)| (scheme.trim().to_string(), id.trim().to_string())),
+        None => seller_email.clone().map(|email| ("EM".to_string(), email)),
+    };
+    let seller = Party {
+        address: Address::parse(&seller_name, &read_setting(conn, "business_address")?.unwrap_or_default()).ok(),
+        name: seller_name,
+        vat_id: text_setting(conn, "business_vat_id")?,
+        email: seller_email,
+        endpoint: seller_endpoint,
+    };
+    let buyer = Party {
+        address: Address::parse(&client_name, &billing_address).ok(),
+        name: client_name,
+        vat_id: None,
+        endpoint: client_email.clone().map(|email| ("EM".to_string(), email)),
+        email: client_email,
+    };
+
+    let has_vat_id = seller.vat_id.is_some();
+    let mut stmt = conn.prepare(
+        "SELECT description, quantity, unit_price, tax_rate_bp, net_amount, event_id
+         FROM invoice_lines WHERE invoice_id = ?1 ORDER BY position, id",
+    )?;
+    let lines = stmt
+        .query_map([invoice_id], |row| {
+            let (quantity, unit_price, rate_bp, net): (f64, i64, i64, i64) = (row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?);
+            let gross = line_amounts(quantity, unit_price, 0, rate_bp).net;
+            Ok(Line {
+                description: row.get(0)?,
+                quantity,
+                // Lines billed from events are hours
+                unit_code: if row.get::<_, Option<i64>>(5)?.is_some() { "HUR" } else { "C62" },
+                unit_price,
+                allowance: gross - net,
+                net,
+                category: tax_category(rate_bp, has_vat_id),
+                rate_bp,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let taxes = invoice_totals(conn, invoice_id)?
+        .taxes
+        .into_iter()
+        .map(|t| TaxSubtotal { category: tax_category(t.tax_rate_bp, has_vat_id), rate_bp: t.tax_rate_bp, net: t.net, tax: t.tax })
+        .collect();
+
+    Ok(Document {
+        number,
+        issue_date: date_only(&issued_at),
+        due_date: date_only(&due_date),
+        currency,
+        seller,
+        buyer,
+        lines,
+        taxes,
+        subtotal,
+        tax_total,
+        total,
+        iban: text_setting(conn, "bank_iban")?.map(|iban| iban.replace(' ', "").to_uppercase()),
+        bic: text_setting(conn, "bank_bic")?,
+    })
+}
+
+/// The EN 16931 and Peppol business rules checked before export, a subset of the full
+/// rule sets; returns the broken ones
+fn validate(doc: &Document) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut check = |ok: bool, rule: &str, message: &str| {
+        if !ok {
+            errors.push(format!("{} {}", rule, message));
+        }
+    };
+
+    check(!doc.number.is_empty(), "BR-02", "the invoice has no number");
+    check(
+        doc.currency.len() == 3 && doc.currency.chars().all(|c| c.is_ascii_uppercase()),
+        "BR-05",
+        "the currency must be an ISO 4217 code",
+    );
+    check(!doc.seller.name.is_empty(), "BR-06", "the seller name is missing (setting business_name)");
+    check(!doc.buyer.name.is_empty(), "BR-07", "the client has no name");
+    check(doc.seller.address.is_some(), "BR-08", "the seller address needs a `postal code town` line (setting business_address)");
+    check(doc.buyer.address.is_some(), "BR-10", "the client's billing address needs a `postal code town` line");
+    check(doc.seller.endpoint.is_some(), "PEPPOL-EN16931-R020", "the seller needs an electronic address (setting peppol_endpoint_id or business_email)");
+    check(doc.buyer.endpoint.is_some(), "PEPPOL-EN16931-R010", "the client needs an email address");
+    check(!doc.lines.is_empty(), "BR-16", "the invoice has no lines");
+    for (index, line) in doc.lines.iter().enumerate() {
+        check(!line.description.trim().is_empty(), "BR-25", &format!("line {} has no description", index + 1));
+        check(line.quantity.is_finite(), "BR-22", &format!("line {} has no valid quantity", index + 1));
+        check(line.unit_price >= 0, "BR-27", &format!("line {} has a negative price", index + 1));
+    }
+    check(doc.iban.is_some(), "BR-61", "a credit transfer needs the seller's account (setting bank_iban)");
+
+    check(doc.lines.iter().map(|l| l.net).sum::<i64>() == doc.subtotal, "BR-CO-10", "the lines do not add up to the subtotal");
+    check(!doc.taxes.is_empty(), "BR-CO-18", "the invoice has no VAT breakdown");
+    check(doc.taxes.iter().map(|t| t.net).sum::<i64>() == doc.subtotal, "BR-CO-13", "the VAT breakdown does not add up to the subtotal");
+    check(doc.taxes.iter().map(|t| t.tax).sum::<i64>() == doc.tax_total, "BR-CO-14", "the VAT breakdown does not add up to the VAT total");
+    check(doc.subtotal + doc.tax_total == doc.total, "BR-CO-15", "the total is not subtotal plus VAT");
+    for tax in &doc.taxes {
+        let expected = round_div(tax.net as i128 * tax.rate_bp as i128, FULL_RATE_BP as i128);
+        check(
+            tax.tax == expected,
+            "BR-CO-17",
+            "VAT must be rounded once per rate, not per line (setting tax_rounding = per_rate)",
+        );
+    }
+    let has_vat_id = doc.seller.vat_id.is_some();
+    check(has_vat_id || doc.taxes.iter().all(|t| t.category == "O"), "BR-S-02", "taxed lines need the seller's VAT id (setting business_vat_id)");
+    check(
+        doc.taxes.iter().all(|t| t.category == "O") || doc.taxes.iter().all(|t| t.category != "O"),
+        "BR-O-11",
+        "lines not subject to VAT cannot be mixed with taxed lines",
+    );
+    errors
+}
+
+/// Minimal XML writer with indentation
+struct XmlWriter {
+    out: String,
+    depth: usize,
+}
+
+impl XmlWriter {
+    fn new() -> XmlWriter {
+        XmlWriter { out: "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string(), depth: 0 }
+    }
+
+    fn start_tag(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push('<');
+        self.out.push_str(tag);
+        for (name, value) in attributes {
+            self.out.push_str(&format!(" {}=\"{}\"", name, escape(*value)));
+        }
+        self.out.push('>');
+    }
+
+    fn open(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.start_tag(tag, attributes);
+        self.out.push('\n');
+        self.depth += 1;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.out.push_str(&format!("{}</{}>\n", "  ".repeat(self.depth), tag));
+    }
+
+    fn leaf(&mut self, tag: &str, attributes: &[(&str, &str)], text: &str) {
+        self.start_tag(tag, attributes);
+        self.out.push_str(&format!("{}</{}>\n", escape(text), tag));
+    }
+}
+
+/// Formats basis points as a plain percentage, e.g. `810` as `8.1`
+fn format_percent(bp: i64) -> String {
+    format!("{}", bp as f64 / 100.0)
+}
+
+fn to_ubl(doc: &Document) -> String {
+    let currency = [("currencyID", doc.currency.as_str())];
+    let digits = currency_digits(&doc.currency);
+    let mut xml = XmlWriter::new();
+    xml.open(
+        "Invoice",
+        &[
+            ("xmlns", "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2"),
+            ("xmlns:cac", "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2"),
+            ("xmlns:cbc", "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2"),
+        ],
+    );
+    xml.leaf("cbc:CustomizationID", &[], PEPPOL_CUSTOMIZATION_ID);
+    xml.leaf("cbc:ProfileID", &[], PEPPOL_PROFILE_ID);
+    xml.leaf("cbc:ID", &[], &doc.number);
+    xml.leaf("cbc:IssueDate", &[], &doc.issue_date);
+    xml.leaf("cbc:DueDate", &[], &doc.due_date);
+    xml.leaf("cbc:InvoiceTypeCode", &[], "380");
+    xml.leaf("cbc:DocumentCurrencyCode", &[], &doc.currency);
+    xml.leaf("cbc:BuyerReference", &[], &doc.number);
+
+    for (wrapper, party) in [("cac:AccountingSupplierParty", &doc.seller), ("cac:AccountingCustomerParty", &doc.buyer)] {
+        xml.open(wrapper, &[]);
+        xml.open("cac:Party", &[]);
+        if let Some((scheme, id)) = &party.endpoint {
+            xml.leaf("cbc:EndpointID", &[("schemeID", scheme)], id);
+        }
+        xml.open("cac:PartyName", &[]);
+        xml.leaf("cbc:Name", &[], &party.name);
+        xml.close("cac:PartyName");
+        if let Some(address) = &party.address {
+            xml.open("cac:PostalAddress", &[]);
+            if !address.street.is_empty() {
+                xml.leaf("cbc:StreetName", &[], &address.street);
+            }
+            xml.leaf("cbc:CityName", &[], &address.town);
+            xml.leaf("cbc:PostalZone", &[], &address.postal_code);
+            xml.open("cac:Country", &[]);
+            xml.leaf("cbc:IdentificationCode", &[], &address.country);
+            xml.close("cac:Country");
+            xml.close("cac:PostalAddress");
+        }
+        if let Some(vat_id) = &party.vat_id {
+            xml.open("cac:PartyTaxScheme", &[]);
+            xml.leaf("cbc:CompanyID", &[], vat_id);
+            xml.open("cac:TaxScheme", &[]);
+            xml.leaf("cbc:ID", &[], "VAT");
+            xml.close("cac:TaxScheme");
+            xml.close("cac:PartyTaxScheme");
+        }
+        xml.open("cac:PartyLegalEntity", &[]);
+        xml.leaf("cbc:RegistrationName", &[], &party.name);
+        xml.close("cac:PartyLegalEntity");
+        if let Some(email) = &party.email {
+            xml.open("cac:Contact", &[]);
+            xml.leaf("cbc:ElectronicMail", &[], email);
+            xml.close("cac:Contact");
+        }
+        xml.close("cac:Party");
+        xml.close(wrapper);
+    }
+
+    if let Some(iban) = &doc.iban {
+        xml.open("cac:PaymentMeans", &[]);
+        xml.leaf("cbc:PaymentMeansCode", &[], "58");
+        xml.leaf("cbc:PaymentID", &[], &doc.number);
+        xml.open("cac:PayeeFinancialAccount", &[]);
+        xml.leaf("cbc:ID", &[], iban);
+        if let Some(bic) = &doc.bic {
+            xml.open("cac:FinancialInstitutionBranch", &[]);
+            xml.leaf("cbc:ID", &[], bic);
+            xml.close("cac:FinancialInstitutionBranch");
+        }
+        xml.close("cac:PayeeFinancialAccount");
+        xml.close("cac:PaymentMeans");
+    }
+
+    xml.open("cac:TaxTotal", &[]);
+    xml.leaf("cbc:TaxAmount", &currency, &format_minor(doc.tax_total, digits));
+    for tax in &doc.taxes {
+        xml.open("cac:TaxSubtotal", &[]);
+        xml.leaf("cbc:TaxableAmount", &currency, &format_minor(tax.net, digits));
+        xml.leaf("cbc:TaxAmount", &currency, &format_minor(tax.tax, digits));
+        ubl_tax_category(&mut xml, "cac:TaxCategory", tax.category, tax.rate_bp, true);
+        xml.close("cac:TaxSubtotal");
+    }
+    xml.close("cac:TaxTotal");
+
+    xml.open("cac:LegalMonetaryTotal", &[]);
+    xml.leaf("cbc:LineExtensionAmount", &currency, &format_minor(doc.subtotal, digits));
+    xml.leaf("cbc:TaxExclusiveAmount", &currency, &format_minor(doc.subtotal, digits));
+    xml.leaf("cbc:TaxInclusiveAmount", &currency, &format_minor(doc.total, digits));
+    xml.leaf("cbc:PayableAmount", &currency, &format_minor(doc.total, digits));
+    xml.close("cac:LegalMonetaryTotal");
+
+    for (index, line) in doc.lines.iter().enumerate() {
+        xml.open("cac:InvoiceLine", &[]);
+        xml.leaf("cbc:ID", &[], &(index + 1).to_string());
+        xml.leaf("cbc:InvoicedQuantity", &[("unitCode", line.unit_code)], &line.quantity.to_string());
+        xml.leaf("cbc:LineExtensionAmount", &currency, &format_minor(line.net, digits));
+        if line.allowance != 0 {
+            xml.open("cac:AllowanceCharge", &[]);
+            xml.leaf("cbc:ChargeIndicator", &[], "false");
+            xml.leaf("cbc:AllowanceChargeReasonCode", &[], "95");
+            xml.leaf("cbc:AllowanceChargeReason", &[], "Discount");
+            xml.leaf("cbc:Amount", &currency, &format_minor(line.allowance, digits));
+            xml.close("cac:AllowanceCharge");
+        }
+        xml.open("cac:Item", &[]);
+        xml.leaf("cbc:Name", &[], &line.description);
+        ubl_tax_category(&mut xml, "cac:ClassifiedTaxCategory", line.category, line.rate_bp, false);
+        xml.close("cac:Item");
+        xml.open("cac:Price", &[]);
+        xml.leaf("cbc:PriceAmount", &currency, &format_minor(line.unit_price, digits));
+        xml.close("cac:Price");
+        xml.close("cac:InvoiceLine");
+    }
+    xml.close("Invoice");
+    xml.out
+}
+
+fn ubl_tax_category(xml: &mut XmlWriter, tag: &str, category: &str, rate_bp: i64, with_reason: bool) {
+    xml.open(tag, &[]);
+    xml.leaf("cbc:ID", &[], category);
+    if category != "O" {
+        xml.leaf("cbc:Percent", &[], &format_percent(rate_bp));
+    } else if with_reason {
+        xml.leaf("cbc:TaxExemptionReason", &[], "Not subject to VAT");
+    }
+    xml.open("cac:TaxScheme", &[]);
+    xml.leaf("cbc:ID", &[], "VAT");
+    xml.close("cac:TaxScheme");
+    xml.close(tag);
+}
+
+fn cii_date(date: &str) -> String {
+    date.replace('-', "")
+}
+
+fn to_cii(doc: &Document) -> String {
+    let currency = [("currencyID", doc.currency.as_str())];
+    let digits = currency_digits(&doc.currency);
+    let mut xml = XmlWriter::new();
+    xml.open(
+        "rsm:CrossIndustryInvoice",
+        &[
+            ("xmlns:rsm", "urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100"),
+            ("xmlns:ram", "urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100"),
+            ("xmlns:qdt", "urn:un:unece:uncefact:data:standard:QualifiedDataType:100"),
+            ("xmlns:udt", "urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100"),
+        ],
+    );
+    xml.open("rsm:ExchangedDocumentContext", &[]);
+    xml.open("ram:GuidelineSpecifiedDocumentContextParameter", &[]);
+    xml.leaf("ram:ID", &[], FACTUR_X_GUIDELINE_ID);
+    xml.close("ram:GuidelineSpecifiedDocumentContextParameter");
+    xml.close("rsm:ExchangedDocumentContext");
+
+    xml.open("rsm:ExchangedDocument", &[]);
+    xml.leaf("ram:ID", &[], &doc.number);
+    xml.leaf("ram:TypeCode", &[], "380");
+    xml.open("ram:IssueDateTime", &[]);
+    xml.leaf("udt:DateTimeString", &[("format", "102")], &cii_date(&doc.issue_date));
+    xml.close("ram:IssueDateTime");
+    xml.close("rsm:ExchangedDocument");
+
+    xml.open("rsm:SupplyChainTradeTransaction", &[]);
+    for (index, line) in doc.lines.iter().enumerate() {
+        xml.open("ram:IncludedSupplyChainTradeLineItem", &[]);
+        xml.open("ram:AssociatedDocumentLineDocument", &[]);
+        xml.leaf("ram:LineID", &[], &(index + 1).to_string());
+        xml.close("ram:AssociatedDocumentLineDocument");
+        xml.open("ram:SpecifiedTradeProduct", &[]);
+        xml.leaf("ram:Name", &[], &line.description);
+        xml.close("ram:SpecifiedTradeProduct");
+        xml.open("ram:SpecifiedLineTradeAgreement", &[]);
+        xml.open("ram:NetPriceProductTradePrice", &[]);
+        xml.leaf("ram:ChargeAmount", &[], &format_minor(line.unit_price, digits));
+        xml.close("ram:NetPriceProductTradePrice");
+        xml.close("ram:SpecifiedLineTradeAgreement");
+        xml.open("ram:SpecifiedLineTradeDelivery", &[]);
+        xml.leaf("ram:BilledQuantity", &[("unitCode", line.unit_code)], &line.quantity.to_string());
+        xml.close("ram:SpecifiedLineTradeDelivery");
+        xml.open("ram:SpecifiedLineTradeSettlement", &[]);
+        xml.open("ram:ApplicableTradeTax", &[]);
+        xml.leaf("ram:TypeCode", &[], "VAT");
+        xml.leaf("ram:CategoryCode", &[], line.category);
+        if line.category != "O" {
+            xml.leaf("ram:RateApplicablePercent", &[], &format_percent(line.rate_bp));
+        }
+        xml.close("ram:ApplicableTradeTax");
+        if line.allowance != 0 {
+            xml.open("ram:SpecifiedTradeAllowanceCharge", &[]);
+            xml.open("ram:ChargeIndicator", &[]);
+            xml.leaf("udt:Indicator", &[], "false");
+            xml.close("ram:ChargeIndicator");
+            xml.leaf("ram:ActualAmount", &[], &format_minor(line.allowance, digits));
+            xml.leaf("ram:ReasonCode", &[], "95");
+            xml.leaf("ram:Reason", &[], "Discount");
+            xml.close("ram:SpecifiedTradeAllowanceCharge");
+        }
+        xml.open("ram:SpecifiedTradeSettlementLineMonetarySummation", &[]);
+        xml.leaf("ram:LineTotalAmount", &[], &format_minor(line.net, digits));
+        xml.close("ram:SpecifiedTradeSettlementLineMonetarySummation");
+        xml.close("ram:SpecifiedLineTradeSettlement");
+        xml.close("ram:IncludedSupplyChainTradeLineItem");
+    }
+
+    xml.open("ram:ApplicableHeaderTradeAgreement", &[]);
+    xml.leaf("ram:BuyerReference", &[], &doc.number);
+    for (tag, party) in [("ram:SellerTradeParty", &doc.seller), ("ram:BuyerTradeParty", &doc.buyer)] {
+        xml.open(tag, &[]);
+        xml.leaf("ram:Name", &[], &party.name);
+        if let Some(address) = &party.address {
+            xml.open("ram:PostalTradeAddress", &[]);
+            xml.leaf("ram:PostcodeCode", &[], &address.postal_code);
+            if !address.street.is_empty() {
+                xml.leaf("ram:LineOne", &[], &address.street);
+            }
+            xml.leaf("ram:CityName", &[], &address.town);
+            xml.leaf("ram:CountryID", &[], &address.country);
+            xml.close("ram:PostalTradeAddress");
+        }
+        if let Some((scheme, id)) = &party.endpoint {
+            xml.open("ram:URIUniversalCommunication", &[]);
+            xml.leaf("ram:URIID", &[("schemeID", scheme)], id);
+            xml.close("ram:URIUniversalCommunication");
+        }
+        if let Some(vat_id) = &party.vat_id {
+            xml.open("ram:SpecifiedTaxRegistration", &[]);
+            xml.leaf("ram:ID", &[("schemeID", "VA")], vat_id);
+            xml.close("ram:SpecifiedTaxRegistration");
+        }
+        xml.close(tag);
+    }
+    xml.close("ram:ApplicableHeaderTradeAgreement");
+    xml.open("ram:ApplicableHeaderTradeDelivery", &[]);
+    xml.close("ram:ApplicableHeaderTradeDelivery");
+
+    xml.open("ram:ApplicableHeaderTradeSettlement", &[]);
+    xml.leaf("ram:PaymentReference", &[], &doc.number);
+    xml.leaf("ram:InvoiceCurrencyCode", &[], &doc.currency);
+    if let Some(iban) = &doc.iban {
+        xml.open("ram:SpecifiedTradeSettlementPaymentMeans", &[]);
+        xml.leaf("ram:TypeCode", &[], "58");
+        xml.open("ram:PayeePartyCreditorFinancialAccount", &[]);
+        xml.leaf("ram:IBANID", &[], iban);
+        xml.close("ram:PayeePartyCreditorFinancialAccount");
+        if let Some(bic) = &doc.bic {
+            xml.open("ram:PayeeSpecifiedCreditorFinancialInstitution", &[]);
+            xml.leaf("ram:BICID", &[], bic);
+            xml.close("ram:PayeeSpecifiedCreditorFinancialInstitution");
+        }
+        xml.close("ram:SpecifiedTradeSettlementPaymentMeans");
+    }
+    for tax in &doc.taxes {
+        xml.open("ram:ApplicableTradeTax", &[]);
+        xml.leaf("ram:CalculatedAmount", &[], &format_minor(tax.tax, digits));
+        xml.leaf("ram:TypeCode", &[], "VAT");
+        if tax.category == "O" {
+            xml.leaf("ram:ExemptionReason", &[], "Not subject to VAT");
+        }
+        xml.leaf("ram:BasisAmount", &[], &format_minor(tax.net, digits));
+        xml.leaf("ram:CategoryCode", &[], tax.category);
+        if tax.category != "O" {
+            xml.leaf("ram:RateApplicablePercent", &[], &format_percent(tax.rate_bp));
+        }
+        xml.close("ram:ApplicableTradeTax");
+    }
+    xml.open("ram:SpecifiedTradePaymentTerms", &[]);
+    xml.open("ram:DueDateDateTime", &[]);
+    xml.leaf("udt:DateTimeString", &[("format", "102")], &cii_date(&doc.due_date));
+    xml.close("ram:DueDateDateTime");
+    xml.close("ram:SpecifiedTradePaymentTerms");
+    xml.open("ram:SpecifiedTradeSettlementHeaderMonetarySummation", &[]);
+    xml.leaf("ram:LineTotalAmount", &[], &format_minor(doc.subtotal, digits));
+    xml.leaf("ram:TaxBasisTotalAmount", &[], &format_minor(doc.subtotal, digits));
+    xml.leaf("ram:TaxTotalAmount", &currency, &format_minor(doc.tax_total, digits));
+    xml.leaf("ram:GrandTotalAmount", &[], &format_minor(doc.total, digits));
+    xml.leaf("ram:DuePayableAmount", &[], &format_minor(doc.total, digits));
+    xml.close("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+    xml.close("ram:ApplicableHeaderTradeSettlement");
+    xml.close("rsm:SupplyChainTradeTransaction");
+    xml.close("rsm:CrossIndustryInvoice");
+    xml.out
+}
+
+/// Validates `xml` against the XSD at `xsd`, whose imports resolve relative to it
+fn check_schema(xml: &str, xsd: &Path) -> Result<(), EInvoiceError> {
+    let messages = |errors: Vec<StructuredError>| -> Vec<String> {
+        errors
+            .into_iter()
+            .map(|e| {
+                let message = e.message.unwrap_or_default().trim().to_string();
+                match e.line {
+                    Some(line) => format!("line {}: {}", line, message),
+                    None => message,
+                }
+            })
+            .collect()
+    };
+    let path = xsd.to_str().ok_or_else(|| EInvoiceError::ArtefactError(xsd.display().to_string(), "not a UTF-8 path".into()))?;
+
+    let _libxml = LIBXML.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let document = Parser::default().parse_string(xml).map_err(|e| EInvoiceError::SchemaInvalid(vec![e.to_string()]))?;
+    let mut parser = SchemaParserContext::from_file(path);
+    let mut schema = SchemaValidationContext::from_parser(&mut parser)
+        .map_err(|errors| EInvoiceError::ArtefactError(xsd.display().to_string(), messages(errors).join("; ")))?;
+    schema.validate_document(&document).map_err(|errors| EInvoiceError::SchemaInvalid(messages(errors)))
+}
+
+/// Checks generated XML against its official XSD and Schematron files, all of which must be
+/// installed. Failed rules flagged as warnings are logged, not fatal.
+fn check_conformance(xml: &str, artefacts: &Path, xsd: &str, rules: &[&str]) -> Result<(), EInvoiceError> {
+    let installed = |file: &str| {
+        let path = artefacts.join(file);
+        if path.is_file() { Ok(path) } else { Err(EInvoiceError::MissingArtefact(path.display().to_string())) }
+    };
+    let xsd = installed(xsd)?;
+    let rules = rules.iter().map(|file| installed(file)).collect::<Result<Vec<_>, _>>()?;
+
+    check_schema(xml, &xsd)?;
+    let mut errors = Vec::new();
+    for path in rules {
+        let findings = Schema::load(&path)?.check(xml)?;
+        for warning in findings.warnings {
+            println!("[einvoice.rs::check_conformance] {}: {}", path.display(), warning);
+        }
+        errors.extend(findings.errors);
+    }
+    if !errors.is_empty() {
+        return Err(EInvoiceError::RulesFailed(errors));
+    }
+    Ok(())
+}
+
+/// Builds, validates and renders an issued invoice; returns the file name, MIME type and bytes.
+/// `artefacts` is the directory holding the official XSD and Schematron files.
+pub fn export(
+    conn: &Connection,
+    invoice_id: i64,
+    format: EInvoiceFormat,
+    artefacts: &Path,
+) -> Result<(String, &'static str, Vec<u8>), EInvoiceError> {
+    let doc = load(conn, invoice_id)?;
+    let errors = validate(&doc);
+    if !errors.is_empty() {
+        return Err(EInvoiceError::Invalid(errors));
+    }
+
+    match format {
+        EInvoiceFormat::Ubl => {
+            let xml = to_ubl(&doc);
+            check_conformance(&xml, artefacts, UBL_XSD, &[EN16931_UBL_RULES, PEPPOL_UBL_RULES])?;
+            Ok((format!("{}.xml", doc.number), "application/xml", xml.into_bytes()))
+        }
+        EInvoiceFormat::FacturX => {
+            let xml = to_cii(&doc);
+            check_conformance(&xml, artefacts, CII_XSD, &[EN16931_CII_RULES])?;
+
+            let mut builder = PdfBuilder::new()?;
+            builder.set_pdf_a3(FACTUR_X_METADATA);
+            layout_invoice(conn, invoice_id, &mut builder)?;
+            builder.attach_file(FACTUR_X_FILE_NAME, "text/xml", "Factur-X invoice", "Alternative", xml.into_bytes());
+            Ok((format!("{}.pdf", doc.number), "application/pdf", builder.finish()?))
+        }
+    }
+}
+
+/// 📤 Export an issued invoice as an e-invoice and save it as an attachment of the invoice.
+/// `format` is `ubl` (Peppol BIS Billing 3.0) or `facturx` (Factur-X/ZUGFeRD PDF/A-3).
+/// Nothing is stored unless the XML passes the bundled XSD and EN 16931/Peppol Schematron.
+#[tauri::command]
+pub fn export_invoice(
+    state: tauri::State<StateWrapper>,
+    app_handle: tauri::AppHandle,
+    invoice_id: i64,
+    format: String,
+) -> Result<Attachment, EInvoiceError> {
+    let format = EInvoiceFormat::parse(&format).ok_or(EInvoiceError::UnknownFormat(format))?;
+    let artefacts = app_handle.path().resource_dir()?.join(ARTEFACTS_DIR);
+    let db_conn = open_state_db(&state)?;
+    let (file_name, mime_type, bytes) = export(&db_conn, invoice_id, format, &artefacts)?;
+    let files = state_attachment_files(&state)?;
+    Ok(store(&db_conn, &files, "invoice", invoice_id, &file_name, mime_type, &bytes)?)
+}
@@ -0,0 +1,493 @@
+//! Expenses with a managed category list and receipt attachments.
+//!
+//! Amounts are gross (tax included) in minor units; the tax part is derived from the rate
+//! when the expense is saved. Receipts are stored through `attachments` with owner type
+//! `expense`, so they are encrypted like every other attachment and go away with the expense.
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::attachments::{self, state_attachment_files, Attachment, AttachmentError, AttachmentFiles};
+use crate::currencies::{currency_or_base, parse_date, CurrencyError};
+use crate::db_api::{open_state_db, DbApiError};
+use crate::ledger::{post_expense, LedgerError};
+use crate::money::{round_div, FULL_RATE_BP};
+use crate::StateWrapper;
+
+/// Owner type of receipts in the `attachments` table
+pub const RECEIPT_OWNER_TYPE: &str = "expense";
+
+#[derive(Debug, Error)]
+pub enum ExpenseError {
+    #[error("[expenses.rs::not_found] Expense {0} does not exist.")]
+    NotFound(i64),
+
+    #[error("[expenses.rs::category_not_found] Expense category {0} does not exist.")]
+    CategoryNotFound(i64),
+
+    #[error("[expenses.rs::duplicate_category] An expense category named {0:?} already exists.")]
+    DuplicateCategory(String),
+
+    #[error("[expenses.rs::category_in_use] Expense category {0:?} is used by {1} expense(s).")]
+    CategoryInUse(String, i64),
+
+    #[error("[expenses.rs::unsupported_receipt] Receipts must be an image or a PDF, got {0:?}.")]
+    UnsupportedReceipt(String),
+
+    #[error("[expenses.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[expenses.rs::io] Could not read {0:?}: {1}")]
+    IoError(String, std::io::Error),
+
+    #[error("[expenses.rs::attachment] {0}")]
+    AttachmentError(#[from] AttachmentError),
+
+    #[error("[expenses.rs::ledger] {0}")]
+    LedgerError(#[from] LedgerError),
+
+    #[error("[expenses.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[expenses.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[expenses.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for ExpenseError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 🧾 Expense Struct. `amount` is gross in minor units, the rate in basis points (8.1 % = 810).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Expense {
+    pub id: Option<i64>,
+    /// `YYYY-MM-DD`; `DD.MM.YYYY` is accepted as input
+    pub date: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub vendor: Option<String>,
+    #[serde(default)]
+    pub category_id: Option<i64>,
+    /// Name of the category, filled in when reading
+    #[serde(default)]
+    pub category: Option<String>,
+    pub amount: i64,
+    #[serde(default)]
+    pub tax_rate_bp: i64,
+    /// Tax contained in `amount`, computed when the expense is saved
+    #[serde(default)]
+    pub tax_amount: i64,
+    /// ISO 4217 code; defaults to `settings.base_currency`
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub event_id: Option<i64>,
+    /// Number of attached receipts, filled in when reading
+    #[serde(default)]
+    pub receipt_count: i64,
+    /// Set when a bank transaction is confirmed as the payment of the expense
+    #[serde(default)]
+    pub paid_at: Option<String>,
+    #[serde(default)]
+    pub bank_transaction_id: Option<i64>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// 🔎 Filters for `list_expenses`; every field is optional and dates are inclusive
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ExpenseFilter {
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub category_id: Option<i64>,
+    #[serde(default)]
+    pub event_id: Option<i64>,
+}
+
+/// 🏷️ Expense Category Struct
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExpenseCategory {
+    pub id: i64,
+    pub name: String,
+    /// Ledger account the expenses are booked to; the default expense account if not set
+    pub account_id: Option<i64>,
+    /// Number of expenses filed under the category
+    pub expense_count: i64,
+}
+
+const EXPENSE_SELECT: &str = "
+    SELECT e.id, e.date, e.description, e.vendor, e.category_id, c.name, e.amount, e.tax_rate_bp,
+           e.tax_amount, e.currency, e.event_id,
+           (SELECT COUNT(*) FROM attachments a WHERE a.owner_type = 'expense' AND a.owner_id = e.id),
+           e.paid_at, e.bank_transaction_id, e.created_at
+    FROM expenses e
+    LEFT JOIN expense_categories c ON c.id = e.category_id";
+
+fn expense_from_row(row: &rusqlite::Row) -> rusqlite::Result<Expense> {
+    Ok(Expense {
+        id: row.get(0)?,
+        date: row.get(1)?,
+        description: row.get(2)?,
+        vendor: row.get(3)?,
+        category_id: row.get(4)?,
+        category: row.get(5)?,
+        amount: row.get(6)?,
+        tax_rate_bp: row.get(7)?,
+        tax_amount: row.get(8)?,
+        currency: row.get(9)?,
+        event_id: row.get(10)?,
+        receipt_count: row.get(11)?,
+        paid_at: row.get(12)?,
+        bank_transaction_id: row.get(13)?,
+        created_at: row.get(14)?,
+    })
+}
+
+/// Tax contained in a gross amount, e.g. 108.10 at 8.1 % contains 8.10
+pub fn included_tax(gross: i64, tax_rate_bp: i64) -> i64 {
+    round_div(gross as i128 * tax_rate_bp as i128, (FULL_RATE_BP + tax_rate_bp) as i128)
+}
+
+fn normalize_date(field: &'static str, value: &str) -> Result<String, ExpenseError> {
+    parse_date(value)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .ok_or_else(|| ExpenseError::InvalidValue(field, value.to_string()))
+}
+
+fn ensure_category(conn: &Connection, category_id: Option<i64>) -> Result<(), ExpenseError> {
+    if let Some(category_id) = category_id {
+        let exists: bool =
+            conn.query_row("SELECT EXISTS(SELECT 1 FROM expense_categories WHERE id = ?1)", [category_id], |row| row.get(0))?;
+        if !exists {
+            return Err(ExpenseError::CategoryNotFound(category_id));
+        }
+    }
+    Ok(())
+}
+
+/// Checks an expense and returns its normalized date and currency
+fn validate(conn: &Connection, expense: &Expense) -> Result<(String, String), ExpenseError> {
+    let date = normalize_date("date", &expense.date)?;
+    if expense.amount == 0 {
+        return Err(ExpenseError::InvalidValue("amount", expense.amount.to_string()));
+    }
+    if expense.tax_rate_bp < 0 {
+        return Err(ExpenseError::InvalidValue("tax_rate_bp", expense.tax_rate_bp.to_string()));
+    }
+    ensure_category(conn, expense.category_id)?;
+    let currency = currency_or_base(conn, expense.currency.as_deref())?;
+    Ok((date, currency))
+}
+
+fn trimmed(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+pub fn get(conn: &Connection, expense_id: i64) -> Result<Expense, ExpenseError> {
+    conn.query_row(&format!("{} WHERE e.id = ?1", EXPENSE_SELECT), [expense_id], expense_from_row)
+        .optional()?
+        .ok_or(ExpenseError::NotFound(expense_id))
+}
+
+/// Expenses matching `filter`, newest first
+pub fn list(conn: &Connection, filter: &ExpenseFilter) -> Result<Vec<Expense>, ExpenseError> {
+    let from = filter.from.as_deref().map(|v| normalize_date("from", v)).transpose()?;
+    let to = filter.to.as_deref().map(|v| normalize_date("to", v)).transpose()?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR date(e.date) >= ?1)
+             AND (?2 IS NULL OR date(e.date) <= ?2)
+             AND (?3 IS NULL OR e.category_id = ?3)
+             AND (?4 IS NULL OR e.event_id = ?4)
+           ORDER BY date(e.date) DESC, e.id DESC",
+        EXPENSE_SELECT
+    ))?;
+    let expenses = stmt
+        .query_map(params![from, to, filter.category_id, filter.event_id], expense_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(expenses)
+}
+
+pub fn create(conn: &Connection, expense: &Expense) -> Result<i64, ExpenseError> {
+    let (date, currency) = validate(conn, expense)?;
+    conn.execute(
+        "INSERT INTO expenses (date, description, vendor, category_id, amount, tax_rate_bp, tax_amount, currency, event_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            date,
+            expense.description.trim(),
+            trimmed(&expense.vendor),
+            expense.category_id,
+            expense.amount,
+            expense.tax_rate_bp,
+            included_tax(expense.amount, expense.tax_rate_bp),
+            currency,
+            expense.event_id
+        ],
+    )?;
+    let expense_id = conn.last_insert_rowid();
+    post_expense(conn, expense_id)?;
+    Ok(expense_id)
+}
+
+pub fn update(conn: &Connection, expense: &Expense) -> Result<(), ExpenseError> {
+    let expense_id = expense.id.ok_or(ExpenseError::InvalidValue("id", "missing".to_string()))?;
+    let (date, currency) = validate(conn, expense)?;
+    let updated = conn.execute(
+        "UPDATE expenses SET date = ?1, description = ?2, vendor = ?3, category_id = ?4, amount = ?5,
+                             tax_rate_bp = ?6, tax_amount = ?7, currency = ?8, event_id = ?9
+         WHERE id = ?10",
+        params![
+            date,
+            expense.description.trim(),
+            trimmed(&expense.vendor),
+            expense.category_id,
+            expense.amount,
+            expense.tax_rate_bp,
+            included_tax(expense.amount, expense.tax_rate_bp),
+            currency,
+            expense.event_id,
+            expense_id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(ExpenseError::NotFound(expense_id));
+    }
+    post_expense(conn, expense_id)?;
+    Ok(())
+}
+
+/// Deletes an expense together with its receipts. The receipt files are removed only once
+/// the deletion is committed, so a failure leaves both rows and files in place.
+pub fn delete(conn: &mut Connection, files: &AttachmentFiles, expense_id: i64) -> Result<(), ExpenseError> {
+    let tx = conn.transaction()?;
+    get(&tx, expense_id)?;
+    let mut paths = Vec::new();
+    for receipt in attachments::list(&tx, RECEIPT_OWNER_TYPE, expense_id)? {
+        paths.push(attachments::delete_row(&tx, receipt.id)?);
+    }
+    tx.execute("DELETE FROM expenses WHERE id = ?1", [expense_id])?;
+    post_expense(&tx, expense_id)?;
+    tx.commit()?;
+    for path in paths {
+        attachments::remove_file(files, &path)?;
+    }
+    Ok(())
+}
+
+/// Mime type of a receipt file, judged by its extension
+pub fn receipt_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "pdf" => Some("application/pdf"),
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "heic" => Some("image/heic"),
+        "tif" | "tiff" => Some("image/tiff"),
+        _ => None,
+    }
+}
+
+/// Stores a receipt for an expense
+pub fn attach_receipt(
+    conn: &Connection,
+    files: &AttachmentFiles,
+    expense_id: i64,
+    file_name: &str,
+    bytes: &[u8],
+) -> Result<Attachment, ExpenseError> {
+    let mime_type =
+        receipt_mime_type(Path::new(file_name)).ok_or_else(|| ExpenseError::UnsupportedReceipt(file_name.to_string()))?;
+    get(conn, expense_id)?;
+    Ok(attachments::store(conn, files, RECEIPT_OWNER_TYPE, expense_id, file_name, mime_type, bytes)?)
+}
+
+pub fn list_categories(conn: &Connection) -> Result<Vec<ExpenseCategory>, ExpenseError> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, c.account_id, (SELECT COUNT(*) FROM expenses e WHERE e.category_id = c.id)
+         FROM expense_categories c ORDER BY c.name COLLATE NOCASE",
+    )?;
+    let categories = stmt
+        .query_map([], |row| {
+            Ok(ExpenseCategory { id: row.get(0)?, name: row.get(1)?, account_id: row.get(2)?, expense_count: row.get(3)? })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(categories)
+}
+
+fn category_name(name: &str, existing_id: Option<i64>, conn: &Connection) -> Result<String, ExpenseError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ExpenseError::InvalidValue("name", name.to_string()));
+    }
+    let taken: Option<i64> = conn
+        .query_row("SELECT id FROM expense_categories WHERE name = ?1", [name], |row| row.get(0))
+        .optional()?;
+    if taken.is_some_and(|id| Some(id) != existing_id) {
+        return Err(ExpenseError::DuplicateCategory(name.to_string()));
+    }
+    Ok(name.to_string())
+}
+
+pub fn create_category(conn: &Connection, name: &str) -> Result<i64, ExpenseError> {
+    let name = category_name(name, None, conn)?;
+    conn.execute("INSERT INTO expense_categories (name) VALUES (?1)", [name])?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn rename_category(conn: &Connection, category_id: i64, name: &str) -> Result<(), ExpenseError> {
+    let name = category_name(name, Some(category_id), conn)?;
+    let updated = conn.execute("UPDATE expense_categories SET name = ?1 WHERE id = ?2", params![name, category_id])?;
+    if updated == 0 {
+        return Err(ExpenseError::CategoryNotFound(category_id));
+    }
+    Ok(())
+}
+
+/// Sets the ledger account expenses of a category are booked to from now on;
+/// entries already posted stay as they are
+pub fn set_category_account(conn: &Connection, category_id: i64, account_id: Option<i64>) -> Result<(), ExpenseError> {
+    if let Some(account_id) = account_id {
+        let active: Option<bool> =
+            conn.query_row("SELECT active FROM accounts WHERE id = ?1", [account_id], |row| row.get(0)).optional()?;
+        if active != Some(true) {
+            return Err(ExpenseError::InvalidValue("account_id", account_id.to_string()));
+        }
+    }
+    let updated =
+        conn.execute("UPDATE expense_categories SET account_id = ?1 WHERE id = ?2", params![account_id, category_id])?;
+    if updated == 0 {
+        return Err(ExpenseError::CategoryNotFound(category_id));
+    }
+    Ok(())
+}
+
+/// Deletes a category that no expense uses
+pub fn delete_category(conn: &Connection, category_id: i64) -> Result<(), ExpenseError> {
+    let (name, used): (String, i64) = conn
+        .query_row(
+            "SELECT name, (SELECT COUNT(*) FROM expenses WHERE category_id = ?1) FROM expense_categories WHERE id = ?1",
+            [category_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or(ExpenseError::CategoryNotFound(category_id))?;
+    if used > 0 {
+        return Err(ExpenseError::CategoryInUse(name, used));
+    }
+    conn.execute("DELETE FROM expense_categories WHERE id = ?1", [category_id])?;
+    Ok(())
+}
+
+/// ➕ Create an expense, returns its id
+#[tauri::command]
+pub fn create_expense(state: tauri::State<StateWrapper>, expense: Expense) -> Result<i64, ExpenseError> {
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    let expense_id = create(&tx, &expense)?;
+    tx.commit()?;
+    Ok(expense_id)
+}
+
+/// ✏️ Update an expense
+#[tauri::command]
+pub fn update_expense(state: tauri::State<StateWrapper>, expense: Expense) -> Result<(), ExpenseError> {
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    update(&tx, &expense)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 🗑️ Delete an expense and its receipts
+#[tauri::command]
+pub fn delete_expense(state: tauri::State<StateWrapper>, expense_id: i64) -> Result<(), ExpenseError> {
+    let mut db_conn = open_state_db(&state)?;
+    delete(&mut db_conn, &state_attachment_files(&state)?, expense_id)
+}
+
+/// 🔍 Get one expense
+#[tauri::command]
+pub fn get_expense(state: tauri::State<StateWrapper>, expense_id: i64) -> Result<Expense, ExpenseError> {
+    let db_conn = open_state_db(&state)?;
+    get(&db_conn, expense_id)
+}
+
+/// 📋 List expenses by date range, category and event
+#[tauri::command]
+pub fn list_expenses(state: tauri::State<StateWrapper>, filter: Option<ExpenseFilter>) -> Result<Vec<Expense>, ExpenseError> {
+    let db_conn = open_state_db(&state)?;
+    list(&db_conn, &filter.unwrap_or_default())
+}
+
+/// 📎 Attach a receipt image or PDF from disk to an expense.
+/// Receipts are listed, read and removed with the attachment commands (owner type `expense`).
+#[tauri::command]
+pub fn attach_expense_receipt(state: tauri::State<StateWrapper>, expense_id: i64, path: String) -> Result<Attachment, ExpenseError> {
+    let file_path = Path::new(&path);
+    let file_name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| ExpenseError::InvalidValue("path", path.clone()))?;
+    if receipt_mime_type(file_path).is_none() {
+        return Err(ExpenseError::UnsupportedReceipt(file_name));
+    }
+    let bytes = std::fs::read(file_path).map_err(|e| ExpenseError::IoError(path.clone(), e))?;
+    let db_conn = open_state_db(&state)?;
+    attach_receipt(&db_conn, &state_attachment_files(&state)?, expense_id, &file_name, &bytes)
+}
+
+/// 🏷️ List expense categories
+#[tauri::command]
+pub fn list_expense_categories(state: tauri::State<StateWrapper>) -> Result<Vec<ExpenseCategory>, ExpenseError> {
+    let db_conn = open_state_db(&state)?;
+    list_categories(&db_conn)
+}
+
+/// ➕ Add an expense category, returns its id
+#[tauri::command]
+pub fn create_expense_category(state: tauri::State<StateWrapper>, name: String) -> Result<i64, ExpenseError> {
+    let db_conn = open_state_db(&state)?;
+    create_category(&db_conn, &name)
+}
+
+/// ✏️ Rename an expense category
+#[tauri::command]
+pub fn rename_expense_category(state: tauri::State<StateWrapper>, category_id: i64, name: String) -> Result<(), ExpenseError> {
+    let db_conn = open_state_db(&state)?;
+    rename_category(&db_conn, category_id, &name)
+}
+
+/// 🗑️ Delete an expense category that no expense uses
+#[tauri::command]
+pub fn delete_expense_category(state: tauri::State<StateWrapper>, category_id: i64) -> Result<(), ExpenseError> {
+    let db_conn = open_state_db(&state)?;
+    delete_category(&db_conn, category_id)
+}
+
+/// 📒 Book the expenses of a category to a ledger account, or to the default one with `None`
+#[tauri::command]
+pub fn set_expense_category_account(
+    state: tauri::State<StateWrapper>,
+    category_id: i64,
+    account_id: Option<i64>,
+) -> Result<(), ExpenseError> {
+    let db_conn = open_state_db(&state)?;
+    set_category_account(&db_conn, category_id, account_id)
+}
@@ -0,0 +1,405 @@
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use thiserror::Error;
+
+/// Errors raised while reading iCalendar (RFC 5545) data
+#[derive(Debug, Error)]
+pub enum IcalError {
+    #[error("[ical.rs::missing_property] VEVENT is missing the {0} property")]
+    MissingProperty(&'static str),
+
+    #[error("[ical.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[ical.rs::no_event] The calendar data does not contain a VEVENT")]
+    NoEvent,
+}
+
+/// A single unfolded iCalendar content line: `NAME;PARAM=VALUE:value`
+#[derive(Clone, Debug)]
+pub struct ContentLine {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl ContentLine {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        // The value starts at the first colon that is not inside a quoted parameter
+        let mut in_quotes = false;
+        let split = line.char_indices().find(|&(_, c)| {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            }
+            c == ':' && !in_quotes
+        })?;
+        let (head, value) = (&line[..split.0], &line[split.0 + 1..]);
+
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (k.to_ascii_uppercase(), v.trim_matches('"').to_string()))
+            .collect();
+
+        Some(ContentLine { name, params, value: value.to_string() })
+    }
+}
+
+/// The subset of a VEVENT that maps onto the `events` table.
+/// Times are UTC, like everything else stored in the database.
+#[derive(Clone, Debug)]
+pub struct VEvent {
+    pub uid: String,
+    pub summary: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub all_day: bool,
+    pub last_modified: Option<NaiveDateTime>,
+    pub sequence: i64,
+    pub description: Option<String>,
+    pub organizer: Option<Organizer>,
+}
+
+/// The ORGANIZER of an event: `ORGANIZER;CN=Jane Doe:mailto:jane@example.com`
+#[derive(Clone, Debug)]
+pub struct Organizer {
+    pub name: Option<String>,
+    pub email: String,
+}
+
+/// Joins folded lines (a CRLF followed by a space or tab continues the previous line)
+pub fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        if let Some(rest) = raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        if !raw.is_empty() {
+            lines.push(raw.to_string());
+        }
+    }
+    lines
+}
+
+/// Folds a content line to 75 octets per physical line, never splitting a UTF-8 character
+pub fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += len;
+    }
+    out.push_str("\r\n");
+    out
+}
+
+pub fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+pub fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Formats a UTC time as an iCalendar UTC date-time (`20250131T093000Z`)
+pub fn format_utc(value: &NaiveDateTime) -> String {
+    value.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Resolves a DATE or DATE-TIME property to UTC. Returns the time and whether it was a plain DATE.
+fn parse_time(line: &ContentLine, property: &'static str) -> Result<(NaiveDateTime, bool), IcalError> {
+    let invalid = || IcalError::InvalidValue(property, line.value.clone());
+    let value = line.value.trim();
+
+    if line.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+        let midnight = date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?;
+        return Ok((local_to_utc(&midnight), true));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let dt = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        return Ok((dt, false));
+    }
+
+    let dt = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+    let tz = line.param("TZID").and_then(|tzid| {
+        // Some clients prefix IANA names, e.g. `/mozilla.org/20050126_1/Europe/Berlin`
+        tzid.parse::<Tz>().ok().or_else(|| {
+            let parts: Vec<&str> = tzid.rsplitn(3, '/').collect();
+            parts.get(..2).and_then(|p| format!("{}/{}", p[1], p[0]).parse::<Tz>().ok())
+        })
+    });
+    match tz {
+        Some(tz) => tz
+            .from_local_datetime(&dt)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc).naive_utc())
+            .ok_or_else(invalid)
+            .map(|t| (t, false)),
+        // Floating time, or a TZID we cannot resolve: read it as the user's local time
+        None => Ok((local_to_utc(&dt), false)),
+    }
+}
+
+fn local_to_utc(value: &NaiveDateTime) -> NaiveDateTime {
+    Local
+        .from_local_datetime(value)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc).naive_utc())
+        .unwrap_or(*value)
+}
+
+fn utc_to_local(value: &NaiveDateTime) -> NaiveDateTime {
+    Utc.from_utc_datetime(value).with_timezone(&Local).naive_local()
+}
+
+/// Parses an RFC 5545 DURATION such as `PT1H30M`, `P1D` or `-P2W`
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, rest) = match value.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.trim().trim_start_matches('+')),
+    };
+    let rest = rest.strip_prefix('P')?;
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match (unit, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(if negative { -total } else { total })
+}
+
+/// Returns the lines of the first master VEVENT (one without RECURRENCE-ID).
+/// Recurrence overrides are ignored; recurring events are synced as their first occurrence.
+fn master_event_lines(lines: &[ContentLine]) -> Option<&[ContentLine]> {
+    let mut start = None;
+    for (i, line) in lines.iter().enumerate() {
+        match (line.name.as_str(), line.value.to_ascii_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => start = Some(i + 1),
+            ("END", "VEVENT") => {
+                if let Some(s) = start.take() {
+                    let body = &lines[s..i];
+                    if !body.iter().any(|l| l.name == "RECURRENCE-ID") {
+                        return Some(body);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_lines(text: &str) -> Vec<ContentLine> {
+    unfold(text).iter().filter_map(|l| ContentLine::parse(l)).collect()
+}
+
+/// Parses the master VEVENT of a calendar object resource
+pub fn parse_event(text: &str) -> Result<VEvent, IcalError> {
+    let lines = parse_lines(text);
+    let body = master_event_lines(&lines).ok_or(IcalError::NoEvent)?;
+    let find = |name: &str| body.iter().find(|l| l.name == name);
+
+    let uid = find("UID").ok_or(IcalError::MissingProperty("UID"))?.value.clone();
+    let (start, all_day) = parse_time(find("DTSTART").ok_or(IcalError::MissingProperty("DTSTART"))?, "DTSTART")?;
+
+    let end = match (find("DTEND"), find("DURATION")) {
+        (Some(line), _) => parse_time(line, "DTEND")?.0,
+        (None, Some(line)) => {
+            start + parse_duration(&line.value).ok_or_else(|| IcalError::InvalidValue("DURATION", line.value.clone()))?
+        }
+        (None, None) if all_day => start + Duration::days(1),
+        (None, None) => start,
+    };
+
+    let last_modified = find("LAST-MODIFIED")
+        .map(|l| parse_time(l, "LAST-MODIFIED"))
+        .transpose()?
+        .map(|(t, _)| t);
+
+    Ok(VEvent {
+        uid,
+        summary: find("SUMMARY").map(|l| unescape_text(&l.value)).unwrap_or_default(),
+        start,
+        end,
+        all_day,
+        last_modified,
+        sequence: find("SEQUENCE").and_then(|l| l.value.trim().parse().ok()).unwrap_or(0),
+        description: find("DESCRIPTION").map(|l| unescape_text(&l.value)),
+        organizer: find("ORGANIZER").map(|l| Organizer {
+            name: l.param("CN").map(str::to_string),
+            email: strip_mailto(&l.value),
+        }),
+    })
+}
+
+/// Parses every master VEVENT of a file that may hold several events, such as an exported `.ics`
+pub fn parse_events(text: &str) -> Vec<Result<VEvent, IcalError>> {
+    let mut events = Vec::new();
+    let mut current: Option<Vec<String>> = None;
+    for line in unfold(text) {
+        let upper = line.to_ascii_uppercase();
+        if upper == "BEGIN:VEVENT" {
+            current = Some(Vec::new());
+        }
+        if let Some(buffer) = current.as_mut() {
+            buffer.push(line);
+        }
+        if upper == "END:VEVENT" {
+            if let Some(buffer) = current.take() {
+                if !buffer.iter().any(|l| l.to_ascii_uppercase().starts_with("RECURRENCE-ID")) {
+                    events.push(parse_event(&buffer.join("\r\n")));
+                }
+            }
+        }
+    }
+    events
+}
+
+fn strip_mailto(value: &str) -> String {
+    let value = value.trim();
+    match value.get(..7) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("mailto:") => value[7..].to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn time_lines(name: &str, value: &NaiveDateTime, all_day: bool) -> String {
+    if all_day {
+        fold(&format!("{};VALUE=DATE:{}", name, utc_to_local(value).format("%Y%m%d")))
+    } else {
+        fold(&format!("{}:{}", name, format_utc(value)))
+    }
+}
+
+/// Serializes an event as a complete VCALENDAR object.
+///
+/// When `existing` holds the resource previously fetched from the server, its other
+/// properties and components (descriptions, alarms, time zones, overrides…) are kept and
+/// only the fields owned by the `events` table are replaced, so editing an event here does
+/// not strip what was set on a phone.
+pub fn write_event(event: &VEvent, existing: Option<&str>) -> String {
+    let now = format_utc(&Utc::now().naive_utc());
+    let all_day = event.all_day && is_local_midnight(&event.start) && is_local_midnight(&event.end);
+
+    let owned = [
+        "UID", "SUMMARY", "DTSTART", "DTEND", "DURATION", "DTSTAMP", "LAST-MODIFIED", "SEQUENCE",
+    ];
+    let mut props = String::new();
+    props.push_str(&fold(&format!("UID:{}", event.uid)));
+    props.push_str(&fold(&format!("DTSTAMP:{}", now)));
+    props.push_str(&fold(&format!("LAST-MODIFIED:{}", now)));
+    props.push_str(&fold(&format!("SEQUENCE:{}", event.sequence)));
+    props.push_str(&fold(&format!("SUMMARY:{}", escape_text(&event.summary))));
+    props.push_str(&time_lines("DTSTART", &event.start, all_day));
+    props.push_str(&time_lines("DTEND", &event.end, all_day));
+
+    if let Some(existing) = existing {
+        let mut out = String::new();
+        let mut in_master = false;
+        let mut replaced = false;
+        let mut buffered: Vec<String> = Vec::new();
+        for raw in unfold(existing) {
+            let upper = raw.to_ascii_uppercase();
+            if upper == "BEGIN:VEVENT" && !replaced {
+                in_master = true;
+                buffered.clear();
+                buffered.push(raw);
+                continue;
+            }
+            if in_master {
+                if upper == "END:VEVENT" {
+                    in_master = false;
+                    let is_override = buffered.iter().any(|l| l.to_ascii_uppercase().starts_with("RECURRENCE-ID"));
+                    for line in buffered.drain(..) {
+                        let name = ContentLine::parse(&line).map(|l| l.name).unwrap_or_default();
+                        if is_override || !owned.contains(&name.as_str()) {
+                            out.push_str(&fold(&line));
+                        }
+                    }
+                    if !is_override {
+                        out.push_str(&props);
+                        replaced = true;
+                    }
+                    out.push_str(&fold(&raw));
+                } else {
+                    buffered.push(raw);
+                }
+                continue;
+            }
+            out.push_str(&fold(&raw));
+        }
+        if replaced {
+            return out;
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("BEGIN:VCALENDAR\r\n");
+    out.push_str("VERSION:2.0\r\n");
+    out.push_str("PRODID:-//BuffMod//Calendar//EN\r\n");
+    out.push_str("BEGIN:VEVENT\r\n");
+    out.push_str(&props);
+    out.push_str("END:VEVENT\r\n");
+    out.push_str("END:VCALENDAR\r\n");
+    out
+}
+
+fn is_local_midnight(value: &NaiveDateTime) -> bool {
+    utc_to_local(value).time() == chrono::NaiveTime::MIN
+}
@@ -0,0 +1,314 @@
+//! Invoice PDFs rendered from a user-editable template.
+//!
+//! The template (setting `invoice_template`, or [`DEFAULT_INVOICE_TEMPLATE`]) is a Jinja
+//! template that produces the line markup understood by `pdf::layout_markup`. It sees
+//! `business`, `client`, `invoice`, `lines` and `taxes`; amounts are already formatted.
+//! The business profile comes from the `business_*` and `bank_*` settings. Issued invoices
+//! payable to a Swiss account end with a QR-bill payment part (see `qrbill`).
+
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::attachments::{state_attachment_files, store, Attachment, AttachmentError};
+use crate::db_api::{date_only, open_state_db, DbApiError};
+use crate::invoices::{invoice_totals, InvoiceError};
+use crate::money::{currency_digits, format_minor};
+use crate::pdf::{layout_markup, PdfBuilder, PdfError, A4_HEIGHT};
+use crate::qrbill::{self, QrBillError, QR_BILL_HEIGHT};
+use crate::settings::{read_setting, text_setting};
+use crate::StateWrapper;
+
+pub const DEFAULT_INVOICE_TEMPLATE: &str = r#"[logo]
+>!{{ business.name }}
+{% for line in business.address_lines %}
+>{{ line }}
+{% endfor %}
+{% if business.vat_id %}
+>VAT {{ business.vat_id }}
+{% endif %}
+
+!{{ client.name }}
+{% for line in client.address_lines %}
+{{ line }}
+{% endfor %}
+
+# Invoice {{ invoice.number or "(draft)" }}
+Date: {{ invoice.issued_at or "not issued" }}
+Due: {{ invoice.due_date }}
+
+|! Description | Quantity | Unit price | VAT | Amount |
+{% for line in lines %}
+| {{ line.description }}{% if line.discount %} (−{{ line.discount }}){% endif %} | {{ line.quantity }} | {{ line.unit_price }} | {{ line.tax_rate }} | {{ line.net_amount }} |
+{% endfor %}
+---
+| Subtotal | | | | {{ invoice.subtotal }} |
+{% for tax in taxes %}
+| VAT {{ tax.rate }} on {{ tax.net }} | | | | {{ tax.tax }} |
+{% endfor %}
+|! Total {{ invoice.currency }} | | | | {{ invoice.total }} |
+
+Please pay by {{ invoice.due_date }}{% if invoice.number %}, quoting {{ invoice.number }}{% endif %}.
+{% if business.iban %}
+
+!Bank details
+{% if business.bank_name %}
+{{ business.bank_name }}
+{% endif %}
+IBAN {{ business.iban }}{% if business.bic %}, BIC {{ business.bic }}{% endif %}
+
+{% endif %}
+{% if business.email or business.phone %}
+{{ business.email }}{% if business.email and business.phone %} · {% endif %}{{ business.phone }}
+{% endif %}
+"#;
+
+#[derive(Debug, Error)]
+pub enum InvoicePdfError {
+    #[error("[invoice_pdf.rs::not_found] Invoice {0} does not exist.")]
+    InvoiceNotFound(i64),
+
+    #[error("[invoice_pdf.rs::template] Invoice template error: {0}")]
+    TemplateError(String),
+
+    #[error("[invoice_pdf.rs::logo] Could not read the logo {0:?}: {1}")]
+    LogoError(String, String),
+
+    #[error("[invoice_pdf.rs::pdf] {0}")]
+    PdfError(#[from] PdfError),
+
+    #[error("[invoice_pdf.rs::qr_bill] {0}")]
+    QrBillError(#[from] QrBillError),
+
+    #[error("[invoice_pdf.rs::attachment] {0}")]
+    AttachmentError(#[from] AttachmentError),
+
+    #[error("[invoice_pdf.rs::invoice] {0}")]
+    InvoiceError(#[from] InvoiceError),
+
+    #[error("[invoice_pdf.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[invoice_pdf.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for InvoicePdfError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// Business details from the settings, as templates see them
+#[derive(Serialize)]
+pub struct BusinessProfile {
+    name: String,
+    address_lines: Vec<String>,
+    vat_id: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    bank_name: Option<String>,
+    iban: Option<String>,
+    bic: Option<String>,
+    #[serde(skip)]
+    logo_path: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ClientContext {
+    name: String,
+    email: String,
+    address_lines: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct InvoiceContext {
+    id: i64,
+    number: Option<String>,
+    status: String,
+    issued_at: Option<String>,
+    due_date: String,
+    subtotal: String,
+    tax_total: String,
+    total: String,
+    currency: String,
+}
+
+#[derive(Serialize)]
+struct LineContext {
+    description: String,
+    quantity: String,
+    unit_price: String,
+    discount: Option<String>,
+    tax_rate: String,
+    net_amount: String,
+    tax_amount: String,
+}
+
+#[derive(Serialize)]
+struct TaxContext {
+    rate: String,
+    net: String,
+    tax: String,
+}
+
+/// Non-empty lines of a multi-line setting or column
+fn address_lines(value: Option<String>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn business_profile(conn: &Connection) -> Result<BusinessProfile, rusqlite::Error> {
+    Ok(BusinessProfile {
+        name: text_setting(conn, "business_name")?.unwrap_or_default(),
+        address_lines: address_lines(read_setting(conn, "business_address")?),
+        vat_id: text_setting(conn, "business_vat_id")?,
+        email: text_setting(conn, "business_email")?,
+        phone: text_setting(conn, "business_phone")?,
+        bank_name: text_setting(conn, "bank_name")?,
+        iban: text_setting(conn, "bank_iban")?,
+        bic: text_setting(conn, "bank_bic")?,
+        logo_path: text_setting(conn, "business_logo_path")?,
+    })
+}
+
+/// Formats basis points as a percentage, e.g. `810` as `8.1%`
+pub fn format_rate(bp: i64) -> String {
+    format!("{}%", bp as f64 / 100.0)
+}
+
+/// Renders the template of an invoice into PDF markup
+fn render_markup(conn: &Connection, invoice_id: i64, business: &BusinessProfile) -> Result<String, InvoicePdfError> {
+    let (invoice, client) = conn
+        .query_row(
+            "SELECT i.number, i.status, i.issued_at, i.due_date, i.subtotal, i.tax_total, i.total,
+                    c.name, c.email, c.billing_address, i.currency
+             FROM invoices i LEFT JOIN clients c ON c.id = i.client_id
+             WHERE i.id = ?1",
+            [invoice_id],
+            |row| {
+                let currency: String = row.get(10)?;
+                let digits = currency_digits(&currency);
+                Ok((
+                    InvoiceContext {
+                        id: invoice_id,
+                        number: row.get(0)?,
+                        status: row.get(1)?,
+                        issued_at: row.get::<_, Option<String>>(2)?.as_deref().map(date_only),
+                        due_date: date_only(&row.get::<_, String>(3)?),
+                        subtotal: format_minor(row.get(4)?, digits),
+                        tax_total: format_minor(row.get(5)?, digits),
+                        total: format_minor(row.get(6)?, digits),
+                        currency,
+                    },
+                    ClientContext {
+                        name: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+                        email: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+                        address_lines: address_lines(row.get(9)?),
+                    },
+                ))
+            },
+        )
+        .optional()?
+        .ok_or(InvoicePdfError::InvoiceNotFound(invoice_id))?;
+    let digits = currency_digits(&invoice.currency);
+
+    let mut stmt = conn.prepare(
+        "SELECT description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount, tax_amount
+         FROM invoice_lines WHERE invoice_id = ?1 ORDER BY id",
+    )?;
+    let lines = stmt
+        .query_map([invoice_id], |row| {
+            let discount_bp: i64 = row.get(3)?;
+            Ok(LineContext {
+                description: row.get(0)?,
+                quantity: row.get::<_, f64>(1)?.to_string(),
+                unit_price: format_minor(row.get(2)?, digits),
+                discount: (discount_bp != 0).then(|| format_rate(discount_bp)),
+                tax_rate: format_rate(row.get(4)?),
+                net_amount: format_minor(row.get(5)?, digits),
+                tax_amount: format_minor(row.get(6)?, digits),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let taxes: Vec<TaxContext> = invoice_totals(conn, invoice_id)?
+        .taxes
+        .iter()
+        .map(|t| TaxContext { rate: format_rate(t.tax_rate_bp), net: format_minor(t.net, digits), tax: format_minor(t.tax, digits) })
+        .collect();
+
+    let template = read_setting(conn, "invoice_template")?
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_INVOICE_TEMPLATE.to_string());
+    let mut env = minijinja::Environment::new();
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.render_str(
+        &template,
+        minijinja::context! { business => business, client => client, invoice => invoice, lines => lines, taxes => taxes },
+    )
+    .map_err(|e| InvoicePdfError::TemplateError(e.to_string()))
+}
+
+/// Renders an invoice as a PDF document
+pub fn render_invoice(conn: &Connection, invoice_id: i64) -> Result<Vec<u8>, InvoicePdfError> {
+    let mut builder = PdfBuilder::new()?;
+    layout_invoice(conn, invoice_id, &mut builder)?;
+    Ok(builder.finish()?)
+}
+
+/// Lays an invoice out on `builder`, which may already be set up, e.g. as PDF/A
+pub fn layout_invoice(conn: &Connection, invoice_id: i64, builder: &mut PdfBuilder) -> Result<(), InvoicePdfError> {
+    let business = business_profile(conn)?;
+    let markup = render_markup(conn, invoice_id, &business)?;
+
+    let logo = match &business.logo_path {
+        Some(path) => {
+            let bytes = std::fs::read(path).map_err(|e| InvoicePdfError::LogoError(path.clone(), e.to_string()))?;
+            Some(builder.add_image(&bytes)?)
+        }
+        None => None,
+    };
+    let number: Option<String> =
+        conn.query_row("SELECT number FROM invoices WHERE id = ?1", [invoice_id], |row| row.get(0))?;
+    builder.set_title(&format!("Invoice {}", number.as_deref().unwrap_or("(draft)")));
+    let bottom = layout_markup(builder, &markup, logo);
+
+    if let Some(bill) = qrbill::for_invoice(conn, invoice_id)? {
+        if bottom > A4_HEIGHT - QR_BILL_HEIGHT {
+            builder.add_page();
+        }
+        bill.draw(builder, A4_HEIGHT - QR_BILL_HEIGHT)?;
+    }
+    Ok(())
+}
+
+/// 🖨️ Render an invoice to PDF and save it as an attachment of the invoice
+#[tauri::command]
+pub fn render_invoice_pdf(state: tauri::State<StateWrapper>, invoice_id: i64) -> Result<Attachment, InvoicePdfError> {
+    let db_conn = open_state_db(&state)?;
+    let bytes = render_invoice(&db_conn, invoice_id)?;
+
+    let number: Option<String> =
+        db_conn.query_row("SELECT number FROM invoices WHERE id = ?1", [invoice_id], |row| row.get(0))?;
+    let file_name = match number {
+        Some(number) => format!("{}.pdf", number),
+        None => format!("invoice-{}-draft.pdf", invoice_id),
+    };
+    let files = state_attachment_files(&state)?;
+    Ok(store(&db_conn, &files, "invoice", invoice_id, &file_name, "application/pdf", &bytes)?)
+}
+
+/// 📄 The built-in invoice template, as a starting point for `settings.invoice_template`
+#[tauri::command]
+pub fn get_default_invoice_template() -> String {
+    DEFAULT_INVOICE_TEMPLATE.to_string()
+}
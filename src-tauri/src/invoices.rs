@@ -0,0 +1,710 @@
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::currencies::{base_currency, CurrencyError};
+use crate::db_api::{db_now, open_state_db, parse_db_timestamp, DbApiError, Invoice, DB_TIMESTAMP_FORMAT};
+use crate::ledger::{post_invoice, LedgerError};
+use crate::members::current_member_id;
+use crate::money::{
+    currency_digits, document_totals, line_amounts, parse_minor, parse_percent_bp, DocumentTotals, LineAmounts, TaxRounding, FULL_RATE_BP,
+};
+use crate::numbering::{next_number, NumberingError};
+use crate::products::{line_defaults, ProductError};
+use crate::settings::read_setting;
+use crate::StateWrapper;
+
+/// Payment term used when `settings.payment_terms_days` is not set
+const DEFAULT_PAYMENT_TERMS_DAYS: i64 = 30;
+
+/// Numbering pattern used when `settings.invoice_number_pattern` is not set
+pub const DEFAULT_INVOICE_NUMBER_PATTERN: &str = "INV-{YYYY}-{seq:04}";
+
+#[derive(Debug, Error)]
+pub enum InvoiceError {
+    #[error("[invoices.rs::no_events] Select at least one event to invoice.")]
+    NoEvents,
+
+    #[error("[invoices.rs::event_not_found] Event {0} does not exist.")]
+    EventNotFound(i32),
+
+    #[error("[invoices.rs::client_mismatch] Event {0} does not belong to this client.")]
+    ClientMismatch(i32),
+
+    #[error("[invoices.rs::not_completed] Event {0} has not ended yet.")]
+    EventNotCompleted(i32),
+
+    #[error("[invoices.rs::already_billed] Event {0} is already billed on invoice {1}.")]
+    AlreadyBilled(i32, i64),
+
+    #[error("[invoices.rs::missing_rate] No hourly rate for this client and no `default_hourly_rate` setting.")]
+    MissingHourlyRate,
+
+    #[error("[invoices.rs::invoice_not_found] Invoice {0} does not exist.")]
+    InvoiceNotFound(i64),
+
+    #[error("[invoices.rs::line_not_found] Invoice line {0} does not exist.")]
+    LineNotFound(i64),
+
+    #[error("[invoices.rs::not_draft] Invoice {0} is {1} and can no longer be edited.")]
+    NotDraft(i64, String),
+
+    #[error("[invoices.rs::invalid_transition] Invoice {0} cannot go from {1} to {2}.")]
+    InvalidTransition(i64, String, String),
+
+    #[error("[invoices.rs::empty_invoice] Invoice {0} has no lines.")]
+    EmptyInvoice(i64),
+
+    #[error("[invoices.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[invoices.rs::numbering] {0}")]
+    NumberingError(#[from] NumberingError),
+
+    #[error("[invoices.rs::ledger] {0}")]
+    LedgerError(#[from] LedgerError),
+
+    #[error("[invoices.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[invoices.rs::products] {0}")]
+    ProductError(#[from] ProductError),
+
+    #[error("[invoices.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[invoices.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for InvoiceError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 🚦 Lifecycle state of an invoice, stored as text in `invoices.status`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum InvoiceStatus {
+    Draft,
+    Issued,
+    PartiallyPaid,
+    Paid,
+    Overdue,
+    Void,
+}
+
+impl InvoiceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Draft => "Draft",
+            InvoiceStatus::Issued => "Issued",
+            InvoiceStatus::PartiallyPaid => "PartiallyPaid",
+            InvoiceStatus::Paid => "Paid",
+            InvoiceStatus::Overdue => "Overdue",
+            InvoiceStatus::Void => "Void",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<InvoiceStatus> {
+        match value {
+            "Draft" => Some(InvoiceStatus::Draft),
+            "Issued" => Some(InvoiceStatus::Issued),
+            "PartiallyPaid" => Some(InvoiceStatus::PartiallyPaid),
+            "Paid" => Some(InvoiceStatus::Paid),
+            "Overdue" => Some(InvoiceStatus::Overdue),
+            "Void" => Some(InvoiceStatus::Void),
+            _ => None,
+        }
+    }
+
+    /// Allowed moves: drafts are only left by finalizing, void is final, and a paid
+    /// invoice can fall back to an open state when a payment is refunded or be voided
+    pub fn can_become(self, next: InvoiceStatus) -> bool {
+        use InvoiceStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Issued)
+                | (Issued, PartiallyPaid | Paid | Overdue | Void)
+                | (PartiallyPaid, Issued | Paid | Overdue | Void)
+                | (Overdue, Issued | PartiallyPaid | Paid | Void)
+                | (Paid, Issued | PartiallyPaid | Overdue | Void)
+        )
+    }
+}
+
+/// 📜 Status History Entry Struct
+#[derive(Serialize)]
+pub struct StatusChange {
+    pub id: i64,
+    pub invoice_id: i64,
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub member_id: Option<i64>,
+    /// Resolved from the member; empty for changes made by the background job
+    pub member_name: Option<String>,
+    pub note: Option<String>,
+    pub changed_at: String,
+}
+
+/// 🧾 Invoice Line Struct. Money is in minor units, rates in basis points (8.1 % = 810).
+#[derive(Serialize, Deserialize)]
+pub struct InvoiceLine {
+    pub id: Option<i32>,
+    pub invoice_id: i32,
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: i64,
+    #[serde(default)]
+    pub discount_bp: i64,
+    #[serde(default)]
+    pub tax_rate_bp: i64,
+    /// Computed when the invoice is saved
+    #[serde(default)]
+    pub net_amount: i64,
+    #[serde(default)]
+    pub tax_amount: i64,
+    pub event_id: Option<i32>,
+    /// Catalog product the line was created from
+    #[serde(default)]
+    pub product_id: Option<i64>,
+}
+
+/// Rounds a quantity to two decimals, e.g. hours worked
+fn round_quantity(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Hourly rate for a client in minor units of the base currency `currency`: its own rate,
+/// else `settings.default_hourly_rate`
+fn hourly_rate(conn: &Connection, client_id: i32, currency: &str) -> Result<i64, InvoiceError> {
+    let client_rate: Option<i64> = conn
+        .query_row("SELECT hourly_rate FROM clients WHERE id = ?1", [client_id], |row| row.get(0))
+        .optional()?
+        .flatten();
+    if let Some(rate) = client_rate {
+        return Ok(rate);
+    }
+
+    match read_setting(conn, "default_hourly_rate")? {
+        Some(value) => parse_minor(&value, currency_digits(currency)).ok_or(InvoiceError::InvalidValue("default_hourly_rate", value)),
+        None => Err(InvoiceError::MissingHourlyRate),
+    }
+}
+
+/// Tax rate for new lines from `settings.default_tax_rate` (a percentage), 0 when unset
+pub fn default_tax_rate_bp(conn: &Connection) -> Result<i64, InvoiceError> {
+    match read_setting(conn, "default_tax_rate")? {
+        Some(value) => parse_percent_bp(&value).ok_or(InvoiceError::InvalidValue("default_tax_rate", value)),
+        None => Ok(0),
+    }
+}
+
+pub fn tax_rounding_name(rounding: TaxRounding) -> &'static str {
+    match rounding {
+        TaxRounding::PerLine => "PerLine",
+        TaxRounding::PerRate => "PerRate",
+    }
+}
+
+fn validate_line(line: &InvoiceLine) -> Result<(), InvoiceError> {
+    if line.description.trim().is_empty() {
+        return Err(InvoiceError::InvalidValue("description", line.description.clone()));
+    }
+    if !line.quantity.is_finite() {
+        return Err(InvoiceError::InvalidValue("quantity", line.quantity.to_string()));
+    }
+    if !(0..=FULL_RATE_BP).contains(&line.discount_bp) {
+        return Err(InvoiceError::InvalidValue("discount_bp", line.discount_bp.to_string()));
+    }
+    if line.tax_rate_bp < 0 {
+        return Err(InvoiceError::InvalidValue("tax_rate_bp", line.tax_rate_bp.to_string()));
+    }
+    Ok(())
+}
+
+/// Fails unless the invoice exists and is still a draft
+pub fn ensure_draft(conn: &Connection, invoice_id: i64) -> Result<(), InvoiceError> {
+    let status: String = conn
+        .query_row("SELECT status FROM invoices WHERE id = ?1", [invoice_id], |row| row.get(0))
+        .optional()?
+        .ok_or(InvoiceError::InvoiceNotFound(invoice_id))?;
+    if status != "Draft" {
+        return Err(InvoiceError::NotDraft(invoice_id, status));
+    }
+    Ok(())
+}
+
+/// Recomputes every line and the totals of a draft invoice from quantities, prices and rates,
+/// using the `tax_rounding` setting (`per_rate` by default, or `per_line`)
+pub fn recompute_invoice_totals(conn: &Connection, invoice_id: i64) -> Result<DocumentTotals, InvoiceError> {
+    let rounding = TaxRounding::from_setting(read_setting(conn, "tax_rounding")?.as_deref());
+    let mut stmt = conn.prepare(
+        "SELECT id, quantity, unit_price, discount_bp, tax_rate_bp FROM invoice_lines WHERE invoice_id = ?1"
+    )?;
+    let lines = stmt
+        .query_map([invoice_id], |row| {
+            let amounts = line_amounts(row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?);
+            Ok((row.get::<_, i64>(0)?, amounts, row.get::<_, i64>(4)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (line_id, amounts, _) in &lines {
+        conn.execute(
+            "UPDATE invoice_lines SET net_amount = ?1, tax_amount = ?2 WHERE id = ?3",
+            params![amounts.net, amounts.tax, line_id],
+        )?;
+    }
+    let amounts: Vec<_> = lines.into_iter().map(|(_, amounts, rate)| (amounts, rate)).collect();
+    let totals = document_totals(&amounts, rounding);
+    conn.execute(
+        "UPDATE invoices SET subtotal = ?1, tax_total = ?2, total = ?3, tax_rounding = ?4 WHERE id = ?5",
+        params![totals.subtotal, totals.tax_total, totals.total, tax_rounding_name(rounding), invoice_id],
+    )?;
+    Ok(totals)
+}
+
+/// Totals of an invoice from its stored line amounts, with the rounding it was computed with
+pub fn invoice_totals(conn: &Connection, invoice_id: i64) -> Result<DocumentTotals, InvoiceError> {
+    let rounding: String = conn
+        .query_row("SELECT tax_rounding FROM invoices WHERE id = ?1", [invoice_id], |row| row.get(0))
+        .optional()?
+        .ok_or(InvoiceError::InvoiceNotFound(invoice_id))?;
+    let rounding = if rounding == "PerLine" { TaxRounding::PerLine } else { TaxRounding::PerRate };
+
+    let mut stmt = conn.prepare("SELECT net_amount, tax_amount, tax_rate_bp FROM invoice_lines WHERE invoice_id = ?1")?;
+    let lines = stmt
+        .query_map([invoice_id], |row| {
+            Ok((LineAmounts { net: row.get(0)?, tax: row.get(1)? }, row.get(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(document_totals(&lines, rounding))
+}
+
+pub fn payment_terms_days(conn: &Connection) -> Result<i64, InvoiceError> {
+    match read_setting(conn, "payment_terms_days")? {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| InvoiceError::InvalidValue("payment_terms_days", value)),
+        None => Ok(DEFAULT_PAYMENT_TERMS_DAYS),
+    }
+}
+
+struct BillableEvent {
+    id: i32,
+    title: String,
+    start_date: String,
+    end_date: String,
+    client_id: Option<i32>,
+    billed_invoice_id: Option<i64>,
+}
+
+/// Creates a draft invoice for completed events of one client, one line per event.
+/// Hours come from each event's duration and are priced at the client's hourly rate.
+pub fn create_invoice_from_events(conn: &mut Connection, client_id: i32, event_ids: &[i32]) -> Result<i64, InvoiceError> {
+    if event_ids.is_empty() {
+        return Err(InvoiceError::NoEvents);
+    }
+    let currency = base_currency(conn)?;
+    let rate = hourly_rate(conn, client_id, &currency)?;
+    let tax_rate_bp = default_tax_rate_bp(conn)?;
+    let now = Utc::now().naive_utc();
+    let due_date = (now + Duration::days(payment_terms_days(conn)?)).format(DB_TIMESTAMP_FORMAT).to_string();
+
+    let tx = conn.transaction()?;
+    let mut lines = Vec::with_capacity(event_ids.len());
+    for &event_id in event_ids {
+        let event = tx
+            .query_row(
+                "SELECT id, title, start_date, end_date, client_id, billed_invoice_id FROM events WHERE id = ?1",
+                [event_id],
+                |row| {
+                    Ok(BillableEvent {
+                        id: row.get(0)?,
+                        title: row.get(1)?,
+                        start_date: row.get(2)?,
+                        end_date: row.get(3)?,
+                        client_id: row.get(4)?,
+                        billed_invoice_id: row.get(5)?,
+                    })
+                },
+            )
+            .optional()?
+            .ok_or(InvoiceError::EventNotFound(event_id))?;
+
+        if event.client_id != Some(client_id) {
+            return Err(InvoiceError::ClientMismatch(event.id));
+        }
+        if let Some(invoice_id) = event.billed_invoice_id {
+            return Err(InvoiceError::AlreadyBilled(event.id, invoice_id));
+        }
+        let start = parse_db_timestamp(&event.start_date)
+            .ok_or_else(|| InvoiceError::InvalidValue("start_date", event.start_date.clone()))?;
+        let end = parse_db_timestamp(&event.end_date)
+            .ok_or_else(|| InvoiceError::InvalidValue("end_date", event.end_date.clone()))?;
+        if end > now {
+            return Err(InvoiceError::EventNotCompleted(event.id));
+        }
+
+        let hours = round_quantity((end - start).num_minutes().max(0) as f64 / 60.0);
+        let description = format!("{} ({}, {} h)", event.title, start.format("%Y-%m-%d"), hours);
+        lines.push((event.id, description, hours));
+    }
+
+    let single_event = if lines.len() == 1 { Some(lines[0].0) } else { None };
+    tx.execute(
+        "INSERT INTO invoices (client_id, due_date, status, event_id, currency) VALUES (?1, ?2, 'Draft', ?3, ?4)",
+        params![client_id, due_date, single_event, currency],
+    )?;
+    let invoice_id = tx.last_insert_rowid();
+
+    for (position, (event_id, description, hours)) in lines.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO invoice_lines (invoice_id, position, description, quantity, unit_price, tax_rate_bp, net_amount, event_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7)",
+            params![invoice_id, position as i64, description, hours, rate, tax_rate_bp, event_id],
+        )?;
+        mark_billed(&tx, *event_id, invoice_id)?;
+    }
+
+    recompute_invoice_totals(&tx, invoice_id)?;
+    tx.commit()?;
+    Ok(invoice_id)
+}
+
+/// Marks an event as billed on an invoice. The IS NULL guard keeps a concurrent run from
+/// billing the same event twice.
+pub fn mark_billed(conn: &Connection, event_id: i32, invoice_id: i64) -> Result<(), InvoiceError> {
+    let marked = conn.execute(
+        "UPDATE events SET billed_invoice_id = ?1 WHERE id = ?2 AND billed_invoice_id IS NULL",
+        params![invoice_id, event_id],
+    )?;
+    if marked == 0 {
+        let billed_on: Option<i64> = conn
+            .query_row("SELECT billed_invoice_id FROM events WHERE id = ?1", [event_id], |row| row.get(0))
+            .optional()?
+            .ok_or(InvoiceError::EventNotFound(event_id))?;
+        return Err(InvoiceError::AlreadyBilled(event_id, billed_on.unwrap_or(invoice_id)));
+    }
+    Ok(())
+}
+
+/// Moves an invoice to `next` if the lifecycle allows it and records the change.
+/// `member_id` is the user who made the change, `None` for the background job.
+pub fn transition(
+    conn: &Connection,
+    invoice_id: i64,
+    next: InvoiceStatus,
+    member_id: Option<i64>,
+    note: Option<&str>,
+) -> Result<(), InvoiceError> {
+    let current: String = conn
+        .query_row("SELECT status FROM invoices WHERE id = ?1", [invoice_id], |row| row.get(0))
+        .optional()?
+        .ok_or(InvoiceError::InvoiceNotFound(invoice_id))?;
+    let allowed = InvoiceStatus::parse(&current).is_some_and(|status| status == next || status.can_become(next));
+    if !allowed {
+        return Err(InvoiceError::InvalidTransition(invoice_id, current, next.as_str().to_string()));
+    }
+    if current == next.as_str() {
+        return Ok(());
+    }
+
+    conn.execute("UPDATE invoices SET status = ?1 WHERE id = ?2", params![next.as_str(), invoice_id])?;
+    conn.execute(
+        "INSERT INTO invoice_status_log (invoice_id, from_status, to_status, member_id, note, changed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![invoice_id, current, next.as_str(), member_id, note, db_now()],
+    )?;
+    Ok(())
+}
+
+/// Scheduler job: flips open invoices whose due date has passed to `Overdue`
+pub fn mark_overdue_invoices(conn: &mut Connection) -> Result<usize, InvoiceError> {
+    let tx = conn.transaction()?;
+    let overdue = {
+        let mut stmt = tx.prepare(
+            "SELECT id FROM invoices
+             WHERE status IN ('Issued', 'PartiallyPaid') AND datetime(due_date) < datetime(?1)"
+        )?;
+        let ids = stmt.query_map([db_now()], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>, _>>()?;
+        ids
+    };
+    for invoice_id in &overdue {
+        transition(&tx, *invoice_id, InvoiceStatus::Overdue, None, Some("Due date passed"))?;
+    }
+    tx.commit()?;
+    Ok(overdue.len())
+}
+
+/// Finalizes a draft: takes the next number from `settings.invoice_number_pattern` and dates the
+/// invoice today. The draft's due date stays; when it is unset or before today the payment
+/// term starts today instead. From here on the invoice can no longer change.
+pub fn finalize(conn: &mut Connection, invoice_id: i64, member_id: Option<i64>) -> Result<String, InvoiceError> {
+    let tx = conn.transaction()?;
+    let number = finalize_draft(&tx, invoice_id, member_id)?;
+    tx.commit()?;
+    Ok(number)
+}
+
+/// Same as `finalize`, inside a transaction the caller commits
+pub fn finalize_draft(conn: &Connection, invoice_id: i64, member_id: Option<i64>) -> Result<String, InvoiceError> {
+    let pattern = read_setting(conn, "invoice_number_pattern")?
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_INVOICE_NUMBER_PATTERN.to_string());
+    let now = Utc::now().naive_utc();
+
+    ensure_draft(conn, invoice_id)?;
+    let draft_due: Option<String> = conn.query_row("SELECT due_date FROM invoices WHERE id = ?1", [invoice_id], |row| row.get(0))?;
+    let due_date = match draft_due.as_deref().and_then(parse_db_timestamp).filter(|due| due.date() >= now.date()) {
+        Some(due) => due,
+        None => now + Duration::days(payment_terms_days(conn)?),
+    };
+    let line_count: i64 = conn.query_row("SELECT COUNT(*) FROM invoice_lines WHERE invoice_id = ?1", [invoice_id], |row| row.get(0))?;
+    if line_count == 0 {
+        return Err(InvoiceError::EmptyInvoice(invoice_id));
+    }
+    recompute_invoice_totals(conn, invoice_id)?;
+
+    let number = next_number(conn, "invoice", pattern.trim(), now.date())?;
+    conn.execute(
+        "UPDATE invoices SET number = ?1, issued_at = ?2, due_date = ?3 WHERE id = ?4",
+        params![number, now.format(DB_TIMESTAMP_FORMAT).to_string(), due_date.format(DB_TIMESTAMP_FORMAT).to_string(), invoice_id],
+    )?;
+    transition(conn, invoice_id, InvoiceStatus::Issued, member_id, None)?;
+    post_invoice(conn, invoice_id)?;
+    Ok(number)
+}
+
+/// 🔒 Finalize a draft invoice and return its number
+#[tauri::command]
+pub fn finalize_invoice(state: tauri::State<StateWrapper>, invoice_id: i64) -> Result<String, InvoiceError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    finalize(&mut db_conn, invoice_id, member_id)
+}
+
+/// 📜 Status history of an invoice, oldest first
+#[tauri::command]
+pub fn list_invoice_status_log(state: tauri::State<StateWrapper>, invoice_id: i64) -> Result<Vec<StatusChange>, InvoiceError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT l.id, l.invoice_id, l.from_status, l.to_status, l.member_id, m.name, l.note, l.changed_at
+         FROM invoice_status_log l
+         LEFT JOIN members m ON m.id = l.member_id
+         WHERE l.invoice_id = ?1
+         ORDER BY l.changed_at, l.id"
+    )?;
+
+    let changes = stmt
+        .query_map([invoice_id], |row| {
+            Ok(StatusChange {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                from_status: row.get(2)?,
+                to_status: row.get(3)?,
+                member_id: row.get(4)?,
+                member_name: row.get(5)?,
+                note: row.get(6)?,
+                changed_at: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(changes)
+}
+
+/// 🧮 Create a draft invoice from completed events of a client
+#[tauri::command]
+pub fn invoice_from_events(state: tauri::State<StateWrapper>, client_id: i32, event_ids: Vec<i32>) -> Result<i64, InvoiceError> {
+    let mut db_conn = open_state_db(&state)?;
+    create_invoice_from_events(&mut db_conn, client_id, &event_ids)
+}
+
+/// 📋 List invoices, optionally for a single client
+#[tauri::command]
+pub fn list_invoices(state: tauri::State<StateWrapper>, client_id: Option<i32>) -> Result<Vec<Invoice>, InvoiceError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, number, client_id, subtotal, tax_total, total, due_date, status, event_id, issued_at, quote_id, currency FROM invoices
+         WHERE ?1 IS NULL OR client_id = ?1
+         ORDER BY id DESC"
+    )?;
+
+    let invoices = stmt
+        .query_map([client_id], |row| {
+            Ok(Invoice {
+                id: row.get(0)?,
+                number: row.get(1)?,
+                client_id: row.get(2)?,
+                subtotal: row.get(3)?,
+                tax_total: row.get(4)?,
+                total: row.get(5)?,
+                due_date: row.get(6)?,
+                status: row.get(7)?,
+                event_id: row.get(8)?,
+                issued_at: row.get(9)?,
+                quote_id: row.get(10)?,
+                currency: row.get(11)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(invoices)
+}
+
+/// 📄 List the lines of an invoice
+#[tauri::command]
+pub fn list_invoice_lines(state: tauri::State<StateWrapper>, invoice_id: i32) -> Result<Vec<InvoiceLine>, InvoiceError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, invoice_id, description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount, tax_amount, event_id,
+                product_id
+         FROM invoice_lines WHERE invoice_id = ?1 ORDER BY position, id"
+    )?;
+
+    let lines = stmt
+        .query_map([invoice_id], |row| {
+            Ok(InvoiceLine {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                description: row.get(2)?,
+                quantity: row.get(3)?,
+                unit_price: row.get(4)?,
+                discount_bp: row.get(5)?,
+                tax_rate_bp: row.get(6)?,
+                net_amount: row.get(7)?,
+                tax_amount: row.get(8)?,
+                event_id: row.get(9)?,
+                product_id: row.get(10)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+/// ➕ Add a line to a draft invoice and return its id. A line for an event marks the event
+/// as billed on the invoice and fails when another invoice already bills it.
+#[tauri::command]
+pub fn add_invoice_line(state: tauri::State<StateWrapper>, line: InvoiceLine) -> Result<i64, InvoiceError> {
+    validate_line(&line)?;
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    ensure_draft(&tx, line.invoice_id as i64)?;
+
+    tx.execute(
+        "INSERT INTO invoice_lines (invoice_id, position, description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount, event_id, product_id)
+         VALUES (?1, (SELECT COALESCE(MAX(position) + 1, 0) FROM invoice_lines WHERE invoice_id = ?1), ?2, ?3, ?4, ?5, ?6, 0, ?7, ?8)",
+        params![
+            line.invoice_id,
+            line.description,
+            line.quantity,
+            line.unit_price,
+            line.discount_bp,
+            line.tax_rate_bp,
+            line.event_id,
+            line.product_id
+        ],
+    )?;
+    let line_id = tx.last_insert_rowid();
+    if let Some(event_id) = line.event_id {
+        mark_billed(&tx, event_id, line.invoice_id as i64)?;
+    }
+    recompute_invoice_totals(&tx, line.invoice_id as i64)?;
+    tx.commit()?;
+    Ok(line_id)
+}
+
+/// Adds a line for a catalog product to a draft invoice, priced for the invoice's client and
+/// in its currency, and returns the line id
+pub fn add_product_line(conn: &Connection, invoice_id: i64, product_id: i64, quantity: f64) -> Result<i64, InvoiceError> {
+    if !quantity.is_finite() {
+        return Err(InvoiceError::InvalidValue("quantity", quantity.to_string()));
+    }
+    ensure_draft(conn, invoice_id)?;
+    let (client_id, currency): (Option<i64>, String) =
+        conn.query_row("SELECT client_id, currency FROM invoices WHERE id = ?1", [invoice_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    let defaults = line_defaults(conn, product_id, client_id, &currency)?;
+    conn.execute(
+        "INSERT INTO invoice_lines (invoice_id, position, description, quantity, unit_price, tax_rate_bp, net_amount, product_id)
+         VALUES (?1, (SELECT COALESCE(MAX(position) + 1, 0) FROM invoice_lines WHERE invoice_id = ?1), ?2, ?3, ?4, ?5, 0, ?6)",
+        params![invoice_id, defaults.description, quantity, defaults.unit_price, defaults.tax_rate_bp, product_id],
+    )?;
+    let line_id = conn.last_insert_rowid();
+    recompute_invoice_totals(conn, invoice_id)?;
+    Ok(line_id)
+}
+
+/// 📦 Add a catalog product to a draft invoice and return the line id
+#[tauri::command]
+pub fn add_invoice_product_line(
+    state: tauri::State<StateWrapper>,
+    invoice_id: i64,
+    product_id: i64,
+    quantity: f64,
+) -> Result<i64, InvoiceError> {
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    let line_id = add_product_line(&tx, invoice_id, product_id, quantity)?;
+    tx.commit()?;
+    Ok(line_id)
+}
+
+/// ✏️ Update a line of a draft invoice
+#[tauri::command]
+pub fn update_invoice_line(state: tauri::State<StateWrapper>, line: InvoiceLine) -> Result<(), InvoiceError> {
+    validate_line(&line)?;
+    let line_id = line.id.ok_or(InvoiceError::InvalidValue("id", "missing".to_string()))? as i64;
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    ensure_draft(&tx, line.invoice_id as i64)?;
+
+    let updated = tx.execute(
+        "UPDATE invoice_lines SET description = ?1, quantity = ?2, unit_price = ?3, discount_bp = ?4, tax_rate_bp = ?5
+         WHERE id = ?6 AND invoice_id = ?7",
+        params![line.description, line.quantity, line.unit_price, line.discount_bp, line.tax_rate_bp, line_id, line.invoice_id],
+    )?;
+    if updated == 0 {
+        return Err(InvoiceError::LineNotFound(line_id));
+    }
+    recompute_invoice_totals(&tx, line.invoice_id as i64)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 🗑️ Remove a line from a draft invoice. A billed event on that line becomes billable again.
+#[tauri::command]
+pub fn delete_invoice_line(state: tauri::State<StateWrapper>, line_id: i64) -> Result<(), InvoiceError> {
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    let (invoice_id, event_id): (i64, Option<i64>) = tx
+        .query_row("SELECT invoice_id, event_id FROM invoice_lines WHERE id = ?1", [line_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()?
+        .ok_or(InvoiceError::LineNotFound(line_id))?;
+    ensure_draft(&tx, invoice_id)?;
+
+    tx.execute("DELETE FROM invoice_lines WHERE id = ?1", [line_id])?;
+    if let Some(event_id) = event_id {
+        tx.execute(
+            "UPDATE events SET billed_invoice_id = NULL WHERE id = ?1 AND billed_invoice_id = ?2",
+            params![event_id, invoice_id],
+        )?;
+    }
+    recompute_invoice_totals(&tx, invoice_id)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 🧮 Subtotal, tax per rate and total of an invoice, in minor units
+#[tauri::command]
+pub fn get_invoice_totals(state: tauri::State<StateWrapper>, invoice_id: i64) -> Result<DocumentTotals, InvoiceError> {
+    let db_conn = open_state_db(&state)?;
+    invoice_totals(&db_conn, invoice_id)
+}
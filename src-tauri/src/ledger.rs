@@ -0,0 +1,770 @@
+//! Double-entry bookkeeping.
+//!
+//! The journal is never written by hand: issuing an invoice or credit note, recording a
+//! payment and booking or paying an expense each post an entry derived from that record
+//! through `post`. Amounts are in minor units of the base currency, converted at the date of
+//! the record; payments clear receivables at the rate of their invoice and book the rest as
+//! exchange difference. Every entry balances and journal rows are immutable: when a record
+//! changes or disappears its entry is reversed on the day of the change and, if still
+//! needed, posted anew. A record whose exchange rate is missing is saved unposted and
+//! caught up by `sync`.
+//!
+//! Accounts with a `system_key` are the ones postings go to; expenses go to the account of
+//! their category, else to the `expenses` account.
+
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::currencies::{base_currency, convert, parse_date, CurrencyError};
+use crate::db_api::{open_state_db, DbApiError};
+use crate::StateWrapper;
+
+#[derive(Debug, Error)]
+pub enum LedgerError {
+    #[error("[ledger.rs::account_not_found] Account {0} does not exist.")]
+    AccountNotFound(i64),
+
+    #[error("[ledger.rs::missing_account] No account is set up for {0:?} postings.")]
+    MissingAccount(String),
+
+    #[error("[ledger.rs::duplicate_code] An account with code {0:?} already exists.")]
+    DuplicateCode(String),
+
+    #[error("[ledger.rs::system_account] Account {0} receives automatic postings; it must stay active and keep its kind.")]
+    SystemAccount(String),
+
+    #[error("[ledger.rs::unbalanced] Journal entry does not balance: debit {0}, credit {1}.")]
+    Unbalanced(i64, i64),
+
+    #[error("[ledger.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[ledger.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[ledger.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[ledger.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for LedgerError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// Record a journal entry was posted from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Invoice,
+    CreditNote,
+    Payment,
+    Expense,
+    /// The bank payment of an expense, once `expenses.paid_at` is set
+    ExpensePayment,
+}
+
+impl Source {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Invoice => "Invoice",
+            Source::CreditNote => "CreditNote",
+            Source::Payment => "Payment",
+            Source::Expense => "Expense",
+            Source::ExpensePayment => "ExpensePayment",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Source> {
+        match value {
+            "Invoice" => Some(Source::Invoice),
+            "CreditNote" => Some(Source::CreditNote),
+            "Payment" => Some(Source::Payment),
+            "Expense" => Some(Source::Expense),
+            "ExpensePayment" => Some(Source::ExpensePayment),
+            _ => None,
+        }
+    }
+}
+
+const ACCOUNT_KINDS: [&str; 5] = ["Asset", "Liability", "Equity", "Revenue", "Expense"];
+
+/// 📒 Account Struct: one account of the chart of accounts
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Account {
+    pub id: Option<i64>,
+    pub code: String,
+    pub name: String,
+    /// `Asset`, `Liability`, `Equity`, `Revenue` or `Expense`
+    pub kind: String,
+    /// Set on the accounts automatic postings go to, e.g. `receivables`; read-only
+    #[serde(default)]
+    pub system_key: Option<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// 🧮 Journal Line Struct, in minor units of the entry currency
+#[derive(Serialize, Debug)]
+pub struct JournalLine {
+    pub account_id: i64,
+    pub account_code: String,
+    pub account_name: String,
+    pub debit: i64,
+    pub credit: i64,
+}
+
+/// 📓 Journal Entry Struct
+#[derive(Serialize, Debug)]
+pub struct JournalEntry {
+    pub id: i64,
+    pub date: String,
+    pub description: String,
+    /// `Invoice`, `CreditNote`, `Payment`, `Expense` or `ExpensePayment`
+    pub source_type: String,
+    pub source_id: i64,
+    /// Base currency the amounts are in
+    pub currency: String,
+    /// The entry this one reverses
+    pub reverses_id: Option<i64>,
+    pub lines: Vec<JournalLine>,
+}
+
+/// 🔎 Filters for `list_journal_entries`; dates are inclusive
+#[derive(Deserialize, Default, Debug)]
+pub struct JournalFilter {
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub account_id: Option<i64>,
+}
+
+/// ⚖️ Trial Balance Row: totals of one account; `balance` is debit less credit
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct TrialBalanceRow {
+    pub account_id: i64,
+    pub code: String,
+    pub name: String,
+    pub kind: String,
+    pub debit: i64,
+    pub credit: i64,
+    pub balance: i64,
+}
+
+/// ⚖️ Trial Balance Struct
+#[derive(Serialize, Debug)]
+pub struct TrialBalance {
+    pub as_of: String,
+    pub currency: String,
+    pub rows: Vec<TrialBalanceRow>,
+    pub total_debit: i64,
+    pub total_credit: i64,
+}
+
+/// Entry to post, with debits positive and credits negative
+struct Posting {
+    date: NaiveDate,
+    description: String,
+    lines: Vec<(i64, i64)>,
+}
+
+impl Posting {
+    fn new(date: NaiveDate, description: String) -> Posting {
+        Posting { date, description, lines: Vec::new() }
+    }
+
+    fn add(&mut self, account_id: i64, amount: i64) {
+        self.lines.push((account_id, amount));
+    }
+
+    /// One line per account in account order, without zero lines
+    fn normalized(&self) -> Vec<(i64, i64)> {
+        let mut lines: Vec<(i64, i64)> = Vec::new();
+        for &(account_id, amount) in &self.lines {
+            match lines.iter_mut().find(|(id, _)| *id == account_id) {
+                Some(line) => line.1 += amount,
+                None => lines.push((account_id, amount)),
+            }
+        }
+        lines.retain(|(_, amount)| *amount != 0);
+        lines.sort_unstable();
+        lines
+    }
+}
+
+/// The latest entry of a record that has not been reversed
+struct PostedEntry {
+    id: i64,
+    date: String,
+    description: String,
+    currency: String,
+    lines: Vec<(i64, i64)>,
+}
+
+/// SQL condition on `journal_entries e`: neither a reversal nor reversed
+const ACTIVE_ENTRY_SQL: &str =
+    "e.reverses_id IS NULL AND NOT EXISTS (SELECT 1 FROM journal_entries r WHERE r.reverses_id = e.id)";
+
+fn system_account(conn: &Connection, key: &str) -> Result<i64, LedgerError> {
+    conn.query_row("SELECT id FROM accounts WHERE system_key = ?1", [key], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| LedgerError::MissingAccount(key.to_string()))
+}
+
+fn record_date(value: &str) -> Result<NaiveDate, LedgerError> {
+    parse_date(value).ok_or_else(|| LedgerError::InvalidValue("date", value.to_string()))
+}
+
+fn invoice_posting(
+    conn: &Connection,
+    description: String,
+    date: NaiveDate,
+    (total, tax_total, currency, rate_date): (i64, i64, &str, NaiveDate),
+) -> Result<Posting, LedgerError> {
+    let base = base_currency(conn)?;
+    let total = convert(conn, total, currency, &base, rate_date)?;
+    let tax = convert(conn, tax_total, currency, &base, rate_date)?;
+    let mut posting = Posting::new(date, description);
+    posting.add(system_account(conn, "receivables")?, total);
+    posting.add(system_account(conn, "output_vat")?, -tax);
+    posting.add(system_account(conn, "revenue")?, tax - total);
+    Ok(posting)
+}
+
+/// The entry a record calls for right now, `None` if it calls for none
+fn expected(conn: &Connection, source: Source, source_id: i64) -> Result<Option<Posting>, LedgerError> {
+    let base = base_currency(conn)?;
+    match source {
+        Source::Invoice => {
+            type InvoiceRow = (Option<String>, Option<String>, String, String, i64, i64);
+            let invoice: Option<InvoiceRow> = conn
+                .query_row(
+                    "SELECT number, issued_at, status, currency, total, tax_total FROM invoices WHERE id = ?1",
+                    [source_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+                )
+                .optional()?;
+            let Some((number, Some(issued_at), status, currency, total, tax_total)) = invoice else {
+                return Ok(None);
+            };
+            if status == "Draft" {
+                return Ok(None);
+            }
+            let date = record_date(&issued_at)?;
+            let description = format!("Invoice {}", number.unwrap_or_default());
+            Ok(Some(invoice_posting(conn, description, date, (total, tax_total, &currency, date))?))
+        }
+        Source::CreditNote => {
+            let note: Option<(String, String, Option<String>, String, i64, i64)> = conn
+                .query_row(
+                    "SELECT n.number, n.issued_at, i.issued_at, i.currency, n.total, n.tax_total
+                     FROM credit_notes n JOIN invoices i ON i.id = n.invoice_id WHERE n.id = ?1",
+                    [source_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+                )
+                .optional()?;
+            let Some((number, issued_at, invoice_issued_at, currency, total, tax_total)) = note else {
+                return Ok(None);
+            };
+            let date = record_date(&issued_at)?;
+            // Taken back at the rate the invoice was booked at
+            let rate_date = invoice_issued_at.as_deref().and_then(parse_date).unwrap_or(date);
+            let description = format!("Credit note {}", number);
+            Ok(Some(invoice_posting(conn, description, date, (total, tax_total, &currency, rate_date))?))
+        }
+        Source::Payment => {
+            type PaymentRow = (String, i64, String, Option<String>, Option<i64>, Option<String>, Option<String>, Option<String>);
+            let payment: Option<PaymentRow> = conn
+                .query_row(
+                    "SELECT p.kind, p.amount, p.date, p.currency, p.invoice_id, i.number, i.issued_at, c.name
+                     FROM payments p
+                     LEFT JOIN invoices i ON i.id = p.invoice_id
+                     LEFT JOIN clients c ON c.id = p.client_id
+                     WHERE p.id = ?1",
+                    [source_id],
+                    |row| {
+                        Ok((
+                            row.get(0)?,
+                            row.get(1)?,
+                            row.get(2)?,
+                            row.get(3)?,
+                            row.get(4)?,
+                            row.get(5)?,
+                            row.get(6)?,
+                            row.get(7)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((kind, amount, date, currency, invoice_id, number, invoice_issued_at, client)) = payment else {
+                return Ok(None);
+            };
+            // Credit moves come in pairs; the half on the invoice carries the posting
+            if kind == "Credit" && invoice_id.is_none() {
+                return Ok(None);
+            }
+            let date = record_date(&date)?;
+            let currency = currency.unwrap_or_else(|| base.clone());
+            let counter_date = invoice_issued_at.as_deref().and_then(parse_date).unwrap_or(date);
+            let counter_amount = convert(conn, amount, &currency, &base, counter_date)?;
+
+            let description = match (&number, &client) {
+                (Some(number), _) => format!("{} on invoice {}", kind, number),
+                (None, Some(client)) => format!("{} of client {}", kind, client),
+                (None, None) => kind.clone(),
+            };
+            let mut posting = Posting::new(date, description);
+            if kind == "Credit" {
+                posting.add(system_account(conn, "customer_credit")?, counter_amount);
+                posting.add(system_account(conn, "receivables")?, -counter_amount);
+            } else {
+                let bank_amount = convert(conn, amount, &currency, &base, date)?;
+                let counter = if invoice_id.is_some() { "receivables" } else { "customer_credit" };
+                posting.add(system_account(conn, "bank")?, bank_amount);
+                posting.add(system_account(conn, counter)?, -counter_amount);
+                posting.add(system_account(conn, "exchange_differences")?, counter_amount - bank_amount);
+            }
+            Ok(Some(posting))
+        }
+        Source::Expense | Source::ExpensePayment => {
+            type ExpenseRow = (String, Option<String>, String, Option<String>, i64, i64, String, Option<i64>);
+            let expense: Option<ExpenseRow> = conn
+                .query_row(
+                    "SELECT e.date, e.paid_at, e.description, e.vendor, e.amount, e.tax_amount, e.currency, c.account_id
+                     FROM expenses e LEFT JOIN expense_categories c ON c.id = e.category_id
+                     WHERE e.id = ?1",
+                    [source_id],
+                    |row| {
+                        Ok((
+                            row.get(0)?,
+                            row.get(1)?,
+                            row.get(2)?,
+                            row.get(3)?,
+                            row.get(4)?,
+                            row.get(5)?,
+                            row.get(6)?,
+                            row.get(7)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((date, paid_at, description, vendor, amount, tax_amount, currency, account_id)) = expense else {
+                return Ok(None);
+            };
+            let label = Some(description.trim()).filter(|d| !d.is_empty()).or(vendor.as_deref()).unwrap_or_default();
+            let date = record_date(&date)?;
+            let gross = convert(conn, amount, &currency, &base, date)?;
+
+            if source == Source::Expense {
+                let tax = convert(conn, tax_amount, &currency, &base, date)?;
+                let account_id = match account_id {
+                    Some(id) => id,
+                    None => system_account(conn, "expenses")?,
+                };
+                let mut posting = Posting::new(date, format!("Expense {}", label).trim_end().to_string());
+                posting.add(account_id, gross - tax);
+                posting.add(system_account(conn, "input_vat")?, tax);
+                posting.add(system_account(conn, "payables")?, -gross);
+                return Ok(Some(posting));
+            }
+
+            let Some(paid_at) = paid_at else {
+                return Ok(None);
+            };
+            let paid_on = record_date(&paid_at)?;
+            let bank_amount = convert(conn, amount, &currency, &base, paid_on)?;
+            let mut posting = Posting::new(paid_on, format!("Payment of expense {}", label).trim_end().to_string());
+            posting.add(system_account(conn, "payables")?, gross);
+            posting.add(system_account(conn, "bank")?, -bank_amount);
+            posting.add(system_account(conn, "exchange_differences")?, bank_amount - gross);
+            Ok(Some(posting))
+        }
+    }
+}
+
+fn posted_entry(conn: &Connection, source: Source, source_id: i64) -> Result<Option<PostedEntry>, LedgerError> {
+    let entry: Option<(i64, String, String, String)> = conn
+        .query_row(
+            &format!(
+                "SELECT e.id, e.date, e.description, e.currency FROM journal_entries e
+                 WHERE e.source_type = ?1 AND e.source_id = ?2 AND {}
+                 ORDER BY e.id DESC LIMIT 1",
+                ACTIVE_ENTRY_SQL
+            ),
+            params![source.as_str(), source_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?;
+    let Some((id, date, description, currency)) = entry else {
+        return Ok(None);
+    };
+    let mut stmt = conn.prepare("SELECT account_id, debit - credit FROM journal_lines WHERE entry_id = ?1 ORDER BY account_id")?;
+    let lines = stmt.query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(PostedEntry { id, date, description, currency, lines }))
+}
+
+fn insert_entry(
+    conn: &Connection,
+    (source, source_id): (Source, i64),
+    date: &str,
+    description: &str,
+    currency: &str,
+    reverses_id: Option<i64>,
+    lines: &[(i64, i64)],
+) -> Result<i64, LedgerError> {
+    let debit: i64 = lines.iter().map(|&(_, amount)| amount.max(0)).sum();
+    let credit: i64 = lines.iter().map(|&(_, amount)| (-amount).max(0)).sum();
+    if debit != credit || lines.is_empty() {
+        return Err(LedgerError::Unbalanced(debit, credit));
+    }
+    conn.execute(
+        "INSERT INTO journal_entries (date, description, source_type, source_id, currency, reverses_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![date, description, source.as_str(), source_id, currency, reverses_id],
+    )?;
+    let entry_id = conn.last_insert_rowid();
+    for &(account_id, amount) in lines {
+        conn.execute(
+            "INSERT INTO journal_lines (entry_id, account_id, debit, credit) VALUES (?1, ?2, ?3, ?4)",
+            params![entry_id, account_id, amount.max(0), (-amount).max(0)],
+        )?;
+    }
+    Ok(entry_id)
+}
+
+/// Brings the journal in line with a record: posts its entry if it has none, reverses and
+/// reposts it if the record changed, reverses it if the record is gone. Returns the number
+/// of entries written.
+pub fn post(conn: &Connection, source: Source, source_id: i64) -> Result<usize, LedgerError> {
+    let expected = expected(conn, source, source_id)?
+        .map(|posting| (posting.date.to_string(), posting.description.clone(), posting.normalized()))
+        .filter(|(_, _, lines)| !lines.is_empty());
+    let posted = posted_entry(conn, source, source_id)?;
+    if let (Some(entry), Some((date, _, lines))) = (&posted, &expected) {
+        if entry.date == *date && entry.lines == *lines {
+            return Ok(0);
+        }
+    }
+
+    let mut written = 0;
+    if let Some(entry) = posted {
+        reverse(conn, (source, source_id), &entry)?;
+        written += 1;
+    }
+    if let Some((date, description, lines)) = expected {
+        insert_entry(conn, (source, source_id), &date, &description, &base_currency(conn)?, None, &lines)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Reverses an entry on the day of the change, so periods already reported or exported stay
+/// as they were; an entry dated in the future is reversed on its own date
+fn reverse(conn: &Connection, record: (Source, i64), entry: &PostedEntry) -> Result<i64, LedgerError> {
+    let reversed: Vec<_> = entry.lines.iter().map(|&(account_id, amount)| (account_id, -amount)).collect();
+    let description = format!("Reversal: {}", entry.description);
+    let date = Utc::now().date_naive().to_string().max(entry.date.clone());
+    insert_entry(conn, record, &date, &description, &entry.currency, Some(entry.id), &reversed)
+}
+
+/// `post` for the paths that change a record: when its amounts can't be converted because
+/// the exchange rate is missing, the record goes unposted and `sync` posts it once the rate
+/// exists, instead of the change failing. An entry it had before is reversed meanwhile.
+pub fn post_or_defer(conn: &Connection, source: Source, source_id: i64) -> Result<usize, LedgerError> {
+    match post(conn, source, source_id) {
+        Err(LedgerError::CurrencyError(err @ CurrencyError::MissingRate(..))) => {
+            println!("[ledger.rs::post_or_defer] {} {} is left to the ledger sync: {}", source.as_str(), source_id, err);
+            match posted_entry(conn, source, source_id)? {
+                Some(entry) => {
+                    reverse(conn, (source, source_id), &entry)?;
+                    Ok(1)
+                }
+                None => Ok(0),
+            }
+        }
+        result => result,
+    }
+}
+
+/// Posts an invoice together with its credit notes and payments; see `post_or_defer`
+pub fn post_invoice(conn: &Connection, invoice_id: i64) -> Result<usize, LedgerError> {
+    let mut written = post_or_defer(conn, Source::Invoice, invoice_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT 'CreditNote', id FROM credit_notes WHERE invoice_id = ?1
+         UNION ALL
+         SELECT 'Payment', id FROM payments WHERE invoice_id = ?1",
+    )?;
+    let records = stmt.query_map([invoice_id], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+    for (source, source_id) in records {
+        let source = if source == "Payment" { Source::Payment } else { Source::CreditNote };
+        written += post_or_defer(conn, source, source_id)?;
+    }
+    Ok(written)
+}
+
+/// Posts an expense and its payment; see `post_or_defer`
+pub fn post_expense(conn: &Connection, expense_id: i64) -> Result<usize, LedgerError> {
+    Ok(post_or_defer(conn, Source::Expense, expense_id)? + post_or_defer(conn, Source::ExpensePayment, expense_id)?)
+}
+
+/// Scheduler job: posts every record that has no entry yet and reverses the entries of deleted
+/// records, e.g. for data from before the ledger existed or records waiting for an exchange
+/// rate. Each record is posted in its own transaction; one that fails is logged and retried on
+/// the next run without holding up the others. Returns the number of entries written.
+pub fn sync(conn: &mut Connection) -> Result<usize, LedgerError> {
+    let pending = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT 'Invoice', i.id FROM invoices i
+             WHERE i.status <> 'Draft' AND i.issued_at IS NOT NULL AND i.total <> 0
+               AND NOT EXISTS (SELECT 1 FROM journal_entries e WHERE e.source_type = 'Invoice' AND e.source_id = i.id AND {active})
+             UNION ALL
+             SELECT 'CreditNote', n.id FROM credit_notes n
+             WHERE n.total <> 0
+               AND NOT EXISTS (SELECT 1 FROM journal_entries e
+                               WHERE e.source_type = 'CreditNote' AND e.source_id = n.id AND {active})
+             UNION ALL
+             SELECT 'Payment', p.id FROM payments p
+             WHERE p.amount <> 0 AND NOT (p.kind = 'Credit' AND p.invoice_id IS NULL)
+               AND NOT EXISTS (SELECT 1 FROM journal_entries e WHERE e.source_type = 'Payment' AND e.source_id = p.id AND {active})
+             UNION ALL
+             SELECT 'Expense', x.id FROM expenses x
+             WHERE x.amount <> 0
+               AND NOT EXISTS (SELECT 1 FROM journal_entries e WHERE e.source_type = 'Expense' AND e.source_id = x.id AND {active})
+             UNION ALL
+             SELECT 'ExpensePayment', x.id FROM expenses x
+             WHERE x.paid_at IS NOT NULL AND x.amount <> 0
+               AND NOT EXISTS (SELECT 1 FROM journal_entries e
+                               WHERE e.source_type = 'ExpensePayment' AND e.source_id = x.id AND {active})
+             UNION ALL
+             SELECT e.source_type, e.source_id FROM journal_entries e
+             WHERE {active} AND (
+                 (e.source_type = 'Payment' AND NOT EXISTS (SELECT 1 FROM payments p WHERE p.id = e.source_id))
+                 OR (e.source_type = 'Expense' AND NOT EXISTS (SELECT 1 FROM expenses x WHERE x.id = e.source_id))
+                 OR (e.source_type = 'ExpensePayment'
+                     AND NOT EXISTS (SELECT 1 FROM expenses x WHERE x.id = e.source_id AND x.paid_at IS NOT NULL)))",
+            active = ACTIVE_ENTRY_SQL
+        ))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    let mut written = 0;
+    for (source, source_id) in pending {
+        let Some(source) = Source::parse(&source) else { continue };
+        let tx = conn.transaction()?;
+        match post(&tx, source, source_id) {
+            Ok(count) => {
+                tx.commit()?;
+                written += count;
+            }
+            Err(err) => println!("[ledger.rs::sync] {} {} could not be posted: {}", source.as_str(), source_id, err),
+        }
+    }
+    Ok(written)
+}
+
+pub fn list_accounts(conn: &Connection) -> Result<Vec<Account>, LedgerError> {
+    let mut stmt = conn.prepare("SELECT id, code, name, kind, system_key, active FROM accounts ORDER BY code")?;
+    let accounts = stmt
+        .query_map([], |row| {
+            Ok(Account {
+                id: row.get(0)?,
+                code: row.get(1)?,
+                name: row.get(2)?,
+                kind: row.get(3)?,
+                system_key: row.get(4)?,
+                active: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(accounts)
+}
+
+fn validate_account(conn: &Connection, account: &Account) -> Result<(String, String), LedgerError> {
+    let code = account.code.trim();
+    let name = account.name.trim();
+    if code.is_empty() {
+        return Err(LedgerError::InvalidValue("code", account.code.clone()));
+    }
+    if name.is_empty() {
+        return Err(LedgerError::InvalidValue("name", account.name.clone()));
+    }
+    if !ACCOUNT_KINDS.contains(&account.kind.as_str()) {
+        return Err(LedgerError::InvalidValue("kind", account.kind.clone()));
+    }
+    let taken: Option<i64> = conn.query_row("SELECT id FROM accounts WHERE code = ?1", [code], |row| row.get(0)).optional()?;
+    if taken.is_some_and(|id| Some(id) != account.id) {
+        return Err(LedgerError::DuplicateCode(code.to_string()));
+    }
+    Ok((code.to_string(), name.to_string()))
+}
+
+pub fn create_account(conn: &Connection, account: &Account) -> Result<i64, LedgerError> {
+    let (code, name) = validate_account(conn, account)?;
+    conn.execute(
+        "INSERT INTO accounts (code, name, kind, active) VALUES (?1, ?2, ?3, ?4)",
+        params![code, name, account.kind, account.active],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Updates code, name, kind and the active flag. Accounts that receive automatic postings
+/// can be renamed and renumbered but not deactivated or given another kind.
+pub fn update_account(conn: &Connection, account: &Account) -> Result<(), LedgerError> {
+    let account_id = account.id.ok_or(LedgerError::InvalidValue("id", "missing".to_string()))?;
+    let (system_key, kind): (Option<String>, String) = conn
+        .query_row("SELECT system_key, kind FROM accounts WHERE id = ?1", [account_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?
+        .ok_or(LedgerError::AccountNotFound(account_id))?;
+    let (code, name) = validate_account(conn, account)?;
+    if system_key.is_some() && (!account.active || account.kind != kind) {
+        return Err(LedgerError::SystemAccount(code));
+    }
+    conn.execute(
+        "UPDATE accounts SET code = ?1, name = ?2, kind = ?3, active = ?4 WHERE id = ?5",
+        params![code, name, account.kind, account.active, account_id],
+    )?;
+    Ok(())
+}
+
+pub fn list_entries(conn: &Connection, filter: &JournalFilter) -> Result<Vec<JournalEntry>, LedgerError> {
+    let from = filter.from.as_deref().map(record_date).transpose()?.map(|d| d.to_string());
+    let to = filter.to.as_deref().map(record_date).transpose()?.map(|d| d.to_string());
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.date, e.description, e.source_type, e.source_id, e.currency, e.reverses_id
+         FROM journal_entries e
+         WHERE (?1 IS NULL OR e.date >= ?1) AND (?2 IS NULL OR e.date <= ?2)
+           AND (?3 IS NULL OR EXISTS (SELECT 1 FROM journal_lines l WHERE l.entry_id = e.id AND l.account_id = ?3))
+         ORDER BY e.date, e.id",
+    )?;
+    let mut entries = stmt
+        .query_map(params![from, to, filter.account_id], |row| {
+            Ok(JournalEntry {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                description: row.get(2)?,
+                source_type: row.get(3)?,
+                source_id: row.get(4)?,
+                currency: row.get(5)?,
+                reverses_id: row.get(6)?,
+                lines: Vec::new(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut lines = conn.prepare(
+        "SELECT l.account_id, a.code, a.name, l.debit, l.credit
+         FROM journal_lines l JOIN accounts a ON a.id = l.account_id
+         WHERE l.entry_id = ?1 ORDER BY l.id",
+    )?;
+    for entry in &mut entries {
+        entry.lines = lines
+            .query_map([entry.id], |row| {
+                Ok(JournalLine {
+                    account_id: row.get(0)?,
+                    account_code: row.get(1)?,
+                    account_name: row.get(2)?,
+                    debit: row.get(3)?,
+                    credit: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+    }
+    Ok(entries)
+}
+
+/// Debit and credit totals per account of all entries up to and including `as_of`
+pub fn trial_balance(conn: &Connection, as_of: NaiveDate) -> Result<TrialBalance, LedgerError> {
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.code, a.name, a.kind, SUM(l.debit), SUM(l.credit)
+         FROM journal_lines l
+         JOIN journal_entries e ON e.id = l.entry_id
+         JOIN accounts a ON a.id = l.account_id
+         WHERE e.date <= ?1
+         GROUP BY a.id
+         ORDER BY a.code",
+    )?;
+    let rows = stmt
+        .query_map([as_of.to_string()], |row| {
+            let (debit, credit): (i64, i64) = (row.get(4)?, row.get(5)?);
+            Ok(TrialBalanceRow {
+                account_id: row.get(0)?,
+                code: row.get(1)?,
+                name: row.get(2)?,
+                kind: row.get(3)?,
+                debit,
+                credit,
+                balance: debit - credit,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(TrialBalance {
+        as_of: as_of.to_string(),
+        currency: base_currency(conn)?,
+        total_debit: rows.iter().map(|r| r.debit).sum(),
+        total_credit: rows.iter().map(|r| r.credit).sum(),
+        rows,
+    })
+}
+
+/// 📒 List the chart of accounts
+#[tauri::command]
+pub fn list_ledger_accounts(state: tauri::State<StateWrapper>) -> Result<Vec<Account>, LedgerError> {
+    let db_conn = open_state_db(&state)?;
+    list_accounts(&db_conn)
+}
+
+/// ➕ Add an account to the chart of accounts, returns its id
+#[tauri::command]
+pub fn create_ledger_account(state: tauri::State<StateWrapper>, account: Account) -> Result<i64, LedgerError> {
+    let db_conn = open_state_db(&state)?;
+    create_account(&db_conn, &account)
+}
+
+/// ✏️ Update an account
+#[tauri::command]
+pub fn update_ledger_account(state: tauri::State<StateWrapper>, account: Account) -> Result<(), LedgerError> {
+    let db_conn = open_state_db(&state)?;
+    update_account(&db_conn, &account)
+}
+
+/// 📓 List journal entries with their lines
+#[tauri::command]
+pub fn list_journal_entries(
+    state: tauri::State<StateWrapper>,
+    filter: Option<JournalFilter>,
+) -> Result<Vec<JournalEntry>, LedgerError> {
+    let db_conn = open_state_db(&state)?;
+    list_entries(&db_conn, &filter.unwrap_or_default())
+}
+
+/// ⚖️ Trial balance as of a date, today if none is given
+#[tauri::command]
+pub fn get_trial_balance(state: tauri::State<StateWrapper>, as_of: Option<String>) -> Result<TrialBalance, LedgerError> {
+    let db_conn = open_state_db(&state)?;
+    let as_of = match as_of.as_deref().filter(|d| !d.trim().is_empty()) {
+        Some(date) => record_date(date)?,
+        None => Utc::now().date_naive(),
+    };
+    trial_balance(&db_conn, as_of)
+}
+
+/// 🔄 Post whatever is missing from the journal, returns the number of entries written
+#[tauri::command]
+pub fn sync_ledger(state: tauri::State<StateWrapper>) -> Result<usize, LedgerError> {
+    let mut db_conn = open_state_db(&state)?;
+    sync(&mut db_conn)
+}
@@ -3,6 +3,38 @@ pub mod storage;
 pub mod auth;
 pub mod db_api;
 pub mod secure_db_access;
+pub mod settings;
+pub mod ical;
+pub mod caldav;
+pub mod reminders;
+pub mod scheduler;
+pub mod members;
+pub mod attendees;
+pub mod money;
+pub mod numbering;
+pub mod invoices;
+pub mod payments;
+pub mod booking;
+pub mod attachments;
+pub mod pdf;
+pub mod qrbill;
+pub mod invoice_pdf;
+pub mod einvoice;
+pub mod schematron;
+pub mod quotes;
+pub mod recurring;
+pub mod dunning;
+pub mod credit_notes;
+pub mod currencies;
+pub mod expenses;
+pub mod xml;
+pub mod bank;
+pub mod reconciliation;
+pub mod ledger;
+pub mod reports;
+pub mod accounting_export;
+pub mod products;
+pub mod budgets;
 
 use tauri::Manager;
 use std::{path::PathBuf, sync::Mutex};
@@ -15,6 +47,7 @@ use serde::{Deserialize, Serialize};
 pub struct AppState {
     pub db_key: Option<String>,
     pub db_path: Option<PathBuf>,
+    pub user_id: Option<String>,
 }
 
 type StateWrapper = Mutex<Option<AppState>>;
@@ -30,15 +63,153 @@ async fn main() {
                 .expect("could not resolve app local data path")
                 .join("salt.txt");
             app.handle().plugin(tauri_plugin_stronghold::Builder::with_argon2(&salt_path).build())?;
+            scheduler::start(app.handle().clone());
             Ok(())
         })
+        .plugin(tauri_plugin_notification::init())
         .manage(Mutex::new(None::<AppState>)) // Initialize state as None
         .invoke_handler(tauri::generate_handler![
             auth::initial_sign_up,
             auth::sign_in,
             db_api::create_client,
             db_api::list_clients,
-            db_api::get_client_by_id
+            db_api::get_client_by_id,
+            db_api::create_event,
+            db_api::list_events,
+            db_api::update_event,
+            db_api::delete_event,
+            settings::get_setting,
+            settings::set_setting,
+            settings::list_settings,
+            caldav::caldav_list_calendars,
+            caldav::caldav_sync,
+            reminders::add_reminder,
+            reminders::list_reminders,
+            reminders::delete_reminder,
+            db_api::create_client_contact,
+            db_api::list_client_contacts,
+            db_api::delete_client_contact,
+            members::save_member,
+            members::list_members,
+            attendees::add_event_attendee,
+            attendees::set_attendee_rsvp,
+            attendees::remove_event_attendee,
+            attendees::list_event_attendees,
+            attendees::assign_event_staff,
+            attendees::list_schedule,
+            db_api::set_client_hourly_rate,
+            db_api::set_client_billing_address,
+            invoices::invoice_from_events,
+            invoices::list_invoices,
+            invoices::list_invoice_lines,
+            invoices::add_invoice_line,
+            invoices::update_invoice_line,
+            invoices::delete_invoice_line,
+            invoices::get_invoice_totals,
+            invoices::finalize_invoice,
+            invoices::list_invoice_status_log,
+            payments::record_payment,
+            payments::record_refund,
+            payments::apply_client_credit,
+            payments::delete_payment,
+            payments::list_payments,
+            payments::get_client_balance,
+            booking::list_booking_requests,
+            booking::accept_booking_request,
+            booking::propose_booking_time,
+            booking::decline_booking_request,
+            booking::import_booking_requests,
+            attachments::list_attachments,
+            attachments::read_attachment,
+            attachments::delete_attachment,
+            invoice_pdf::render_invoice_pdf,
+            invoice_pdf::get_default_invoice_template,
+            einvoice::export_invoice,
+            quotes::create_quote,
+            quotes::update_quote,
+            quotes::delete_quote,
+            quotes::list_quotes,
+            quotes::get_quote,
+            quotes::list_quote_lines,
+            quotes::add_quote_line,
+            quotes::update_quote_line,
+            quotes::delete_quote_line,
+            quotes::send_quote,
+            quotes::accept_quote,
+            quotes::reject_quote,
+            quotes::convert_quote_to_invoice,
+            recurring::create_recurring_profile,
+            recurring::update_recurring_profile,
+            recurring::delete_recurring_profile,
+            recurring::list_recurring_profiles,
+            recurring::list_recurring_lines,
+            recurring::add_recurring_line,
+            recurring::update_recurring_line,
+            recurring::delete_recurring_line,
+            recurring::run_recurring_invoices,
+            dunning::list_dunning_levels,
+            dunning::save_dunning_level,
+            dunning::delete_dunning_level,
+            dunning::get_default_reminder_template,
+            dunning::list_due_reminders,
+            dunning::record_reminder_sent,
+            dunning::list_sent_reminders,
+            credit_notes::issue_credit_note,
+            credit_notes::void_invoice,
+            credit_notes::list_credit_notes,
+            credit_notes::get_credit_note,
+            credit_notes::list_credit_note_lines,
+            credit_notes::get_credit_note_totals,
+            currencies::list_exchange_rates,
+            currencies::save_exchange_rate,
+            currencies::delete_exchange_rate,
+            currencies::import_exchange_rates,
+            currencies::convert_amount,
+            expenses::create_expense,
+            expenses::update_expense,
+            expenses::delete_expense,
+            expenses::get_expense,
+            expenses::list_expenses,
+            expenses::attach_expense_receipt,
+            expenses::list_expense_categories,
+            expenses::create_expense_category,
+            expenses::rename_expense_category,
+            expenses::delete_expense_category,
+            bank::import_bank_statement,
+            bank::list_bank_accounts,
+            bank::rename_bank_account,
+            bank::list_bank_statements,
+            bank::delete_bank_statement,
+            bank::list_bank_transactions,
+            reconciliation::propose_bank_matches,
+            reconciliation::list_bank_matches,
+            reconciliation::confirm_bank_match,
+            reconciliation::reject_bank_match,
+            expenses::set_expense_category_account,
+            ledger::list_ledger_accounts,
+            ledger::create_ledger_account,
+            ledger::update_ledger_account,
+            ledger::list_journal_entries,
+            ledger::get_trial_balance,
+            ledger::sync_ledger,
+            reports::get_profit_and_loss,
+            reports::get_cash_flow,
+            reports::get_receivables_aging,
+            reports::get_vat_summary,
+            accounting_export::export_accounting,
+            products::list_products,
+            products::get_product,
+            products::create_product,
+            products::update_product,
+            products::delete_product,
+            products::list_product_client_prices,
+            products::set_product_client_price,
+            invoices::add_invoice_product_line,
+            quotes::add_quote_product_line,
+            budgets::list_budgets,
+            budgets::set_budget,
+            budgets::delete_budget,
+            budgets::get_budget_report
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,80 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::db_api::{open_state_db, DbApiError};
+use crate::StateWrapper;
+
+/// 👥 Member Struct: a person of the organisation who can be assigned to events
+#[derive(Serialize, Deserialize)]
+pub struct Member {
+    pub id: Option<i32>,
+    /// Supabase user id, when the member has an account
+    pub user_id: Option<String>,
+    pub name: String,
+    pub email: Option<String>,
+}
+
+/// Makes sure the signed-in user has a member row, keyed by their Supabase user id
+pub fn ensure_member(conn: &Connection, user_id: &str, name: &str, email: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO members (user_id, name, email) VALUES (?1, ?2, ?3)
+         ON CONFLICT(user_id) DO UPDATE SET email = excluded.email",
+        params![user_id, name, email]
+    )?;
+    Ok(())
+}
+
+/// Resolves the member id of the signed-in user
+pub fn current_member_id(state: &StateWrapper, conn: &Connection) -> Result<i64, DbApiError> {
+    let user_id = state
+        .lock()
+        .map_err(|_| DbApiError::DatabaseLockError)?
+        .as_ref()
+        .and_then(|s| s.user_id.clone())
+        .ok_or(DbApiError::DatabaseLocked)?;
+
+    conn.query_row("SELECT id FROM members WHERE user_id = ?1", [user_id], |row| row.get(0))
+        .optional()?
+        .ok_or(DbApiError::MemberNotFound)
+}
+
+/// 🏷️ Create or update a member
+#[tauri::command]
+pub fn save_member(state: tauri::State<StateWrapper>, member: Member) -> Result<i64, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    match member.id {
+        Some(id) => {
+            db_conn.execute(
+                "UPDATE members SET user_id = ?1, name = ?2, email = ?3 WHERE id = ?4",
+                params![member.user_id, member.name, member.email, id]
+            )?;
+            Ok(id as i64)
+        }
+        None => {
+            db_conn.execute(
+                "INSERT INTO members (user_id, name, email) VALUES (?1, ?2, ?3)",
+                params![member.user_id, member.name, member.email]
+            )?;
+            Ok(db_conn.last_insert_rowid())
+        }
+    }
+}
+
+/// 📋 List all members
+#[tauri::command]
+pub fn list_members(state: tauri::State<StateWrapper>) -> Result<Vec<Member>, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare("SELECT id, user_id, name, email FROM members ORDER BY name")?;
+
+    let members = stmt
+        .query_map([], |row| {
+            Ok(Member {
+                id: row.get(0)?,
+                user_id: row.get(1)?,
+                name: row.get(2)?,
+                email: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(members)
+}
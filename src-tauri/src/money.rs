@@ -0,0 +1,301 @@
+//! Money arithmetic on integer minor units.
+//!
+//! Amounts are `i64` minor units of the document currency (cents for CHF/EUR), percentages
+//! such as tax and discount rates are `i64` basis points (8.1 % = 810) and quantities are
+//! rounded to thousandths. Every intermediate product is exact and each rounding step is
+//! explicit, half away from zero, so totals and VAT reports reconcile to the cent.
+
+use serde::{Deserialize, Serialize};
+
+/// 100 % in basis points
+pub const FULL_RATE_BP: i64 = 10_000;
+
+/// Currency used when `settings.base_currency` is not set
+pub const DEFAULT_CURRENCY: &str = "CHF";
+
+/// Exchange rates are fixed-point with nine decimals: 1.0956 is stored as 1_095_600_000
+pub const RATE_SCALE: i64 = 1_000_000_000;
+
+/// How tax is rounded when an invoice has several lines at the same rate
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaxRounding {
+    /// Round the tax of each line, then add the lines up
+    PerLine,
+    /// Add up the net amounts of each rate, then round the tax once per rate (EN 16931)
+    PerRate,
+}
+
+impl TaxRounding {
+    /// Reads the `tax_rounding` setting; anything but `per_line` means per rate
+    pub fn from_setting(value: Option<&str>) -> TaxRounding {
+        match value.map(str::trim) {
+            Some("per_line") => TaxRounding::PerLine,
+            _ => TaxRounding::PerRate,
+        }
+    }
+}
+
+/// Net and tax of a single line, in minor units
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineAmounts {
+    pub net: i64,
+    pub tax: i64,
+}
+
+/// 🧮 Tax of one rate on a document
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TaxBreakdown {
+    pub tax_rate_bp: i64,
+    pub net: i64,
+    pub tax: i64,
+}
+
+/// 🧮 Totals of a document, in minor units
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocumentTotals {
+    pub subtotal: i64,
+    pub tax_total: i64,
+    pub total: i64,
+    /// One entry per tax rate, lowest rate first
+    pub taxes: Vec<TaxBreakdown>,
+}
+
+/// Divides and rounds half away from zero
+pub fn round_div(numerator: i128, denominator: i128) -> i64 {
+    let (n, d) = (numerator.abs(), denominator.abs());
+    let rounded = (n + d / 2) / d;
+    if (numerator < 0) != (denominator < 0) {
+        -rounded as i64
+    } else {
+        rounded as i64
+    }
+}
+
+/// Converts a percentage such as `8.1` into basis points
+pub fn percent_to_bp(value: f64) -> i64 {
+    (value * 100.0).round() as i64
+}
+
+/// Parses a user-entered percentage such as `"8.1"` or `"8.1 %"` into basis points
+pub fn parse_percent_bp(value: &str) -> Option<i64> {
+    value.trim().trim_end_matches('%').trim().parse::<f64>().ok().map(percent_to_bp)
+}
+
+/// Formats minor units with `digits` decimals as a plain decimal, e.g. `-1234` as `-12.34`
+pub fn format_minor(amount: i64, digits: u32) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let (value, scale) = (amount.unsigned_abs(), 10u64.pow(digits));
+    if digits == 0 {
+        return format!("{}{}", sign, value);
+    }
+    format!("{}{}.{:0width$}", sign, value / scale, value % scale, width = digits as usize)
+}
+
+/// Net and tax of `quantity × unit_price`, less a discount in basis points
+pub fn line_amounts(quantity: f64, unit_price: i64, discount_bp: i64, tax_rate_bp: i64) -> LineAmounts {
+    let quantity_milli = (quantity * 1000.0).round() as i128;
+    let gross = round_div(quantity_milli * unit_price as i128, 1000);
+    let discount = round_div(gross as i128 * discount_bp as i128, FULL_RATE_BP as i128);
+    let net = gross - discount;
+    LineAmounts {
+        net,
+        tax: round_div(net as i128 * tax_rate_bp as i128, FULL_RATE_BP as i128),
+    }
+}
+
+/// Totals of a document from its lines as `(amounts, tax_rate_bp)`
+pub fn document_totals(lines: &[(LineAmounts, i64)], rounding: TaxRounding) -> DocumentTotals {
+    let mut taxes: Vec<TaxBreakdown> = Vec::new();
+    for (amounts, rate) in lines {
+        match taxes.iter_mut().find(|t| t.tax_rate_bp == *rate) {
+            Some(entry) => {
+                entry.net += amounts.net;
+                entry.tax += amounts.tax;
+            }
+            None => taxes.push(TaxBreakdown { tax_rate_bp: *rate, net: amounts.net, tax: amounts.tax }),
+        }
+    }
+    if rounding == TaxRounding::PerRate {
+        for entry in taxes.iter_mut() {
+            entry.tax = round_div(entry.net as i128 * entry.tax_rate_bp as i128, FULL_RATE_BP as i128);
+        }
+    }
+    taxes.sort_by_key(|t| t.tax_rate_bp);
+
+    let subtotal = taxes.iter().map(|t| t.net).sum();
+    let tax_total = taxes.iter().map(|t| t.tax).sum();
+    DocumentTotals {
+        subtotal,
+        tax_total,
+        total: subtotal + tax_total,
+        taxes,
+    }
+}
+
+/// Whether `code` looks like an ISO 4217 currency code such as `CHF`
+pub fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Decimals of the minor unit of an ISO 4217 currency: 2 unless listed otherwise
+pub fn currency_digits(code: &str) -> u32 {
+    match code {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX" | "UYI" | "VND"
+        | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        "CLF" | "UYW" => 4,
+        _ => 2,
+    }
+}
+
+/// Parses a decimal exchange rate such as `"1.0956"` or `"0,9412"` exactly into
+/// `RATE_SCALE` units; only positive rates are valid
+pub fn parse_rate(value: &str) -> Option<i64> {
+    let value = value.trim().replace(',', ".");
+    let (whole, fraction) = value.split_once('.').unwrap_or((value.as_str(), ""));
+    let digits = RATE_SCALE.ilog10() as usize;
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        || fraction.trim_end_matches('0').len() > digits
+    {
+        return None;
+    }
+    let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let fraction = format!("{:0<width$}", fraction.get(..digits.min(fraction.len()))?, width = digits);
+    let rate = whole.checked_mul(RATE_SCALE)?.checked_add(fraction.parse().ok()?)?;
+    (rate > 0).then_some(rate)
+}
+
+/// Parses a decimal amount such as `"-1234.5"` exactly into minor units with `digits`
+/// decimals; the separator is `.`, so thousands separators must be removed first
+pub fn parse_minor(value: &str, digits: u32) -> Option<i64> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let digits = digits as usize;
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        || fraction.trim_end_matches('0').len() > digits
+    {
+        return None;
+    }
+    let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let fraction = fraction.get(..digits.min(fraction.len()))?;
+    let fraction: i64 = if digits == 0 { 0 } else { format!("{:0<width$}", fraction, width = digits).parse().ok()? };
+    let amount = whole.checked_mul(10i64.pow(digits as u32))?.checked_add(fraction)?;
+    Some(if negative { -amount } else { amount })
+}
+
+/// Formats a rate in `RATE_SCALE` units without trailing zeros, e.g. `1.0956`
+pub fn format_exchange_rate(rate: i64) -> String {
+    let digits = RATE_SCALE.ilog10() as usize;
+    let fraction = format!("{:0width$}", rate % RATE_SCALE, width = digits);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (rate / RATE_SCALE).to_string()
+    } else {
+        format!("{}.{}", rate / RATE_SCALE, fraction)
+    }
+}
+
+/// Converts minor units of `from` into minor units of `to` at the exact rate
+/// `numerator / denominator` (units of `to` per unit of `from`)
+pub fn convert_minor(amount: i64, from: &str, to: &str, numerator: i128, denominator: i128) -> i64 {
+    let (from_digits, to_digits) = (currency_digits(from), currency_digits(to));
+    round_div(
+        amount as i128 * numerator * 10i128.pow(to_digits),
+        denominator * 10i128.pow(from_digits),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(net: i64, tax_rate_bp: i64) -> (LineAmounts, i64) {
+        (line_amounts(1.0, net, 0, tax_rate_bp), tax_rate_bp)
+    }
+
+    #[test]
+    fn round_div_rounds_half_away_from_zero() {
+        assert_eq!(round_div(5, 10), 1);
+        assert_eq!(round_div(15, 10), 2);
+        assert_eq!(round_div(14, 10), 1);
+        assert_eq!(round_div(-5, 10), -1);
+        assert_eq!(round_div(-15, 10), -2);
+        assert_eq!(round_div(15, -10), -2);
+    }
+
+    #[test]
+    fn line_amounts_round_tax_of_the_line() {
+        // 3 × 19.99 at 8.1 %: 59.97 net, 4.85757 tax
+        assert_eq!(line_amounts(3.0, 1999, 0, 810), LineAmounts { net: 5997, tax: 486 });
+        // Credit lines round the same way, away from zero
+        assert_eq!(line_amounts(-3.0, 1999, 0, 810), LineAmounts { net: -5997, tax: -486 });
+    }
+
+    #[test]
+    fn line_amounts_apply_discount_before_tax() {
+        // 12.5 % off 100.00, then 7.7 % tax on 87.50
+        assert_eq!(line_amounts(1.0, 10000, 1250, 770), LineAmounts { net: 8750, tax: 674 });
+    }
+
+    #[test]
+    fn line_amounts_round_quantity_to_thousandths() {
+        assert_eq!(line_amounts(0.3333, 3000, 0, 0).net, 999);
+        assert_eq!(line_amounts(1.5, 12000, 0, 0).net, 18000);
+    }
+
+    #[test]
+    fn per_line_rounding_adds_rounded_line_taxes() {
+        // Each line: 10.05 at 8.1 % = 0.81405, rounded to 0.81
+        let totals = document_totals(&[line(1005, 810), line(1005, 810), line(1005, 810)], TaxRounding::PerLine);
+        assert_eq!(totals.subtotal, 3015);
+        assert_eq!(totals.tax_total, 243);
+        assert_eq!(totals.total, 3258);
+    }
+
+    #[test]
+    fn per_rate_rounding_rounds_once_per_rate() {
+        // 30.15 at 8.1 % = 2.44215, rounded once to 2.44
+        let totals = document_totals(&[line(1005, 810), line(1005, 810), line(1005, 810)], TaxRounding::PerRate);
+        assert_eq!(totals.subtotal, 3015);
+        assert_eq!(totals.tax_total, 244);
+        assert_eq!(totals.total, 3259);
+    }
+
+    #[test]
+    fn document_totals_break_down_taxes_lowest_rate_first() {
+        let totals = document_totals(&[line(10000, 810), line(5000, 260), line(2000, 810)], TaxRounding::PerRate);
+        assert_eq!(
+            totals.taxes,
+            vec![
+                TaxBreakdown { tax_rate_bp: 260, net: 5000, tax: 130 },
+                TaxBreakdown { tax_rate_bp: 810, net: 12000, tax: 972 },
+            ]
+        );
+        assert_eq!(totals.total, 17000 + 130 + 972);
+    }
+
+    #[test]
+    fn parse_minor_is_exact() {
+        assert_eq!(parse_minor("12.3", 2), Some(1230));
+        assert_eq!(parse_minor("-0.05", 2), Some(-5));
+        assert_eq!(parse_minor("+7", 2), Some(700));
+        assert_eq!(parse_minor("1.230", 2), Some(123));
+        assert_eq!(parse_minor("1500", 0), Some(1500));
+        assert_eq!(parse_minor("1.234", 2), None);
+        assert_eq!(parse_minor("1,50", 2), None);
+        assert_eq!(parse_minor("", 2), None);
+    }
+
+    #[test]
+    fn format_minor_pads_decimals() {
+        assert_eq!(format_minor(-1234, 2), "-12.34");
+        assert_eq!(format_minor(5, 3), "0.005");
+        assert_eq!(format_minor(1500, 0), "1500");
+    }
+}
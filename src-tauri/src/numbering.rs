@@ -0,0 +1,178 @@
+//! Gapless document numbers such as `INV-2025-0042`.
+//!
+//! A pattern mixes literal text with the placeholders `{YYYY}`, `{YY}`, `{MM}` and `{seq}`
+//! (`{seq:04}` pads the counter to four digits). Each scope keeps one counter per period,
+//! where the period is made of the date placeholders in the pattern: `{YYYY}` restarts
+//! the counter every year, `{YYYY}{MM}` every month and a pattern without a date never.
+//!
+//! Numbers must be taken inside the transaction that stores them, so a rollback also
+//! gives the number back and the sequence stays without gaps.
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum NumberingError {
+    #[error("[numbering.rs::invalid_pattern] Invalid number pattern {0:?}: {1}")]
+    InvalidPattern(String, &'static str),
+
+    #[error("[numbering.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+enum Part {
+    Literal(String),
+    Year,
+    ShortYear,
+    Month,
+    Sequence { width: usize },
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Part>, NumberingError> {
+    let invalid = |reason| NumberingError::InvalidPattern(pattern.to_string(), reason);
+    let mut parts = Vec::new();
+    let mut rest = pattern;
+    let mut has_sequence = false;
+
+    while let Some(open) = rest.find('{') {
+        if open > 0 {
+            parts.push(Part::Literal(rest[..open].to_string()));
+        }
+        let close = rest[open..].find('}').ok_or_else(|| invalid("unclosed `{`"))? + open;
+        let token = &rest[open + 1..close];
+        parts.push(match token {
+            "YYYY" => Part::Year,
+            "YY" => Part::ShortYear,
+            "MM" => Part::Month,
+            "seq" => Part::Sequence { width: 0 },
+            _ => match token.strip_prefix("seq:") {
+                Some(width) => Part::Sequence {
+                    width: width.parse().map_err(|_| invalid("`{seq:N}` needs a number"))?,
+                },
+                None => return Err(invalid("unknown placeholder")),
+            },
+        });
+        has_sequence |= matches!(parts.last(), Some(Part::Sequence { .. }));
+        rest = &rest[close + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_string()));
+    }
+    if !has_sequence {
+        return Err(invalid("`{seq}` is missing"));
+    }
+    Ok(parts)
+}
+
+/// Counter period of a pattern on a date, e.g. `2025` for `INV-{YYYY}-{seq}`
+fn period(parts: &[Part], date: NaiveDate) -> String {
+    let yearly = parts.iter().any(|p| matches!(p, Part::Year | Part::ShortYear));
+    let monthly = parts.iter().any(|p| matches!(p, Part::Month));
+    match (yearly, monthly) {
+        (_, true) => date.format("%Y-%m").to_string(),
+        (true, false) => date.format("%Y").to_string(),
+        (false, false) => String::new(),
+    }
+}
+
+fn render(parts: &[Part], date: NaiveDate, sequence: i64) -> String {
+    parts
+        .iter()
+        .map(|part| match part {
+            Part::Literal(text) => text.clone(),
+            Part::Year => date.format("%Y").to_string(),
+            Part::ShortYear => date.format("%y").to_string(),
+            Part::Month => date.format("%m").to_string(),
+            Part::Sequence { width } => format!("{:0width$}", sequence, width = width),
+        })
+        .collect()
+}
+
+/// Takes the next number of `scope` (e.g. `invoice`) for a document dated `date`
+pub fn next_number(conn: &Connection, scope: &str, pattern: &str, date: NaiveDate) -> Result<String, NumberingError> {
+    let parts = parse_pattern(pattern)?;
+    let sequence: i64 = conn.query_row(
+        "INSERT INTO number_sequences (scope, period, last_value) VALUES (?1, ?2, 1)
+         ON CONFLICT(scope, period) DO UPDATE SET last_value = last_value + 1
+         RETURNING last_value",
+        params![scope, period(&parts, date)],
+        |row| row.get(0),
+    )?;
+    Ok(render(&parts, date, sequence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE number_sequences (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                scope TEXT NOT NULL,
+                period TEXT NOT NULL,
+                last_value INTEGER NOT NULL,
+                UNIQUE (scope, period)
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parse_pattern_rejects_invalid_patterns() {
+        for pattern in ["INV-{YYYY}", "INV-{seq", "INV-{DD}-{seq}", "INV-{seq:x}"] {
+            assert!(matches!(parse_pattern(pattern), Err(NumberingError::InvalidPattern(..))), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn render_fills_placeholders_and_padding() {
+        let parts = parse_pattern("R{YY}{MM}/{seq:03}").unwrap();
+        assert_eq!(render(&parts, date(2025, 3, 9), 7), "R2503/007");
+        let parts = parse_pattern("{seq}").unwrap();
+        assert_eq!(render(&parts, date(2025, 3, 9), 1234), "1234");
+    }
+
+    #[test]
+    fn period_follows_date_placeholders() {
+        let day = date(2025, 3, 9);
+        assert_eq!(period(&parse_pattern("INV-{YYYY}-{seq}").unwrap(), day), "2025");
+        assert_eq!(period(&parse_pattern("{YY}{MM}-{seq}").unwrap(), day), "2025-03");
+        assert_eq!(period(&parse_pattern("INV-{seq}").unwrap(), day), "");
+    }
+
+    #[test]
+    fn next_number_counts_up_and_restarts_every_year() {
+        let conn = connection();
+        let pattern = "INV-{YYYY}-{seq:04}";
+        assert_eq!(next_number(&conn, "invoice", pattern, date(2024, 12, 30)).unwrap(), "INV-2024-0001");
+        assert_eq!(next_number(&conn, "invoice", pattern, date(2024, 12, 31)).unwrap(), "INV-2024-0002");
+        assert_eq!(next_number(&conn, "invoice", pattern, date(2025, 1, 1)).unwrap(), "INV-2025-0001");
+        assert_eq!(next_number(&conn, "invoice", pattern, date(2024, 12, 31)).unwrap(), "INV-2024-0003");
+    }
+
+    #[test]
+    fn next_number_keeps_scopes_apart() {
+        let conn = connection();
+        let day = date(2025, 5, 1);
+        assert_eq!(next_number(&conn, "invoice", "{seq}", day).unwrap(), "1");
+        assert_eq!(next_number(&conn, "quote", "Q{seq}", day).unwrap(), "Q1");
+        assert_eq!(next_number(&conn, "invoice", "{seq}", day).unwrap(), "2");
+    }
+
+    #[test]
+    fn next_number_gives_the_number_back_on_rollback() {
+        let mut conn = connection();
+        let tx = conn.transaction().unwrap();
+        assert_eq!(next_number(&tx, "invoice", "{seq}", date(2025, 5, 1)).unwrap(), "1");
+        drop(tx);
+        assert_eq!(next_number(&conn, "invoice", "{seq}", date(2025, 5, 1)).unwrap(), "1");
+    }
+}
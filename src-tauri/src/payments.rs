@@ -0,0 +1,886 @@
+//! Payments ledger.
+//!
+//! Every row is a signed amount in minor units of its currency, which on an invoice is the
+//! invoice currency; credit is kept per currency. `Payment` rows are money received, `Refund`
+//! rows (negative) money paid back, and `Credit` rows move money between an invoice and the
+//! client's credit as a pair that nets to zero. Client credit is simply the sum of the rows
+//! without an invoice: the part of a payment beyond the invoice balance is booked there,
+//! as are prepayments and whatever was paid on an invoice that gets voided.
+//!
+//! What a client owes on an invoice is its total less its credit notes. Invoice status is
+//! never set by hand here: after every change the status is derived from that balance and
+//! the due date.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::currencies::{currency_or_base, normalize_currency, CurrencyError};
+use crate::db_api::{db_now, open_state_db, parse_db_timestamp, DbApiError, DB_TIMESTAMP_FORMAT};
+use crate::invoices::{transition, InvoiceError, InvoiceStatus};
+use crate::ledger::{post_or_defer, LedgerError, Source};
+use crate::members::current_member_id;
+use crate::StateWrapper;
+
+#[derive(Debug, Error)]
+pub enum PaymentError {
+    #[error("[payments.rs::not_found] Payment {0} does not exist.")]
+    NotFound(i64),
+
+    #[error("[payments.rs::credit_move] Payment {0} moves credit between an invoice and the client and cannot be deleted.")]
+    CreditMove(i64),
+
+    #[error("[payments.rs::split_excess] Payment {0} is the credit part of payment {1}; delete that payment instead.")]
+    SplitExcess(i64, i64),
+
+    #[error("[payments.rs::unlinked_credit] Money paid on invoice {0} was moved to client credit without a link to its payment; refund the credit instead.")]
+    UnlinkedCredit(i64),
+
+    #[error("[payments.rs::invoice_not_found] Invoice {0} does not exist.")]
+    InvoiceNotFound(i64),
+
+    #[error("[payments.rs::not_payable] Invoice {0} is {1} and cannot take payments.")]
+    NotPayable(i64, String),
+
+    #[error("[payments.rs::missing_client] A payment without an invoice needs a client.")]
+    MissingClient,
+
+    #[error("[payments.rs::invalid_amount] The amount must be greater than zero.")]
+    InvalidAmount,
+
+    #[error("[payments.rs::refund_too_large] Only {0} can be refunded.")]
+    RefundTooLarge(i64),
+
+    #[error("[payments.rs::exceeds_balance] Only {0} is still open on this invoice.")]
+    ExceedsBalance(i64),
+
+    #[error("[payments.rs::insufficient_credit] The client only has {0} of credit.")]
+    InsufficientCredit(i64),
+
+    #[error("[payments.rs::invalid_date] Invalid payment date: {0}")]
+    InvalidDate(String),
+
+    #[error("[payments.rs::currency_mismatch] The invoice is in {0}, not {1}.")]
+    CurrencyMismatch(String, String),
+
+    #[error("[payments.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[payments.rs::invoices] {0}")]
+    InvoiceError(#[from] InvoiceError),
+
+    #[error("[payments.rs::ledger] {0}")]
+    LedgerError(#[from] LedgerError),
+
+    #[error("[payments.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[payments.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for PaymentError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 💳 Payment Struct. `amount` is in minor units and always positive on input;
+/// refunds are stored negative.
+#[derive(Serialize, Deserialize)]
+pub struct Payment {
+    pub id: Option<i64>,
+    pub client_id: Option<i64>,
+    pub invoice_id: Option<i64>,
+    /// `Payment`, `Refund` or `Credit`; set by the ledger
+    #[serde(default)]
+    pub kind: String,
+    pub amount: i64,
+    pub date: String,
+    pub method: Option<String>,
+    pub reference: Option<String>,
+    /// ISO 4217 code; that of the invoice, else `settings.base_currency`
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// 📊 Client Balance Struct, in minor units of one currency
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct ClientBalance {
+    pub client_id: i64,
+    pub currency: String,
+    /// Totals of issued, non-void invoices
+    pub invoiced: i64,
+    /// Credit notes on those invoices, negative
+    pub credited: i64,
+    /// Money received less refunds
+    pub paid: i64,
+    /// Still owed on open invoices
+    pub outstanding: i64,
+    /// Overpayments and unallocated payments not yet used or refunded
+    pub credit: i64,
+}
+
+struct PayableInvoice {
+    client_id: Option<i64>,
+    due: i64,
+    currency: String,
+}
+
+/// Invoice total less its credit notes, as an SQL expression on `invoices i`
+const INVOICE_DUE_SQL: &str =
+    "i.total + COALESCE((SELECT SUM(n.total) FROM credit_notes n WHERE n.invoice_id = i.id), 0)";
+
+fn load_invoice(conn: &Connection, invoice_id: i64) -> Result<PayableInvoice, PaymentError> {
+    let (client_id, due, status, currency): (Option<i64>, i64, String, String) = conn
+        .query_row(
+            &format!("SELECT i.client_id, {}, i.status, i.currency FROM invoices i WHERE i.id = ?1", INVOICE_DUE_SQL),
+            [invoice_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?
+        .ok_or(PaymentError::InvoiceNotFound(invoice_id))?;
+
+    match InvoiceStatus::parse(&status) {
+        Some(InvoiceStatus::Draft) | Some(InvoiceStatus::Void) | None => Err(PaymentError::NotPayable(invoice_id, status)),
+        Some(_) => Ok(PayableInvoice { client_id, due, currency }),
+    }
+}
+
+/// Net amount paid on an invoice
+pub fn invoice_paid(conn: &Connection, invoice_id: i64) -> Result<i64, rusqlite::Error> {
+    conn.query_row("SELECT COALESCE(SUM(amount), 0) FROM payments WHERE invoice_id = ?1", [invoice_id], |row| row.get(0))
+}
+
+/// Invoice total less its credit notes
+pub fn invoice_due(conn: &Connection, invoice_id: i64) -> Result<i64, rusqlite::Error> {
+    conn.query_row(
+        &format!("SELECT {} FROM invoices i WHERE i.id = ?1", INVOICE_DUE_SQL),
+        [invoice_id],
+        |row| row.get(0),
+    )
+}
+
+/// Outstanding, paid and credit of a client in one currency
+pub fn client_balance(conn: &Connection, client_id: i64, currency: &str) -> Result<ClientBalance, rusqlite::Error> {
+    conn.query_row(
+        "WITH per_invoice AS (
+             SELECT i.total AS total,
+                    COALESCE((SELECT SUM(n.total) FROM credit_notes n WHERE n.invoice_id = i.id), 0) AS credited,
+                    COALESCE((SELECT SUM(p.amount) FROM payments p WHERE p.invoice_id = i.id), 0) AS paid
+             FROM invoices i
+             WHERE i.client_id = ?1 AND i.currency = ?2 AND i.status NOT IN ('Draft', 'Void')
+         )
+         SELECT
+             COALESCE((SELECT SUM(total) FROM per_invoice), 0),
+             COALESCE((SELECT SUM(credited) FROM per_invoice), 0),
+             COALESCE((SELECT SUM(amount) FROM payments
+                       WHERE client_id = ?1 AND currency = ?2 AND kind IN ('Payment', 'Refund')), 0),
+             COALESCE((SELECT SUM(MAX(total + credited - paid, 0)) FROM per_invoice), 0),
+             COALESCE((SELECT SUM(amount) FROM payments WHERE client_id = ?1 AND currency = ?2 AND invoice_id IS NULL), 0)",
+        params![client_id, currency],
+        |row| {
+            Ok(ClientBalance {
+                client_id,
+                currency: currency.to_string(),
+                invoiced: row.get(0)?,
+                credited: row.get(1)?,
+                paid: row.get(2)?,
+                outstanding: row.get(3)?,
+                credit: row.get(4)?,
+            })
+        },
+    )
+}
+
+/// What of each row paid on an invoice is not yet moved to client credit, newest first
+fn unreleased_rows(conn: &Connection, invoice_id: i64) -> Result<Vec<(i64, i64)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.amount + COALESCE((SELECT SUM(r.amount) FROM payments r
+                                           WHERE r.split_from_id = p.id AND r.invoice_id = p.invoice_id), 0)
+         FROM payments p WHERE p.invoice_id = ?1 AND p.split_from_id IS NULL
+         ORDER BY p.date DESC, p.id DESC",
+    )?;
+    let rows = stmt.query_map([invoice_id], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Moves `amount` of a row paid on an invoice to the client's credit, as a pair of credit rows
+/// linked to that row so deleting it takes the credit back too
+fn release_row(conn: &Connection, payment_id: i64, amount: i64) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO payments (client_id, invoice_id, kind, amount, date, method, currency, split_from_id)
+         SELECT client_id, invoice_id, 'Credit', -?2, ?3, 'Credit', currency, id FROM payments WHERE id = ?1
+         UNION ALL
+         SELECT client_id, NULL, 'Credit', ?2, ?3, 'Credit', currency, id FROM payments WHERE id = ?1",
+        params![payment_id, amount, db_now()],
+    )?;
+    Ok(())
+}
+
+/// Moves whatever was paid on a voided invoice to the client's credit
+pub fn release_to_credit(conn: &Connection, invoice_id: i64) -> Result<(), rusqlite::Error> {
+    for (payment_id, unreleased) in unreleased_rows(conn, invoice_id)? {
+        if unreleased != 0 {
+            release_row(conn, payment_id, unreleased)?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves whatever was paid on an invoice beyond its balance to the client's credit,
+/// e.g. after a credit note lowered what is due. The latest payments are released first.
+pub fn release_excess_to_credit(conn: &Connection, invoice_id: i64) -> Result<i64, rusqlite::Error> {
+    let excess = invoice_paid(conn, invoice_id)? - invoice_due(conn, invoice_id)?.max(0);
+    if excess <= 0 {
+        return Ok(0);
+    }
+    let mut left = excess;
+    for (payment_id, unreleased) in unreleased_rows(conn, invoice_id)? {
+        if left == 0 {
+            break;
+        }
+        if unreleased > 0 {
+            let amount = unreleased.min(left);
+            release_row(conn, payment_id, amount)?;
+            left -= amount;
+        }
+    }
+    Ok(excess)
+}
+
+/// Derives the status of an invoice from its balance and due date. An invoice its credit
+/// notes took back completely without anything paid on it is void, not paid.
+pub fn sync_invoice_status(conn: &Connection, invoice_id: i64, member_id: Option<i64>) -> Result<(), PaymentError> {
+    let (due, credited, due_date, status): (i64, i64, String, String) = conn.query_row(
+        &format!(
+            "SELECT {}, COALESCE((SELECT SUM(n.total) FROM credit_notes n WHERE n.invoice_id = i.id), 0), i.due_date, i.status
+             FROM invoices i WHERE i.id = ?1",
+            INVOICE_DUE_SQL
+        ),
+        [invoice_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    if matches!(InvoiceStatus::parse(&status), Some(InvoiceStatus::Draft) | Some(InvoiceStatus::Void)) {
+        return Ok(());
+    }
+
+    let paid = invoice_paid(conn, invoice_id)?;
+    if credited != 0 && due <= 0 && paid == 0 {
+        transition(conn, invoice_id, InvoiceStatus::Void, member_id, Some("Fully credited"))?;
+        return Ok(());
+    }
+    let past_due = parse_db_timestamp(&due_date).is_some_and(|due| due < chrono::Utc::now().naive_utc());
+    let next = if paid >= due {
+        InvoiceStatus::Paid
+    } else if past_due {
+        InvoiceStatus::Overdue
+    } else if paid > 0 {
+        InvoiceStatus::PartiallyPaid
+    } else {
+        InvoiceStatus::Issued
+    };
+    transition(conn, invoice_id, next, member_id, None)?;
+    Ok(())
+}
+
+fn normalize_date(date: &str) -> Result<String, PaymentError> {
+    parse_db_timestamp(date)
+        .map(|d| d.format(DB_TIMESTAMP_FORMAT).to_string())
+        .ok_or_else(|| PaymentError::InvalidDate(date.to_string()))
+}
+
+/// Fills in the currency of a payment on an invoice, which must be the invoice currency
+fn use_invoice_currency(payment: &mut Payment, currency: &str) -> Result<(), PaymentError> {
+    if let Some(code) = payment.currency.as_deref().filter(|c| !c.trim().is_empty()) {
+        let code = normalize_currency(code)?;
+        if code != currency {
+            return Err(PaymentError::CurrencyMismatch(currency.to_string(), code));
+        }
+    }
+    payment.currency = Some(currency.to_string());
+    Ok(())
+}
+
+fn insert_row(conn: &Connection, payment: &Payment, kind: &str, amount: i64) -> Result<i64, PaymentError> {
+    conn.execute(
+        "INSERT INTO payments (client_id, invoice_id, kind, amount, date, method, reference, currency)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            payment.client_id,
+            payment.invoice_id,
+            kind,
+            amount,
+            normalize_date(&payment.date)?,
+            payment.method,
+            payment.reference,
+            payment.currency
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Records money received, on an invoice or as a prepayment of the client.
+/// Anything beyond the invoice balance becomes client credit.
+pub fn record(conn: &mut Connection, payment: Payment, member_id: Option<i64>) -> Result<i64, PaymentError> {
+    let tx = conn.transaction()?;
+    let payment_id = record_in(&tx, payment, member_id)?;
+    tx.commit()?;
+    Ok(payment_id)
+}
+
+/// `record` within a transaction the caller commits
+pub fn record_in(tx: &Connection, mut payment: Payment, member_id: Option<i64>) -> Result<i64, PaymentError> {
+    if payment.amount <= 0 {
+        return Err(PaymentError::InvalidAmount);
+    }
+    let mut to_invoice = 0;
+    if let Some(invoice_id) = payment.invoice_id {
+        let invoice = load_invoice(tx, invoice_id)?;
+        use_invoice_currency(&mut payment, &invoice.currency)?;
+        payment.client_id = invoice.client_id;
+        to_invoice = payment.amount.min((invoice.due - invoice_paid(tx, invoice_id)?).max(0));
+    } else {
+        payment.currency = Some(currency_or_base(tx, payment.currency.as_deref())?);
+    }
+    if payment.client_id.is_none() {
+        return Err(PaymentError::MissingClient);
+    }
+
+    let invoice_id = payment.invoice_id;
+    let excess = payment.amount - to_invoice;
+    let mut payment_ids = Vec::with_capacity(2);
+    if to_invoice > 0 {
+        payment_ids.push(insert_row(tx, &payment, "Payment", to_invoice)?);
+    }
+    if excess > 0 {
+        let excess_id = insert_row(tx, &Payment { invoice_id: None, ..payment }, "Payment", excess)?;
+        if let Some(split_from_id) = payment_ids.first() {
+            tx.execute("UPDATE payments SET split_from_id = ?1 WHERE id = ?2", params![split_from_id, excess_id])?;
+        }
+        payment_ids.push(excess_id);
+    }
+    if let Some(invoice_id) = invoice_id {
+        sync_invoice_status(tx, invoice_id, member_id)?;
+    }
+    for payment_id in &payment_ids {
+        post_or_defer(tx, Source::Payment, *payment_id)?;
+    }
+    Ok(payment_ids[0])
+}
+
+/// Records money paid back: against an invoice up to what was paid on it, otherwise
+/// out of the client's credit
+pub fn refund(conn: &mut Connection, mut payment: Payment, member_id: Option<i64>) -> Result<i64, PaymentError> {
+    if payment.amount <= 0 {
+        return Err(PaymentError::InvalidAmount);
+    }
+    let tx = conn.transaction()?;
+    match payment.invoice_id {
+        Some(invoice_id) => {
+            let (client_id, currency): (Option<i64>, String) = tx
+                .query_row("SELECT client_id, currency FROM invoices WHERE id = ?1", [invoice_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })
+                .optional()?
+                .ok_or(PaymentError::InvoiceNotFound(invoice_id))?;
+            use_invoice_currency(&mut payment, &currency)?;
+            payment.client_id = client_id;
+            let paid = invoice_paid(&tx, invoice_id)?;
+            if payment.amount > paid {
+                return Err(PaymentError::RefundTooLarge(paid));
+            }
+        }
+        None => {
+            let client_id = payment.client_id.ok_or(PaymentError::MissingClient)?;
+            let currency = currency_or_base(&tx, payment.currency.as_deref())?;
+            let credit = client_balance(&tx, client_id, &currency)?.credit;
+            payment.currency = Some(currency);
+            if payment.amount > credit {
+                return Err(PaymentError::InsufficientCredit(credit));
+            }
+        }
+    }
+
+    let payment_id = insert_row(&tx, &payment, "Refund", -payment.amount)?;
+    if let Some(invoice_id) = payment.invoice_id {
+        sync_invoice_status(&tx, invoice_id, member_id)?;
+    }
+    post_or_defer(&tx, Source::Payment, payment_id)?;
+    tx.commit()?;
+    Ok(payment_id)
+}
+
+/// Settles (part of) an invoice with the client's existing credit
+pub fn apply_credit(conn: &mut Connection, invoice_id: i64, amount: i64, member_id: Option<i64>) -> Result<(), PaymentError> {
+    if amount <= 0 {
+        return Err(PaymentError::InvalidAmount);
+    }
+    let tx = conn.transaction()?;
+    let invoice = load_invoice(&tx, invoice_id)?;
+    let client_id = invoice.client_id.ok_or(PaymentError::MissingClient)?;
+    let credit = client_balance(&tx, client_id, &invoice.currency)?.credit;
+    if amount > credit {
+        return Err(PaymentError::InsufficientCredit(credit));
+    }
+    let outstanding = (invoice.due - invoice_paid(&tx, invoice_id)?).max(0);
+    if amount > outstanding {
+        return Err(PaymentError::ExceedsBalance(outstanding));
+    }
+
+    let today = chrono::Utc::now().naive_utc().format(DB_TIMESTAMP_FORMAT).to_string();
+    let entry = |invoice_id| Payment {
+        id: None,
+        client_id: Some(client_id),
+        invoice_id,
+        kind: String::new(),
+        amount,
+        date: today.clone(),
+        method: Some("Credit".to_string()),
+        reference: None,
+        currency: Some(invoice.currency.clone()),
+    };
+    insert_row(&tx, &entry(None), "Credit", -amount)?;
+    let payment_id = insert_row(&tx, &entry(Some(invoice_id)), "Credit", amount)?;
+    sync_invoice_status(&tx, invoice_id, member_id)?;
+    post_or_defer(&tx, Source::Payment, payment_id)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Deletes a payment or refund recorded by mistake, together with the client credit split off
+/// it: the part of an overpayment and what a void or credit note moved to credit. Credit moves
+/// and the credit part of an overpayment cannot be deleted on their own. A bank match
+/// confirmed as the payment becomes a proposal again.
+pub fn delete(conn: &mut Connection, payment_id: i64, member_id: Option<i64>) -> Result<(), PaymentError> {
+    let tx = conn.transaction()?;
+    let (kind, invoice_id, split_from_id): (String, Option<i64>, Option<i64>) = tx
+        .query_row("SELECT kind, invoice_id, split_from_id FROM payments WHERE id = ?1", [payment_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .optional()?
+        .ok_or(PaymentError::NotFound(payment_id))?;
+    if kind == "Credit" {
+        return Err(PaymentError::CreditMove(payment_id));
+    }
+    if let Some(split_from_id) = split_from_id {
+        return Err(PaymentError::SplitExcess(payment_id, split_from_id));
+    }
+
+    // Credit released before releases were linked to their payment can't be taken back
+    if let Some(invoice_id) = invoice_id {
+        let unlinked: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM payments
+                           WHERE invoice_id = ?1 AND kind = 'Credit' AND amount < 0 AND split_from_id IS NULL)",
+            [invoice_id],
+            |row| row.get(0),
+        )?;
+        if unlinked {
+            return Err(PaymentError::UnlinkedCredit(invoice_id));
+        }
+    }
+
+    let mut stmt = tx.prepare("SELECT id FROM payments WHERE split_from_id = ?1")?;
+    let split_ids = stmt.query_map([payment_id], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>, _>>()?;
+    drop(stmt);
+    // The credit split off must still be there, not already spent on another invoice
+    let split_credit: Option<(i64, String, i64)> = tx
+        .query_row(
+            "SELECT client_id, currency, SUM(amount) FROM payments WHERE split_from_id = ?1 AND invoice_id IS NULL
+             GROUP BY client_id, currency",
+            [payment_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    if let Some((client_id, currency, excess)) = split_credit {
+        let credit = client_balance(&tx, client_id, &currency)?.credit;
+        if credit < excess {
+            return Err(PaymentError::InsufficientCredit(credit));
+        }
+    }
+    for split_id in &split_ids {
+        tx.execute("DELETE FROM payments WHERE id = ?1", [split_id])?;
+        post_or_defer(&tx, Source::Payment, *split_id)?;
+    }
+    // A bank transaction confirmed as this payment is open again and its match back to a proposal
+    tx.execute(
+        "UPDATE bank_matches SET status = 'Proposed', payment_id = NULL, member_id = NULL, decided_at = NULL
+         WHERE payment_id = ?1",
+        [payment_id],
+    )?;
+    tx.execute("DELETE FROM payments WHERE id = ?1", [payment_id])?;
+    if let Some(invoice_id) = invoice_id {
+        sync_invoice_status(&tx, invoice_id, member_id)?;
+    }
+    post_or_defer(&tx, Source::Payment, payment_id)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 💳 Record a payment received
+#[tauri::command]
+pub fn record_payment(state: tauri::State<StateWrapper>, payment: Payment) -> Result<i64, PaymentError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    record(&mut db_conn, payment, member_id)
+}
+
+/// ↩️ Record a refund to a client
+#[tauri::command]
+pub fn record_refund(state: tauri::State<StateWrapper>, payment: Payment) -> Result<i64, PaymentError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    refund(&mut db_conn, payment, member_id)
+}
+
+/// 🔁 Use client credit to pay an invoice
+#[tauri::command]
+pub fn apply_client_credit(state: tauri::State<StateWrapper>, invoice_id: i64, amount: i64) -> Result<(), PaymentError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    apply_credit(&mut db_conn, invoice_id, amount, member_id)
+}
+
+/// 🗑️ Delete a payment recorded by mistake
+#[tauri::command]
+pub fn delete_payment(state: tauri::State<StateWrapper>, payment_id: i64) -> Result<(), PaymentError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    delete(&mut db_conn, payment_id, member_id)
+}
+
+/// 📋 List payments, optionally of one client or one invoice
+#[tauri::command]
+pub fn list_payments(
+    state: tauri::State<StateWrapper>,
+    client_id: Option<i64>,
+    invoice_id: Option<i64>,
+) -> Result<Vec<Payment>, PaymentError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, client_id, invoice_id, kind, amount, date, method, reference, currency FROM payments
+         WHERE (?1 IS NULL OR client_id = ?1) AND (?2 IS NULL OR invoice_id = ?2)
+         ORDER BY date DESC, id DESC"
+    )?;
+
+    let payments = stmt
+        .query_map(params![client_id, invoice_id], |row| {
+            Ok(Payment {
+                id: row.get(0)?,
+                client_id: row.get(1)?,
+                invoice_id: row.get(2)?,
+                kind: row.get(3)?,
+                amount: row.get(4)?,
+                date: row.get(5)?,
+                method: row.get(6)?,
+                reference: row.get(7)?,
+                currency: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(payments)
+}
+
+/// ⚖️ Outstanding, paid and credit of a client, in the base currency unless another is given
+#[tauri::command]
+pub fn get_client_balance(
+    state: tauri::State<StateWrapper>,
+    client_id: i64,
+    currency: Option<String>,
+) -> Result<ClientBalance, PaymentError> {
+    let db_conn = open_state_db(&state)?;
+    let currency = currency_or_base(&db_conn, currency.as_deref())?;
+    Ok(client_balance(&db_conn, client_id, &currency)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT);
+            CREATE TABLE clients (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL);
+            CREATE TABLE invoices (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                client_id INTEGER,
+                number TEXT,
+                status TEXT NOT NULL,
+                total INTEGER NOT NULL,
+                tax_total INTEGER NOT NULL DEFAULT 0,
+                currency TEXT NOT NULL DEFAULT 'CHF',
+                due_date TIMESTAMP NOT NULL,
+                issued_at TIMESTAMP
+            );
+            CREATE TABLE invoice_status_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                invoice_id INTEGER NOT NULL,
+                from_status TEXT,
+                to_status TEXT NOT NULL,
+                member_id INTEGER,
+                note TEXT,
+                changed_at TIMESTAMP
+            );
+            CREATE TABLE credit_notes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                invoice_id INTEGER NOT NULL,
+                total INTEGER NOT NULL
+            );
+            CREATE TABLE payments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                client_id INTEGER,
+                invoice_id INTEGER,
+                kind TEXT NOT NULL DEFAULT 'Payment',
+                amount INTEGER NOT NULL,
+                date TIMESTAMP NOT NULL,
+                method TEXT,
+                reference TEXT,
+                currency TEXT NOT NULL DEFAULT 'CHF',
+                split_from_id INTEGER REFERENCES payments(id)
+            );
+            CREATE TABLE bank_matches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                transaction_id INTEGER NOT NULL,
+                invoice_id INTEGER,
+                status TEXT NOT NULL DEFAULT 'Proposed',
+                payment_id INTEGER,
+                member_id INTEGER,
+                decided_at TIMESTAMP
+            );
+            CREATE TABLE exchange_rates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date DATE NOT NULL,
+                base_currency TEXT NOT NULL,
+                quote_currency TEXT NOT NULL,
+                rate INTEGER NOT NULL,
+                source TEXT
+            );
+            CREATE TABLE accounts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                code TEXT NOT NULL UNIQUE,
+                name TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                system_key TEXT UNIQUE
+            );
+            INSERT INTO accounts (code, name, kind, system_key) VALUES
+                ('1020', 'Bank', 'Asset', 'bank'),
+                ('1100', 'Accounts receivable', 'Asset', 'receivables'),
+                ('2030', 'Customer credit', 'Liability', 'customer_credit'),
+                ('6950', 'Exchange differences', 'Expense', 'exchange_differences');
+            CREATE TABLE journal_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date DATE NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                source_type TEXT NOT NULL,
+                source_id INTEGER NOT NULL,
+                currency TEXT NOT NULL,
+                reverses_id INTEGER
+            );
+            CREATE TABLE journal_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entry_id INTEGER NOT NULL,
+                account_id INTEGER NOT NULL,
+                debit INTEGER NOT NULL DEFAULT 0,
+                credit INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT INTO clients (name) VALUES ('Muster AG');",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// An issued invoice of client 1 over `total`, due in a month
+    fn invoice(conn: &Connection, total: i64) -> i64 {
+        let due_date = (chrono::Utc::now() + chrono::Duration::days(30)).naive_utc().format(DB_TIMESTAMP_FORMAT).to_string();
+        conn.execute(
+            "INSERT INTO invoices (client_id, number, status, total, due_date, issued_at)
+             VALUES (1, 'INV-' || (SELECT COUNT(*) + 1 FROM invoices), 'Issued', ?1, ?2, '2025-01-10 00:00:00')",
+            params![total, due_date],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn payment(invoice_id: Option<i64>, amount: i64) -> Payment {
+        Payment {
+            id: None,
+            client_id: Some(1),
+            invoice_id,
+            kind: String::new(),
+            amount,
+            date: "2025-01-20".to_string(),
+            method: Some("Bank transfer".to_string()),
+            reference: None,
+            currency: None,
+        }
+    }
+
+    fn status(conn: &Connection, invoice_id: i64) -> String {
+        conn.query_row("SELECT status FROM invoices WHERE id = ?1", [invoice_id], |row| row.get(0)).unwrap()
+    }
+
+    fn credit(conn: &Connection) -> i64 {
+        client_balance(conn, 1, "CHF").unwrap().credit
+    }
+
+    /// Debit less credit on the account with `system_key`
+    fn account_balance(conn: &Connection, system_key: &str) -> i64 {
+        conn.query_row(
+            "SELECT COALESCE(SUM(l.debit - l.credit), 0) FROM journal_lines l JOIN accounts a ON a.id = l.account_id
+             WHERE a.system_key = ?1",
+            [system_key],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn partial_payment_leaves_the_rest_open() {
+        let mut conn = connection();
+        let invoice_id = invoice(&conn, 10000);
+
+        record(&mut conn, payment(Some(invoice_id), 4000), None).unwrap();
+        assert_eq!(status(&conn, invoice_id), "PartiallyPaid");
+        assert_eq!(invoice_paid(&conn, invoice_id).unwrap(), 4000);
+        let balance = client_balance(&conn, 1, "CHF").unwrap();
+        assert_eq!((balance.invoiced, balance.paid, balance.outstanding, balance.credit), (10000, 4000, 6000, 0));
+        assert_eq!(account_balance(&conn, "bank"), 4000);
+
+        record(&mut conn, payment(Some(invoice_id), 6000), None).unwrap();
+        assert_eq!(status(&conn, invoice_id), "Paid");
+        assert_eq!(client_balance(&conn, 1, "CHF").unwrap().outstanding, 0);
+    }
+
+    #[test]
+    fn overpayment_becomes_client_credit() {
+        let mut conn = connection();
+        let invoice_id = invoice(&conn, 10000);
+
+        let payment_id = record(&mut conn, payment(Some(invoice_id), 12500), None).unwrap();
+        assert_eq!(status(&conn, invoice_id), "Paid");
+        assert_eq!(invoice_paid(&conn, invoice_id).unwrap(), 10000);
+        assert_eq!(credit(&conn), 2500);
+        let (excess, split_from_id): (i64, Option<i64>) = conn
+            .query_row("SELECT amount, split_from_id FROM payments WHERE invoice_id IS NULL", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((excess, split_from_id), (2500, Some(payment_id)));
+        assert_eq!(account_balance(&conn, "bank"), 12500);
+        assert_eq!(account_balance(&conn, "customer_credit"), -2500);
+        assert_eq!(account_balance(&conn, "receivables"), -10000);
+
+        // The credit pays the next invoice
+        let next_id = invoice(&conn, 2000);
+        assert!(matches!(
+            apply_credit(&mut conn, next_id, 3000, None),
+            Err(PaymentError::InsufficientCredit(2500))
+        ));
+        apply_credit(&mut conn, next_id, 2000, None).unwrap();
+        assert_eq!(status(&conn, next_id), "Paid");
+        assert_eq!(credit(&conn), 500);
+        assert_eq!(account_balance(&conn, "customer_credit"), -500);
+    }
+
+    #[test]
+    fn deleting_a_split_payment_takes_its_credit_back() {
+        let mut conn = connection();
+        let invoice_id = invoice(&conn, 10000);
+        let payment_id = record(&mut conn, payment(Some(invoice_id), 12500), None).unwrap();
+        let excess_id: i64 = conn
+            .query_row("SELECT id FROM payments WHERE split_from_id = ?1", [payment_id], |row| row.get(0))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO bank_matches (transaction_id, invoice_id, status, payment_id, decided_at)
+             VALUES (1, ?1, 'Confirmed', ?2, '2025-01-21 00:00:00')",
+            params![invoice_id, payment_id],
+        )
+        .unwrap();
+
+        assert!(matches!(
+            delete(&mut conn, excess_id, None),
+            Err(PaymentError::SplitExcess(id, from)) if id == excess_id && from == payment_id
+        ));
+        delete(&mut conn, payment_id, None).unwrap();
+
+        let left: i64 = conn.query_row("SELECT COUNT(*) FROM payments", [], |row| row.get(0)).unwrap();
+        assert_eq!(left, 0);
+        assert_eq!(status(&conn, invoice_id), "Issued");
+        assert_eq!(credit(&conn), 0);
+        let (match_status, match_payment): (String, Option<i64>) = conn
+            .query_row("SELECT status, payment_id FROM bank_matches", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((match_status.as_str(), match_payment), ("Proposed", None));
+        for system_key in ["bank", "receivables", "customer_credit"] {
+            assert_eq!(account_balance(&conn, system_key), 0, "{}", system_key);
+        }
+    }
+
+    #[test]
+    fn delete_refuses_when_the_split_credit_is_spent() {
+        let mut conn = connection();
+        let invoice_id = invoice(&conn, 10000);
+        let payment_id = record(&mut conn, payment(Some(invoice_id), 12500), None).unwrap();
+        let next_id = invoice(&conn, 2000);
+        apply_credit(&mut conn, next_id, 2000, None).unwrap();
+
+        assert!(matches!(delete(&mut conn, payment_id, None), Err(PaymentError::InsufficientCredit(500))));
+        let credit_move: i64 = conn
+            .query_row("SELECT id FROM payments WHERE kind = 'Credit' AND invoice_id = ?1", [next_id], |row| row.get(0))
+            .unwrap();
+        assert!(matches!(delete(&mut conn, credit_move, None), Err(PaymentError::CreditMove(_))));
+    }
+
+    #[test]
+    fn refunds_are_limited_to_what_was_paid_or_credited() {
+        let mut conn = connection();
+        let invoice_id = invoice(&conn, 10000);
+        record(&mut conn, payment(Some(invoice_id), 10000), None).unwrap();
+        record(&mut conn, payment(None, 1500), None).unwrap();
+
+        assert!(matches!(
+            refund(&mut conn, payment(Some(invoice_id), 10001), None),
+            Err(PaymentError::RefundTooLarge(10000))
+        ));
+        refund(&mut conn, payment(Some(invoice_id), 3000), None).unwrap();
+        assert_eq!(status(&conn, invoice_id), "PartiallyPaid");
+
+        assert!(matches!(refund(&mut conn, payment(None, 2000), None), Err(PaymentError::InsufficientCredit(1500))));
+        refund(&mut conn, payment(None, 1500), None).unwrap();
+        assert_eq!(credit(&conn), 0);
+        assert_eq!(account_balance(&conn, "bank"), 10000 + 1500 - 3000 - 1500);
+    }
+
+    #[test]
+    fn credit_notes_release_overpaid_money_and_void_unpaid_invoices() {
+        let mut conn = connection();
+        let invoice_id = invoice(&conn, 10000);
+        record(&mut conn, payment(Some(invoice_id), 6000), None).unwrap();
+        record(&mut conn, payment(Some(invoice_id), 4000), None).unwrap();
+
+        conn.execute("INSERT INTO credit_notes (invoice_id, total) VALUES (?1, -3000)", [invoice_id]).unwrap();
+        assert_eq!(release_excess_to_credit(&conn, invoice_id).unwrap(), 3000);
+        sync_invoice_status(&conn, invoice_id, None).unwrap();
+        assert_eq!(invoice_paid(&conn, invoice_id).unwrap(), 7000);
+        assert_eq!(credit(&conn), 3000);
+        assert_eq!(status(&conn, invoice_id), "Paid");
+        // The latest payment is released first
+        let released: Vec<(i64, i64)> = conn
+            .prepare("SELECT split_from_id, amount FROM payments WHERE kind = 'Credit' AND invoice_id IS NULL")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(released, vec![(2, 3000)]);
+
+        let unpaid_id = invoice(&conn, 5000);
+        conn.execute("INSERT INTO credit_notes (invoice_id, total) VALUES (?1, -5000)", [unpaid_id]).unwrap();
+        sync_invoice_status(&conn, unpaid_id, None).unwrap();
+        assert_eq!(status(&conn, unpaid_id), "Void");
+    }
+}
@@ -0,0 +1,787 @@
+//! Small PDF writer for business documents.
+//!
+//! Pages are A4 and positions are in points measured from the top-left corner, which is
+//! how layouts are usually thought about; the conversion to PDF's bottom-left origin
+//! happens here. Text is set in the bundled DejaVu Sans, embedded as a subset, so every
+//! document renders the same everywhere and any Unicode text can be printed.
+//!
+//! Documents can also be written as PDF/A-3b, with files such as e-invoice XML attached.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Timelike, Utc};
+use miniz_oxide::deflate::{compress_to_vec_zlib, CompressionLevel};
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo};
+use pdf_writer::{Content, Date, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use thiserror::Error;
+use ttf_parser::{Face, GlyphId};
+
+pub const A4_WIDTH: f32 = 595.28;
+pub const A4_HEIGHT: f32 = 841.89;
+
+const REGULAR_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+/// Output intent of PDF/A documents
+const SRGB_PROFILE: &[u8] = include_bytes!("../assets/icc/sRGB_v4.icc");
+
+const PRODUCER: &str = "buffmod";
+
+#[derive(Debug, Error)]
+pub enum PdfError {
+    #[error("[pdf.rs::font] Could not load font: {0}")]
+    FontError(String),
+
+    #[error("[pdf.rs::image] Unsupported or broken image: {0}")]
+    ImageError(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FontStyle {
+    Regular,
+    Bold,
+    /// The standard PDF Helvetica, for layouts that prescribe it (e.g. the Swiss QR-bill).
+    /// It is not embedded and only prints Latin-1 characters and `€`; PDF/A documents, which
+    /// must embed every font, use DejaVu Sans instead.
+    Helvetica,
+    HelveticaBold,
+}
+
+/// Resource name of the font used for a style
+fn font_resource(style: FontStyle) -> Name<'static> {
+    match style {
+        FontStyle::Regular => Name(b"F1"),
+        FontStyle::Bold => Name(b"F2"),
+        FontStyle::Helvetica => Name(b"F3"),
+        FontStyle::HelveticaBold => Name(b"F4"),
+    }
+}
+
+/// Encodes text for a standard font in WinAnsiEncoding, replacing what it cannot show with `?`
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x20..=0x7E | 0xA0..=0xFF => c as u8,
+            0x20AC => 0x80,
+            _ => b'?',
+        })
+        .collect()
+}
+
+struct Font {
+    data: &'static [u8],
+    face: Face<'static>,
+    base_name: &'static str,
+    /// Glyphs used so far, with the character each one stands for
+    used: BTreeMap<u16, char>,
+}
+
+impl Font {
+    fn load(data: &'static [u8], base_name: &'static str) -> Result<Font, PdfError> {
+        let face = Face::parse(data, 0).map_err(|e| PdfError::FontError(e.to_string()))?;
+        Ok(Font { data, face, base_name, used: BTreeMap::new() })
+    }
+
+    /// Converts font units to thousandths of an em, the unit of PDF glyph metrics
+    fn to_pdf_units(&self, value: f32) -> f32 {
+        value * 1000.0 / self.face.units_per_em() as f32
+    }
+
+    fn glyph(&self, c: char) -> u16 {
+        self.face.glyph_index(c).map(|g| g.0).unwrap_or(0)
+    }
+
+    fn advance(&self, glyph: u16) -> f32 {
+        self.to_pdf_units(self.face.glyph_hor_advance(GlyphId(glyph)).unwrap_or(0) as f32)
+    }
+}
+
+/// A raster image ready to be placed on a page
+struct Image {
+    width: u32,
+    height: u32,
+    filter: Filter,
+    data: Vec<u8>,
+    color_components: u8,
+    alpha: Option<Vec<u8>>,
+}
+
+/// Handle of an image added with [`PdfBuilder::add_image`]
+#[derive(Clone, Copy)]
+pub struct ImageId {
+    index: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A file embedded in the document and associated with it (PDF/A-3 `AF`)
+struct AttachedFile {
+    name: String,
+    mime_type: String,
+    description: String,
+    relationship: &'static str,
+    data: Vec<u8>,
+}
+
+pub struct PdfBuilder {
+    fonts: [Font; 2],
+    images: Vec<Image>,
+    pages: Vec<Content>,
+    title: Option<String>,
+    uses_standard_fonts: bool,
+    /// Extra XMP of a PDF/A document; `None` for plain PDF
+    pdf_a_metadata: Option<String>,
+    files: Vec<AttachedFile>,
+}
+
+impl PdfBuilder {
+    /// Starts a document with one empty page
+    pub fn new() -> Result<PdfBuilder, PdfError> {
+        Ok(PdfBuilder {
+            fonts: [Font::load(REGULAR_FONT, "DejaVuSans")?, Font::load(BOLD_FONT, "DejaVuSans-Bold")?],
+            images: Vec::new(),
+            pages: vec![Content::new()],
+            title: None,
+            uses_standard_fonts: false,
+            pdf_a_metadata: None,
+            files: Vec::new(),
+        })
+    }
+
+    /// Writes the document as PDF/A-3b. `extra_metadata` holds further `rdf:Description`
+    /// elements for the XMP metadata, e.g. the schema of an attached e-invoice.
+    pub fn set_pdf_a3(&mut self, extra_metadata: &str) {
+        self.pdf_a_metadata = Some(extra_metadata.to_string());
+    }
+
+    /// Embeds a file; `relationship` is its PDF/A-3 `AFRelationship`, e.g. `Alternative`
+    pub fn attach_file(&mut self, name: &str, mime_type: &str, description: &str, relationship: &'static str, data: Vec<u8>) {
+        self.files.push(AttachedFile {
+            name: name.to_string(),
+            mime_type: mime_type.to_string(),
+            description: description.to_string(),
+            relationship,
+            data,
+        });
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.title = Some(title.to_string());
+    }
+
+    pub fn add_page(&mut self) {
+        self.pages.push(Content::new());
+    }
+
+    fn font(&self, style: FontStyle) -> &Font {
+        match style {
+            FontStyle::Regular | FontStyle::Helvetica => &self.fonts[0],
+            FontStyle::Bold | FontStyle::HelveticaBold => &self.fonts[1],
+        }
+    }
+
+    fn page(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("a document always has a page")
+    }
+
+    /// Width of `text` in points. Helvetica is measured with DejaVu Sans, which runs slightly
+    /// wider, so text fitted with this never overflows.
+    pub fn text_width(&self, text: &str, size: f32, style: FontStyle) -> f32 {
+        let font = self.font(style);
+        text.chars().map(|c| font.advance(font.glyph(c))).sum::<f32>() * size / 1000.0
+    }
+
+    /// Writes one line of text with its baseline `top` points below the top of the page
+    pub fn text(&mut self, x: f32, top: f32, size: f32, style: FontStyle, text: &str) {
+        let style = match style {
+            FontStyle::Helvetica if self.pdf_a_metadata.is_some() => FontStyle::Regular,
+            FontStyle::HelveticaBold if self.pdf_a_metadata.is_some() => FontStyle::Bold,
+            _ => style,
+        };
+        let encoded = match style {
+            FontStyle::Regular | FontStyle::Bold => {
+                let font = &mut self.fonts[style as usize];
+                let mut encoded = Vec::with_capacity(text.len() * 2);
+                for c in text.chars() {
+                    let glyph = font.glyph(c);
+                    font.used.entry(glyph).or_insert(c);
+                    encoded.extend_from_slice(&glyph.to_be_bytes());
+                }
+                encoded
+            }
+            FontStyle::Helvetica | FontStyle::HelveticaBold => {
+                self.uses_standard_fonts = true;
+                win_ansi(text)
+            }
+        };
+
+        let content = self.page();
+        content.begin_text();
+        content.set_font(font_resource(style), size);
+        content.next_line(x, A4_HEIGHT - top);
+        content.show(Str(&encoded));
+        content.end_text();
+    }
+
+    /// Writes text so that it ends at `right`
+    pub fn text_right(&mut self, right: f32, top: f32, size: f32, style: FontStyle, text: &str) {
+        let width = self.text_width(text, size, style);
+        self.text(right - width, top, size, style, text);
+    }
+
+    pub fn line(&mut self, x1: f32, top1: f32, x2: f32, top2: f32, width: f32) {
+        let content = self.page();
+        content.set_line_width(width);
+        content.move_to(x1, A4_HEIGHT - top1);
+        content.line_to(x2, A4_HEIGHT - top2);
+        content.stroke();
+    }
+
+    /// Fills a rectangle whose top-left corner is at (`x`, `top`), in a shade of grey
+    /// from 0.0 (black) to 1.0 (white)
+    pub fn fill_rect(&mut self, x: f32, top: f32, width: f32, height: f32, gray: f32) {
+        let content = self.page();
+        content.set_fill_gray(gray);
+        content.rect(x, A4_HEIGHT - top - height, width, height);
+        content.fill_nonzero();
+        content.set_fill_gray(0.0);
+    }
+
+    /// Adds a PNG or JPEG image to the document; place it with [`PdfBuilder::image`]
+    pub fn add_image(&mut self, bytes: &[u8]) -> Result<ImageId, PdfError> {
+        let image = if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+            decode_png(bytes)?
+        } else if bytes.starts_with(&[0xFF, 0xD8]) {
+            read_jpeg(bytes)?
+        } else {
+            return Err(PdfError::ImageError("only PNG and JPEG are supported".to_string()));
+        };
+        let id = ImageId { index: self.images.len(), width: image.width, height: image.height };
+        self.images.push(image);
+        Ok(id)
+    }
+
+    /// Draws an image into the box whose top-left corner is at (`x`, `top`)
+    pub fn image(&mut self, image: ImageId, x: f32, top: f32, width: f32, height: f32) {
+        let name = format!("Im{}", image.index);
+        let content = self.page();
+        content.save_state();
+        content.transform([width, 0.0, 0.0, height, x, A4_HEIGHT - top - height]);
+        content.x_object(Name(name.as_bytes()));
+        content.restore_state();
+    }
+
+    /// Writes the document
+    pub fn finish(self) -> Result<Vec<u8>, PdfError> {
+        let mut pdf = Pdf::new();
+        let mut next_id = 1;
+        let mut alloc = || {
+            let id = Ref::new(next_id);
+            next_id += 1;
+            id
+        };
+
+        let catalog_id = alloc();
+        let page_tree_id = alloc();
+        let font_ids: Vec<Ref> = self.fonts.iter().map(|_| alloc()).collect();
+        let standard_font_ids = self.uses_standard_fonts.then(|| (alloc(), alloc()));
+        let image_ids: Vec<(Ref, Option<Ref>)> = self
+            .images
+            .iter()
+            .map(|image| (alloc(), image.alpha.as_ref().map(|_| alloc())))
+            .collect();
+        let page_ids: Vec<(Ref, Ref)> = self.pages.iter().map(|_| (alloc(), alloc())).collect();
+
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().map(|(page_id, _)| *page_id))
+            .count(page_ids.len() as i32);
+
+        for (content, (page_id, content_id)) in self.pages.into_iter().zip(&page_ids) {
+            let mut page = pdf.page(*page_id);
+            page.media_box(Rect::new(0.0, 0.0, A4_WIDTH, A4_HEIGHT));
+            page.parent(page_tree_id);
+            page.contents(*content_id);
+            let mut resources = page.resources();
+            let mut fonts = resources.fonts();
+            fonts.pair(font_resource(FontStyle::Regular), font_ids[0]);
+            fonts.pair(font_resource(FontStyle::Bold), font_ids[1]);
+            if let Some((helvetica_id, helvetica_bold_id)) = standard_font_ids {
+                fonts.pair(font_resource(FontStyle::Helvetica), helvetica_id);
+                fonts.pair(font_resource(FontStyle::HelveticaBold), helvetica_bold_id);
+            }
+            fonts.finish();
+            let mut x_objects = resources.x_objects();
+            for (index, (image_id, _)) in image_ids.iter().enumerate() {
+                let name = format!("Im{}", index);
+                x_objects.pair(Name(name.as_bytes()), *image_id);
+            }
+            x_objects.finish();
+            resources.finish();
+            page.finish();
+
+            let compressed = compress_to_vec_zlib(&content.finish(), CompressionLevel::DefaultLevel as u8);
+            pdf.stream(*content_id, &compressed).filter(Filter::FlateDecode);
+        }
+
+        for (font, font_id) in self.fonts.iter().zip(&font_ids) {
+            write_font(&mut pdf, font, *font_id, &mut alloc)?;
+        }
+        if let Some((helvetica_id, helvetica_bold_id)) = standard_font_ids {
+            for (id, name) in [(helvetica_id, Name(b"Helvetica")), (helvetica_bold_id, Name(b"Helvetica-Bold"))] {
+                pdf.type1_font(id).base_font(name).encoding_predefined(Name(b"WinAnsiEncoding"));
+            }
+        }
+
+        for (image, (image_id, mask_id)) in self.images.iter().zip(&image_ids) {
+            let mut xobject = pdf.image_xobject(*image_id, &image.data);
+            xobject.filter(image.filter);
+            xobject.width(image.width as i32);
+            xobject.height(image.height as i32);
+            if image.color_components == 1 {
+                xobject.color_space().device_gray();
+            } else {
+                xobject.color_space().device_rgb();
+            }
+            xobject.bits_per_component(8);
+            if let Some(mask_id) = mask_id {
+                xobject.s_mask(*mask_id);
+            }
+            xobject.finish();
+
+            if let (Some(mask_id), Some(alpha)) = (mask_id, &image.alpha) {
+                let mut mask = pdf.image_xobject(*mask_id, alpha);
+                mask.filter(Filter::FlateDecode);
+                mask.width(image.width as i32);
+                mask.height(image.height as i32);
+                mask.color_space().device_gray();
+                mask.bits_per_component(8);
+            }
+        }
+
+        let file_ids: Vec<(Ref, Ref)> = self.files.iter().map(|_| (alloc(), alloc())).collect();
+        let now = Utc::now();
+        let date = Date::new(now.year() as u16)
+            .month(now.month() as u8)
+            .day(now.day() as u8)
+            .hour(now.hour() as u8)
+            .minute(now.minute() as u8)
+            .second(now.second() as u8)
+            .utc_offset_hour(0);
+        for (file, (spec_id, stream_id)) in self.files.iter().zip(&file_ids) {
+            let compressed = compress_to_vec_zlib(&file.data, CompressionLevel::DefaultLevel as u8);
+            let mut stream = pdf.embedded_file(*stream_id, &compressed);
+            stream.filter(Filter::FlateDecode);
+            stream.subtype(Name(file.mime_type.as_bytes()));
+            stream.params().size(file.data.len() as i32).modification_date(date);
+            stream.finish();
+
+            let mut spec = pdf.file_spec(*spec_id);
+            spec.path(Str(file.name.as_bytes()));
+            spec.unic_file(TextStr(&file.name));
+            spec.description(TextStr(&file.description));
+            spec.insert(Name(b"EF")).dict().pair(Name(b"F"), *stream_id).pair(Name(b"UF"), *stream_id);
+            spec.pair(Name(b"AFRelationship"), Name(file.relationship.as_bytes()));
+        }
+
+        let info_needed = self.title.is_some() || self.pdf_a_metadata.is_some();
+        if info_needed {
+            let mut info = pdf.document_info(alloc());
+            if let Some(title) = &self.title {
+                info.title(TextStr(title));
+            }
+            info.producer(TextStr(PRODUCER)).creation_date(date).modified_date(date);
+        }
+
+        let pdf_a_ids = self.pdf_a_metadata.as_ref().map(|extra| {
+            let (metadata_id, profile_id) = (alloc(), alloc());
+            let xmp = xmp_metadata(self.title.as_deref().unwrap_or(""), &now.format("%Y-%m-%dT%H:%M:%S+00:00").to_string(), extra);
+            pdf.metadata(metadata_id, xmp.as_bytes()).pair(Name(b"Subtype"), Name(b"XML"));
+            let compressed = compress_to_vec_zlib(SRGB_PROFILE, CompressionLevel::DefaultLevel as u8);
+            pdf.icc_profile(profile_id, &compressed).n(3).range([0.0, 1.0, 0.0, 1.0, 0.0, 1.0]).filter(Filter::FlateDecode);
+            (metadata_id, profile_id)
+        });
+
+        let mut catalog = pdf.catalog(catalog_id);
+        catalog.pages(page_tree_id);
+        if let Some((metadata_id, profile_id)) = pdf_a_ids {
+            catalog.metadata(metadata_id);
+            let mut intents = catalog.output_intents();
+            intents
+                .push()
+                .pair(Name(b"Type"), Name(b"OutputIntent"))
+                .pair(Name(b"S"), Name(b"GTS_PDFA1"))
+                .pair(Name(b"OutputConditionIdentifier"), TextStr("sRGB IEC61966-2.1"))
+                .pair(Name(b"Info"), TextStr("sRGB IEC61966-2.1"))
+                .pair(Name(b"DestOutputProfile"), profile_id);
+            intents.finish();
+        }
+        if !file_ids.is_empty() {
+            let mut names = catalog.names();
+            let mut embedded = names.embedded_files();
+            let mut entries = embedded.names();
+            let mut sorted: Vec<(&AttachedFile, &(Ref, Ref))> = self.files.iter().zip(&file_ids).collect();
+            sorted.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+            for (file, (spec_id, _)) in sorted {
+                entries.insert(Str(file.name.as_bytes()), *spec_id);
+            }
+            entries.finish();
+            embedded.finish();
+            names.finish();
+            catalog.insert(Name(b"AF")).array().items(file_ids.iter().map(|(spec_id, _)| *spec_id));
+        }
+        catalog.finish();
+
+        if self.pdf_a_metadata.is_some() {
+            pdf.set_version(1, 7);
+            let id = uuid::Uuid::new_v4().as_bytes().to_vec();
+            pdf.set_file_id((id.clone(), id));
+        }
+        Ok(pdf.finish())
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// XMP metadata of a PDF/A-3b document, matching its document information dictionary
+fn xmp_metadata(title: &str, date: &str, extra: &str) -> String {
+    format!(
+        r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description rdf:about="" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/">
+<pdfaid:part>3</pdfaid:part>
+<pdfaid:conformance>B</pdfaid:conformance>
+</rdf:Description>
+<rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:title><rdf:Alt><rdf:li xml:lang="x-default">{title}</rdf:li></rdf:Alt></dc:title>
+</rdf:Description>
+<rdf:Description rdf:about="" xmlns:pdf="http://ns.adobe.com/pdf/1.3/">
+<pdf:Producer>{producer}</pdf:Producer>
+</rdf:Description>
+<rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/">
+<xmp:CreatorTool>{producer}</xmp:CreatorTool>
+<xmp:CreateDate>{date}</xmp:CreateDate>
+<xmp:ModifyDate>{date}</xmp:ModifyDate>
+</rdf:Description>
+{extra}
+</rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        bom = '\u{feff}',
+        title = xml_escape(title),
+        producer = PRODUCER,
+        date = date,
+        extra = extra,
+    )
+}
+
+/// Six capital letters naming a font subset, derived from its glyphs
+fn subset_tag(glyphs: &[u16]) -> String {
+    let mut hash: u32 = 2166136261;
+    for glyph in glyphs {
+        for byte in glyph.to_be_bytes() {
+            hash = (hash ^ byte as u32).wrapping_mul(16777619);
+        }
+    }
+    (0..6)
+        .map(|i| (b'A' + ((hash >> (i * 5)) % 26) as u8) as char)
+        .collect()
+}
+
+/// Embeds the used glyphs of a font as a CID-keyed TrueType font with Identity-H encoding
+fn write_font(pdf: &mut Pdf, font: &Font, font_id: Ref, alloc: &mut impl FnMut() -> Ref) -> Result<(), PdfError> {
+    let cid_font_id = alloc();
+    let descriptor_id = alloc();
+    let file_id = alloc();
+    let cmap_id = alloc();
+
+    let mut glyphs: Vec<u16> = font.used.keys().copied().collect();
+    if !glyphs.contains(&0) {
+        glyphs.insert(0, 0);
+    }
+    let base_font = format!("{}+{}", subset_tag(&glyphs), font.base_name);
+    let system_info = SystemInfo { registry: Str(b"Adobe"), ordering: Str(b"Identity"), supplement: 0 };
+
+    pdf.type0_font(font_id)
+        .base_font(Name(base_font.as_bytes()))
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_font_id)
+        .to_unicode(cmap_id);
+
+    let mut cid_font = pdf.cid_font(cid_font_id);
+    cid_font
+        .subtype(CidFontType::Type2)
+        .base_font(Name(base_font.as_bytes()))
+        .system_info(system_info)
+        .font_descriptor(descriptor_id)
+        .default_width(0.0)
+        .cid_to_gid_map_predefined(Name(b"Identity"));
+    let mut widths = cid_font.widths();
+    for &glyph in &glyphs {
+        widths.consecutive(glyph, [font.advance(glyph)]);
+    }
+    widths.finish();
+    cid_font.finish();
+
+    let bbox = font.face.global_bounding_box();
+    let mut flags = FontFlags::NON_SYMBOLIC;
+    if font.face.is_monospaced() {
+        flags |= FontFlags::FIXED_PITCH;
+    }
+    pdf.font_descriptor(descriptor_id)
+        .name(Name(base_font.as_bytes()))
+        .flags(flags)
+        .bbox(Rect::new(
+            font.to_pdf_units(bbox.x_min as f32),
+            font.to_pdf_units(bbox.y_min as f32),
+            font.to_pdf_units(bbox.x_max as f32),
+            font.to_pdf_units(bbox.y_max as f32),
+        ))
+        .italic_angle(0.0)
+        .ascent(font.to_pdf_units(font.face.ascender() as f32))
+        .descent(font.to_pdf_units(font.face.descender() as f32))
+        .cap_height(font.to_pdf_units(font.face.capital_height().unwrap_or(font.face.ascender()) as f32))
+        .stem_v(80.0)
+        .font_file2(file_id);
+
+    let subset = subsetter::subset(font.data, 0, subsetter::Profile::pdf(&glyphs))
+        .map_err(|e| PdfError::FontError(format!("{:?}", e)))?;
+    let compressed = compress_to_vec_zlib(&subset, CompressionLevel::DefaultLevel as u8);
+    pdf.stream(file_id, &compressed)
+        .filter(Filter::FlateDecode)
+        .pair(Name(b"Length1"), subset.len() as i32);
+
+    let mut cmap = pdf_writer::types::UnicodeCmap::new(Name(b"Custom"), system_info);
+    for (&glyph, &c) in &font.used {
+        cmap.pair(glyph, c);
+    }
+    pdf.cmap(cmap_id, &cmap.finish());
+    Ok(())
+}
+
+fn decode_png(bytes: &[u8]) -> Result<Image, PdfError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| PdfError::ImageError(e.to_string()))?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(|e| PdfError::ImageError(e.to_string()))?;
+    pixels.truncate(info.buffer_size());
+
+    let (components, has_alpha) = match info.color_type {
+        png::ColorType::Grayscale => (1, false),
+        png::ColorType::GrayscaleAlpha => (1, true),
+        png::ColorType::Rgb => (3, false),
+        png::ColorType::Rgba => (3, true),
+        png::ColorType::Indexed => return Err(PdfError::ImageError("indexed PNG was not expanded".to_string())),
+    };
+    let (color, alpha) = if has_alpha {
+        let stride = components + 1;
+        let color: Vec<u8> = pixels.chunks(stride).flat_map(|p| p[..components].to_vec()).collect();
+        let alpha: Vec<u8> = pixels.chunks(stride).map(|p| p[components]).collect();
+        (color, Some(alpha))
+    } else {
+        (pixels, None)
+    };
+
+    let level = CompressionLevel::DefaultLevel as u8;
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        filter: Filter::FlateDecode,
+        data: compress_to_vec_zlib(&color, level),
+        color_components: components as u8,
+        alpha: alpha.map(|a| compress_to_vec_zlib(&a, level)),
+    })
+}
+
+/// JPEG data is embedded as is; only the frame header is read for size and colour model
+fn read_jpeg(bytes: &[u8]) -> Result<Image, PdfError> {
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() {
+        if bytes[pos] != 0xFF {
+            break;
+        }
+        let marker = bytes[pos + 1];
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        // Start-of-frame markers, except DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) && pos + 9 < bytes.len() {
+            let height = u16::from_be_bytes([bytes[pos + 5], bytes[pos + 6]]) as u32;
+            let width = u16::from_be_bytes([bytes[pos + 7], bytes[pos + 8]]) as u32;
+            let components = bytes[pos + 9];
+            if components != 1 && components != 3 {
+                return Err(PdfError::ImageError("CMYK JPEGs are not supported".to_string()));
+            }
+            return Ok(Image {
+                width,
+                height,
+                filter: Filter::DctDecode,
+                data: bytes.to_vec(),
+                color_components: components,
+                alpha: None,
+            });
+        }
+        pos += 2 + length;
+    }
+    Err(PdfError::ImageError("no JPEG frame header".to_string()))
+}
+
+const MARGIN: f32 = 50.0;
+const BOTTOM_MARGIN: f32 = 60.0;
+const BODY_SIZE: f32 = 10.0;
+const NUMBER_COLUMN_WIDTH: f32 = 72.0;
+const LOGO_MAX_WIDTH: f32 = 160.0;
+const LOGO_MAX_HEIGHT: f32 = 60.0;
+
+/// Splits `text` into lines no wider than `width`, breaking words only when they do not fit alone
+pub fn wrap(builder: &PdfBuilder, text: &str, width: f32, size: f32, style: FontStyle) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+        if builder.text_width(&candidate, size, style) <= width {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            current.push(c);
+            if builder.text_width(&current, size, style) > width && current.chars().count() > 1 {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+            }
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Lays out simple line-based markup, starting new pages as needed:
+///
+/// - `# text` and `## text`: title and heading
+/// - `!text`: bold line, `>text` or `>!text`: right-aligned line
+/// - `| a | b | c |`: table row, the first column wraps and the others are right-aligned;
+///   `|! a | b |` is a bold header row, repeated at the top of each new page
+/// - `---`: horizontal rule, `[logo]`: the logo if there is one, `[page]`: page break
+/// - an empty line adds a little space; anything else is wrapped body text
+///
+/// Returns how far down the last page the text reaches.
+pub fn layout_markup(builder: &mut PdfBuilder, markup: &str, logo: Option<ImageId>) -> f32 {
+    let content_width = A4_WIDTH - 2.0 * MARGIN;
+    let mut top = MARGIN;
+    let mut table_header: Option<Vec<String>> = None;
+
+    // Moves to a new page when `height` does not fit; returns whether it did
+    let ensure_space = |builder: &mut PdfBuilder, top: &mut f32, height: f32| {
+        if *top + height > A4_HEIGHT - BOTTOM_MARGIN {
+            builder.add_page();
+            *top = MARGIN;
+            return true;
+        }
+        false
+    };
+
+    for raw in markup.lines() {
+        let line = raw.trim_end();
+        if line.is_empty() {
+            top += BODY_SIZE * 0.7;
+            continue;
+        }
+        if line == "---" {
+            ensure_space(builder, &mut top, 8.0);
+            top += 3.0;
+            builder.line(MARGIN, top, A4_WIDTH - MARGIN, top, 0.5);
+            top += 5.0;
+            continue;
+        }
+        if line == "[page]" {
+            builder.add_page();
+            top = MARGIN;
+            continue;
+        }
+        if line == "[logo]" {
+            if let Some(logo) = logo {
+                let scale = (LOGO_MAX_WIDTH / logo.width as f32).min(LOGO_MAX_HEIGHT / logo.height as f32);
+                let (width, height) = (logo.width as f32 * scale, logo.height as f32 * scale);
+                ensure_space(builder, &mut top, height);
+                builder.image(logo, MARGIN, top, width, height);
+                top += height + 6.0;
+            }
+            continue;
+        }
+        if let Some(row) = line.strip_prefix('|') {
+            let (header, row) = match row.strip_prefix('!') {
+                Some(row) => (true, row),
+                None => (false, row),
+            };
+            let cells: Vec<String> = row.trim_end_matches('|').split('|').map(|c| c.trim().to_string()).collect();
+            if header {
+                table_header = Some(cells.clone());
+            }
+            let first_width = content_width - NUMBER_COLUMN_WIDTH * (cells.len().saturating_sub(1)) as f32;
+            let style = if header { FontStyle::Bold } else { FontStyle::Regular };
+            let wrapped = wrap(builder, &cells[0], first_width - 6.0, BODY_SIZE, style);
+            let height = wrapped.len() as f32 * BODY_SIZE * 1.4 + if header { 4.0 } else { 0.0 };
+
+            if ensure_space(builder, &mut top, height) && !header {
+                if let Some(header_cells) = table_header.clone() {
+                    draw_row(builder, &mut top, &header_cells, true, content_width);
+                }
+            }
+            draw_row(builder, &mut top, &cells, header, content_width);
+            continue;
+        }
+
+        let (right, rest) = match line.strip_prefix('>') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (size, style, text) = if let Some(text) = rest.strip_prefix("## ") {
+            (12.0, FontStyle::Bold, text)
+        } else if let Some(text) = rest.strip_prefix("# ") {
+            (18.0, FontStyle::Bold, text)
+        } else if let Some(text) = rest.strip_prefix('!') {
+            (BODY_SIZE, FontStyle::Bold, text)
+        } else {
+            (BODY_SIZE, FontStyle::Regular, rest)
+        };
+        if size > BODY_SIZE {
+            top += size * 0.4;
+        }
+        for wrapped in wrap(builder, text, content_width, size, style) {
+            ensure_space(builder, &mut top, size * 1.4);
+            top += size;
+            if right {
+                builder.text_right(A4_WIDTH - MARGIN, top, size, style, &wrapped);
+            } else {
+                builder.text(MARGIN, top, size, style, &wrapped);
+            }
+            top += size * 0.4;
+        }
+    }
+    top
+}
+
+fn draw_row(builder: &mut PdfBuilder, top: &mut f32, cells: &[String], header: bool, content_width: f32) {
+    let style = if header { FontStyle::Bold } else { FontStyle::Regular };
+    let first_width = content_width - NUMBER_COLUMN_WIDTH * (cells.len().saturating_sub(1)) as f32;
+    let wrapped = wrap(builder, &cells[0], first_width - 6.0, BODY_SIZE, style);
+
+    let first_baseline = *top + BODY_SIZE;
+    for (index, text) in wrapped.iter().enumerate() {
+        builder.text(MARGIN, first_baseline + index as f32 * BODY_SIZE * 1.4, BODY_SIZE, style, text);
+    }
+    for (index, cell) in cells.iter().enumerate().skip(1) {
+        let right = MARGIN + first_width + NUMBER_COLUMN_WIDTH * index as f32;
+        builder.text_right(right, first_baseline, BODY_SIZE, style, cell);
+    }
+    *top += wrapped.len() as f32 * BODY_SIZE * 1.4;
+    if header {
+        builder.line(MARGIN, *top - 2.0, A4_WIDTH - MARGIN, *top - 2.0, 0.5);
+        *top += 4.0;
+    }
+}
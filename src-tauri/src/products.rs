@@ -0,0 +1,344 @@
+//! Catalog of products and services billed on invoice and quote lines.
+//!
+//! A product carries the name, unit, price and default tax rate a line starts from; the line
+//! keeps a copy of those values and a reference to the product, so editing or deactivating
+//! the product later does not change documents already written. A client can get its own
+//! price for a product, which replaces the catalog price on that client's documents.
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::currencies::{convert, currency_or_base, CurrencyError};
+use crate::db_api::{open_state_db, DbApiError};
+use crate::StateWrapper;
+
+#[derive(Debug, Error)]
+pub enum ProductError {
+    #[error("[products.rs::product_not_found] Product {0} does not exist.")]
+    ProductNotFound(i64),
+
+    #[error("[products.rs::client_not_found] Client {0} does not exist.")]
+    ClientNotFound(i64),
+
+    #[error("[products.rs::inactive] Product {0:?} is no longer active.")]
+    Inactive(String),
+
+    #[error("[products.rs::duplicate_sku] Another product already uses SKU {0:?}.")]
+    DuplicateSku(String),
+
+    #[error("[products.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[products.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[products.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[products.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for ProductError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 📦 Product Struct. `unit_price` is in minor units of `currency`, the rate in basis points.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Product {
+    pub id: Option<i64>,
+    /// Optional article number, unique when set
+    #[serde(default)]
+    pub sku: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Free text such as `h`, `day` or `pcs`
+    #[serde(default)]
+    pub unit: String,
+    pub unit_price: i64,
+    /// ISO 4217 code; defaults to `settings.base_currency`
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub tax_rate_bp: i64,
+    /// Inactive products stay on existing lines but cannot be added to new ones
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// 💲 Price a client pays for a product instead of the catalog price
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientPrice {
+    pub product_id: i64,
+    pub client_id: i64,
+    /// Name of the client, filled in when reading
+    #[serde(default)]
+    pub client_name: Option<String>,
+    /// Minor units of the product currency
+    pub unit_price: i64,
+}
+
+/// Values a new document line takes from a product
+#[derive(Debug, PartialEq)]
+pub struct LineDefaults {
+    pub description: String,
+    /// In the currency of the document
+    pub unit_price: i64,
+    pub tax_rate_bp: i64,
+}
+
+const PRODUCT_COLUMNS: &str = "id, sku, name, description, unit, unit_price, currency, tax_rate_bp, active";
+
+fn product_from_row(row: &rusqlite::Row) -> rusqlite::Result<Product> {
+    Ok(Product {
+        id: row.get(0)?,
+        sku: row.get(1)?,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        unit: row.get(4)?,
+        unit_price: row.get(5)?,
+        currency: row.get(6)?,
+        tax_rate_bp: row.get(7)?,
+        active: row.get(8)?,
+    })
+}
+
+pub fn get(conn: &Connection, product_id: i64) -> Result<Product, ProductError> {
+    conn.query_row(&format!("SELECT {} FROM products WHERE id = ?1", PRODUCT_COLUMNS), [product_id], product_from_row)
+        .optional()?
+        .ok_or(ProductError::ProductNotFound(product_id))
+}
+
+/// Products by name; inactive ones only with `include_inactive`
+pub fn list(conn: &Connection, include_inactive: bool) -> Result<Vec<Product>, ProductError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM products WHERE active = 1 OR ?1 ORDER BY name COLLATE NOCASE, id",
+        PRODUCT_COLUMNS
+    ))?;
+    let products = stmt.query_map([include_inactive], product_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(products)
+}
+
+/// Checks a product and returns it with trimmed text, its SKU and currency
+fn validated(conn: &Connection, product: &Product) -> Result<(Product, Option<String>, String), ProductError> {
+    let name = product.name.trim();
+    if name.is_empty() {
+        return Err(ProductError::InvalidValue("name", product.name.clone()));
+    }
+    if product.unit_price < 0 {
+        return Err(ProductError::InvalidValue("unit_price", product.unit_price.to_string()));
+    }
+    if product.tax_rate_bp < 0 {
+        return Err(ProductError::InvalidValue("tax_rate_bp", product.tax_rate_bp.to_string()));
+    }
+    let sku = product.sku.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+    if let Some(sku) = &sku {
+        let taken: Option<i64> =
+            conn.query_row("SELECT id FROM products WHERE sku = ?1", [sku], |row| row.get(0)).optional()?;
+        if taken.is_some_and(|id| Some(id) != product.id) {
+            return Err(ProductError::DuplicateSku(sku.clone()));
+        }
+    }
+    let currency = currency_or_base(conn, product.currency.as_deref())?;
+    let product = Product {
+        name: name.to_string(),
+        description: product.description.trim().to_string(),
+        unit: product.unit.trim().to_string(),
+        ..product.clone()
+    };
+    Ok((product, sku, currency))
+}
+
+pub fn create(conn: &Connection, product: &Product) -> Result<i64, ProductError> {
+    let (product, sku, currency) = validated(conn, product)?;
+    conn.execute(
+        "INSERT INTO products (sku, name, description, unit, unit_price, currency, tax_rate_bp, active)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            sku,
+            product.name,
+            product.description,
+            product.unit,
+            product.unit_price,
+            currency,
+            product.tax_rate_bp,
+            product.active
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Updates a product; lines that already use it keep their values
+pub fn update(conn: &Connection, product: &Product) -> Result<(), ProductError> {
+    let product_id = product.id.ok_or(ProductError::InvalidValue("id", "missing".to_string()))?;
+    let (product, sku, currency) = validated(conn, product)?;
+    let updated = conn.execute(
+        "UPDATE products SET sku = ?1, name = ?2, description = ?3, unit = ?4, unit_price = ?5, currency = ?6,
+                tax_rate_bp = ?7, active = ?8
+         WHERE id = ?9",
+        params![
+            sku,
+            product.name,
+            product.description,
+            product.unit,
+            product.unit_price,
+            currency,
+            product.tax_rate_bp,
+            product.active,
+            product_id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(ProductError::ProductNotFound(product_id));
+    }
+    Ok(())
+}
+
+/// Deletes a product and its client prices; lines that used it keep their values
+pub fn delete(conn: &Connection, product_id: i64) -> Result<(), ProductError> {
+    let deleted = conn.execute("DELETE FROM products WHERE id = ?1", [product_id])?;
+    if deleted == 0 {
+        return Err(ProductError::ProductNotFound(product_id));
+    }
+    Ok(())
+}
+
+pub fn client_prices(conn: &Connection, product_id: i64) -> Result<Vec<ClientPrice>, ProductError> {
+    let mut stmt = conn.prepare(
+        "SELECT p.product_id, p.client_id, c.name, p.unit_price FROM product_client_prices p
+         JOIN clients c ON c.id = p.client_id
+         WHERE p.product_id = ?1 ORDER BY c.name COLLATE NOCASE",
+    )?;
+    let prices = stmt
+        .query_map([product_id], |row| {
+            Ok(ClientPrice { product_id: row.get(0)?, client_id: row.get(1)?, client_name: row.get(2)?, unit_price: row.get(3)? })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(prices)
+}
+
+/// Sets the price a client pays for a product, or goes back to the catalog price with `None`
+pub fn set_client_price(conn: &Connection, product_id: i64, client_id: i64, unit_price: Option<i64>) -> Result<(), ProductError> {
+    get(conn, product_id)?;
+    let client: Option<i64> = conn.query_row("SELECT id FROM clients WHERE id = ?1", [client_id], |row| row.get(0)).optional()?;
+    if client.is_none() {
+        return Err(ProductError::ClientNotFound(client_id));
+    }
+    match unit_price {
+        Some(price) if price < 0 => return Err(ProductError::InvalidValue("unit_price", price.to_string())),
+        Some(price) => conn.execute(
+            "INSERT INTO product_client_prices (product_id, client_id, unit_price) VALUES (?1, ?2, ?3)
+             ON CONFLICT (product_id, client_id) DO UPDATE SET unit_price = excluded.unit_price",
+            params![product_id, client_id, price],
+        )?,
+        None => conn.execute(
+            "DELETE FROM product_client_prices WHERE product_id = ?1 AND client_id = ?2",
+            params![product_id, client_id],
+        )?,
+    };
+    Ok(())
+}
+
+/// Description, price and tax rate of a new line for `product_id` on a document of
+/// `client_id` in `currency`. The client's own price wins over the catalog price; a price in
+/// another currency is converted at the rate of today's UTC date, the date drafts are issued on.
+pub fn line_defaults(
+    conn: &Connection,
+    product_id: i64,
+    client_id: Option<i64>,
+    currency: &str,
+) -> Result<LineDefaults, ProductError> {
+    let product = get(conn, product_id)?;
+    if !product.active {
+        return Err(ProductError::Inactive(product.name));
+    }
+    let client_price: Option<i64> = match client_id {
+        Some(client_id) => conn
+            .query_row(
+                "SELECT unit_price FROM product_client_prices WHERE product_id = ?1 AND client_id = ?2",
+                params![product_id, client_id],
+                |row| row.get(0),
+            )
+            .optional()?,
+        None => None,
+    };
+    let price = client_price.unwrap_or(product.unit_price);
+    let product_currency = currency_or_base(conn, product.currency.as_deref())?;
+    let unit_price = if product_currency == currency {
+        price
+    } else {
+        convert(conn, price, &product_currency, currency, Utc::now().date_naive())?
+    };
+    let description = if product.description.is_empty() {
+        product.name
+    } else {
+        format!("{}\n{}", product.name, product.description)
+    };
+    Ok(LineDefaults { description, unit_price, tax_rate_bp: product.tax_rate_bp })
+}
+
+/// 📦 List the products of the catalog
+#[tauri::command]
+pub fn list_products(state: tauri::State<StateWrapper>, include_inactive: Option<bool>) -> Result<Vec<Product>, ProductError> {
+    let db_conn = open_state_db(&state)?;
+    list(&db_conn, include_inactive.unwrap_or(false))
+}
+
+/// 🔍 Get a product by id
+#[tauri::command]
+pub fn get_product(state: tauri::State<StateWrapper>, product_id: i64) -> Result<Product, ProductError> {
+    let db_conn = open_state_db(&state)?;
+    get(&db_conn, product_id)
+}
+
+/// ➕ Add a product to the catalog, returns its id
+#[tauri::command]
+pub fn create_product(state: tauri::State<StateWrapper>, product: Product) -> Result<i64, ProductError> {
+    let db_conn = open_state_db(&state)?;
+    create(&db_conn, &product)
+}
+
+/// ✏️ Update a product
+#[tauri::command]
+pub fn update_product(state: tauri::State<StateWrapper>, product: Product) -> Result<(), ProductError> {
+    let db_conn = open_state_db(&state)?;
+    update(&db_conn, &product)
+}
+
+/// 🗑️ Delete a product; deactivate it instead to keep it listed with old documents
+#[tauri::command]
+pub fn delete_product(state: tauri::State<StateWrapper>, product_id: i64) -> Result<(), ProductError> {
+    let db_conn = open_state_db(&state)?;
+    delete(&db_conn, product_id)
+}
+
+/// 💲 List the client prices of a product
+#[tauri::command]
+pub fn list_product_client_prices(state: tauri::State<StateWrapper>, product_id: i64) -> Result<Vec<ClientPrice>, ProductError> {
+    let db_conn = open_state_db(&state)?;
+    client_prices(&db_conn, product_id)
+}
+
+/// 💲 Set a client's price for a product; `None` removes it
+#[tauri::command]
+pub fn set_product_client_price(
+    state: tauri::State<StateWrapper>,
+    product_id: i64,
+    client_id: i64,
+    unit_price: Option<i64>,
+) -> Result<(), ProductError> {
+    let db_conn = open_state_db(&state)?;
+    set_client_price(&db_conn, product_id, client_id, unit_price)
+}
@@ -0,0 +1,555 @@
+//! Swiss QR-bill payment part, after the Swiss Implementation Guidelines for the QR-bill.
+//!
+//! The payment part is the 210 × 105 mm slip at the bottom of the invoice: a receipt on the
+//! left and the payment part with the QR code on the right. The QR code carries the `SPC`
+//! payload with the creditor account, amount, debtor and payment reference. A QR-IBAN takes
+//! a 27-digit QR reference (QRR), any other IBAN an ISO 11649 creditor reference (SCOR);
+//! both are derived from the invoice number.
+
+use qrcodegen::{QrCode, QrCodeEcc, QrSegment, Version};
+use rusqlite::{Connection, OptionalExtension};
+use thiserror::Error;
+
+use crate::money::format_minor;
+use crate::pdf::{wrap, FontStyle, PdfBuilder, A4_WIDTH};
+use crate::settings::{read_setting, text_setting};
+
+/// Height of the payment part: 105 mm
+pub const QR_BILL_HEIGHT: f32 = 297.64;
+
+/// Heading and text font sizes of the receipt and the payment part
+const RECEIPT: (f32, f32) = (6.0, 8.0);
+const PAYMENT_PART: (f32, f32) = (8.0, 10.0);
+
+/// Largest amount a QR-bill accepts, in minor units
+const MAX_AMOUNT: i64 = 99_999_999_999;
+
+/// CHF and EUR, the only QR-bill currencies, both have two decimals
+const AMOUNT_DIGITS: u32 = 2;
+
+#[derive(Debug, Error)]
+pub enum QrBillError {
+    #[error("[qrbill.rs::invalid_iban] {0:?} is not a valid Swiss or Liechtenstein IBAN.")]
+    InvalidIban(String),
+
+    #[error("[qrbill.rs::invalid_reference] {0:?} is not a valid payment reference.")]
+    InvalidReference(String),
+
+    #[error("[qrbill.rs::no_reference] No payment reference can be derived from invoice number {0:?}.")]
+    NoReference(String),
+
+    #[error("[qrbill.rs::currency] QR-bills can only be issued in CHF or EUR, not {0}.")]
+    UnsupportedCurrency(String),
+
+    #[error("[qrbill.rs::amount] {0} is outside the amounts a QR-bill accepts.")]
+    InvalidAmount(String),
+
+    #[error("[qrbill.rs::address] The {0} address cannot be used on a QR-bill: {1}")]
+    InvalidAddress(&'static str, String),
+
+    #[error("[qrbill.rs::data_too_long] The QR-bill data does not fit into a QR code.")]
+    DataTooLong,
+
+    #[error("[qrbill.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+fn mm(value: f32) -> f32 {
+    value * 72.0 / 25.4
+}
+
+/// Remainder modulo 97 of a string whose letters count as 10 (A) to 35 (Z), as in ISO 7064
+fn mod97(value: &str) -> Option<u32> {
+    let mut remainder = 0;
+    for c in value.chars() {
+        let digit = c.to_digit(36)?;
+        remainder = if digit < 10 { (remainder * 10 + digit) % 97 } else { (remainder * 100 + digit) % 97 };
+    }
+    Some(remainder)
+}
+
+/// Check digit of the recursive modulo 10 method used by QR references
+fn mod10_recursive(digits: &str) -> Option<u32> {
+    const TABLE: [u32; 10] = [0, 9, 4, 6, 8, 2, 7, 1, 3, 5];
+    let mut carry = 0;
+    for c in digits.chars() {
+        carry = TABLE[((carry + c.to_digit(10)?) % 10) as usize];
+    }
+    Some((10 - carry) % 10)
+}
+
+/// Removes spaces and checks length, country and check digits of a CH or LI IBAN
+pub fn validate_iban(value: &str) -> Result<String, QrBillError> {
+    let iban: String = value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+    let valid = iban.len() == 21
+        && (iban.starts_with("CH") || iban.starts_with("LI"))
+        && iban.chars().all(|c| c.is_ascii_alphanumeric())
+        && mod97(&format!("{}{}", &iban[4..], &iban[..4])) == Some(1);
+    if !valid {
+        return Err(QrBillError::InvalidIban(value.to_string()));
+    }
+    Ok(iban)
+}
+
+/// QR-IBANs have an institution id from 30000 to 31999 and only accept QR references
+pub fn is_qr_iban(iban: &str) -> bool {
+    iban.get(4..9)
+        .and_then(|iid| iid.parse::<u32>().ok())
+        .is_some_and(|iid| (30000..=31999).contains(&iid))
+}
+
+/// 27-digit QR reference made of the digits of an invoice number and a check digit
+pub fn qr_reference(invoice_number: &str) -> Result<String, QrBillError> {
+    let digits: String = invoice_number.chars().filter(char::is_ascii_digit).collect();
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() || digits.len() > 26 {
+        return Err(QrBillError::NoReference(invoice_number.to_string()));
+    }
+    let body = format!("{:0>26}", digits);
+    let check = mod10_recursive(&body).ok_or_else(|| QrBillError::NoReference(invoice_number.to_string()))?;
+    Ok(format!("{}{}", body, check))
+}
+
+pub fn validate_qr_reference(reference: &str) -> Result<(), QrBillError> {
+    let valid = reference.len() == 27
+        && reference.chars().all(|c| c.is_ascii_digit())
+        && reference.chars().any(|c| c != '0')
+        && mod10_recursive(&reference[..26]) == reference[26..].parse().ok();
+    if !valid {
+        return Err(QrBillError::InvalidReference(reference.to_string()));
+    }
+    Ok(())
+}
+
+/// ISO 11649 creditor reference (`RF..`) made of the letters and digits of an invoice number
+pub fn creditor_reference(invoice_number: &str) -> Result<String, QrBillError> {
+    let body: String = invoice_number.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_uppercase();
+    if body.is_empty() || body.len() > 21 {
+        return Err(QrBillError::NoReference(invoice_number.to_string()));
+    }
+    let remainder = mod97(&format!("{}RF00", body)).ok_or_else(|| QrBillError::NoReference(invoice_number.to_string()))?;
+    Ok(format!("RF{:02}{}", 98 - remainder, body))
+}
+
+pub fn validate_creditor_reference(reference: &str) -> Result<(), QrBillError> {
+    let valid = (5..=25).contains(&reference.len())
+        && reference.starts_with("RF")
+        && reference.chars().all(|c| c.is_ascii_alphanumeric())
+        && mod97(&format!("{}{}", &reference[4..], &reference[..4])) == Some(1);
+    if !valid {
+        return Err(QrBillError::InvalidReference(reference.to_string()));
+    }
+    Ok(())
+}
+
+/// Structured address (type `S`) of the creditor or the debtor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Address {
+    pub name: String,
+    pub street: String,
+    pub postal_code: String,
+    pub town: String,
+    pub country: String,
+}
+
+impl Address {
+    /// Reads an address written as lines: the street first and `postal code + town` last,
+    /// optionally followed by a two-letter country code (CH when there is none)
+    pub fn parse(name: &str, text: &str) -> Result<Address, String> {
+        let mut lines: Vec<&str> = text.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        let country = match lines.last() {
+            Some(last) if last.len() == 2 && last.chars().all(|c| c.is_ascii_uppercase()) => {
+                let country = last.to_string();
+                lines.pop();
+                country
+            }
+            _ => "CH".to_string(),
+        };
+        let last = lines.pop().ok_or("the postal code and town are missing")?;
+        let (postal_code, town) = last
+            .split_once(char::is_whitespace)
+            .ok_or("the last line must be the postal code and town")?;
+        // `CH-8001` is an old way of writing the postal code
+        let postal_code = postal_code.rsplit('-').next().unwrap_or(postal_code);
+
+        let address = Address {
+            name: name.trim().to_string(),
+            street: lines.first().map(|s| s.to_string()).unwrap_or_default(),
+            postal_code: postal_code.to_string(),
+            town: town.trim().to_string(),
+            country,
+        };
+        if address.name.is_empty() || address.name.chars().count() > 70 {
+            return Err("the name must have 1 to 70 characters".to_string());
+        }
+        if address.street.chars().count() > 70 || address.postal_code.chars().count() > 16 || address.town.chars().count() > 35 {
+            return Err("the street, postal code or town is too long".to_string());
+        }
+        Ok(address)
+    }
+
+    fn payload_fields(&self) -> [&str; 7] {
+        ["S", &self.name, &self.street, "", &self.postal_code, &self.town, &self.country]
+    }
+
+    fn display_lines(&self) -> Vec<String> {
+        let mut lines = vec![self.name.clone()];
+        if !self.street.is_empty() {
+            lines.push(self.street.clone());
+        }
+        let town = format!("{} {}", self.postal_code, self.town);
+        lines.push(if self.country == "CH" { town } else { format!("{}-{}", self.country, town) });
+        lines
+    }
+}
+
+/// The data of one QR-bill
+#[derive(Clone, Debug)]
+pub struct QrBill {
+    pub iban: String,
+    pub creditor: Address,
+    pub currency: String,
+    /// Minor units; `None` leaves the amount for the payer to fill in
+    pub amount: Option<i64>,
+    pub debtor: Option<Address>,
+    /// `QRR`, `SCOR` or `NON`
+    pub reference_type: &'static str,
+    pub reference: String,
+    pub message: Option<String>,
+}
+
+impl QrBill {
+    /// Validates the account and derives the reference from the invoice number
+    pub fn new(
+        iban: &str,
+        creditor: Address,
+        currency: &str,
+        amount: Option<i64>,
+        debtor: Option<Address>,
+        invoice_number: &str,
+    ) -> Result<QrBill, QrBillError> {
+        let iban = validate_iban(iban)?;
+        if currency != "CHF" && currency != "EUR" {
+            return Err(QrBillError::UnsupportedCurrency(currency.to_string()));
+        }
+        if let Some(amount) = amount.filter(|a| !(1..=MAX_AMOUNT).contains(a)) {
+            return Err(QrBillError::InvalidAmount(format_minor(amount, AMOUNT_DIGITS)));
+        }
+
+        let (reference_type, reference) = if is_qr_iban(&iban) {
+            let reference = qr_reference(invoice_number)?;
+            validate_qr_reference(&reference)?;
+            ("QRR", reference)
+        } else {
+            let reference = creditor_reference(invoice_number)?;
+            validate_creditor_reference(&reference)?;
+            ("SCOR", reference)
+        };
+        Ok(QrBill {
+            iban,
+            creditor,
+            currency: currency.to_string(),
+            amount,
+            debtor,
+            reference_type,
+            reference,
+            message: Some(format!("Invoice {}", invoice_number)),
+        })
+    }
+
+    /// The `SPC` text encoded in the QR code
+    pub fn payload(&self) -> String {
+        let mut fields: Vec<&str> = vec!["SPC", "0200", "1", &self.iban];
+        fields.extend(self.creditor.payload_fields());
+        // Ultimate creditor, reserved for future use
+        fields.extend([""; 7]);
+        let amount = self.amount.map(|a| format_minor(a, AMOUNT_DIGITS)).unwrap_or_default();
+        fields.push(&amount);
+        fields.push(&self.currency);
+        match &self.debtor {
+            Some(debtor) => fields.extend(debtor.payload_fields()),
+            None => fields.extend([""; 7]),
+        }
+        fields.push(self.reference_type);
+        fields.push(&self.reference);
+        fields.push(self.message.as_deref().unwrap_or(""));
+        fields.push("EPD");
+        fields.join("\r\n")
+    }
+
+    fn display_reference(&self) -> String {
+        match self.reference_type {
+            // QR references are grouped in fives from the right
+            "QRR" => {
+                let (head, tail) = self.reference.split_at(2);
+                let groups: Vec<&str> = tail.as_bytes().chunks(5).map(|c| std::str::from_utf8(c).unwrap_or("")).collect();
+                format!("{} {}", head, groups.join(" "))
+            }
+            _ => group_by_four(&self.reference),
+        }
+    }
+
+    /// Draws the payment part on the current page, starting `top` points below the page top
+    pub fn draw(&self, builder: &mut PdfBuilder, top: f32) -> Result<(), QrBillError> {
+        let qr = QrCode::encode_segments_advanced(
+            &[QrSegment::make_bytes(self.payload().as_bytes())],
+            QrCodeEcc::Medium,
+            Version::MIN,
+            Version::new(25),
+            None,
+            false,
+        )
+        .map_err(|_| QrBillError::DataTooLong)?;
+
+        builder.line(0.0, top, A4_WIDTH, top, 0.5);
+        builder.line(mm(62.0), top, mm(62.0), top + QR_BILL_HEIGHT, 0.5);
+
+        let amount = self.amount.map(format_amount);
+        let account = [group_by_four(&self.iban)].into_iter().chain(self.creditor.display_lines()).collect::<Vec<_>>();
+        let reference = (self.reference_type != "NON").then(|| self.display_reference());
+
+        // Receipt
+        let x = mm(5.0);
+        let width = mm(52.0);
+        builder.text(x, top + mm(5.0) + 11.0, 11.0, FontStyle::HelveticaBold, "Receipt");
+        let mut y = top + mm(12.0);
+        y = section(builder, x, y, width, RECEIPT, "Account / Payable to", &account);
+        if let Some(reference) = &reference {
+            y = section(builder, x, y, width, RECEIPT, "Reference", std::slice::from_ref(reference));
+        }
+        match &self.debtor {
+            Some(debtor) => {
+                section(builder, x, y, width, RECEIPT, "Payable by", &debtor.display_lines());
+            }
+            None => {
+                section(builder, x, y, width, RECEIPT, "Payable by (name/address)", &[]);
+                corner_marks(builder, x, y + 9.0, mm(52.0), mm(20.0));
+            }
+        }
+        amount_section(builder, x, x + mm(12.0), top + mm(68.0), RECEIPT, &self.currency, amount.as_deref());
+        if amount.is_none() {
+            corner_marks(builder, x + mm(22.0), top + mm(68.0) + 9.0, mm(30.0), mm(10.0));
+        }
+        builder.text_right(mm(57.0), top + mm(82.0) + 6.0, 6.0, FontStyle::HelveticaBold, "Acceptance point");
+
+        // Payment part
+        let x = mm(67.0);
+        builder.text(x, top + mm(5.0) + 11.0, 11.0, FontStyle::HelveticaBold, "Payment part");
+        draw_qr_code(builder, &qr, x, top + mm(17.0), mm(46.0));
+        amount_section(builder, x, x + mm(14.0), top + mm(68.0), PAYMENT_PART, &self.currency, amount.as_deref());
+        if amount.is_none() {
+            corner_marks(builder, x + mm(11.0), top + mm(68.0) + 14.0, mm(40.0), mm(15.0));
+        }
+
+        let x = mm(118.0);
+        let width = mm(87.0);
+        let mut y = top + mm(5.0);
+        y = section(builder, x, y, width, PAYMENT_PART, "Account / Payable to", &account);
+        if let Some(reference) = &reference {
+            y = section(builder, x, y, width, PAYMENT_PART, "Reference", std::slice::from_ref(reference));
+        }
+        if let Some(message) = &self.message {
+            y = section(builder, x, y, width, PAYMENT_PART, "Additional information", std::slice::from_ref(message));
+        }
+        match &self.debtor {
+            Some(debtor) => {
+                section(builder, x, y, width, PAYMENT_PART, "Payable by", &debtor.display_lines());
+            }
+            None => {
+                section(builder, x, y, width, PAYMENT_PART, "Payable by (name/address)", &[]);
+                corner_marks(builder, x, y + 11.0, mm(65.0), mm(25.0));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn group_by_four(value: &str) -> String {
+    let groups: Vec<&str> = value.as_bytes().chunks(4).map(|c| std::str::from_utf8(c).unwrap_or("")).collect();
+    groups.join(" ")
+}
+
+/// Formats minor units with spaces between thousands, e.g. `1 234.50`
+fn format_amount(amount: i64) -> String {
+    let plain = format_minor(amount, AMOUNT_DIGITS);
+    let (units, cents) = plain.split_once('.').unwrap_or((&plain, "00"));
+    let mut grouped = String::new();
+    for (index, c) in units.chars().enumerate() {
+        if index > 0 && (units.len() - index) % 3 == 0 {
+            grouped.push(' ');
+        }
+        grouped.push(c);
+    }
+    format!("{}.{}", grouped, cents)
+}
+
+/// Writes a heading and its lines in the given `(heading, text)` font sizes; returns where
+/// the next section starts
+fn section(builder: &mut PdfBuilder, x: f32, top: f32, width: f32, sizes: (f32, f32), heading: &str, lines: &[String]) -> f32 {
+    let (heading_size, size) = sizes;
+    let line_height = size + 1.0;
+    let mut y = top + heading_size;
+    builder.text(x, y, heading_size, FontStyle::HelveticaBold, heading);
+    for line in lines {
+        for wrapped in wrap(builder, line, width, size, FontStyle::Helvetica) {
+            y += line_height;
+            builder.text(x, y, size, FontStyle::Helvetica, &wrapped);
+        }
+    }
+    y + line_height
+}
+
+fn amount_section(builder: &mut PdfBuilder, x: f32, amount_x: f32, top: f32, sizes: (f32, f32), currency: &str, amount: Option<&str>) {
+    let (heading_size, size) = sizes;
+    builder.text(x, top + heading_size, heading_size, FontStyle::HelveticaBold, "Currency");
+    builder.text(amount_x, top + heading_size, heading_size, FontStyle::HelveticaBold, "Amount");
+    builder.text(x, top + heading_size + size + 2.0, size, FontStyle::Helvetica, currency);
+    if let Some(amount) = amount {
+        builder.text(amount_x, top + heading_size + size + 2.0, size, FontStyle::Helvetica, amount);
+    }
+}
+
+/// Marks the corners of a field the payer fills in by hand
+fn corner_marks(builder: &mut PdfBuilder, x: f32, top: f32, width: f32, height: f32) {
+    let arm = mm(3.0);
+    let (right, bottom) = (x + width, top + height);
+    for (cx, cy, dx, dy) in [(x, top, 1.0, 1.0), (right, top, -1.0, 1.0), (x, bottom, 1.0, -1.0), (right, bottom, -1.0, -1.0)] {
+        builder.line(cx, cy, cx + dx * arm, cy, 0.75);
+        builder.line(cx, cy, cx, cy + dy * arm, 0.75);
+    }
+}
+
+/// Draws the QR code with the Swiss cross in its centre
+fn draw_qr_code(builder: &mut PdfBuilder, qr: &QrCode, x: f32, top: f32, size: f32) {
+    let module = size / qr.size() as f32;
+    for row in 0..qr.size() {
+        let mut column = 0;
+        while column < qr.size() {
+            if !qr.get_module(column, row) {
+                column += 1;
+                continue;
+            }
+            let start = column;
+            while column < qr.size() && qr.get_module(column, row) {
+                column += 1;
+            }
+            // A hair of overlap keeps viewers from showing seams between modules
+            let width = (column - start) as f32 * module;
+            builder.fill_rect(x + start as f32 * module, top + row as f32 * module, width + 0.05, module + 0.05, 0.0);
+        }
+    }
+
+    // 7 × 7 mm: a white border around a black square with the white cross
+    let cross_x = x + (size - mm(7.0)) / 2.0;
+    let cross_top = top + (size - mm(7.0)) / 2.0;
+    builder.fill_rect(cross_x, cross_top, mm(7.0), mm(7.0), 1.0);
+    builder.fill_rect(cross_x + mm(0.5), cross_top + mm(0.5), mm(6.0), mm(6.0), 0.0);
+    let (arm_width, arm_length) = (mm(6.0) * 6.0 / 32.0, mm(6.0) * 20.0 / 32.0);
+    let (center_x, center_y) = (cross_x + mm(3.5), cross_top + mm(3.5));
+    builder.fill_rect(center_x - arm_width / 2.0, center_y - arm_length / 2.0, arm_width, arm_length, 1.0);
+    builder.fill_rect(center_x - arm_length / 2.0, center_y - arm_width / 2.0, arm_length, arm_width, 1.0);
+}
+
+/// QR-bill of an issued invoice, from `settings.qr_iban` (or `bank_iban`), the business
+/// address and the client's billing address. There is none for drafts, invoices without
+/// anything to pay, accounts outside Switzerland and Liechtenstein, or with `qr_bill = false`.
+pub fn for_invoice(conn: &Connection, invoice_id: i64) -> Result<Option<QrBill>, QrBillError> {
+    if text_setting(conn, "qr_bill")?.as_deref() == Some("false") {
+        return Ok(None);
+    }
+    let iban = match text_setting(conn, "qr_iban")?.or(text_setting(conn, "bank_iban")?) {
+        Some(iban) => iban,
+        None => return Ok(None),
+    };
+    let country = iban.trim_start().get(..2).map(str::to_uppercase);
+    if !matches!(country.as_deref(), Some("CH") | Some("LI")) {
+        return Ok(None);
+    }
+
+    let invoice = conn
+        .query_row(
+            "SELECT i.number, i.total, c.name, c.billing_address, i.currency
+             FROM invoices i LEFT JOIN clients c ON c.id = i.client_id
+             WHERE i.id = ?1",
+            [invoice_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            },
+        )
+        .optional()?;
+    // QR-bills exist for CHF and EUR only; other invoices go out without a payment part
+    let (number, total, client_name, billing_address, currency) = match invoice {
+        Some((Some(number), total, client_name, billing_address, currency))
+            if total > 0 && (currency == "CHF" || currency == "EUR") =>
+        {
+            (number, total, client_name, billing_address, currency)
+        }
+        _ => return Ok(None),
+    };
+
+    let creditor = Address::parse(
+        &text_setting(conn, "business_name")?.unwrap_or_default(),
+        &read_setting(conn, "business_address")?.unwrap_or_default(),
+    )
+    .map_err(|reason| QrBillError::InvalidAddress("business", reason))?;
+    // Without a usable address the payer writes theirs in by hand
+    let debtor = Address::parse(&client_name.unwrap_or_default(), &billing_address.unwrap_or_default()).ok();
+
+    QrBill::new(&iban, creditor, &currency, Some(total), debtor, &number).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Examples from the SIX Swiss Payment Standards and ISO 11649
+
+    #[test]
+    fn validate_iban_accepts_published_examples() {
+        assert_eq!(validate_iban("CH44 3199 9123 0008 8901 2").unwrap(), "CH4431999123000889012");
+        assert_eq!(validate_iban("ch58 0079 1123 0008 8901 2").unwrap(), "CH5800791123000889012");
+        assert_eq!(validate_iban("CH9300762011623852957").unwrap(), "CH9300762011623852957");
+    }
+
+    #[test]
+    fn validate_iban_rejects_wrong_check_digits_and_countries() {
+        assert!(validate_iban("CH45 3199 9123 0008 8901 2").is_err());
+        assert!(validate_iban("CH44 3199 9123 0008 8901").is_err());
+        assert!(validate_iban("DE89 3704 0044 0532 0130 00").is_err());
+    }
+
+    #[test]
+    fn is_qr_iban_checks_the_institution_id() {
+        assert!(is_qr_iban("CH4431999123000889012"));
+        assert!(!is_qr_iban("CH5800791123000889012"));
+    }
+
+    #[test]
+    fn qr_reference_matches_published_example() {
+        assert_eq!(qr_reference("21000000000313947143000901").unwrap(), "210000000003139471430009017");
+        assert!(validate_qr_reference("210000000003139471430009017").is_ok());
+        assert!(validate_qr_reference("210000000003139471430009018").is_err());
+    }
+
+    #[test]
+    fn qr_reference_pads_the_digits_of_the_invoice_number() {
+        let reference = qr_reference("INV-2025-0042").unwrap();
+        assert_eq!(&reference[..26], "00000000000000000020250042");
+        assert!(validate_qr_reference(&reference).is_ok());
+        assert!(qr_reference("INV-").is_err());
+    }
+
+    #[test]
+    fn creditor_reference_matches_published_example() {
+        assert_eq!(creditor_reference("5390 0754 7034").unwrap(), "RF18539007547034");
+        assert!(validate_creditor_reference("RF18539007547034").is_ok());
+        assert!(validate_creditor_reference("RF485000056789012345").is_ok());
+        assert!(validate_creditor_reference("RF19539007547034").is_err());
+    }
+}
@@ -0,0 +1,684 @@
+//! Quotes (estimates) sent before work starts.
+//!
+//! A quote is drafted like an invoice, gets its number from `settings.quote_number_pattern`
+//! when it is sent and is then accepted, rejected, or expires once its validity date has
+//! passed. An accepted quote can create the event for the work and is converted into a
+//! draft invoice with the same lines; both records point to each other.
+
+use chrono::{Duration, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::currencies::{currency_or_base, normalize_currency, CurrencyError};
+use crate::db_api::{db_now, insert_event, open_state_db, parse_db_timestamp, DbApiError, Event, DB_TIMESTAMP_FORMAT};
+use crate::invoices::{mark_billed, payment_terms_days, recompute_invoice_totals, tax_rounding_name, InvoiceError};
+use crate::money::{document_totals, line_amounts, DocumentTotals, TaxRounding, FULL_RATE_BP};
+use crate::numbering::{next_number, NumberingError};
+use crate::products::{line_defaults, ProductError};
+use crate::settings::read_setting;
+use crate::StateWrapper;
+
+/// Validity used when `settings.quote_validity_days` is not set
+const DEFAULT_QUOTE_VALIDITY_DAYS: i64 = 30;
+
+/// Numbering pattern used when `settings.quote_number_pattern` is not set
+pub const DEFAULT_QUOTE_NUMBER_PATTERN: &str = "QUO-{YYYY}-{seq:04}";
+
+#[derive(Debug, Error)]
+pub enum QuoteError {
+    #[error("[quotes.rs::quote_not_found] Quote {0} does not exist.")]
+    QuoteNotFound(i64),
+
+    #[error("[quotes.rs::line_not_found] Quote line {0} does not exist.")]
+    LineNotFound(i64),
+
+    #[error("[quotes.rs::not_draft] Quote {0} is {1} and can no longer be edited.")]
+    NotDraft(i64, String),
+
+    #[error("[quotes.rs::invalid_transition] Quote {0} cannot go from {1} to {2}.")]
+    InvalidTransition(i64, String, String),
+
+    #[error("[quotes.rs::empty_quote] Quote {0} has no lines.")]
+    EmptyQuote(i64),
+
+    #[error("[quotes.rs::expired] Quote {0} is past its validity date.")]
+    Expired(i64),
+
+    #[error("[quotes.rs::no_client] Quote {0} has no client.")]
+    NoClient(i64),
+
+    #[error("[quotes.rs::already_converted] Quote {0} is already invoiced on invoice {1}.")]
+    AlreadyConverted(i64, i64),
+
+    #[error("[quotes.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[quotes.rs::invoice] {0}")]
+    InvoiceError(#[from] InvoiceError),
+
+    #[error("[quotes.rs::numbering] {0}")]
+    NumberingError(#[from] NumberingError),
+
+    #[error("[quotes.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[quotes.rs::products] {0}")]
+    ProductError(#[from] ProductError),
+
+    #[error("[quotes.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[quotes.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for QuoteError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 🚦 Lifecycle state of a quote, stored as text in `quotes.status`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum QuoteStatus {
+    Draft,
+    Sent,
+    Accepted,
+    Rejected,
+    Expired,
+}
+
+impl QuoteStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuoteStatus::Draft => "Draft",
+            QuoteStatus::Sent => "Sent",
+            QuoteStatus::Accepted => "Accepted",
+            QuoteStatus::Rejected => "Rejected",
+            QuoteStatus::Expired => "Expired",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<QuoteStatus> {
+        match value {
+            "Draft" => Some(QuoteStatus::Draft),
+            "Sent" => Some(QuoteStatus::Sent),
+            "Accepted" => Some(QuoteStatus::Accepted),
+            "Rejected" => Some(QuoteStatus::Rejected),
+            "Expired" => Some(QuoteStatus::Expired),
+            _ => None,
+        }
+    }
+
+    /// Allowed moves: only a sent quote gets an answer, and an expired one can be sent
+    /// again once its validity has been extended
+    pub fn can_become(self, next: QuoteStatus) -> bool {
+        use QuoteStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Sent) | (Sent, Accepted | Rejected | Expired) | (Expired, Sent)
+        )
+    }
+}
+
+/// 📝 Quote Struct. Money is in minor units.
+#[derive(Serialize, Deserialize)]
+pub struct Quote {
+    pub id: Option<i64>,
+    /// Assigned when the quote is sent
+    #[serde(default)]
+    pub number: Option<String>,
+    pub client_id: Option<i64>,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub subtotal: i64,
+    #[serde(default)]
+    pub tax_total: i64,
+    #[serde(default)]
+    pub total: i64,
+    /// Defaults to `settings.quote_validity_days` from today
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub sent_at: Option<String>,
+    #[serde(default)]
+    pub decided_at: Option<String>,
+    /// Event created when the quote was accepted
+    #[serde(default)]
+    pub event_id: Option<i64>,
+    /// Invoice the quote was converted into
+    #[serde(default)]
+    pub invoice_id: Option<i64>,
+    /// ISO 4217 code; defaults to `settings.base_currency`
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// 📝 Quote Line Struct. Money is in minor units, rates in basis points (8.1 % = 810).
+#[derive(Serialize, Deserialize)]
+pub struct QuoteLine {
+    pub id: Option<i64>,
+    pub quote_id: i64,
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: i64,
+    #[serde(default)]
+    pub discount_bp: i64,
+    #[serde(default)]
+    pub tax_rate_bp: i64,
+    /// Computed when the quote is saved
+    #[serde(default)]
+    pub net_amount: i64,
+    #[serde(default)]
+    pub tax_amount: i64,
+    /// Catalog product the line was created from
+    #[serde(default)]
+    pub product_id: Option<i64>,
+}
+
+/// 🗓️ Time of the event to create when a quote is accepted
+#[derive(Serialize, Deserialize)]
+pub struct QuoteEvent {
+    /// Defaults to the quote title
+    #[serde(default)]
+    pub title: Option<String>,
+    pub start_date: String,
+    pub end_date: String,
+    #[serde(default)]
+    pub assigned_member_id: Option<i32>,
+}
+
+const QUOTE_COLUMNS: &str =
+    "id, number, client_id, title, subtotal, tax_total, total, valid_until, status, sent_at, decided_at, event_id, invoice_id, currency";
+
+fn quote_from_row(row: &rusqlite::Row) -> rusqlite::Result<Quote> {
+    Ok(Quote {
+        id: row.get(0)?,
+        number: row.get(1)?,
+        client_id: row.get(2)?,
+        title: row.get(3)?,
+        subtotal: row.get(4)?,
+        tax_total: row.get(5)?,
+        total: row.get(6)?,
+        valid_until: row.get(7)?,
+        status: row.get(8)?,
+        sent_at: row.get(9)?,
+        decided_at: row.get(10)?,
+        event_id: row.get(11)?,
+        invoice_id: row.get(12)?,
+        currency: row.get(13)?,
+    })
+}
+
+pub fn get(conn: &Connection, quote_id: i64) -> Result<Quote, QuoteError> {
+    conn.query_row(&format!("SELECT {} FROM quotes WHERE id = ?1", QUOTE_COLUMNS), [quote_id], quote_from_row)
+        .optional()?
+        .ok_or(QuoteError::QuoteNotFound(quote_id))
+}
+
+fn status_of(conn: &Connection, quote_id: i64) -> Result<String, QuoteError> {
+    conn.query_row("SELECT status FROM quotes WHERE id = ?1", [quote_id], |row| row.get(0))
+        .optional()?
+        .ok_or(QuoteError::QuoteNotFound(quote_id))
+}
+
+/// Fails unless the quote exists and is still a draft
+fn ensure_draft(conn: &Connection, quote_id: i64) -> Result<(), QuoteError> {
+    let status = status_of(conn, quote_id)?;
+    if status != "Draft" {
+        return Err(QuoteError::NotDraft(quote_id, status));
+    }
+    Ok(())
+}
+
+/// Fails once the validity date of the quote has passed
+fn ensure_valid(conn: &Connection, quote_id: i64) -> Result<(), QuoteError> {
+    let expired: bool = conn.query_row(
+        "SELECT datetime(valid_until) < datetime(?1) FROM quotes WHERE id = ?2",
+        params![db_now(), quote_id],
+        |row| row.get(0),
+    )?;
+    if expired {
+        return Err(QuoteError::Expired(quote_id));
+    }
+    Ok(())
+}
+
+fn validate_line(line: &QuoteLine) -> Result<(), QuoteError> {
+    if line.description.trim().is_empty() {
+        return Err(QuoteError::InvalidValue("description", line.description.clone()));
+    }
+    if !line.quantity.is_finite() {
+        return Err(QuoteError::InvalidValue("quantity", line.quantity.to_string()));
+    }
+    if !(0..=FULL_RATE_BP).contains(&line.discount_bp) {
+        return Err(QuoteError::InvalidValue("discount_bp", line.discount_bp.to_string()));
+    }
+    if line.tax_rate_bp < 0 {
+        return Err(QuoteError::InvalidValue("tax_rate_bp", line.tax_rate_bp.to_string()));
+    }
+    Ok(())
+}
+
+fn default_valid_until(conn: &Connection) -> Result<String, QuoteError> {
+    let days = match read_setting(conn, "quote_validity_days")? {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| QuoteError::InvalidValue("quote_validity_days", value))?,
+        None => DEFAULT_QUOTE_VALIDITY_DAYS,
+    };
+    Ok((Utc::now().naive_utc() + Duration::days(days)).format(DB_TIMESTAMP_FORMAT).to_string())
+}
+
+fn normalize_time(field: &'static str, value: &str) -> Result<String, QuoteError> {
+    parse_db_timestamp(value)
+        .map(|t| t.format(DB_TIMESTAMP_FORMAT).to_string())
+        .ok_or_else(|| QuoteError::InvalidValue(field, value.to_string()))
+}
+
+/// A plain date keeps the quote valid until the end of that day
+fn normalize_valid_until(value: &str) -> Result<String, QuoteError> {
+    match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+        Ok(date) => Ok(format!("{} 23:59:59", date.format("%Y-%m-%d"))),
+        Err(_) => normalize_time("valid_until", value),
+    }
+}
+
+/// Recomputes every line and the totals of a draft quote, like `recompute_invoice_totals`
+pub fn recompute_quote_totals(conn: &Connection, quote_id: i64) -> Result<DocumentTotals, QuoteError> {
+    let rounding = TaxRounding::from_setting(read_setting(conn, "tax_rounding")?.as_deref());
+    let mut stmt =
+        conn.prepare("SELECT id, quantity, unit_price, discount_bp, tax_rate_bp FROM quote_lines WHERE quote_id = ?1")?;
+    let lines = stmt
+        .query_map([quote_id], |row| {
+            let amounts = line_amounts(row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?);
+            Ok((row.get::<_, i64>(0)?, amounts, row.get::<_, i64>(4)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (line_id, amounts, _) in &lines {
+        conn.execute(
+            "UPDATE quote_lines SET net_amount = ?1, tax_amount = ?2 WHERE id = ?3",
+            params![amounts.net, amounts.tax, line_id],
+        )?;
+    }
+    let amounts: Vec<_> = lines.into_iter().map(|(_, amounts, rate)| (amounts, rate)).collect();
+    let totals = document_totals(&amounts, rounding);
+    conn.execute(
+        "UPDATE quotes SET subtotal = ?1, tax_total = ?2, total = ?3, tax_rounding = ?4 WHERE id = ?5",
+        params![totals.subtotal, totals.tax_total, totals.total, tax_rounding_name(rounding), quote_id],
+    )?;
+    Ok(totals)
+}
+
+/// Adds a line for a catalog product to a draft quote, priced for the quote's client and in
+/// its currency, and returns the line id
+pub fn add_product_line(conn: &Connection, quote_id: i64, product_id: i64, quantity: f64) -> Result<i64, QuoteError> {
+    if !quantity.is_finite() {
+        return Err(QuoteError::InvalidValue("quantity", quantity.to_string()));
+    }
+    ensure_draft(conn, quote_id)?;
+    let quote = get(conn, quote_id)?;
+    let currency = currency_or_base(conn, quote.currency.as_deref())?;
+    let defaults = line_defaults(conn, product_id, quote.client_id, &currency)?;
+    conn.execute(
+        "INSERT INTO quote_lines (quote_id, position, description, quantity, unit_price, tax_rate_bp, net_amount, product_id)
+         VALUES (?1, (SELECT COALESCE(MAX(position) + 1, 0) FROM quote_lines WHERE quote_id = ?1), ?2, ?3, ?4, ?5, 0, ?6)",
+        params![quote_id, defaults.description, quantity, defaults.unit_price, defaults.tax_rate_bp, product_id],
+    )?;
+    let line_id = conn.last_insert_rowid();
+    recompute_quote_totals(conn, quote_id)?;
+    Ok(line_id)
+}
+
+/// Moves a quote to `next` if the lifecycle allows it
+fn transition(conn: &Connection, quote_id: i64, next: QuoteStatus) -> Result<(), QuoteError> {
+    let current = status_of(conn, quote_id)?;
+    if !QuoteStatus::parse(&current).is_some_and(|status| status.can_become(next)) {
+        return Err(QuoteError::InvalidTransition(quote_id, current, next.as_str().to_string()));
+    }
+    let decided_at = matches!(next, QuoteStatus::Accepted | QuoteStatus::Rejected).then(db_now);
+    conn.execute(
+        "UPDATE quotes SET status = ?1, decided_at = COALESCE(?2, decided_at) WHERE id = ?3",
+        params![next.as_str(), decided_at, quote_id],
+    )?;
+    Ok(())
+}
+
+/// Sends a quote: a draft gets the next number from `settings.quote_number_pattern`, an
+/// expired quote needs a validity date in the future first
+pub fn send(conn: &mut Connection, quote_id: i64) -> Result<String, QuoteError> {
+    let pattern = read_setting(conn, "quote_number_pattern")?
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_QUOTE_NUMBER_PATTERN.to_string());
+
+    let tx = conn.transaction()?;
+    let quote = get(&tx, quote_id)?;
+    if quote.client_id.is_none() {
+        return Err(QuoteError::NoClient(quote_id));
+    }
+    let line_count: i64 = tx.query_row("SELECT COUNT(*) FROM quote_lines WHERE quote_id = ?1", [quote_id], |row| row.get(0))?;
+    if line_count == 0 {
+        return Err(QuoteError::EmptyQuote(quote_id));
+    }
+    ensure_valid(&tx, quote_id)?;
+
+    let number = match quote.number {
+        Some(number) => number,
+        None => {
+            recompute_quote_totals(&tx, quote_id)?;
+            next_number(&tx, "quote", pattern.trim(), Utc::now().date_naive())?
+        }
+    };
+    transition(&tx, quote_id, QuoteStatus::Sent)?;
+    tx.execute(
+        "UPDATE quotes SET number = ?1, sent_at = ?2 WHERE id = ?3",
+        params![number, db_now(), quote_id],
+    )?;
+    tx.commit()?;
+    Ok(number)
+}
+
+/// Accepts a sent quote and, when `event` is given, creates the event for the work.
+/// Returns the id of the created event.
+pub fn accept(conn: &mut Connection, quote_id: i64, event: Option<QuoteEvent>) -> Result<Option<i64>, QuoteError> {
+    let tx = conn.transaction()?;
+    let quote = get(&tx, quote_id)?;
+    ensure_valid(&tx, quote_id)?;
+    transition(&tx, quote_id, QuoteStatus::Accepted)?;
+
+    let event_id = match event {
+        Some(event) => {
+            let start_date = normalize_time("start_date", &event.start_date)?;
+            let end_date = normalize_time("end_date", &event.end_date)?;
+            if end_date <= start_date {
+                return Err(QuoteError::InvalidValue("end_date", event.end_date));
+            }
+            let title = event
+                .title
+                .filter(|t| !t.trim().is_empty())
+                .unwrap_or_else(|| if quote.title.is_empty() { format!("Quote {}", quote.number.unwrap_or_default()) } else { quote.title });
+            let event_id = insert_event(&tx, &Event {
+                id: None,
+                title,
+                start_date,
+                end_date,
+                client_id: quote.client_id.map(|id| id as i32),
+                assigned_member_id: event.assigned_member_id,
+            })?;
+            tx.execute("UPDATE quotes SET event_id = ?1 WHERE id = ?2", params![event_id, quote_id])?;
+            Some(event_id)
+        }
+        None => None,
+    };
+    tx.commit()?;
+    Ok(event_id)
+}
+
+/// Copies an accepted quote into a new draft invoice and links both. The event created on
+/// acceptance is marked as billed on that invoice so it is not invoiced a second time; when
+/// it already is, the conversion fails.
+pub fn convert_to_invoice(conn: &mut Connection, quote_id: i64) -> Result<i64, QuoteError> {
+    let due_date = (Utc::now().naive_utc() + Duration::days(payment_terms_days(conn)?))
+        .format(DB_TIMESTAMP_FORMAT)
+        .to_string();
+
+    let tx = conn.transaction()?;
+    let quote = get(&tx, quote_id)?;
+    if let Some(invoice_id) = quote.invoice_id {
+        return Err(QuoteError::AlreadyConverted(quote_id, invoice_id));
+    }
+    let status = quote.status.unwrap_or_default();
+    if status != QuoteStatus::Accepted.as_str() {
+        return Err(QuoteError::InvalidTransition(quote_id, status, "Invoiced".to_string()));
+    }
+    let client_id = quote.client_id.ok_or(QuoteError::NoClient(quote_id))?;
+
+    tx.execute(
+        "INSERT INTO invoices (client_id, due_date, status, event_id, quote_id, currency) VALUES (?1, ?2, 'Draft', ?3, ?4, ?5)",
+        params![client_id, due_date, quote.event_id, quote_id, quote.currency],
+    )?;
+    let invoice_id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO invoice_lines (invoice_id, position, description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount, product_id)
+         SELECT ?1, position, description, quantity, unit_price, discount_bp, tax_rate_bp, 0, product_id
+         FROM quote_lines WHERE quote_id = ?2 ORDER BY position, id",
+        params![invoice_id, quote_id],
+    )?;
+    if let Some(event_id) = quote.event_id {
+        mark_billed(&tx, event_id as i32, invoice_id)?;
+    }
+    tx.execute("UPDATE quotes SET invoice_id = ?1 WHERE id = ?2", params![invoice_id, quote_id])?;
+    recompute_invoice_totals(&tx, invoice_id)?;
+    tx.commit()?;
+    Ok(invoice_id)
+}
+
+/// Scheduler job: sent quotes whose validity date has passed become `Expired`
+pub fn expire_quotes(conn: &Connection) -> Result<usize, QuoteError> {
+    let expired = conn.execute(
+        "UPDATE quotes SET status = 'Expired' WHERE status = 'Sent' AND datetime(valid_until) < datetime(?1)",
+        [db_now()],
+    )?;
+    Ok(expired)
+}
+
+/// 📝 Create a draft quote and return its id
+#[tauri::command]
+pub fn create_quote(state: tauri::State<StateWrapper>, quote: Quote) -> Result<i64, QuoteError> {
+    let db_conn = open_state_db(&state)?;
+    let valid_until = match quote.valid_until.as_deref() {
+        Some(value) => normalize_valid_until(value)?,
+        None => default_valid_until(&db_conn)?,
+    };
+    let currency = currency_or_base(&db_conn, quote.currency.as_deref())?;
+    db_conn.execute(
+        "INSERT INTO quotes (client_id, title, valid_until, status, currency) VALUES (?1, ?2, ?3, 'Draft', ?4)",
+        params![quote.client_id, quote.title.trim(), valid_until, currency],
+    )?;
+    Ok(db_conn.last_insert_rowid())
+}
+
+/// ✏️ Change client, title, currency or validity of a quote. All but the validity are fixed once sent;
+/// the validity date can be extended until the quote is answered.
+#[tauri::command]
+pub fn update_quote(state: tauri::State<StateWrapper>, quote: Quote) -> Result<(), QuoteError> {
+    let quote_id = quote.id.ok_or(QuoteError::InvalidValue("id", "missing".to_string()))?;
+    let db_conn = open_state_db(&state)?;
+    let status = status_of(&db_conn, quote_id)?;
+    let valid_until = quote.valid_until.as_deref().map(normalize_valid_until).transpose()?;
+    let currency = quote.currency.as_deref().map(normalize_currency).transpose()?;
+
+    match status.as_str() {
+        "Draft" => {
+            db_conn.execute(
+                "UPDATE quotes SET client_id = ?1, title = ?2, valid_until = COALESCE(?3, valid_until),
+                                   currency = COALESCE(?4, currency)
+                 WHERE id = ?5",
+                params![quote.client_id, quote.title.trim(), valid_until, currency, quote_id],
+            )?;
+        }
+        "Sent" | "Expired" => {
+            let valid_until = valid_until.ok_or_else(|| QuoteError::NotDraft(quote_id, status.clone()))?;
+            db_conn.execute("UPDATE quotes SET valid_until = ?1 WHERE id = ?2", params![valid_until, quote_id])?;
+        }
+        _ => return Err(QuoteError::NotDraft(quote_id, status)),
+    }
+    Ok(())
+}
+
+/// 🗑️ Delete a draft quote with its lines
+#[tauri::command]
+pub fn delete_quote(state: tauri::State<StateWrapper>, quote_id: i64) -> Result<(), QuoteError> {
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    ensure_draft(&tx, quote_id)?;
+    tx.execute("DELETE FROM quote_lines WHERE quote_id = ?1", [quote_id])?;
+    tx.execute("DELETE FROM quotes WHERE id = ?1", [quote_id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 📋 List quotes, optionally for a single client
+#[tauri::command]
+pub fn list_quotes(state: tauri::State<StateWrapper>, client_id: Option<i64>) -> Result<Vec<Quote>, QuoteError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {} FROM quotes WHERE ?1 IS NULL OR client_id = ?1 ORDER BY id DESC",
+        QUOTE_COLUMNS
+    ))?;
+    let quotes = stmt.query_map([client_id], quote_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(quotes)
+}
+
+/// 🔍 Get one quote
+#[tauri::command]
+pub fn get_quote(state: tauri::State<StateWrapper>, quote_id: i64) -> Result<Quote, QuoteError> {
+    let db_conn = open_state_db(&state)?;
+    get(&db_conn, quote_id)
+}
+
+/// 📄 List the lines of a quote
+#[tauri::command]
+pub fn list_quote_lines(state: tauri::State<StateWrapper>, quote_id: i64) -> Result<Vec<QuoteLine>, QuoteError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, quote_id, description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount, tax_amount, product_id
+         FROM quote_lines WHERE quote_id = ?1 ORDER BY position, id"
+    )?;
+
+    let lines = stmt
+        .query_map([quote_id], |row| {
+            Ok(QuoteLine {
+                id: row.get(0)?,
+                quote_id: row.get(1)?,
+                description: row.get(2)?,
+                quantity: row.get(3)?,
+                unit_price: row.get(4)?,
+                discount_bp: row.get(5)?,
+                tax_rate_bp: row.get(6)?,
+                net_amount: row.get(7)?,
+                tax_amount: row.get(8)?,
+                product_id: row.get(9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+/// ➕ Add a line to a draft quote and return its id
+#[tauri::command]
+pub fn add_quote_line(state: tauri::State<StateWrapper>, line: QuoteLine) -> Result<i64, QuoteError> {
+    validate_line(&line)?;
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    ensure_draft(&tx, line.quote_id)?;
+
+    tx.execute(
+        "INSERT INTO quote_lines (quote_id, position, description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount, product_id)
+         VALUES (?1, (SELECT COALESCE(MAX(position) + 1, 0) FROM quote_lines WHERE quote_id = ?1), ?2, ?3, ?4, ?5, ?6, 0, ?7)",
+        params![
+            line.quote_id,
+            line.description,
+            line.quantity,
+            line.unit_price,
+            line.discount_bp,
+            line.tax_rate_bp,
+            line.product_id
+        ],
+    )?;
+    let line_id = tx.last_insert_rowid();
+    recompute_quote_totals(&tx, line.quote_id)?;
+    tx.commit()?;
+    Ok(line_id)
+}
+
+/// 📦 Add a catalog product to a draft quote, priced for the quote's client, and return the line id
+#[tauri::command]
+pub fn add_quote_product_line(
+    state: tauri::State<StateWrapper>,
+    quote_id: i64,
+    product_id: i64,
+    quantity: f64,
+) -> Result<i64, QuoteError> {
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    let line_id = add_product_line(&tx, quote_id, product_id, quantity)?;
+    tx.commit()?;
+    Ok(line_id)
+}
+
+/// ✏️ Update a line of a draft quote
+#[tauri::command]
+pub fn update_quote_line(state: tauri::State<StateWrapper>, line: QuoteLine) -> Result<(), QuoteError> {
+    validate_line(&line)?;
+    let line_id = line.id.ok_or(QuoteError::InvalidValue("id", "missing".to_string()))?;
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    ensure_draft(&tx, line.quote_id)?;
+
+    let updated = tx.execute(
+        "UPDATE quote_lines SET description = ?1, quantity = ?2, unit_price = ?3, discount_bp = ?4, tax_rate_bp = ?5
+         WHERE id = ?6 AND quote_id = ?7",
+        params![line.description, line.quantity, line.unit_price, line.discount_bp, line.tax_rate_bp, line_id, line.quote_id],
+    )?;
+    if updated == 0 {
+        return Err(QuoteError::LineNotFound(line_id));
+    }
+    recompute_quote_totals(&tx, line.quote_id)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 🗑️ Remove a line from a draft quote
+#[tauri::command]
+pub fn delete_quote_line(state: tauri::State<StateWrapper>, line_id: i64) -> Result<(), QuoteError> {
+    let mut db_conn = open_state_db(&state)?;
+    let tx = db_conn.transaction()?;
+    let quote_id: i64 = tx
+        .query_row("SELECT quote_id FROM quote_lines WHERE id = ?1", [line_id], |row| row.get(0))
+        .optional()?
+        .ok_or(QuoteError::LineNotFound(line_id))?;
+    ensure_draft(&tx, quote_id)?;
+
+    tx.execute("DELETE FROM quote_lines WHERE id = ?1", [line_id])?;
+    recompute_quote_totals(&tx, quote_id)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 📨 Mark a quote as sent and return its number
+#[tauri::command]
+pub fn send_quote(state: tauri::State<StateWrapper>, quote_id: i64) -> Result<String, QuoteError> {
+    let mut db_conn = open_state_db(&state)?;
+    send(&mut db_conn, quote_id)
+}
+
+/// ✅ Accept a sent quote, optionally creating the event for the work; returns the event id
+#[tauri::command]
+pub fn accept_quote(state: tauri::State<StateWrapper>, quote_id: i64, event: Option<QuoteEvent>) -> Result<Option<i64>, QuoteError> {
+    let mut db_conn = open_state_db(&state)?;
+    accept(&mut db_conn, quote_id, event)
+}
+
+/// ❌ Record that the client rejected a quote
+#[tauri::command]
+pub fn reject_quote(state: tauri::State<StateWrapper>, quote_id: i64) -> Result<(), QuoteError> {
+    let db_conn = open_state_db(&state)?;
+    transition(&db_conn, quote_id, QuoteStatus::Rejected)
+}
+
+/// 🔁 Turn an accepted quote into a draft invoice and return the invoice id
+#[tauri::command]
+pub fn convert_quote_to_invoice(state: tauri::State<StateWrapper>, quote_id: i64) -> Result<i64, QuoteError> {
+    let mut db_conn = open_state_db(&state)?;
+    convert_to_invoice(&mut db_conn, quote_id)
+}
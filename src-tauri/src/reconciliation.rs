@@ -0,0 +1,748 @@
+//! Matching of imported bank transactions to invoices and expenses.
+//!
+//! `propose` scores every transaction that is not yet reconciled against the open invoices
+//! (incoming money) or unpaid expenses (outgoing money) in the same currency and stores the
+//! best candidates as `Proposed` rows of `bank_matches` with a confidence from 0 to 100 and
+//! the reasons behind it. Confirming a match records the payment on the invoice, or marks the
+//! expense as paid if the transaction paid exactly its amount; a rejected pair is never
+//! proposed again.
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::db_api::{db_now, open_state_db, DbApiError};
+use crate::ledger::{post_expense, LedgerError};
+use crate::members::current_member_id;
+use crate::payments::{invoice_due, invoice_paid, record_in, Payment, PaymentError};
+use crate::qrbill::{creditor_reference, qr_reference};
+use crate::StateWrapper;
+
+/// Candidates below this confidence are not proposed
+const MIN_CONFIDENCE: i64 = 40;
+
+/// Proposals kept per transaction
+const MAX_PROPOSALS: usize = 3;
+
+#[derive(Debug, Error)]
+pub enum ReconciliationError {
+    #[error("[reconciliation.rs::not_found] Match {0} does not exist.")]
+    NotFound(i64),
+
+    #[error("[reconciliation.rs::not_proposed] Match {0} is already {1}.")]
+    NotProposed(i64, String),
+
+    #[error("[reconciliation.rs::already_reconciled] Bank transaction {0} is already reconciled.")]
+    AlreadyReconciled(i64),
+
+    #[error("[reconciliation.rs::expense_paid] Expense {0} is already paid.")]
+    ExpensePaid(i64),
+
+    #[error("[reconciliation.rs::amount_mismatch] Expense {0} is over {1} but the transaction paid {2}; correct the expense first.")]
+    AmountMismatch(i64, i64, i64),
+
+    #[error("[reconciliation.rs::payment] {0}")]
+    PaymentError(#[from] PaymentError),
+
+    #[error("[reconciliation.rs::ledger] {0}")]
+    LedgerError(#[from] LedgerError),
+
+    #[error("[reconciliation.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[reconciliation.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for ReconciliationError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 🔗 Bank Match Struct: a proposed or decided link of a transaction to an invoice or expense
+#[derive(Serialize, Debug)]
+pub struct BankMatch {
+    pub id: i64,
+    pub transaction_id: i64,
+    pub invoice_id: Option<i64>,
+    pub expense_id: Option<i64>,
+    pub confidence: i64,
+    /// Why the pair was proposed: `reference`, `number`, `amount`, `partial_amount`, `name`, `vendor`, `date`
+    pub reasons: Vec<String>,
+    /// `Proposed`, `Confirmed` or `Rejected`
+    pub status: String,
+    /// Payment recorded when an invoice match was confirmed
+    pub payment_id: Option<i64>,
+    pub decided_at: Option<String>,
+    pub booking_date: String,
+    pub amount: i64,
+    pub currency: String,
+    pub counterparty_name: Option<String>,
+    pub remittance: Option<String>,
+    pub invoice_number: Option<String>,
+    pub expense_description: Option<String>,
+}
+
+const MATCH_SELECT: &str = "
+    SELECT m.id, m.transaction_id, m.invoice_id, m.expense_id, m.confidence, m.reasons, m.status, m.payment_id,
+           m.decided_at, t.booking_date, t.amount, t.currency, t.counterparty_name, t.remittance, i.number, e.description
+    FROM bank_matches m
+    JOIN bank_transactions t ON t.id = m.transaction_id
+    LEFT JOIN invoices i ON i.id = m.invoice_id
+    LEFT JOIN expenses e ON e.id = m.expense_id";
+
+fn match_from_row(row: &rusqlite::Row) -> rusqlite::Result<BankMatch> {
+    let reasons: String = row.get(5)?;
+    Ok(BankMatch {
+        id: row.get(0)?,
+        transaction_id: row.get(1)?,
+        invoice_id: row.get(2)?,
+        expense_id: row.get(3)?,
+        confidence: row.get(4)?,
+        reasons: reasons.split(',').filter(|r| !r.is_empty()).map(str::to_string).collect(),
+        status: row.get(6)?,
+        payment_id: row.get(7)?,
+        decided_at: row.get(8)?,
+        booking_date: row.get(9)?,
+        amount: row.get(10)?,
+        currency: row.get(11)?,
+        counterparty_name: row.get(12)?,
+        remittance: row.get(13)?,
+        invoice_number: row.get(14)?,
+        expense_description: row.get(15)?,
+    })
+}
+
+pub fn get(conn: &Connection, match_id: i64) -> Result<BankMatch, ReconciliationError> {
+    conn.query_row(&format!("{} WHERE m.id = ?1", MATCH_SELECT), [match_id], match_from_row)
+        .optional()?
+        .ok_or(ReconciliationError::NotFound(match_id))
+}
+
+struct OpenTransaction {
+    id: i64,
+    booking_date: String,
+    amount: i64,
+    currency: String,
+    structured_reference: Option<String>,
+    counterparty_name: Option<String>,
+    remittance: Option<String>,
+}
+
+struct OpenInvoice {
+    id: i64,
+    currency: String,
+    total: i64,
+    remaining: i64,
+    client_name: Option<String>,
+    /// Invoice number reduced to letters and digits
+    number_key: String,
+    references: Vec<String>,
+}
+
+struct UnpaidExpense {
+    id: i64,
+    date: String,
+    amount: i64,
+    currency: String,
+    vendor: Option<String>,
+}
+
+struct Candidate {
+    confidence: i64,
+    reasons: Vec<&'static str>,
+    invoice_id: Option<i64>,
+    expense_id: Option<i64>,
+}
+
+/// Upper-case letters and digits only, so `INV-2025/001` and `inv 2025 001` compare equal
+fn alnum_key(value: &str) -> String {
+    value.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_uppercase()
+}
+
+/// Lower-case words of three or more letters, without legal forms
+fn name_words(value: &str) -> Vec<String> {
+    const IGNORED: [&str; 8] = ["gmbh", "ag", "sa", "sarl", "ltd", "inc", "llc", "und"];
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() >= 3 && !IGNORED.contains(&w.as_str()))
+        .collect()
+}
+
+/// 20 when one name contains the other, 10 when they share a word
+fn name_score(expected: &str, found: &str) -> i64 {
+    let (expected_words, found_words) = (name_words(expected), name_words(found));
+    if expected_words.is_empty() || found_words.is_empty() {
+        return 0;
+    }
+    let (expected_key, found_key) = (expected_words.join(" "), found_words.join(" "));
+    if found_key.contains(&expected_key) || expected_key.contains(&found_key) {
+        20
+    } else if expected_words.iter().any(|w| found_words.contains(w)) {
+        10
+    } else {
+        0
+    }
+}
+
+fn score_invoice(transaction: &OpenTransaction, invoice: &OpenInvoice) -> (i64, Vec<&'static str>) {
+    let mut score = 0;
+    let mut reasons = Vec::new();
+
+    let structured = transaction.structured_reference.as_deref().map(alnum_key);
+    let text = transaction.remittance.as_deref().map(alnum_key).unwrap_or_default();
+    if structured.is_some_and(|r| invoice.references.contains(&r)) {
+        score += 60;
+        reasons.push("reference");
+    } else if invoice.number_key.len() >= 3 && text.contains(&invoice.number_key) {
+        score += 40;
+        reasons.push("number");
+    }
+
+    if transaction.amount == invoice.remaining {
+        score += 30;
+        reasons.push("amount");
+    } else if transaction.amount == invoice.total || transaction.amount < invoice.remaining {
+        score += 10;
+        reasons.push("partial_amount");
+    }
+
+    if let (Some(client), Some(counterparty)) = (&invoice.client_name, &transaction.counterparty_name) {
+        let name = name_score(client, counterparty);
+        if name > 0 {
+            score += name;
+            reasons.push("name");
+        }
+    }
+    (score.min(100), reasons)
+}
+
+fn score_expense(transaction: &OpenTransaction, expense: &UnpaidExpense) -> (i64, Vec<&'static str>) {
+    let mut score = 0;
+    let mut reasons = Vec::new();
+    if -transaction.amount == expense.amount {
+        score += 50;
+        reasons.push("amount");
+    }
+
+    let day = |value: &str| NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d").ok();
+    if let (Some(paid), Some(spent)) = (day(&transaction.booking_date), day(&expense.date)) {
+        let days = (paid - spent).num_days().abs();
+        if days <= 3 {
+            score += 20;
+            reasons.push("date");
+        } else if days <= 14 {
+            score += 10;
+            reasons.push("date");
+        }
+    }
+
+    if let Some(vendor) = &expense.vendor {
+        let text = format!(
+            "{} {}",
+            transaction.counterparty_name.as_deref().unwrap_or_default(),
+            transaction.remittance.as_deref().unwrap_or_default()
+        );
+        if name_score(vendor, &text) > 0 {
+            score += 30;
+            reasons.push("vendor");
+        }
+    }
+    (score.min(100), reasons)
+}
+
+fn open_transactions(conn: &Connection, account_id: Option<i64>) -> Result<Vec<OpenTransaction>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.booking_date, t.amount, t.currency, t.structured_reference, t.counterparty_name, t.remittance
+         FROM bank_transactions t
+         WHERE (?1 IS NULL OR t.account_id = ?1)
+           AND NOT EXISTS (SELECT 1 FROM bank_matches m WHERE m.transaction_id = t.id AND m.status = 'Confirmed')
+           AND NOT EXISTS (SELECT 1 FROM expenses e WHERE e.bank_transaction_id = t.id)",
+    )?;
+    let transactions = stmt
+        .query_map([account_id], |row| {
+            Ok(OpenTransaction {
+                id: row.get(0)?,
+                booking_date: row.get(1)?,
+                amount: row.get(2)?,
+                currency: row.get(3)?,
+                structured_reference: row.get(4)?,
+                counterparty_name: row.get(5)?,
+                remittance: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(transactions)
+}
+
+fn open_invoices(conn: &Connection) -> Result<Vec<OpenInvoice>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.number, i.currency, i.total, c.name
+         FROM invoices i LEFT JOIN clients c ON c.id = i.client_id
+         WHERE i.number IS NOT NULL AND i.status IN ('Issued', 'PartiallyPaid', 'Overdue')",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut invoices = Vec::with_capacity(rows.len());
+    for (id, number, currency, total, client_name) in rows {
+        let remaining = invoice_due(conn, id)? - invoice_paid(conn, id)?;
+        if remaining <= 0 {
+            continue;
+        }
+        invoices.push(OpenInvoice {
+            id,
+            currency,
+            total,
+            remaining,
+            client_name,
+            number_key: alnum_key(&number),
+            references: [qr_reference(&number).ok(), creditor_reference(&number).ok()].into_iter().flatten().collect(),
+        });
+    }
+    Ok(invoices)
+}
+
+fn unpaid_expenses(conn: &Connection) -> Result<Vec<UnpaidExpense>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT id, date, amount, currency, vendor FROM expenses WHERE paid_at IS NULL AND amount > 0")?;
+    let expenses = stmt
+        .query_map([], |row| {
+            Ok(UnpaidExpense {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                amount: row.get(2)?,
+                currency: row.get(3)?,
+                vendor: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(expenses)
+}
+
+/// Replaces the open proposals of every unreconciled transaction (of one account, if given)
+/// and returns how many were stored
+pub fn propose(conn: &mut Connection, account_id: Option<i64>) -> Result<usize, ReconciliationError> {
+    let tx = conn.transaction()?;
+    let transactions = open_transactions(&tx, account_id)?;
+    let invoices = open_invoices(&tx)?;
+    let expenses = unpaid_expenses(&tx)?;
+
+    let mut proposed = 0;
+    for transaction in &transactions {
+        tx.execute("DELETE FROM bank_matches WHERE transaction_id = ?1 AND status = 'Proposed'", [transaction.id])?;
+
+        let mut candidates: Vec<Candidate> = if transaction.amount > 0 {
+            invoices
+                .iter()
+                .filter(|i| i.currency == transaction.currency)
+                .map(|i| {
+                    let (confidence, reasons) = score_invoice(transaction, i);
+                    Candidate { confidence, reasons, invoice_id: Some(i.id), expense_id: None }
+                })
+                .collect()
+        } else {
+            expenses
+                .iter()
+                .filter(|e| e.currency == transaction.currency)
+                .map(|e| {
+                    let (confidence, reasons) = score_expense(transaction, e);
+                    Candidate { confidence, reasons, invoice_id: None, expense_id: Some(e.id) }
+                })
+                .collect()
+        };
+        candidates.retain(|c| c.confidence >= MIN_CONFIDENCE);
+        candidates.sort_by_key(|c| std::cmp::Reverse(c.confidence));
+
+        for candidate in candidates.into_iter().take(MAX_PROPOSALS) {
+            // A rejected pair keeps its row, so the unique index skips it here
+            proposed += tx.execute(
+                "INSERT OR IGNORE INTO bank_matches (transaction_id, invoice_id, expense_id, confidence, reasons)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    transaction.id,
+                    candidate.invoice_id,
+                    candidate.expense_id,
+                    candidate.confidence,
+                    candidate.reasons.join(",")
+                ],
+            )?;
+        }
+    }
+    tx.commit()?;
+    Ok(proposed)
+}
+
+fn ensure_proposed(conn: &Connection, match_id: i64) -> Result<BankMatch, ReconciliationError> {
+    let found = get(conn, match_id)?;
+    if found.status != "Proposed" {
+        return Err(ReconciliationError::NotProposed(match_id, found.status));
+    }
+    Ok(found)
+}
+
+/// Confirms a proposal: records the payment of the invoice or marks the expense as paid, which
+/// the transaction must match to the cent since the ledger books the payment at the expense
+/// amount. The other proposals of the transaction, and of a now settled invoice or expense,
+/// are dropped.
+pub fn confirm(conn: &mut Connection, match_id: i64, member_id: Option<i64>) -> Result<BankMatch, ReconciliationError> {
+    let tx = conn.transaction()?;
+    let found = ensure_proposed(&tx, match_id)?;
+    let reconciled: bool = tx.query_row(
+        "SELECT EXISTS(SELECT 1 FROM bank_matches WHERE transaction_id = ?1 AND status = 'Confirmed')
+             OR EXISTS(SELECT 1 FROM expenses WHERE bank_transaction_id = ?1)",
+        [found.transaction_id],
+        |row| row.get(0),
+    )?;
+    if reconciled {
+        return Err(ReconciliationError::AlreadyReconciled(found.transaction_id));
+    }
+    let reference: Option<String> =
+        tx.query_row("SELECT reference FROM bank_transactions WHERE id = ?1", [found.transaction_id], |row| row.get(0))?;
+
+    let mut payment_id = None;
+    if let Some(invoice_id) = found.invoice_id {
+        let payment = Payment {
+            id: None,
+            client_id: None,
+            invoice_id: Some(invoice_id),
+            kind: String::new(),
+            amount: found.amount,
+            date: found.booking_date.clone(),
+            method: Some("Bank transfer".to_string()),
+            reference,
+            currency: Some(found.currency.clone()),
+        };
+        payment_id = Some(record_in(&tx, payment, member_id)?);
+        let settled = invoice_due(&tx, invoice_id)? - invoice_paid(&tx, invoice_id)? <= 0;
+        if settled {
+            tx.execute(
+                "DELETE FROM bank_matches WHERE invoice_id = ?1 AND status = 'Proposed' AND id <> ?2",
+                params![invoice_id, match_id],
+            )?;
+        }
+    }
+    if let Some(expense_id) = found.expense_id {
+        let amount: i64 = tx.query_row("SELECT amount FROM expenses WHERE id = ?1", [expense_id], |row| row.get(0))?;
+        if -found.amount != amount {
+            return Err(ReconciliationError::AmountMismatch(expense_id, amount, -found.amount));
+        }
+        let updated = tx.execute(
+            "UPDATE expenses SET paid_at = ?1, bank_transaction_id = ?2 WHERE id = ?3 AND paid_at IS NULL",
+            params![found.booking_date, found.transaction_id, expense_id],
+        )?;
+        if updated == 0 {
+            return Err(ReconciliationError::ExpensePaid(expense_id));
+        }
+        post_expense(&tx, expense_id)?;
+        tx.execute(
+            "DELETE FROM bank_matches WHERE expense_id = ?1 AND status = 'Proposed' AND id <> ?2",
+            params![expense_id, match_id],
+        )?;
+    }
+
+    tx.execute(
+        "UPDATE bank_matches SET status = 'Confirmed', payment_id = ?1, member_id = ?2, decided_at = ?3 WHERE id = ?4",
+        params![payment_id, member_id, db_now(), match_id],
+    )?;
+    tx.execute(
+        "DELETE FROM bank_matches WHERE transaction_id = ?1 AND status = 'Proposed'",
+        [found.transaction_id],
+    )?;
+    let confirmed = get(&tx, match_id)?;
+    tx.commit()?;
+    Ok(confirmed)
+}
+
+/// Rejects a proposal; the same pair will not be proposed again
+pub fn reject(conn: &Connection, match_id: i64, member_id: Option<i64>) -> Result<(), ReconciliationError> {
+    ensure_proposed(conn, match_id)?;
+    conn.execute(
+        "UPDATE bank_matches SET status = 'Rejected', member_id = ?1, decided_at = ?2 WHERE id = ?3",
+        params![member_id, db_now(), match_id],
+    )?;
+    Ok(())
+}
+
+/// Matches by status and transaction, best first
+pub fn list(conn: &Connection, status: Option<&str>, transaction_id: Option<i64>) -> Result<Vec<BankMatch>, ReconciliationError> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR m.status = ?1) AND (?2 IS NULL OR m.transaction_id = ?2)
+           ORDER BY t.booking_date DESC, m.transaction_id, m.confidence DESC, m.id",
+        MATCH_SELECT
+    ))?;
+    let matches = stmt.query_map(params![status, transaction_id], match_from_row)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(matches)
+}
+
+/// 🔍 Propose matches for unreconciled bank transactions, optionally of one account.
+/// Returns the number of proposals.
+#[tauri::command]
+pub fn propose_bank_matches(state: tauri::State<StateWrapper>, account_id: Option<i64>) -> Result<usize, ReconciliationError> {
+    let mut db_conn = open_state_db(&state)?;
+    propose(&mut db_conn, account_id)
+}
+
+/// 📋 List bank matches, e.g. the `Proposed` ones awaiting a decision
+#[tauri::command]
+pub fn list_bank_matches(
+    state: tauri::State<StateWrapper>,
+    status: Option<String>,
+    transaction_id: Option<i64>,
+) -> Result<Vec<BankMatch>, ReconciliationError> {
+    let db_conn = open_state_db(&state)?;
+    list(&db_conn, status.as_deref(), transaction_id)
+}
+
+/// ✅ Confirm a match: records the invoice payment or marks the expense as paid
+#[tauri::command]
+pub fn confirm_bank_match(state: tauri::State<StateWrapper>, match_id: i64) -> Result<BankMatch, ReconciliationError> {
+    let mut db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    confirm(&mut db_conn, match_id, member_id)
+}
+
+/// ❌ Reject a proposed match
+#[tauri::command]
+pub fn reject_bank_match(state: tauri::State<StateWrapper>, match_id: i64) -> Result<(), ReconciliationError> {
+    let db_conn = open_state_db(&state)?;
+    let member_id = current_member_id(&state, &db_conn).ok();
+    reject(&db_conn, match_id, member_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(amount: i64, counterparty_name: Option<&str>, remittance: Option<&str>) -> OpenTransaction {
+        OpenTransaction {
+            id: 1,
+            booking_date: "2025-01-20".to_string(),
+            amount,
+            currency: "CHF".to_string(),
+            structured_reference: None,
+            counterparty_name: counterparty_name.map(str::to_string),
+            remittance: remittance.map(str::to_string),
+        }
+    }
+
+    fn invoice(number: &str, total: i64, remaining: i64) -> OpenInvoice {
+        OpenInvoice {
+            id: 1,
+            currency: "CHF".to_string(),
+            total,
+            remaining,
+            client_name: Some("Muster AG".to_string()),
+            number_key: alnum_key(number),
+            references: [qr_reference(number).ok(), creditor_reference(number).ok()].into_iter().flatten().collect(),
+        }
+    }
+
+    fn expense(date: &str, amount: i64, vendor: Option<&str>) -> UnpaidExpense {
+        UnpaidExpense { id: 1, date: date.to_string(), amount, currency: "CHF".to_string(), vendor: vendor.map(str::to_string) }
+    }
+
+    #[test]
+    fn name_score_compares_words_without_legal_forms() {
+        assert_eq!(name_score("Muster AG", "MUSTER AG, ZUERICH"), 20);
+        assert_eq!(name_score("Muster Handels GmbH", "Handels Partner"), 10);
+        assert_eq!(name_score("Muster AG", "Beispiel SA"), 0);
+        // Nothing but legal forms and short words leaves nothing to compare
+        assert_eq!(name_score("AG", "Muster AG"), 0);
+        assert_eq!(name_score("Muster AG", ""), 0);
+    }
+
+    #[test]
+    fn score_invoice_weighs_reference_number_amount_and_name() {
+        let open = invoice("INV-2025-001", 10000, 10000);
+
+        let mut paid = transaction(10000, Some("Muster AG"), None);
+        paid.structured_reference = qr_reference("INV-2025-001").ok();
+        assert_eq!(score_invoice(&paid, &open), (100, vec!["reference", "amount", "name"]));
+
+        // A creditor reference counts the same, however it is spaced
+        paid.structured_reference = creditor_reference("INV-2025-001").ok().map(|r| r.to_lowercase().replace("inv", "inv "));
+        assert_eq!(score_invoice(&paid, &open).1, vec!["reference", "amount", "name"]);
+
+        let partial = transaction(4000, Some("Someone Else"), Some("Rechnung inv 2025/001"));
+        assert_eq!(score_invoice(&partial, &open), (50, vec!["number", "partial_amount"]));
+
+        let too_much = transaction(12000, None, None);
+        assert_eq!(score_invoice(&too_much, &open), (0, vec![]));
+    }
+
+    #[test]
+    fn score_invoice_counts_the_full_total_on_a_partly_paid_invoice() {
+        let partly_paid = invoice("INV-2025-001", 10000, 6000);
+        assert_eq!(score_invoice(&transaction(6000, None, None), &partly_paid), (30, vec!["amount"]));
+        assert_eq!(score_invoice(&transaction(10000, None, None), &partly_paid), (10, vec!["partial_amount"]));
+        assert_eq!(score_invoice(&transaction(8000, None, None), &partly_paid), (0, vec![]));
+    }
+
+    #[test]
+    fn score_invoice_ignores_short_numbers_in_the_remittance() {
+        let open = invoice("7", 10000, 10000);
+        assert_eq!(score_invoice(&transaction(5000, None, Some("Order 7")), &open), (10, vec!["partial_amount"]));
+    }
+
+    #[test]
+    fn score_expense_weighs_amount_date_and_vendor() {
+        let bill = expense("2025-01-18 00:00:00", 4990, Some("Swisscom (Schweiz) AG"));
+        assert_eq!(
+            score_expense(&transaction(-4990, Some("SWISSCOM SCHWEIZ AG"), None), &bill),
+            (100, vec!["amount", "date", "vendor"])
+        );
+
+        let mut later = transaction(-4990, None, Some("Rechnung Swisscom Januar"));
+        later.booking_date = "2025-01-30".to_string();
+        assert_eq!(score_expense(&later, &bill), (90, vec!["amount", "date", "vendor"]));
+
+        later.booking_date = "2025-02-20".to_string();
+        assert_eq!(score_expense(&later, &bill), (80, vec!["amount", "vendor"]));
+
+        assert_eq!(score_expense(&transaction(-5000, Some("Sunrise"), None), &bill), (20, vec!["date"]));
+    }
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT);
+            CREATE TABLE invoices (id INTEGER PRIMARY KEY AUTOINCREMENT, number TEXT);
+            CREATE TABLE bank_transactions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER NOT NULL,
+                booking_date DATE NOT NULL,
+                amount INTEGER NOT NULL,
+                currency TEXT NOT NULL,
+                reference TEXT,
+                remittance TEXT,
+                structured_reference TEXT,
+                counterparty_name TEXT
+            );
+            CREATE TABLE expense_categories (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, account_id INTEGER);
+            CREATE TABLE expenses (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date TIMESTAMP NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                vendor TEXT,
+                category_id INTEGER,
+                amount INTEGER NOT NULL,
+                tax_amount INTEGER NOT NULL DEFAULT 0,
+                currency TEXT NOT NULL DEFAULT 'CHF',
+                paid_at TIMESTAMP,
+                bank_transaction_id INTEGER
+            );
+            CREATE TABLE bank_matches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                transaction_id INTEGER NOT NULL,
+                invoice_id INTEGER,
+                expense_id INTEGER,
+                confidence INTEGER NOT NULL,
+                reasons TEXT NOT NULL DEFAULT '',
+                status TEXT NOT NULL DEFAULT 'Proposed',
+                payment_id INTEGER,
+                member_id INTEGER,
+                decided_at TIMESTAMP
+            );
+            CREATE TABLE exchange_rates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date DATE NOT NULL,
+                base_currency TEXT NOT NULL,
+                quote_currency TEXT NOT NULL,
+                rate INTEGER NOT NULL,
+                source TEXT
+            );
+            CREATE TABLE accounts (id INTEGER PRIMARY KEY AUTOINCREMENT, code TEXT NOT NULL, system_key TEXT UNIQUE);
+            INSERT INTO accounts (code, system_key) VALUES
+                ('1020', 'bank'), ('1170', 'input_vat'), ('2000', 'payables'), ('6700', 'expenses'),
+                ('6950', 'exchange_differences');
+            CREATE TABLE journal_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date DATE NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                source_type TEXT NOT NULL,
+                source_id INTEGER NOT NULL,
+                currency TEXT NOT NULL,
+                reverses_id INTEGER
+            );
+            CREATE TABLE journal_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entry_id INTEGER NOT NULL,
+                account_id INTEGER NOT NULL,
+                debit INTEGER NOT NULL DEFAULT 0,
+                credit INTEGER NOT NULL DEFAULT 0
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// A proposed match of a new bank transaction over `paid` to a new expense over `amount`
+    fn expense_match(conn: &Connection, paid: i64, amount: i64) -> (i64, i64) {
+        conn.execute(
+            "INSERT INTO bank_transactions (account_id, booking_date, amount, currency, reference, counterparty_name)
+             VALUES (1, '2025-01-20', ?1, 'CHF', 'TX-' || (SELECT COUNT(*) + 1 FROM bank_transactions), 'Swisscom')",
+            [-paid],
+        )
+        .unwrap();
+        let transaction_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO expenses (date, description, vendor, amount) VALUES ('2025-01-18 00:00:00', 'Phone', 'Swisscom', ?1)",
+            [amount],
+        )
+        .unwrap();
+        let expense_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO bank_matches (transaction_id, expense_id, confidence, reasons) VALUES (?1, ?2, 80, 'vendor,date')",
+            params![transaction_id, expense_id],
+        )
+        .unwrap();
+        (conn.last_insert_rowid(), expense_id)
+    }
+
+    #[test]
+    fn confirm_rejects_an_expense_of_another_amount() {
+        let mut conn = connection();
+        let (match_id, expense_id) = expense_match(&conn, 5000, 4990);
+
+        assert!(matches!(
+            confirm(&mut conn, match_id, None),
+            Err(ReconciliationError::AmountMismatch(id, 4990, 5000)) if id == expense_id
+        ));
+        let (paid_at, status): (Option<String>, String) = conn
+            .query_row(
+                "SELECT e.paid_at, m.status FROM expenses e JOIN bank_matches m ON m.expense_id = e.id WHERE m.id = ?1",
+                [match_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((paid_at, status.as_str()), (None, "Proposed"));
+    }
+
+    #[test]
+    fn confirm_pays_an_expense_of_the_same_amount() {
+        let mut conn = connection();
+        let (match_id, expense_id) = expense_match(&conn, 4990, 4990);
+
+        let confirmed = confirm(&mut conn, match_id, Some(3)).unwrap();
+        assert_eq!(confirmed.status, "Confirmed");
+        let (paid_at, transaction_id): (Option<String>, Option<i64>) = conn
+            .query_row("SELECT paid_at, bank_transaction_id FROM expenses WHERE id = ?1", [expense_id], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((paid_at.as_deref(), transaction_id), (Some("2025-01-20"), Some(confirmed.transaction_id)));
+        let entries: i64 = conn
+            .query_row("SELECT COUNT(*) FROM journal_entries WHERE source_type = 'ExpensePayment'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(entries, 1);
+    }
+}
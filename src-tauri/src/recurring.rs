@@ -0,0 +1,550 @@
+//! Recurring invoice profiles for retainer clients.
+//!
+//! A profile bills the same lines every `interval_months` months on `day_of_month` (the last
+//! day of shorter months), from `start_date` until the optional `end_date`. The scheduler
+//! creates one invoice per due date, either as a draft or finalized right away.
+//!
+//! Every generated date is recorded in `recurring_runs`, unique per profile, inside the same
+//! transaction as its invoice. A run is therefore never billed twice, and after the app was
+//! closed for a while the next tick catches up on every date that was missed.
+
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::currencies::{currency_or_base, CurrencyError};
+use crate::db_api::{open_state_db, DbApiError};
+use crate::invoices::{finalize_draft, payment_terms_days, recompute_invoice_totals, InvoiceError};
+use crate::money::FULL_RATE_BP;
+use crate::StateWrapper;
+
+/// Stored format of profile and run dates
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Error)]
+pub enum RecurringError {
+    #[error("[recurring.rs::profile_not_found] Recurring profile {0} does not exist.")]
+    ProfileNotFound(i64),
+
+    #[error("[recurring.rs::line_not_found] Recurring profile line {0} does not exist.")]
+    LineNotFound(i64),
+
+    #[error("[recurring.rs::invalid_value] Invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("[recurring.rs::invoice] {0}")]
+    InvoiceError(#[from] InvoiceError),
+
+    #[error("[recurring.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[recurring.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[recurring.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for RecurringError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// 🔁 Recurring Profile Struct
+#[derive(Serialize, Deserialize)]
+pub struct RecurringProfile {
+    pub id: Option<i64>,
+    pub client_id: i64,
+    #[serde(default)]
+    pub title: String,
+    /// 1 for monthly, 3 for quarterly, 12 for yearly
+    pub interval_months: i64,
+    /// 1–31; months with fewer days bill on their last day
+    pub day_of_month: i64,
+    /// `YYYY-MM-DD`
+    pub start_date: String,
+    #[serde(default)]
+    pub end_date: Option<String>,
+    /// `Draft` leaves generated invoices for review, `AutoIssue` finalizes them
+    pub mode: String,
+    /// Next date an invoice will be generated, if any
+    #[serde(default)]
+    pub next_run: Option<String>,
+    /// ISO 4217 code of the generated invoices; defaults to `settings.base_currency`
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// 🔁 Recurring Profile Line Struct. Descriptions may contain `{period}`, replaced with the
+/// billed month as `YYYY-MM`.
+#[derive(Serialize, Deserialize)]
+pub struct RecurringLine {
+    pub id: Option<i64>,
+    pub profile_id: i64,
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: i64,
+    #[serde(default)]
+    pub discount_bp: i64,
+    #[serde(default)]
+    pub tax_rate_bp: i64,
+}
+
+struct Schedule {
+    interval_months: u32,
+    day_of_month: u32,
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+}
+
+fn parse_date(field: &'static str, value: &str) -> Result<NaiveDate, RecurringError> {
+    NaiveDate::parse_from_str(value.trim(), DATE_FORMAT).map_err(|_| RecurringError::InvalidValue(field, value.to_string()))
+}
+
+fn last_day_of_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .map_or(28, |d| d.day())
+}
+
+/// Months since year 0, to compare billing periods
+fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+impl Schedule {
+    /// The `index`-th billing date counted from the month of `start`
+    fn occurrence(&self, index: u32) -> Option<NaiveDate> {
+        let months = month_index(self.start) + (index * self.interval_months) as i32;
+        let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+        NaiveDate::from_ymd_opt(year, month, self.day_of_month.min(last_day_of_month(year, month)))
+    }
+
+    /// Billing dates in months after the month of `after`, up to and including `until`,
+    /// oldest first
+    fn dates(&self, after: Option<NaiveDate>, until: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = Vec::new();
+        let mut index = 0;
+        while let Some(date) = self.occurrence(index) {
+            if date > until || self.end.is_some_and(|end| date > end) {
+                break;
+            }
+            if date >= self.start && after.map_or(true, |after| month_index(date) > month_index(after)) {
+                dates.push(date);
+            }
+            index += 1;
+        }
+        dates
+    }
+
+    /// First billing date in a month after the month of `after` that is still within the profile
+    fn next(&self, after: Option<NaiveDate>) -> Option<NaiveDate> {
+        (0..)
+            .map_while(|index| self.occurrence(index))
+            .take_while(|date| self.end.map_or(true, |end| *date <= end))
+            .find(|date| *date >= self.start && after.map_or(true, |after| month_index(*date) > month_index(after)))
+    }
+}
+
+fn schedule_of(conn: &Connection, profile_id: i64) -> Result<(Schedule, String), RecurringError> {
+    let (interval_months, day_of_month, start, end, mode): (i64, i64, String, Option<String>, String) = conn
+        .query_row(
+            "SELECT interval_months, day_of_month, start_date, end_date, mode FROM recurring_profiles WHERE id = ?1",
+            [profile_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )
+        .optional()?
+        .ok_or(RecurringError::ProfileNotFound(profile_id))?;
+    let schedule = Schedule {
+        interval_months: interval_months as u32,
+        day_of_month: day_of_month as u32,
+        start: parse_date("start_date", &start)?,
+        end: end.as_deref().map(|end| parse_date("end_date", end)).transpose()?,
+    };
+    Ok((schedule, mode))
+}
+
+/// Last billed date of a profile
+fn last_run(conn: &Connection, profile_id: i64) -> Result<Option<NaiveDate>, RecurringError> {
+    let last: Option<String> =
+        conn.query_row("SELECT MAX(run_date) FROM recurring_runs WHERE profile_id = ?1", [profile_id], |row| row.get(0))?;
+    last.as_deref().map(|date| parse_date("run_date", date)).transpose()
+}
+
+fn validate_profile(profile: &RecurringProfile) -> Result<(), RecurringError> {
+    if !(1..=120).contains(&profile.interval_months) {
+        return Err(RecurringError::InvalidValue("interval_months", profile.interval_months.to_string()));
+    }
+    if !(1..=31).contains(&profile.day_of_month) {
+        return Err(RecurringError::InvalidValue("day_of_month", profile.day_of_month.to_string()));
+    }
+    if !matches!(profile.mode.as_str(), "Draft" | "AutoIssue") {
+        return Err(RecurringError::InvalidValue("mode", profile.mode.clone()));
+    }
+    let start = parse_date("start_date", &profile.start_date)?;
+    if let Some(end) = &profile.end_date {
+        if parse_date("end_date", end)? < start {
+            return Err(RecurringError::InvalidValue("end_date", end.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn validate_line(line: &RecurringLine) -> Result<(), RecurringError> {
+    if line.description.trim().is_empty() {
+        return Err(RecurringError::InvalidValue("description", line.description.clone()));
+    }
+    if !line.quantity.is_finite() {
+        return Err(RecurringError::InvalidValue("quantity", line.quantity.to_string()));
+    }
+    if !(0..=FULL_RATE_BP).contains(&line.discount_bp) {
+        return Err(RecurringError::InvalidValue("discount_bp", line.discount_bp.to_string()));
+    }
+    if line.tax_rate_bp < 0 {
+        return Err(RecurringError::InvalidValue("tax_rate_bp", line.tax_rate_bp.to_string()));
+    }
+    Ok(())
+}
+
+/// Creates the invoice of one billing date and records the run. Returns `None` when that
+/// date has already been billed.
+fn generate(conn: &Connection, profile_id: i64, date: NaiveDate, auto_issue: bool) -> Result<Option<i64>, RecurringError> {
+    let run_date = date.format(DATE_FORMAT).to_string();
+    let recorded = conn.execute(
+        "INSERT OR IGNORE INTO recurring_runs (profile_id, run_date) VALUES (?1, ?2)",
+        params![profile_id, run_date],
+    )?;
+    if recorded == 0 {
+        return Ok(None);
+    }
+
+    let due_date = format!("{} 00:00:00", (date + Duration::days(payment_terms_days(conn)?)).format(DATE_FORMAT));
+    conn.execute(
+        "INSERT INTO invoices (client_id, due_date, status, recurring_profile_id, currency)
+         SELECT client_id, ?1, 'Draft', id, currency FROM recurring_profiles WHERE id = ?2",
+        params![due_date, profile_id],
+    )?;
+    let invoice_id = conn.last_insert_rowid();
+    conn.execute(
+        "INSERT INTO invoice_lines (invoice_id, position, description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount)
+         SELECT ?1, position, replace(description, '{period}', ?2), quantity, unit_price, discount_bp, tax_rate_bp, 0
+         FROM recurring_profile_lines WHERE profile_id = ?3 ORDER BY position, id",
+        params![invoice_id, date.format("%Y-%m").to_string(), profile_id],
+    )?;
+    recompute_invoice_totals(conn, invoice_id)?;
+    if auto_issue {
+        finalize_draft(conn, invoice_id, None)?;
+    }
+    conn.execute(
+        "UPDATE recurring_runs SET invoice_id = ?1 WHERE profile_id = ?2 AND run_date = ?3",
+        params![invoice_id, profile_id, run_date],
+    )?;
+    Ok(Some(invoice_id))
+}
+
+/// Creates the invoices of one profile for every date that came due up to `today`, each in
+/// its own transaction; stops at the first date that fails
+fn generate_for_profile(conn: &mut Connection, profile_id: i64, today: NaiveDate, invoice_ids: &mut Vec<i64>) -> Result<(), RecurringError> {
+    let (schedule, mode) = schedule_of(conn, profile_id)?;
+    // Only months after the last run, so moving the billing day never bills a month twice
+    for date in schedule.dates(last_run(conn, profile_id)?, today) {
+        let tx = conn.transaction()?;
+        if let Some(invoice_id) = generate(&tx, profile_id, date, mode == "AutoIssue")? {
+            invoice_ids.push(invoice_id);
+        }
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Scheduler job: creates the invoices of every profile that came due up to `today`,
+/// including dates missed while the app was closed. A profile that fails is logged and
+/// retried on the next run without holding up the others. Returns the new invoice ids.
+pub fn generate_due_invoices(conn: &mut Connection, today: NaiveDate) -> Result<Vec<i64>, RecurringError> {
+    // Profiles without lines wait until they have something to bill
+    let profile_ids = {
+        let mut stmt = conn.prepare(
+            "SELECT id FROM recurring_profiles p
+             WHERE EXISTS (SELECT 1 FROM recurring_profile_lines l WHERE l.profile_id = p.id)
+             ORDER BY id"
+        )?;
+        let ids = stmt.query_map([], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>, _>>()?;
+        ids
+    };
+
+    let mut invoice_ids = Vec::new();
+    for profile_id in profile_ids {
+        if let Err(err) = generate_for_profile(conn, profile_id, today, &mut invoice_ids) {
+            println!("[recurring.rs::generate_due_invoices] Recurring profile {} failed: {}", profile_id, err);
+        }
+    }
+    Ok(invoice_ids)
+}
+
+/// 🔁 Create a recurring profile and return its id
+#[tauri::command]
+pub fn create_recurring_profile(state: tauri::State<StateWrapper>, profile: RecurringProfile) -> Result<i64, RecurringError> {
+    validate_profile(&profile)?;
+    let db_conn = open_state_db(&state)?;
+    let currency = currency_or_base(&db_conn, profile.currency.as_deref())?;
+    db_conn.execute(
+        "INSERT INTO recurring_profiles (client_id, title, interval_months, day_of_month, start_date, end_date, mode, currency)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            profile.client_id,
+            profile.title.trim(),
+            profile.interval_months,
+            profile.day_of_month,
+            profile.start_date.trim(),
+            profile.end_date.as_deref().map(str::trim),
+            profile.mode,
+            currency
+        ],
+    )?;
+    Ok(db_conn.last_insert_rowid())
+}
+
+/// ✏️ Update a recurring profile. Dates that were already billed are never billed again.
+#[tauri::command]
+pub fn update_recurring_profile(state: tauri::State<StateWrapper>, profile: RecurringProfile) -> Result<(), RecurringError> {
+    validate_profile(&profile)?;
+    let profile_id = profile.id.ok_or(RecurringError::InvalidValue("id", "missing".to_string()))?;
+    let db_conn = open_state_db(&state)?;
+    let currency = currency_or_base(&db_conn, profile.currency.as_deref())?;
+    let updated = db_conn.execute(
+        "UPDATE recurring_profiles SET client_id = ?1, title = ?2, interval_months = ?3, day_of_month = ?4,
+                start_date = ?5, end_date = ?6, mode = ?7, currency = ?8
+         WHERE id = ?9",
+        params![
+            profile.client_id,
+            profile.title.trim(),
+            profile.interval_months,
+            profile.day_of_month,
+            profile.start_date.trim(),
+            profile.end_date.as_deref().map(str::trim),
+            profile.mode,
+            currency,
+            profile_id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(RecurringError::ProfileNotFound(profile_id));
+    }
+    Ok(())
+}
+
+/// 🗑️ Delete a recurring profile. Invoices it generated are kept.
+#[tauri::command]
+pub fn delete_recurring_profile(state: tauri::State<StateWrapper>, profile_id: i64) -> Result<(), RecurringError> {
+    let db_conn = open_state_db(&state)?;
+    let deleted = db_conn.execute("DELETE FROM recurring_profiles WHERE id = ?1", [profile_id])?;
+    if deleted == 0 {
+        return Err(RecurringError::ProfileNotFound(profile_id));
+    }
+    Ok(())
+}
+
+/// 📋 List recurring profiles with their next billing date, optionally for a single client
+#[tauri::command]
+pub fn list_recurring_profiles(state: tauri::State<StateWrapper>, client_id: Option<i64>) -> Result<Vec<RecurringProfile>, RecurringError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, client_id, title, interval_months, day_of_month, start_date, end_date, mode, currency
+         FROM recurring_profiles WHERE ?1 IS NULL OR client_id = ?1 ORDER BY id"
+    )?;
+    let mut profiles = stmt
+        .query_map([client_id], |row| {
+            Ok(RecurringProfile {
+                id: row.get(0)?,
+                client_id: row.get(1)?,
+                title: row.get(2)?,
+                interval_months: row.get(3)?,
+                day_of_month: row.get(4)?,
+                start_date: row.get(5)?,
+                end_date: row.get(6)?,
+                mode: row.get(7)?,
+                next_run: None,
+                currency: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for profile in &mut profiles {
+        let profile_id = profile.id.unwrap_or_default();
+        let (schedule, _) = schedule_of(&db_conn, profile_id)?;
+        profile.next_run = schedule.next(last_run(&db_conn, profile_id)?).map(|d| d.format(DATE_FORMAT).to_string());
+    }
+    Ok(profiles)
+}
+
+/// 📄 List the lines of a recurring profile
+#[tauri::command]
+pub fn list_recurring_lines(state: tauri::State<StateWrapper>, profile_id: i64) -> Result<Vec<RecurringLine>, RecurringError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, profile_id, description, quantity, unit_price, discount_bp, tax_rate_bp
+         FROM recurring_profile_lines WHERE profile_id = ?1 ORDER BY position, id"
+    )?;
+    let lines = stmt
+        .query_map([profile_id], |row| {
+            Ok(RecurringLine {
+                id: row.get(0)?,
+                profile_id: row.get(1)?,
+                description: row.get(2)?,
+                quantity: row.get(3)?,
+                unit_price: row.get(4)?,
+                discount_bp: row.get(5)?,
+                tax_rate_bp: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines)
+}
+
+/// ➕ Add a line to a recurring profile and return its id
+#[tauri::command]
+pub fn add_recurring_line(state: tauri::State<StateWrapper>, line: RecurringLine) -> Result<i64, RecurringError> {
+    validate_line(&line)?;
+    let db_conn = open_state_db(&state)?;
+    schedule_of(&db_conn, line.profile_id)?;
+    db_conn.execute(
+        "INSERT INTO recurring_profile_lines (profile_id, position, description, quantity, unit_price, discount_bp, tax_rate_bp)
+         VALUES (?1, (SELECT COALESCE(MAX(position) + 1, 0) FROM recurring_profile_lines WHERE profile_id = ?1), ?2, ?3, ?4, ?5, ?6)",
+        params![line.profile_id, line.description, line.quantity, line.unit_price, line.discount_bp, line.tax_rate_bp],
+    )?;
+    Ok(db_conn.last_insert_rowid())
+}
+
+/// ✏️ Update a line of a recurring profile
+#[tauri::command]
+pub fn update_recurring_line(state: tauri::State<StateWrapper>, line: RecurringLine) -> Result<(), RecurringError> {
+    validate_line(&line)?;
+    let line_id = line.id.ok_or(RecurringError::InvalidValue("id", "missing".to_string()))?;
+    let db_conn = open_state_db(&state)?;
+    let updated = db_conn.execute(
+        "UPDATE recurring_profile_lines SET description = ?1, quantity = ?2, unit_price = ?3, discount_bp = ?4, tax_rate_bp = ?5
+         WHERE id = ?6 AND profile_id = ?7",
+        params![line.description, line.quantity, line.unit_price, line.discount_bp, line.tax_rate_bp, line_id, line.profile_id],
+    )?;
+    if updated == 0 {
+        return Err(RecurringError::LineNotFound(line_id));
+    }
+    Ok(())
+}
+
+/// 🗑️ Remove a line from a recurring profile
+#[tauri::command]
+pub fn delete_recurring_line(state: tauri::State<StateWrapper>, line_id: i64) -> Result<(), RecurringError> {
+    let db_conn = open_state_db(&state)?;
+    let deleted = db_conn.execute("DELETE FROM recurring_profile_lines WHERE id = ?1", [line_id])?;
+    if deleted == 0 {
+        return Err(RecurringError::LineNotFound(line_id));
+    }
+    Ok(())
+}
+
+/// ▶️ Generate every recurring invoice that is due now and return the new invoice ids
+#[tauri::command]
+pub fn run_recurring_invoices(state: tauri::State<StateWrapper>) -> Result<Vec<i64>, RecurringError> {
+    let mut db_conn = open_state_db(&state)?;
+    generate_due_invoices(&mut db_conn, Utc::now().date_naive())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn schedule(interval_months: u32, day_of_month: u32, start: NaiveDate, end: Option<NaiveDate>) -> Schedule {
+        Schedule { interval_months, day_of_month, start, end }
+    }
+
+    #[test]
+    fn occurrence_clamps_to_the_end_of_shorter_months() {
+        let monthly = schedule(1, 31, date(2025, 1, 31), None);
+        assert_eq!(monthly.occurrence(1), Some(date(2025, 2, 28)));
+        assert_eq!(monthly.occurrence(2), Some(date(2025, 3, 31)));
+        assert_eq!(monthly.occurrence(3), Some(date(2025, 4, 30)));
+        assert_eq!(monthly.occurrence(11), Some(date(2025, 12, 31)));
+        assert_eq!(monthly.occurrence(12), Some(date(2026, 1, 31)));
+
+        let leap_year = schedule(1, 30, date(2024, 1, 1), None);
+        assert_eq!(leap_year.occurrence(1), Some(date(2024, 2, 29)));
+        assert_eq!(leap_year.occurrence(2), Some(date(2024, 3, 30)));
+    }
+
+    #[test]
+    fn dates_step_by_the_interval_and_stop_at_the_end_date() {
+        let quarterly = schedule(3, 31, date(2025, 1, 31), None);
+        assert_eq!(
+            quarterly.dates(None, date(2026, 1, 31)),
+            vec![date(2025, 1, 31), date(2025, 4, 30), date(2025, 7, 31), date(2025, 10, 31), date(2026, 1, 31)]
+        );
+
+        let ending = schedule(3, 31, date(2025, 1, 31), Some(date(2025, 7, 30)));
+        assert_eq!(ending.dates(None, date(2026, 12, 31)), vec![date(2025, 1, 31), date(2025, 4, 30)]);
+        assert_eq!(ending.next(Some(date(2025, 4, 30))), None);
+
+        let yearly = schedule(12, 29, date(2024, 2, 29), None);
+        assert_eq!(
+            yearly.dates(None, date(2028, 3, 1)),
+            vec![date(2024, 2, 29), date(2025, 2, 28), date(2026, 2, 28), date(2027, 2, 28), date(2028, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn dates_skip_a_billing_day_before_the_start() {
+        let monthly = schedule(1, 10, date(2025, 1, 15), None);
+        assert_eq!(monthly.dates(None, date(2025, 3, 10)), vec![date(2025, 2, 10), date(2025, 3, 10)]);
+        assert_eq!(monthly.next(None), Some(date(2025, 2, 10)));
+        assert_eq!(monthly.dates(None, date(2025, 2, 9)), vec![]);
+    }
+
+    #[test]
+    fn dates_catch_up_on_every_month_missed_since_the_last_run() {
+        let monthly = schedule(1, 31, date(2025, 1, 31), None);
+        let last_run = Some(date(2025, 2, 28));
+        assert_eq!(monthly.dates(last_run, date(2025, 5, 30)), vec![date(2025, 3, 31), date(2025, 4, 30)]);
+        assert_eq!(
+            monthly.dates(last_run, date(2025, 5, 31)),
+            vec![date(2025, 3, 31), date(2025, 4, 30), date(2025, 5, 31)]
+        );
+        assert_eq!(monthly.next(last_run), Some(date(2025, 3, 31)));
+        assert_eq!(monthly.dates(Some(date(2025, 5, 31)), date(2025, 5, 31)), vec![]);
+    }
+
+    #[test]
+    fn a_changed_billing_day_does_not_bill_the_same_month_twice() {
+        // Billed on the 31st of March, then moved to the 5th
+        let moved = schedule(1, 5, date(2025, 1, 31), None);
+        let last_run = Some(date(2025, 3, 31));
+        assert_eq!(moved.next(last_run), Some(date(2025, 4, 5)));
+        assert_eq!(moved.dates(last_run, date(2025, 5, 5)), vec![date(2025, 4, 5), date(2025, 5, 5)]);
+    }
+
+    #[test]
+    fn last_run_is_the_latest_recorded_date() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE recurring_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                run_date DATE NOT NULL,
+                UNIQUE (profile_id, run_date)
+            );
+            INSERT INTO recurring_runs (profile_id, run_date) VALUES (1, '2025-02-28'), (1, '2025-01-31'), (2, '2025-06-30');",
+        )
+        .unwrap();
+        assert_eq!(last_run(&conn, 1).unwrap(), Some(date(2025, 2, 28)));
+        assert_eq!(last_run(&conn, 3).unwrap(), None);
+    }
+}
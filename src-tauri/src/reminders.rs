@@ -0,0 +1,156 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::db_api::{open_state_db, DbApiError};
+use crate::StateWrapper;
+
+/// Name of the app event emitted for every fired reminder
+pub const REMINDER_EVENT: &str = "reminder-fired";
+
+/// Above this many missed reminders, a single summary notification is shown instead of one each
+const MISSED_SUMMARY_THRESHOLD: usize = 3;
+
+/// ⏰ Reminder Struct
+#[derive(Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: Option<i32>,
+    pub event_id: i32,
+    /// Minutes before the event start, e.g. `15` or `1440` for one day
+    pub offset_minutes: i64,
+    pub fired_at: Option<String>,
+}
+
+/// 🔔 Payload of the `reminder-fired` app event
+#[derive(Clone, Serialize)]
+pub struct ReminderFired {
+    pub reminder_id: i64,
+    pub event_id: i64,
+    pub title: String,
+    pub start_date: String,
+    pub offset_minutes: i64,
+    /// The reminder was due while the app was closed
+    pub missed: bool,
+}
+
+/// ➕ Add a reminder to an event
+#[tauri::command]
+pub fn add_reminder(state: tauri::State<StateWrapper>, event_id: i32, offset_minutes: i64) -> Result<i64, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute(
+        "INSERT INTO reminders (event_id, offset_minutes) VALUES (?1, ?2)",
+        params![event_id, offset_minutes]
+    )?;
+    Ok(db_conn.last_insert_rowid())
+}
+
+/// 📋 List the reminders of an event
+#[tauri::command]
+pub fn list_reminders(state: tauri::State<StateWrapper>, event_id: i32) -> Result<Vec<Reminder>, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare(
+        "SELECT id, event_id, offset_minutes, fired_at FROM reminders WHERE event_id = ?1 ORDER BY offset_minutes DESC"
+    )?;
+
+    let reminders = stmt
+        .query_map([event_id], |row| {
+            Ok(Reminder {
+                id: row.get(0)?,
+                event_id: row.get(1)?,
+                offset_minutes: row.get(2)?,
+                fired_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(reminders)
+}
+
+/// 🗑️ Delete a reminder
+#[tauri::command]
+pub fn delete_reminder(state: tauri::State<StateWrapper>, reminder_id: i32) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    db_conn.execute("DELETE FROM reminders WHERE id = ?1", [reminder_id])?;
+    Ok(())
+}
+
+/// "15 minutes", "2 hours", "1 day"
+fn describe_offset(minutes: i64) -> String {
+    let (value, unit) = match minutes {
+        m if m >= 1440 && m % 1440 == 0 => (m / 1440, "day"),
+        m if m >= 60 && m % 60 == 0 => (m / 60, "hour"),
+        m => (m, "minute"),
+    };
+    format!("{} {}{}", value, unit, if value == 1 { "" } else { "s" })
+}
+
+/// Scheduler job: fires every reminder whose time has come.
+///
+/// A reminder is due once `start_date - offset_minutes` has passed and it has not fired for
+/// the event's current start (moving an event re-arms its reminders). Reminders that came due
+/// while the app was closed are caught up here on the first tick after sign-in, as long as
+/// their event has not ended yet; those for finished events are marked fired silently.
+pub fn fire_due_reminders(app: &AppHandle, conn: &Connection, tick_seconds: i64) -> Result<(), DbApiError> {
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.event_id, r.offset_minutes, e.title, e.start_date,
+                datetime(e.end_date) < datetime('now') AS ended,
+                datetime(e.start_date, '-' || r.offset_minutes || ' minutes') < datetime('now', ?1) AS missed
+         FROM reminders r
+         JOIN events e ON e.id = r.event_id
+         WHERE (r.fired_at IS NULL OR r.fired_start_date IS NOT e.start_date)
+           AND datetime(e.start_date, '-' || r.offset_minutes || ' minutes') <= datetime('now')
+         ORDER BY e.start_date"
+    )?;
+    let due = stmt
+        .query_map([format!("-{} seconds", tick_seconds * 2)], |row| {
+            Ok((
+                ReminderFired {
+                    reminder_id: row.get(0)?,
+                    event_id: row.get(1)?,
+                    offset_minutes: row.get(2)?,
+                    title: row.get(3)?,
+                    start_date: row.get(4)?,
+                    missed: row.get(6)?,
+                },
+                row.get::<_, bool>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (expired, to_fire): (Vec<_>, Vec<_>) = due.into_iter().partition(|(_, ended)| *ended);
+    let missed_count = to_fire.iter().filter(|(r, _)| r.missed).count();
+    if missed_count > MISSED_SUMMARY_THRESHOLD {
+        notify(app, "Missed reminders", &format!("{} reminders came due while BuffMod was closed", missed_count));
+    }
+
+    for (reminder, _) in &to_fire {
+        if !(reminder.missed && missed_count > MISSED_SUMMARY_THRESHOLD) {
+            let body = if reminder.missed {
+                format!("Missed reminder: starts {}", reminder.start_date)
+            } else {
+                format!("Starts in {}", describe_offset(reminder.offset_minutes))
+            };
+            notify(app, &reminder.title, &body);
+        }
+        if let Err(err) = app.emit(REMINDER_EVENT, reminder.clone()) {
+            println!("[reminders.rs::fire_due_reminders] Could not emit reminder event: {}", err);
+        }
+    }
+
+    for (reminder, _) in to_fire.iter().chain(expired.iter()) {
+        conn.execute(
+            "UPDATE reminders
+             SET fired_at = CURRENT_TIMESTAMP,
+                 fired_start_date = (SELECT start_date FROM events WHERE id = ?2)
+             WHERE id = ?1",
+            params![reminder.reminder_id, reminder.event_id]
+        )?;
+    }
+    Ok(())
+}
+
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    if let Err(err) = app.notification().builder().title(title).body(body).show() {
+        println!("[reminders.rs::notify] Could not show notification: {}", err);
+    }
+}
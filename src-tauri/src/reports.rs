@@ -0,0 +1,577 @@
+//! Financial reports over invoices, credit notes, payments and expenses.
+//!
+//! Every report covers an inclusive date range and is stated in one currency, the base
+//! currency unless another is asked for. Each record is converted with the exchange rate on
+//! its own date: invoices on their issue date, credit notes on theirs, payments on the day
+//! they were made and expenses on the day they were incurred or paid. Credit notes count in
+//! the period they were issued, against revenue and VAT.
+
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::credit_notes::{credit_note_totals, CreditNoteError};
+use crate::currencies::{convert, currency_or_base, parse_date, CurrencyError};
+use crate::db_api::{open_state_db, DbApiError};
+use crate::invoices::{invoice_totals, InvoiceError};
+use crate::money::DocumentTotals;
+use crate::StateWrapper;
+
+#[derive(Debug, Error)]
+pub enum ReportError {
+    #[error("[reports.rs::invalid_date] Invalid date: {0}")]
+    InvalidDate(String),
+
+    #[error("[reports.rs::invalid_range] The report range ends on {1}, before it starts on {0}.")]
+    InvalidRange(NaiveDate, NaiveDate),
+
+    #[error("[reports.rs::invoices] {0}")]
+    InvoiceError(#[from] InvoiceError),
+
+    #[error("[reports.rs::credit_notes] {0}")]
+    CreditNoteError(#[from] CreditNoteError),
+
+    #[error("[reports.rs::currency] {0}")]
+    CurrencyError(#[from] CurrencyError),
+
+    #[error("[reports.rs::db_api] Database API error: {0}")]
+    DbApiError(#[from] DbApiError),
+
+    #[error("[reports.rs::sqlite] SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+impl Serialize for ReportError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// Length of the periods of a profit and loss report
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportPeriod {
+    Month,
+    Quarter,
+    Year,
+}
+
+impl ReportPeriod {
+    /// `2025-03`, `2025-Q1` or `2025`
+    fn label(self, date: NaiveDate) -> String {
+        match self {
+            ReportPeriod::Month => format!("{}-{:02}", date.year(), date.month()),
+            ReportPeriod::Quarter => format!("{}-Q{}", date.year(), (date.month() - 1) / 3 + 1),
+            ReportPeriod::Year => date.year().to_string(),
+        }
+    }
+
+    /// First day of the period after the one `date` is in
+    fn next_start(self, date: NaiveDate) -> NaiveDate {
+        let months = match self {
+            ReportPeriod::Month => 1,
+            ReportPeriod::Quarter => 3,
+            ReportPeriod::Year => 12,
+        };
+        let first_month = match self {
+            ReportPeriod::Month => date.month(),
+            ReportPeriod::Quarter => (date.month() - 1) / 3 * 3 + 1,
+            ReportPeriod::Year => 1,
+        };
+        let index = date.year() * 12 + first_month as i32 - 1 + months;
+        NaiveDate::from_ymd_opt(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1).unwrap_or(NaiveDate::MAX)
+    }
+
+    /// Labels of all periods touching `from..=to`, in order
+    fn labels(self, from: NaiveDate, to: NaiveDate) -> Vec<String> {
+        let mut labels = Vec::new();
+        let mut date = from;
+        while date <= to {
+            labels.push(self.label(date));
+            date = self.next_start(date);
+        }
+        labels
+    }
+}
+
+/// 📈 Profit and loss of one period; `expenses` are net of input VAT
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct ProfitAndLossPeriod {
+    pub period: String,
+    /// Net amount of the invoices issued
+    pub revenue: i64,
+    /// Net amount of the credit notes issued, negative
+    pub credit_notes: i64,
+    pub expenses: i64,
+    pub profit: i64,
+}
+
+/// 🏷️ Net expenses of one category
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct CategoryTotal {
+    pub category_id: Option<i64>,
+    pub category: Option<String>,
+    pub amount: i64,
+}
+
+/// 📈 Profit and Loss Struct
+#[derive(Serialize, Debug)]
+pub struct ProfitAndLoss {
+    pub from: String,
+    pub to: String,
+    pub currency: String,
+    pub periods: Vec<ProfitAndLossPeriod>,
+    pub expenses_by_category: Vec<CategoryTotal>,
+    pub total: ProfitAndLossPeriod,
+}
+
+/// 💵 Money in and out in one month. Refunds count as outflow.
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct CashFlowMonth {
+    /// `YYYY-MM`
+    pub month: String,
+    pub inflow: i64,
+    pub outflow: i64,
+    pub net: i64,
+    /// Net of this and all earlier months of the report
+    pub cumulative: i64,
+}
+
+/// 💵 Cash Flow Struct
+#[derive(Serialize, Debug)]
+pub struct CashFlow {
+    pub from: String,
+    pub to: String,
+    pub currency: String,
+    pub months: Vec<CashFlowMonth>,
+    pub inflow: i64,
+    pub outflow: i64,
+    pub net: i64,
+}
+
+/// ⏳ Outstanding amounts of one client by days past due. Invoices not yet due are in
+/// the first bucket.
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct AgingRow {
+    /// `None` on the totals row
+    pub client_id: Option<i64>,
+    pub client_name: Option<String>,
+    pub invoice_count: i64,
+    pub days_0_30: i64,
+    pub days_31_60: i64,
+    pub days_61_90: i64,
+    pub days_over_90: i64,
+    pub total: i64,
+}
+
+/// ⏳ Receivables Aging Struct, as of the end of the range for every invoice issued by then
+#[derive(Serialize, Debug)]
+pub struct ReceivablesAging {
+    pub from: String,
+    pub as_of: String,
+    pub currency: String,
+    pub rows: Vec<AgingRow>,
+    pub total: AgingRow,
+    /// Part of the total that is not yet due
+    pub not_due: i64,
+}
+
+/// 🧾 VAT of one rate: charged on invoices less credit notes, and paid on expenses
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct VatRate {
+    pub tax_rate_bp: i64,
+    pub sales_net: i64,
+    pub sales_tax: i64,
+    pub purchases_net: i64,
+    pub purchases_tax: i64,
+}
+
+/// 🧾 VAT Summary Struct; `payable` is sales tax less purchase tax
+#[derive(Serialize, Debug)]
+pub struct VatSummary {
+    pub from: String,
+    pub to: String,
+    pub currency: String,
+    pub rates: Vec<VatRate>,
+    pub sales_tax: i64,
+    pub purchases_tax: i64,
+    pub payable: i64,
+}
+
+/// Report range and currency
+pub struct ReportScope {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub currency: String,
+}
+
+impl ReportScope {
+    pub fn new(conn: &Connection, from: &str, to: &str, currency: Option<&str>) -> Result<ReportScope, ReportError> {
+        let date = |value: &str| parse_date(value).ok_or_else(|| ReportError::InvalidDate(value.to_string()));
+        let (from, to) = (date(from)?, date(to)?);
+        if to < from {
+            return Err(ReportError::InvalidRange(from, to));
+        }
+        Ok(ReportScope { from, to, currency: currency_or_base(conn, currency)? })
+    }
+
+    /// Converts an amount into the report currency at the rate on `date`
+    pub fn convert(&self, conn: &Connection, amount: i64, currency: &str, date: NaiveDate) -> Result<i64, ReportError> {
+        Ok(convert(conn, amount, currency, &self.currency, date)?)
+    }
+
+    pub fn params(&self) -> [String; 2] {
+        [self.from.to_string(), self.to.to_string()]
+    }
+}
+
+fn row_date(value: String) -> rusqlite::Result<NaiveDate> {
+    parse_date(&value).ok_or_else(|| rusqlite::Error::InvalidColumnType(0, value, rusqlite::types::Type::Text))
+}
+
+/// `(id, currency, date)` of the invoices issued in the range
+pub fn issued_invoices(conn: &Connection, scope: &ReportScope) -> Result<Vec<(i64, String, NaiveDate)>, ReportError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, currency, date(COALESCE(issued_at, created_at)) FROM invoices
+         WHERE status <> 'Draft' AND date(COALESCE(issued_at, created_at)) BETWEEN ?1 AND ?2",
+    )?;
+    let invoices = stmt
+        .query_map(scope.params(), |row| Ok((row.get(0)?, row.get(1)?, row_date(row.get(2)?)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(invoices)
+}
+
+/// `(id, currency, date)` of the credit notes issued in the range
+pub fn issued_credit_notes(conn: &Connection, scope: &ReportScope) -> Result<Vec<(i64, String, NaiveDate)>, ReportError> {
+    let mut stmt = conn.prepare(
+        "SELECT n.id, i.currency, date(n.issued_at) FROM credit_notes n JOIN invoices i ON i.id = n.invoice_id
+         WHERE date(n.issued_at) BETWEEN ?1 AND ?2",
+    )?;
+    let notes = stmt
+        .query_map(scope.params(), |row| Ok((row.get(0)?, row.get(1)?, row_date(row.get(2)?)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+pub struct ExpenseRow {
+    pub category_id: Option<i64>,
+    pub category: Option<String>,
+    pub amount: i64,
+    pub tax_rate_bp: i64,
+    pub tax_amount: i64,
+    pub currency: String,
+    /// The date the rows were selected by
+    pub date: NaiveDate,
+}
+
+/// Expenses by `date_sql`, an SQL date expression on `expenses e`
+pub fn expenses_in(conn: &Connection, scope: &ReportScope, date_sql: &str) -> Result<Vec<ExpenseRow>, ReportError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.category_id, c.name, e.amount, e.tax_rate_bp, e.tax_amount, e.currency, {date}
+         FROM expenses e LEFT JOIN expense_categories c ON c.id = e.category_id
+         WHERE {date} BETWEEN ?1 AND ?2",
+        date = date_sql
+    ))?;
+    let expenses = stmt
+        .query_map(scope.params(), |row| {
+            Ok(ExpenseRow {
+                category_id: row.get(0)?,
+                category: row.get(1)?,
+                amount: row.get(2)?,
+                tax_rate_bp: row.get(3)?,
+                tax_amount: row.get(4)?,
+                currency: row.get(5)?,
+                date: row_date(row.get(6)?)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(expenses)
+}
+
+/// Revenue, credit notes and net expenses per period
+pub fn profit_and_loss(conn: &Connection, scope: &ReportScope, period: ReportPeriod) -> Result<ProfitAndLoss, ReportError> {
+    let mut periods: Vec<ProfitAndLossPeriod> = period
+        .labels(scope.from, scope.to)
+        .into_iter()
+        .map(|label| ProfitAndLossPeriod { period: label, ..Default::default() })
+        .collect();
+    let period_of = |date: NaiveDate| {
+        let label = period.label(date);
+        periods.iter().position(|p| p.period == label).unwrap_or(0)
+    };
+
+    let mut revenue = Vec::new();
+    for (invoice_id, currency, date) in issued_invoices(conn, scope)? {
+        let subtotal: i64 = conn.query_row("SELECT subtotal FROM invoices WHERE id = ?1", [invoice_id], |row| row.get(0))?;
+        revenue.push((period_of(date), scope.convert(conn, subtotal, &currency, date)?));
+    }
+    let mut credited = Vec::new();
+    for (credit_note_id, currency, date) in issued_credit_notes(conn, scope)? {
+        let subtotal: i64 =
+            conn.query_row("SELECT subtotal FROM credit_notes WHERE id = ?1", [credit_note_id], |row| row.get(0))?;
+        credited.push((period_of(date), scope.convert(conn, subtotal, &currency, date)?));
+    }
+    let mut expenses = Vec::new();
+    let mut by_category: Vec<CategoryTotal> = Vec::new();
+    for expense in expenses_in(conn, scope, "date(e.date)")? {
+        let net = scope.convert(conn, expense.amount - expense.tax_amount, &expense.currency, expense.date)?;
+        expenses.push((period_of(expense.date), net));
+        match by_category.iter_mut().find(|c| c.category_id == expense.category_id) {
+            Some(total) => total.amount += net,
+            None => by_category.push(CategoryTotal { category_id: expense.category_id, category: expense.category, amount: net }),
+        }
+    }
+
+    for (index, amount) in revenue {
+        periods[index].revenue += amount;
+    }
+    for (index, amount) in credited {
+        periods[index].credit_notes += amount;
+    }
+    for (index, amount) in expenses {
+        periods[index].expenses += amount;
+    }
+    let mut total = ProfitAndLossPeriod { period: "Total".to_string(), ..Default::default() };
+    for p in periods.iter_mut() {
+        p.profit = p.revenue + p.credit_notes - p.expenses;
+        total.revenue += p.revenue;
+        total.credit_notes += p.credit_notes;
+        total.expenses += p.expenses;
+        total.profit += p.profit;
+    }
+    by_category.sort_by(|a, b| b.amount.cmp(&a.amount).then_with(|| a.category.cmp(&b.category)));
+
+    Ok(ProfitAndLoss {
+        from: scope.from.to_string(),
+        to: scope.to.to_string(),
+        currency: scope.currency.clone(),
+        periods,
+        expenses_by_category: by_category,
+        total,
+    })
+}
+
+/// Payments received and refunded and expenses paid, per month. Expenses count on the day
+/// they were paid, or on their own date when no payment was recorded.
+pub fn cash_flow(conn: &Connection, scope: &ReportScope) -> Result<CashFlow, ReportError> {
+    let mut months: Vec<CashFlowMonth> = ReportPeriod::Month
+        .labels(scope.from, scope.to)
+        .into_iter()
+        .map(|month| CashFlowMonth { month, ..Default::default() })
+        .collect();
+    let index = |date: NaiveDate| {
+        let label = ReportPeriod::Month.label(date);
+        months.iter().position(|m| m.month == label).unwrap_or(0)
+    };
+
+    // (month, inflow, outflow)
+    let mut flows = Vec::new();
+    let mut stmt = conn.prepare(
+        "SELECT amount, currency, date(date) FROM payments
+         WHERE kind IN ('Payment', 'Refund') AND date(date) BETWEEN ?1 AND ?2",
+    )?;
+    let payments = stmt
+        .query_map(scope.params(), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row_date(row.get(2)?)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (amount, currency, date) in payments {
+        let currency = currency.unwrap_or_else(|| scope.currency.clone());
+        let amount = scope.convert(conn, amount, &currency, date)?;
+        flows.push((index(date), amount.max(0), (-amount).max(0)));
+    }
+    for expense in expenses_in(conn, scope, "date(COALESCE(e.paid_at, e.date))")? {
+        let amount = scope.convert(conn, expense.amount, &expense.currency, expense.date)?;
+        flows.push((index(expense.date), (-amount).max(0), amount.max(0)));
+    }
+
+    for (month, inflow, outflow) in flows {
+        months[month].inflow += inflow;
+        months[month].outflow += outflow;
+    }
+    let mut cumulative = 0;
+    for month in months.iter_mut() {
+        month.net = month.inflow - month.outflow;
+        cumulative += month.net;
+        month.cumulative = cumulative;
+    }
+    Ok(CashFlow {
+        from: scope.from.to_string(),
+        to: scope.to.to_string(),
+        currency: scope.currency.clone(),
+        inflow: months.iter().map(|m| m.inflow).sum(),
+        outflow: months.iter().map(|m| m.outflow).sum(),
+        net: cumulative,
+        months,
+    })
+}
+
+/// What was still owed at the end of the range on every invoice issued by then, per client
+/// and by days past due; the start of the range does not apply
+pub fn receivables_aging(conn: &Connection, scope: &ReportScope) -> Result<ReceivablesAging, ReportError> {
+    let mut stmt = conn.prepare(
+        "SELECT i.client_id, c.name, i.currency, date(COALESCE(i.issued_at, i.created_at)), date(i.due_date),
+                i.total
+                + COALESCE((SELECT SUM(n.total) FROM credit_notes n WHERE n.invoice_id = i.id AND date(n.issued_at) <= ?2), 0)
+                - COALESCE((SELECT SUM(p.amount) FROM payments p WHERE p.invoice_id = i.id AND date(p.date) <= ?2), 0)
+         FROM invoices i LEFT JOIN clients c ON c.id = i.client_id
+         WHERE i.status <> 'Draft' AND date(COALESCE(i.issued_at, i.created_at)) <= ?2
+         ORDER BY c.name COLLATE NOCASE, i.client_id",
+    )?;
+    let invoices = stmt
+        .query_map(scope.params(), |row| {
+            Ok((
+                row.get::<_, Option<i64>>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row_date(row.get(3)?)?,
+                row_date(row.get(4)?)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut rows: Vec<AgingRow> = Vec::new();
+    let mut total = AgingRow::default();
+    let mut not_due = 0;
+    for (client_id, client_name, currency, issued, due, outstanding) in invoices {
+        if outstanding <= 0 {
+            continue;
+        }
+        let amount = scope.convert(conn, outstanding, &currency, issued)?;
+        let days = (scope.to - due).num_days();
+        if days < 0 {
+            not_due += amount;
+        }
+        if rows.last().map_or(true, |r| r.client_id != client_id) {
+            rows.push(AgingRow { client_id, client_name, ..Default::default() });
+        }
+        for row in [rows.last_mut().expect("row was just pushed"), &mut total] {
+            row.invoice_count += 1;
+            row.total += amount;
+            match days {
+                i64::MIN..=30 => row.days_0_30 += amount,
+                31..=60 => row.days_31_60 += amount,
+                61..=90 => row.days_61_90 += amount,
+                _ => row.days_over_90 += amount,
+            }
+        }
+    }
+    Ok(ReceivablesAging {
+        from: scope.from.to_string(),
+        as_of: scope.to.to_string(),
+        currency: scope.currency.clone(),
+        rows,
+        total,
+        not_due,
+    })
+}
+
+fn add_rate(rates: &mut Vec<VatRate>, tax_rate_bp: i64) -> &mut VatRate {
+    let index = match rates.iter().position(|r| r.tax_rate_bp == tax_rate_bp) {
+        Some(index) => index,
+        None => {
+            rates.push(VatRate { tax_rate_bp, ..Default::default() });
+            rates.len() - 1
+        }
+    };
+    &mut rates[index]
+}
+
+/// VAT per rate on invoices and credit notes issued and expenses incurred in the range
+pub fn vat_summary(conn: &Connection, scope: &ReportScope) -> Result<VatSummary, ReportError> {
+    let mut documents: Vec<(DocumentTotals, String, NaiveDate)> = Vec::new();
+    for (invoice_id, currency, date) in issued_invoices(conn, scope)? {
+        documents.push((invoice_totals(conn, invoice_id)?, currency, date));
+    }
+    for (credit_note_id, currency, date) in issued_credit_notes(conn, scope)? {
+        documents.push((credit_note_totals(conn, credit_note_id)?, currency, date));
+    }
+
+    let mut rates: Vec<VatRate> = Vec::new();
+    for (totals, currency, date) in documents {
+        for tax in totals.taxes {
+            let net = scope.convert(conn, tax.net, &currency, date)?;
+            let amount = scope.convert(conn, tax.tax, &currency, date)?;
+            let rate = add_rate(&mut rates, tax.tax_rate_bp);
+            rate.sales_net += net;
+            rate.sales_tax += amount;
+        }
+    }
+    for expense in expenses_in(conn, scope, "date(e.date)")? {
+        let gross = scope.convert(conn, expense.amount, &expense.currency, expense.date)?;
+        let tax = scope.convert(conn, expense.tax_amount, &expense.currency, expense.date)?;
+        let rate = add_rate(&mut rates, expense.tax_rate_bp);
+        rate.purchases_net += gross - tax;
+        rate.purchases_tax += tax;
+    }
+    rates.sort_by_key(|r| r.tax_rate_bp);
+
+    let sales_tax = rates.iter().map(|r| r.sales_tax).sum();
+    let purchases_tax = rates.iter().map(|r| r.purchases_tax).sum();
+    Ok(VatSummary {
+        from: scope.from.to_string(),
+        to: scope.to.to_string(),
+        currency: scope.currency.clone(),
+        rates,
+        sales_tax,
+        purchases_tax,
+        payable: sales_tax - purchases_tax,
+    })
+}
+
+/// 📈 Profit and loss by month, quarter or year
+#[tauri::command]
+pub fn get_profit_and_loss(
+    state: tauri::State<StateWrapper>,
+    from: String,
+    to: String,
+    currency: Option<String>,
+    period: Option<ReportPeriod>,
+) -> Result<ProfitAndLoss, ReportError> {
+    let db_conn = open_state_db(&state)?;
+    let scope = ReportScope::new(&db_conn, &from, &to, currency.as_deref())?;
+    profit_and_loss(&db_conn, &scope, period.unwrap_or(ReportPeriod::Month))
+}
+
+/// 💵 Cash flow by month
+#[tauri::command]
+pub fn get_cash_flow(
+    state: tauri::State<StateWrapper>,
+    from: String,
+    to: String,
+    currency: Option<String>,
+) -> Result<CashFlow, ReportError> {
+    let db_conn = open_state_db(&state)?;
+    let scope = ReportScope::new(&db_conn, &from, &to, currency.as_deref())?;
+    cash_flow(&db_conn, &scope)
+}
+
+/// ⏳ Accounts receivable aging as of the end of the range
+#[tauri::command]
+pub fn get_receivables_aging(
+    state: tauri::State<StateWrapper>,
+    from: String,
+    to: String,
+    currency: Option<String>,
+) -> Result<ReceivablesAging, ReportError> {
+    let db_conn = open_state_db(&state)?;
+    let scope = ReportScope::new(&db_conn, &from, &to, currency.as_deref())?;
+    receivables_aging(&db_conn, &scope)
+}
+
+/// 🧾 VAT summary by rate
+#[tauri::command]
+pub fn get_vat_summary(
+    state: tauri::State<StateWrapper>,
+    from: String,
+    to: String,
+    currency: Option<String>,
+) -> Result<VatSummary, ReportError> {
+    let db_conn = open_state_db(&state)?;
+    let scope = ReportScope::new(&db_conn, &from, &to, currency.as_deref())?;
+    vat_summary(&db_conn, &scope)
+}
@@ -0,0 +1,65 @@
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use tauri::{AppHandle, Manager};
+
+use crate::db_api::{open_state_db, DbApiError};
+use crate::booking;
+use crate::budgets;
+use crate::invoices;
+use crate::ledger;
+use crate::quotes;
+use crate::recurring;
+use crate::reminders;
+use crate::StateWrapper;
+
+/// Seconds between two runs of the background jobs
+pub const TICK_SECONDS: u64 = 30;
+
+/// Starts the background scheduler on its own thread.
+///
+/// Every tick opens the signed-in user's database and runs each job in turn. Nothing runs
+/// until a user has signed in, so the first tick after sign-in also catches up on anything
+/// that came due while the app was closed.
+pub fn start(app: AppHandle) {
+    thread::spawn(move || loop {
+        run_jobs(&app);
+        thread::sleep(Duration::from_secs(TICK_SECONDS));
+    });
+}
+
+fn run_jobs(app: &AppHandle) {
+    let state = app.state::<StateWrapper>();
+    let mut db_conn = match open_state_db(&state) {
+        Ok(conn) => conn,
+        // Not signed in yet
+        Err(DbApiError::DatabaseLocked) | Err(DbApiError::DatabaseConnectionNotFound) => return,
+        Err(err) => {
+            println!("[scheduler.rs::run_jobs] Could not open the database: {}", err);
+            return;
+        }
+    };
+
+    if let Err(err) = reminders::fire_due_reminders(app, &db_conn, TICK_SECONDS as i64) {
+        println!("[scheduler.rs::run_jobs] Reminder job failed: {}", err);
+    }
+    if let Err(err) = booking::import_inbox(app, &db_conn) {
+        println!("[scheduler.rs::run_jobs] Booking inbox import failed: {}", err);
+    }
+    if let Err(err) = invoices::mark_overdue_invoices(&mut db_conn) {
+        println!("[scheduler.rs::run_jobs] Overdue invoice job failed: {}", err);
+    }
+    if let Err(err) = quotes::expire_quotes(&db_conn) {
+        println!("[scheduler.rs::run_jobs] Quote expiry job failed: {}", err);
+    }
+    if let Err(err) = recurring::generate_due_invoices(&mut db_conn, Utc::now().date_naive()) {
+        println!("[scheduler.rs::run_jobs] Recurring invoice job failed: {}", err);
+    }
+    if let Err(err) = ledger::sync(&mut db_conn) {
+        println!("[scheduler.rs::run_jobs] Ledger sync failed: {}", err);
+    }
+    if let Err(err) = budgets::raise_budget_alerts(app, &db_conn) {
+        println!("[scheduler.rs::run_jobs] Budget alert job failed: {}", err);
+    }
+}
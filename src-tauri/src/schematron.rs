@@ -0,0 +1,675 @@
+//! ISO Schematron runner, used to check generated e-invoices against the EN 16931 and Peppol
+//! business rules as published.
+//!
+//! Rules are evaluated directly with an XPath 3.1 engine instead of being compiled to XSLT.
+//! Includes, `let`, abstract patterns, phases, `assert`/`report` with `flag`, and `value-of`/
+//! `name` in messages are supported. Schemas bound to `xslt2` may also declare helpers as
+//! `xsl:function`; those are translated into XPath inline functions, as long as their bodies
+//! only use `xsl:variable`, `xsl:sequence`, `xsl:value-of`, `xsl:choose` and `xsl:if`.
+//! Anything else is refused when the schema is loaded: a rule that cannot run must not pass.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use thiserror::Error;
+use xee_xpath::{Documents, Item, Queries, Query, Sequence};
+
+use crate::xml::XmlNode;
+
+/// Variable holding the translated `xsl:function`s in rewritten expressions
+const FUNCTIONS: &str = "$sch-functions";
+
+#[derive(Debug, Error)]
+pub enum SchematronError {
+    #[error("[schematron.rs::read] Could not read {0}: {1}")]
+    ReadError(String, std::io::Error),
+
+    #[error("[schematron.rs::parse] {0} is not well-formed XML: {1}")]
+    ParseError(String, String),
+
+    #[error("[schematron.rs::malformed] Malformed schema: {0}")]
+    Malformed(String),
+
+    #[error("[schematron.rs::unsupported] The schema uses {0}, which is not supported here.")]
+    Unsupported(String),
+
+    #[error("[schematron.rs::xpath] XPath error in {0}: {1}")]
+    XPathError(String, String),
+
+    #[error("[schematron.rs::document] The document is not well-formed XML: {0}")]
+    DocumentError(String),
+}
+
+#[derive(Clone)]
+struct Let {
+    name: String,
+    value: String,
+}
+
+/// Piece of an assert or report message: literal text, or an expression (once compiled, a query)
+#[derive(Clone)]
+enum MessagePart<X = String> {
+    Text(String),
+    XPath(X),
+}
+
+#[derive(Clone)]
+struct Check {
+    /// `report` fires when its test is true, `assert` when it is false
+    report: bool,
+    id: Option<String>,
+    flag: Option<String>,
+    test: String,
+    message: Vec<MessagePart>,
+}
+
+#[derive(Clone)]
+struct Rule {
+    context: String,
+    flag: Option<String>,
+    lets: Vec<Let>,
+    checks: Vec<Check>,
+}
+
+struct Pattern {
+    id: Option<String>,
+    lets: Vec<Let>,
+    rules: Vec<Rule>,
+}
+
+/// A loaded schema, ready to check documents
+pub struct Schema {
+    namespaces: Vec<(String, String)>,
+    lets: Vec<Let>,
+    patterns: Vec<Pattern>,
+    /// Names of the translated `xsl:function`s, and the XPath map holding them
+    functions: Vec<String>,
+    function_map: String,
+}
+
+/// Failed asserts and fired reports. Those flagged `warning` or `information` are warnings,
+/// everything else is an error.
+#[derive(Debug, Default)]
+pub struct Findings {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+fn required<'a>(node: &'a XmlNode, attribute: &str) -> Result<&'a str, SchematronError> {
+    node.attribute(attribute)
+        .ok_or_else(|| SchematronError::Malformed(format!("<{}> needs a {} attribute", node.name, attribute)))
+}
+
+/// Reads a schema file and replaces every `include` with the element it points to
+fn read(path: &Path, depth: usize) -> Result<XmlNode, SchematronError> {
+    if depth > 16 {
+        return Err(SchematronError::Malformed(format!("includes nest too deep at {}", path.display())));
+    }
+    let text = fs::read_to_string(path).map_err(|e| SchematronError::ReadError(path.display().to_string(), e))?;
+    let mut node = XmlNode::parse(&text).map_err(|e| SchematronError::ParseError(path.display().to_string(), e))?;
+    resolve_includes(&mut node, path.parent().unwrap_or(Path::new(".")), depth)?;
+    Ok(node)
+}
+
+fn resolve_includes(node: &mut XmlNode, dir: &Path, depth: usize) -> Result<(), SchematronError> {
+    for child in &mut node.children {
+        if child.name == "include" {
+            let path = dir.join(required(child, "href")?);
+            *child = read(&path, depth + 1)?;
+        } else {
+            resolve_includes(child, dir, depth)?;
+        }
+    }
+    Ok(())
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Replaces the `$name` parameters of an abstract pattern with their values
+fn substitute(expr: &str, params: &HashMap<String, String>) -> String {
+    if params.is_empty() {
+        return expr.to_string();
+    }
+    let chars: Vec<char> = expr.chars().collect();
+    let mut out = String::with_capacity(expr.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '$' {
+            let end = (i + 1..chars.len()).find(|&j| !is_name_char(chars[j])).unwrap_or(chars.len());
+            let name: String = chars[i + 1..end].iter().collect();
+            if let Some(value) = params.get(&name) {
+                out.push_str(value);
+                i = end;
+                continue;
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Rewrites calls of translated functions, `u:gln(x)` → `$sch-functions('u:gln')($sch-functions, x)`,
+/// leaving string literals and comments alone
+fn rewrite_calls(expr: &str, functions: &[String]) -> String {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut out = String::with_capacity(expr.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c == '\'' || c == '"' {
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == c && chars.get(i + 1) == Some(&c) {
+                    i += 2;
+                } else if chars[i] == c {
+                    break;
+                } else {
+                    i += 1;
+                }
+            }
+            i = (i + 1).min(chars.len());
+            out.extend(&chars[start..i]);
+        } else if c == '(' && chars.get(i + 1) == Some(&':') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == ':' && chars.get(i + 1) == Some(&')')) {
+                i += 1;
+            }
+            i = (i + 2).min(chars.len());
+            out.extend(&chars[start..i]);
+        } else if is_name_start(c) && (i == 0 || !(is_name_char(chars[i - 1]) || matches!(chars[i - 1], '$' | ':'))) {
+            while i < chars.len() && (is_name_char(chars[i]) || chars[i] == ':') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            let open = (i..chars.len()).find(|&j| !chars[j].is_whitespace()).unwrap_or(chars.len());
+            if chars.get(open) == Some(&'(') && functions.contains(&name) {
+                let first = (open + 1..chars.len()).find(|&j| !chars[j].is_whitespace());
+                out.push_str(&format!("{}('{}')({}", FUNCTIONS, name, FUNCTIONS));
+                if first.map(|j| chars[j]) != Some(')') {
+                    out.push_str(", ");
+                }
+                i = open + 1;
+            } else {
+                out.push_str(&name);
+            }
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+fn string_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Applies an XSLT `as` type to a value the way XSLT does, by the function conversion rules
+fn convert(value: String, as_type: Option<&str>) -> String {
+    match as_type {
+        Some(as_type) => format!("function($sch-value as {}) {{ $sch-value }}({})", as_type, value),
+        None => format!("({})", value),
+    }
+}
+
+/// Translates a sequence constructor of an `xsl:function` into one XPath expression
+fn translate_body(nodes: &[XmlNode]) -> Result<String, SchematronError> {
+    let Some((node, rest)) = nodes.split_first() else {
+        return Ok("()".into());
+    };
+    let item = match node.name.as_str() {
+        "variable" => {
+            if !node.children.is_empty() || !node.text.trim().is_empty() {
+                return Err(SchematronError::Unsupported("an xsl:variable without a select attribute".into()));
+            }
+            let value = convert(required(node, "select")?.to_string(), node.attribute("as"));
+            return Ok(format!("let ${} := {} return {}", required(node, "name")?, value, translate_body(rest)?));
+        }
+        "sequence" => format!("({})", required(node, "select")?),
+        "value-of" => {
+            let separator = string_literal(node.attribute("separator").unwrap_or(" "));
+            format!(
+                "xs:untypedAtomic(string-join(for $sch-item in ({}) return string($sch-item), {}))",
+                required(node, "select")?,
+                separator
+            )
+        }
+        "if" => format!("if ({}) then {} else ()", required(node, "test")?, translate_children(node)?),
+        "choose" => {
+            let mut otherwise = "()".to_string();
+            let mut whens = Vec::new();
+            for child in &node.children {
+                match child.name.as_str() {
+                    "when" => whens.push((required(child, "test")?, translate_children(child)?)),
+                    "otherwise" => otherwise = translate_children(child)?,
+                    other => return Err(SchematronError::Unsupported(format!("xsl:{} in xsl:choose", other))),
+                }
+            }
+            whens.into_iter().rev().fold(otherwise, |acc, (test, body)| format!("(if ({}) then {} else {})", test, body, acc))
+        }
+        other => return Err(SchematronError::Unsupported(format!("xsl:{} in an xsl:function", other))),
+    };
+    if rest.is_empty() {
+        Ok(item)
+    } else {
+        Ok(format!("({}, {})", item, translate_body(rest)?))
+    }
+}
+
+fn translate_children(node: &XmlNode) -> Result<String, SchematronError> {
+    if !node.text.trim().is_empty() {
+        return Err(SchematronError::Unsupported(format!("literal text in xsl:{}", node.name)));
+    }
+    Ok(format!("({})", translate_body(&node.children)?))
+}
+
+/// Translates an `xsl:function` into an inline function taking the function map first
+fn translate_function(node: &XmlNode, functions: &[String]) -> Result<String, SchematronError> {
+    let split = node.children.iter().position(|c| c.name != "param").unwrap_or(node.children.len());
+    let (params, body) = node.children.split_at(split);
+    let mut signature = vec![FUNCTIONS.to_string()];
+    for param in params {
+        let name = required(param, "name")?;
+        signature.push(match param.attribute("as") {
+            Some(as_type) => format!("${} as {}", name, as_type),
+            None => format!("${}", name),
+        });
+    }
+    if !node.text.trim().is_empty() {
+        return Err(SchematronError::Unsupported("literal text in an xsl:function".into()));
+    }
+    let mut body = translate_body(body)?;
+    if let Some(as_type) = node.attribute("as") {
+        body = convert(body, Some(as_type));
+    }
+    Ok(format!("function({}) {{ {} }}", signature.join(", "), rewrite_calls(&body, functions)))
+}
+
+fn read_let(node: &XmlNode, params: &HashMap<String, String>) -> Result<Let, SchematronError> {
+    Ok(Let { name: required(node, "name")?.to_string(), value: substitute(required(node, "value")?, params) })
+}
+
+fn read_check(node: &XmlNode, params: &HashMap<String, String>) -> Result<Check, SchematronError> {
+    let mut message = vec![MessagePart::Text(node.text.trim().to_string())];
+    for child in &node.children {
+        match child.name.as_str() {
+            "value-of" => message.push(MessagePart::XPath(substitute(required(child, "select")?, params))),
+            "name" => message.push(MessagePart::XPath(match child.attribute("path") {
+                Some(path) => format!("name({})", substitute(path, params)),
+                None => "name()".into(),
+            })),
+            "emph" | "dir" | "span" => message.push(MessagePart::Text(child.text.trim().to_string())),
+            other => return Err(SchematronError::Unsupported(format!("<{}> in <{}>", other, node.name))),
+        }
+    }
+    Ok(Check {
+        report: node.name == "report",
+        id: node.attribute("id").map(String::from),
+        flag: node.attribute("flag").map(String::from),
+        test: substitute(required(node, "test")?, params),
+        message,
+    })
+}
+
+fn read_rule(node: &XmlNode, params: &HashMap<String, String>) -> Result<Rule, SchematronError> {
+    if node.attribute("abstract") == Some("true") {
+        return Err(SchematronError::Unsupported("abstract rules".into()));
+    }
+    let mut rule = Rule {
+        context: substitute(required(node, "context")?, params),
+        flag: node.attribute("flag").map(String::from),
+        lets: Vec::new(),
+        checks: Vec::new(),
+    };
+    for child in &node.children {
+        match child.name.as_str() {
+            "let" => rule.lets.push(read_let(child, params)?),
+            "assert" | "report" => rule.checks.push(read_check(child, params)?),
+            "title" | "p" => {}
+            other => return Err(SchematronError::Unsupported(format!("<{}> in a rule", other))),
+        }
+    }
+    Ok(rule)
+}
+
+fn read_pattern(node: &XmlNode, params: &HashMap<String, String>) -> Result<Pattern, SchematronError> {
+    let mut pattern = Pattern { id: node.attribute("id").map(String::from), lets: Vec::new(), rules: Vec::new() };
+    for child in &node.children {
+        match child.name.as_str() {
+            "let" => pattern.lets.push(read_let(child, params)?),
+            "rule" => pattern.rules.push(read_rule(child, params)?),
+            "title" | "p" => {}
+            other => return Err(SchematronError::Unsupported(format!("<{}> in a pattern", other))),
+        }
+    }
+    Ok(pattern)
+}
+
+impl Schema {
+    /// Loads a schema, resolving `include`s relative to the file that contains them
+    pub fn load(path: &Path) -> Result<Schema, SchematronError> {
+        Schema::from_node(&read(path, 0)?)
+    }
+
+    fn from_node(root: &XmlNode) -> Result<Schema, SchematronError> {
+        if root.name != "schema" {
+            return Err(SchematronError::Malformed(format!("the root element is <{}>, not <schema>", root.name)));
+        }
+        let no_params = HashMap::new();
+        let mut namespaces = Vec::new();
+        let mut lets = Vec::new();
+        let mut abstract_patterns = HashMap::new();
+        let mut patterns = Vec::new();
+        let mut phases = HashMap::new();
+        let mut function_nodes = Vec::new();
+        for child in &root.children {
+            match child.name.as_str() {
+                "ns" => namespaces.push((required(child, "prefix")?.to_string(), required(child, "uri")?.to_string())),
+                "let" => lets.push(read_let(child, &no_params)?),
+                "pattern" if child.attribute("abstract") == Some("true") => {
+                    abstract_patterns.insert(required(child, "id")?, child);
+                }
+                "pattern" => patterns.push(child),
+                "phase" => {
+                    let active = child.children_named("active").map(|a| required(a, "pattern")).collect::<Result<Vec<_>, _>>()?;
+                    phases.insert(required(child, "id")?, active);
+                }
+                "function" => function_nodes.push(child),
+                "title" | "p" | "diagnostics" | "properties" => {}
+                other => return Err(SchematronError::Unsupported(format!("<{}> in a schema", other))),
+            }
+        }
+
+        if let Some(phase) = root.attribute("defaultPhase").filter(|phase| *phase != "#ALL") {
+            let active = phases.get(phase).ok_or_else(|| SchematronError::Malformed(format!("unknown default phase {}", phase)))?;
+            patterns.retain(|p| p.attribute("id").is_some_and(|id| active.contains(&id)));
+        }
+
+        let patterns = patterns
+            .into_iter()
+            .map(|node| match node.attribute("is-a") {
+                Some(base) => {
+                    let template = abstract_patterns
+                        .get(base)
+                        .ok_or_else(|| SchematronError::Malformed(format!("unknown abstract pattern {}", base)))?;
+                    let params = node
+                        .children_named("param")
+                        .map(|p| Ok((required(p, "name")?.to_string(), required(p, "value")?.to_string())))
+                        .collect::<Result<HashMap<_, _>, SchematronError>>()?;
+                    let mut pattern = read_pattern(template, &params)?;
+                    pattern.id = node.attribute("id").map(String::from);
+                    Ok(pattern)
+                }
+                None => read_pattern(node, &no_params),
+            })
+            .collect::<Result<Vec<_>, SchematronError>>()?;
+
+        let functions = function_nodes.iter().map(|f| required(f, "name").map(String::from)).collect::<Result<Vec<_>, _>>()?;
+        let translated = function_nodes.iter().map(|f| translate_function(f, &functions)).collect::<Result<Vec<_>, _>>()?;
+        let function_map = format!(
+            "map {{ {} }}",
+            functions.iter().zip(&translated).map(|(name, f)| format!("{}: {}", string_literal(name), f)).collect::<Vec<_>>().join(", ")
+        );
+
+        Ok(Schema { namespaces, lets, patterns, functions, function_map })
+    }
+
+    /// Wraps an expression so it can call the schema's functions
+    fn prepare(&self, expr: &str) -> String {
+        let rewritten = rewrite_calls(expr, &self.functions);
+        if rewritten == expr {
+            rewritten
+        } else {
+            format!("let {} := {} return ({})", FUNCTIONS, self.function_map, rewritten)
+        }
+    }
+
+    /// Runs every active pattern against `xml`. Per pattern, each node is handled by the first
+    /// rule whose context matches it.
+    pub fn check(&self, xml: &str) -> Result<Findings, SchematronError> {
+        let mut builder = xee_xpath::context::StaticContextBuilder::default();
+        for (prefix, uri) in &self.namespaces {
+            builder.add_namespace(prefix, uri);
+        }
+        let queries = Queries::new(builder);
+        let compile = |expr: String, source: &str| {
+            queries.sequence(&self.prepare(&expr)).map_err(|e| SchematronError::XPathError(source.to_string(), e.to_string()))
+        };
+        let xpath_err = |source: &str, e: xee_xpath::error::Error| SchematronError::XPathError(source.to_string(), e.to_string());
+
+        let mut documents = Documents::new();
+        let handle = documents.add_string_without_uri(xml).map_err(|e| SchematronError::DocumentError(e.to_string()))?;
+        let root = Item::Node(documents.document_node(handle).ok_or_else(|| SchematronError::DocumentError("no document node".into()))?);
+
+        let mut findings = Findings::default();
+        for pattern in &self.patterns {
+            // Schema and pattern variables are evaluated against the document node
+            let globals: String = self
+                .lets
+                .iter()
+                .chain(&pattern.lets)
+                .map(|l| format!("let ${} := root(.) ! ({}) return ", l.name, l.value))
+                .collect();
+            let mut handled = HashSet::new();
+            for rule in &pattern.rules {
+                let source = format!("rule {} of pattern {}", rule.context, pattern.id.as_deref().unwrap_or("(unnamed)"));
+                let context = compile(format!("{}descendant-or-self::node()/({})", globals, rule.context), &source)?;
+                let locals: String = rule.lets.iter().map(|l| format!("let ${} := ({}) return ", l.name, l.value)).collect();
+                let checks = rule
+                    .checks
+                    .iter()
+                    .map(|check| {
+                        let source = format!("{} test {}", source, check.test);
+                        let test = compile(format!("{}{}({})", globals, locals, check.test), &source)?;
+                        let message = check
+                            .message
+                            .iter()
+                            .map(|part| match part {
+                                MessagePart::Text(text) => Ok(MessagePart::Text(text.clone())),
+                                MessagePart::XPath(expr) => compile(format!("{}{}({})", globals, locals, expr), &source).map(MessagePart::XPath),
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok((check, source, test, message))
+                    })
+                    .collect::<Result<Vec<_>, SchematronError>>()?;
+
+                let nodes = context.execute(&mut documents, &root).map_err(|e| xpath_err(&source, e))?;
+                for item in nodes.iter() {
+                    let node = item.to_node().map_err(|e| SchematronError::XPathError(source.clone(), e.to_string()))?;
+                    if !handled.insert(node) {
+                        continue;
+                    }
+                    for (check, source, test, message) in &checks {
+                        let result = test.execute(&mut documents, &item).map_err(|e| xpath_err(source, e))?;
+                        let fired = result
+                            .effective_boolean_value()
+                            .map_err(|e| SchematronError::XPathError(source.clone(), e.to_string()))?;
+                        if fired != check.report {
+                            continue;
+                        }
+                        let mut parts = Vec::new();
+                        for part in message {
+                            match part {
+                                MessagePart::Text(text) => parts.push(text.clone()),
+                                MessagePart::XPath(query) => {
+                                    let value: Sequence = query.execute(&mut documents, &item).map_err(|e| xpath_err(source, e))?;
+                                    let strings = value
+                                        .iter()
+                                        .map(|i| i.string_value(documents.xot()))
+                                        .collect::<Result<Vec<_>, _>>()
+                                        .map_err(|e| SchematronError::XPathError(source.clone(), e.to_string()))?;
+                                    parts.push(strings.join(" "));
+                                }
+                            }
+                        }
+                        let mut text = parts.into_iter().filter(|p| !p.is_empty()).collect::<Vec<_>>().join(" ");
+                        if let Some(id) = check.id.as_deref().filter(|id| !text.contains(*id)) {
+                            text = format!("[{}] {}", id, text);
+                        }
+                        match check.flag.as_deref().or(rule.flag.as_deref()) {
+                            Some("warning" | "information") => findings.warnings.push(text),
+                            _ => findings.errors.push(text),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVOICE: &str = r#"<Invoice xmlns="urn:test:invoice" xmlns:cbc="urn:test:basic">
+  <cbc:ID>INV-1</cbc:ID>
+  <cbc:EndpointID schemeID="0088">7640000000001</cbc:EndpointID>
+  <Line><cbc:Amount currencyID="CHF">10.00</cbc:Amount></Line>
+  <Line><cbc:Amount currencyID="EUR">5.50</cbc:Amount></Line>
+  <cbc:Total>15.50</cbc:Total>
+</Invoice>"#;
+
+    fn parse(body: &str) -> Result<Schema, SchematronError> {
+        let text = format!(
+            r#"<schema xmlns="http://purl.oclc.org/dsdl/schematron" xmlns:xsl="http://www.w3.org/1999/XSL/Transform" queryBinding="xslt2">
+<ns prefix="inv" uri="urn:test:invoice"/>
+<ns prefix="cbc" uri="urn:test:basic"/>
+<ns prefix="u" uri="utils"/>
+{}
+</schema>"#,
+            body
+        );
+        Schema::from_node(&XmlNode::parse(&text).unwrap())
+    }
+
+    fn check(body: &str) -> Findings {
+        parse(body).unwrap().check(INVOICE).unwrap()
+    }
+
+    #[test]
+    fn asserts_and_reports_are_sorted_by_flag() {
+        let findings = check(
+            r#"<pattern>
+  <rule context="/inv:Invoice">
+    <assert test="cbc:ID" id="R1" flag="fatal">An invoice needs a number.</assert>
+    <assert test="xs:decimal(cbc:Total) = sum(inv:Line/cbc:Amount)" id="R2" flag="fatal">[R2]-Total is the sum of the lines.</assert>
+    <report test="count(inv:Line) &gt; 1" id="R3" flag="warning">Several lines.</report>
+  </rule>
+  <rule context="inv:Line">
+    <assert test="cbc:Amount/@currencyID = 'CHF'" id="R4">Lines must be in CHF, not <value-of select="cbc:Amount/@currencyID"/></assert>
+  </rule>
+</pattern>"#,
+        );
+        assert_eq!(findings.errors, vec!["[R4] Lines must be in CHF, not EUR".to_string()]);
+        assert_eq!(findings.warnings, vec!["[R3] Several lines.".to_string()]);
+    }
+
+    #[test]
+    fn each_node_is_handled_by_the_first_matching_rule_of_a_pattern() {
+        let findings = check(
+            r#"<pattern>
+  <rule context="inv:Line[1]"><assert test="false()" id="FIRST">first</assert></rule>
+  <rule context="inv:Line"><assert test="false()" id="ANY">any</assert></rule>
+</pattern>
+<pattern>
+  <rule context="cbc:Amount | cbc:Total"><report test="true()" id="AMOUNT">amount</report></rule>
+</pattern>"#,
+        );
+        assert_eq!(findings.errors, vec!["[FIRST] first", "[ANY] any", "[AMOUNT] amount", "[AMOUNT] amount", "[AMOUNT] amount"]);
+    }
+
+    #[test]
+    fn abstract_patterns_and_lets_are_expanded() {
+        let findings = check(
+            r#"<let name="currency" value="'CHF'"/>
+<pattern abstract="true" id="model">
+  <rule context="$Invoice">
+    <let name="lines" value="sum(inv:Line/cbc:Amount)"/>
+    <assert test="$BR-CO-10" id="BR-CO-10" flag="fatal">[BR-CO-10]-Total mismatch, lines are <value-of select="$lines"/></assert>
+    <assert test="$BR-CO-1" id="BR-CO-1" flag="fatal">[BR-CO-1]-Document currency</assert>
+  </rule>
+</pattern>
+<pattern is-a="model" id="bound">
+  <param name="Invoice" value="/inv:Invoice"/>
+  <param name="BR-CO-10" value="xs:decimal(cbc:Total) = $lines + 1"/>
+  <param name="BR-CO-1" value="inv:Line[1]/cbc:Amount/@currencyID = $currency"/>
+</pattern>"#,
+        );
+        assert_eq!(findings.errors, vec!["[BR-CO-10]-Total mismatch, lines are 15.5"]);
+    }
+
+    #[test]
+    fn xsl_functions_are_translated() {
+        let findings = check(
+            r#"<xsl:function name="u:gln" as="xs:boolean">
+  <xsl:param name="val"/>
+  <xsl:variable name="digits" select="reverse(string-to-codepoints(substring($val, 1, string-length($val) - 1))) ! (. - 48)"/>
+  <xsl:variable name="sum" as="xs:integer" select="sum(for $i in 1 to count($digits) return $digits[$i] * (if ($i mod 2 = 1) then 3 else 1))"/>
+  <xsl:value-of select="(10 - $sum mod 10) mod 10 = xs:integer(substring($val, string-length($val)))"/>
+</xsl:function>
+<xsl:function name="u:digit-sum" as="xs:integer">
+  <xsl:param name="text" as="xs:string"/>
+  <xsl:choose>
+    <xsl:when test="$text = ''"><xsl:sequence select="0"/></xsl:when>
+    <xsl:otherwise><xsl:sequence select="xs:integer(substring($text, 1, 1)) + u:digit-sum(substring($text, 2))"/></xsl:otherwise>
+  </xsl:choose>
+</xsl:function>
+<xsl:function name="u:label" as="xs:string">
+  <xsl:param name="count" as="xs:integer"/>
+  <xsl:if test="$count = 1"><xsl:sequence select="'one'"/></xsl:if>
+  <xsl:if test="$count != 1"><xsl:sequence select="'many'"/></xsl:if>
+</xsl:function>
+<pattern>
+  <rule context="/inv:Invoice">
+    <assert test="u:gln(cbc:EndpointID)" id="GLN-1">valid</assert>
+    <assert test="u:gln('7640000000002')" id="GLN-2">check digit</assert>
+    <assert test="u:digit-sum('123') = 6 and u:label(count(inv:Line)) = 'many'" id="SUM">sum</assert>
+    <assert test="'u:gln(x)' = 'u:gln(x)'" id="LITERAL">literal</assert>
+  </rule>
+</pattern>"#,
+        );
+        assert_eq!(findings.errors, vec!["[GLN-2] check digit"]);
+    }
+
+    #[test]
+    fn includes_resolve_relative_to_the_including_file() {
+        let dir = std::env::temp_dir().join(format!("schematron-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("rules")).unwrap();
+        fs::write(
+            dir.join("main.sch"),
+            r#"<schema xmlns="http://purl.oclc.org/dsdl/schematron"><ns prefix="inv" uri="urn:test:invoice"/><include href="rules/lines.sch"/></schema>"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("rules/lines.sch"),
+            r#"<pattern xmlns="http://purl.oclc.org/dsdl/schematron"><rule context="inv:Line"><report test="true()" id="LINE">line</report></rule></pattern>"#,
+        )
+        .unwrap();
+        let findings = Schema::load(&dir.join("main.sch")).unwrap().check(INVOICE).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(findings.errors.len(), 2);
+    }
+
+    #[test]
+    fn what_cannot_run_is_refused() {
+        let loop_body = r#"<xsl:function name="u:f"><xsl:param name="x"/><xsl:for-each select="$x"><xsl:sequence select="."/></xsl:for-each></xsl:function>"#;
+        assert!(matches!(parse(loop_body), Err(SchematronError::Unsupported(_))));
+        let abstract_rule = r#"<pattern><rule abstract="true" id="a"><assert test="true()">x</assert></rule></pattern>"#;
+        assert!(matches!(parse(abstract_rule), Err(SchematronError::Unsupported(_))));
+        let extends = r#"<pattern><rule context="inv:Line"><extends rule="a"/></rule></pattern>"#;
+        assert!(matches!(parse(extends), Err(SchematronError::Unsupported(_))));
+
+        let unknown_function = parse(r#"<pattern><rule context="/"><assert test="format-number(1, '0') = '1'">x</assert></rule></pattern>"#).unwrap();
+        assert!(matches!(unknown_function.check(INVOICE), Err(SchematronError::XPathError(_, _))));
+    }
+}
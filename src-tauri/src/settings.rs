@@ -0,0 +1,61 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::db_api::{open_state_db, DbApiError};
+use crate::StateWrapper;
+
+/// ⚙️ Setting Struct
+#[derive(Serialize)]
+pub struct Setting {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+/// Reads a single value from the `settings` table
+pub fn read_setting(conn: &Connection, key: &str) -> Result<Option<String>, rusqlite::Error> {
+    conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0))
+        .optional()
+        .map(Option::flatten)
+}
+
+/// A setting with surrounding whitespace removed, `None` when blank
+pub fn text_setting(conn: &Connection, key: &str) -> Result<Option<String>, rusqlite::Error> {
+    Ok(read_setting(conn, key)?.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()))
+}
+
+/// Inserts or replaces a value in the `settings` table
+pub fn write_setting(conn: &Connection, key: &str, value: Option<&str>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value]
+    )?;
+    Ok(())
+}
+
+/// 🔎 Get a setting by key
+#[tauri::command]
+pub fn get_setting(state: tauri::State<StateWrapper>, key: String) -> Result<Option<String>, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    Ok(read_setting(&db_conn, &key)?)
+}
+
+/// 💾 Set (or clear, with `null`) a setting
+#[tauri::command]
+pub fn set_setting(state: tauri::State<StateWrapper>, key: String, value: Option<String>) -> Result<(), DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    write_setting(&db_conn, &key, value.as_deref())?;
+    Ok(())
+}
+
+/// 📋 List all settings
+#[tauri::command]
+pub fn list_settings(state: tauri::State<StateWrapper>) -> Result<Vec<Setting>, DbApiError> {
+    let db_conn = open_state_db(&state)?;
+    let mut stmt = db_conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+
+    let settings = stmt
+        .query_map([], |row| Ok(Setting { key: row.get(0)?, value: row.get(1)? }))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(settings)
+}
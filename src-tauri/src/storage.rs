@@ -17,11 +17,20 @@ pub enum StorageError {
         path: Option<PathBuf>
     },
 
+    #[error("[storage.rs::data_subdir] Could not create directory {path:?}: {source}")]
+    DirectoryError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     #[error("[storage.rs::tauri_error] Tauri error encountered: {0}")]
     TauriError(#[from] tauri::Error),
 
     #[error("[storage.rs::db_api] Database API error: {0}")]
     DbApiError(#[from] DbApiError),
+
+    #[error("[storage.rs::run_migrations] Migration {0} left {1} broken foreign key reference(s).")]
+    ForeignKeyViolation(usize, i64),
 }
 
 impl Serialize for StorageError {
@@ -50,6 +59,20 @@ pub fn get_database_path(app_handle: &AppHandle, user_id: &str) -> Result<PathBu
     Ok(data_path)
 }
 
+/// Returns `<data dir>/buffmod/<name>`, creating it when missing
+pub fn data_subdir(app_handle: &AppHandle, name: &str) -> Result<PathBuf, StorageError> {
+    let dir = app_handle
+        .path()
+        .data_dir()
+        .map_err(StorageError::TauriError)?
+        .join("buffmod")
+        .join(name);
+
+    std::fs::create_dir_all(&dir)
+        .map_err(|source| StorageError::DirectoryError { path: dir.clone(), source })?;
+    Ok(dir)
+}
+
 /// Initialize the storage with an optional encryption key
 /// 
 /// # Parameters:
@@ -82,15 +105,17 @@ pub fn new_db(state: tauri::State<StateWrapper>, app_handle: &AppHandle, user_id
     // ✅ Update AppState with the new database path
     if let Some(ref mut s) = *loc_state {
         s.db_path = Some(db_path.clone());
+        s.user_id = Some(user_id.to_string());
     } else {
-        *loc_state = Some(AppState { db_key: db_key.to_owned(), db_path: Some(db_path.clone()) });
+        *loc_state = Some(AppState { db_key: db_key.to_owned(), db_path: Some(db_path.clone()), user_id: Some(user_id.to_string()) });
     }
 
     // Open the database with encryption
-    let db_conn = open_encrypted_db(&db_path, &db_key.unwrap())?;
+    let mut db_conn = open_encrypted_db(&db_path, &db_key.unwrap())?;
 
     // Initialize tables
     initialize_tables(&db_conn)?;
+    run_migrations(&mut db_conn)?;
 
     Ok(db_conn)
 }
@@ -167,3 +192,989 @@ fn initialize_tables(conn: &Connection) -> Result<(), StorageError> {
     Ok(())
 }
 
+
+
+/// Schema changes applied on top of the base tables, oldest first.
+/// `PRAGMA user_version` stores how many of them a database has already run,
+/// so entries must never be edited or reordered once released — only appended.
+const MIGRATIONS: &[&str] = &[
+    // 1: CalDAV sync state on events
+    "
+    ALTER TABLE events ADD COLUMN uid TEXT;
+    ALTER TABLE events ADD COLUMN updated_at TIMESTAMP;
+    ALTER TABLE events ADD COLUMN caldav_href TEXT;
+    ALTER TABLE events ADD COLUMN caldav_etag TEXT;
+    ALTER TABLE events ADD COLUMN caldav_ics TEXT;
+    ALTER TABLE events ADD COLUMN caldav_dirty INTEGER NOT NULL DEFAULT 1;
+    UPDATE events SET uid = lower(hex(randomblob(16))) WHERE uid IS NULL;
+    CREATE UNIQUE INDEX idx_events_uid ON events(uid);
+    CREATE UNIQUE INDEX idx_events_caldav_href ON events(caldav_href);
+
+    CREATE TABLE caldav_tombstones (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        href TEXT NOT NULL,
+        etag TEXT,
+        deleted_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE TABLE caldav_state (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        calendar_url TEXT UNIQUE NOT NULL,
+        sync_token TEXT,
+        last_synced_at TIMESTAMP
+    );
+    ",
+    // 2: Event reminders
+    "
+    CREATE TABLE reminders (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        event_id INTEGER NOT NULL,
+        offset_minutes INTEGER NOT NULL CHECK (offset_minutes >= 0),
+        fired_at TIMESTAMP,
+        fired_start_date TIMESTAMP,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_reminders_event ON reminders(event_id);
+    ",
+    // 3: Members, client contacts, event attendees and staff assignment
+    "
+    CREATE TABLE members (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id TEXT UNIQUE,
+        name TEXT NOT NULL,
+        email TEXT,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE TABLE client_contacts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        client_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        email TEXT,
+        phone TEXT,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE CASCADE
+    );
+
+    CREATE TABLE event_attendees (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        event_id INTEGER NOT NULL,
+        contact_id INTEGER,
+        member_id INTEGER,
+        rsvp_status TEXT CHECK (rsvp_status IN ('NeedsAction', 'Accepted', 'Declined', 'Tentative')) NOT NULL DEFAULT 'NeedsAction',
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        CHECK ((contact_id IS NULL) <> (member_id IS NULL)),
+        UNIQUE (event_id, contact_id),
+        UNIQUE (event_id, member_id),
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE,
+        FOREIGN KEY (contact_id) REFERENCES client_contacts(id) ON DELETE CASCADE,
+        FOREIGN KEY (member_id) REFERENCES members(id) ON DELETE CASCADE
+    );
+
+    ALTER TABLE events ADD COLUMN assigned_member_id INTEGER REFERENCES members(id) ON DELETE SET NULL;
+    CREATE INDEX idx_events_assigned_member ON events(assigned_member_id, start_date);
+    ",
+    // 4: Draft invoices with lines generated from billed events
+    "
+    CREATE TABLE invoices_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        client_id INTEGER,
+        amount REAL NOT NULL,
+        due_date TIMESTAMP NOT NULL,
+        status TEXT CHECK (status IN ('Draft', 'Paid', 'Pending', 'Overdue')) NOT NULL DEFAULT 'Pending',
+        event_id INTEGER,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL,
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE SET NULL
+    );
+    INSERT INTO invoices_new (id, client_id, amount, due_date, status, event_id, created_at)
+        SELECT id, client_id, amount, due_date, status, event_id, created_at FROM invoices;
+    DROP TABLE invoices;
+    ALTER TABLE invoices_new RENAME TO invoices;
+
+    CREATE TABLE invoice_lines (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        invoice_id INTEGER NOT NULL,
+        position INTEGER NOT NULL DEFAULT 0,
+        description TEXT NOT NULL,
+        quantity REAL NOT NULL,
+        unit_price REAL NOT NULL,
+        amount REAL NOT NULL,
+        event_id INTEGER,
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE SET NULL
+    );
+
+    ALTER TABLE clients ADD COLUMN hourly_rate REAL;
+    ALTER TABLE events ADD COLUMN billed_invoice_id INTEGER REFERENCES invoices(id) ON DELETE SET NULL;
+    ",
+    // 5: Booking requests inbox
+    "
+    CREATE TABLE booking_requests (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        external_id TEXT UNIQUE,
+        source TEXT NOT NULL DEFAULT 'manual',
+        requester_name TEXT,
+        requester_email TEXT,
+        requester_phone TEXT,
+        client_id INTEGER,
+        title TEXT NOT NULL,
+        message TEXT,
+        requested_start TIMESTAMP NOT NULL,
+        requested_end TIMESTAMP NOT NULL,
+        proposed_start TIMESTAMP,
+        proposed_end TIMESTAMP,
+        status TEXT CHECK (status IN ('Pending', 'Proposed', 'Accepted', 'Declined')) NOT NULL DEFAULT 'Pending',
+        decline_reason TEXT,
+        event_id INTEGER,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL,
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE SET NULL
+    );
+    CREATE INDEX idx_booking_requests_status ON booking_requests(status, requested_start);
+    ",
+    // 6: Invoice money in integer minor units, line discounts and tax rates. Invoices have no
+    // currency yet; amounts are in the base currency, which migration 15 assigns them.
+    "
+    CREATE TEMP TABLE minor_unit AS
+        SELECT CASE
+            WHEN code IN ('BIF', 'CLP', 'DJF', 'GNF', 'ISK', 'JPY', 'KMF', 'KRW', 'PYG', 'RWF', 'UGX', 'UYI', 'VND',
+                         'VUV', 'XAF', 'XOF', 'XPF') THEN 1
+            WHEN code IN ('BHD', 'IQD', 'JOD', 'KWD', 'LYD', 'OMR', 'TND') THEN 1000
+            WHEN code IN ('CLF', 'UYW') THEN 10000
+            ELSE 100
+        END AS factor
+        FROM (SELECT COALESCE(
+            (SELECT UPPER(TRIM(value)) FROM settings WHERE key = 'base_currency' AND LENGTH(TRIM(value)) = 3),
+            'CHF'
+        ) AS code);
+
+    CREATE TABLE invoices_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        client_id INTEGER,
+        subtotal INTEGER NOT NULL DEFAULT 0,
+        tax_total INTEGER NOT NULL DEFAULT 0,
+        total INTEGER NOT NULL DEFAULT 0,
+        tax_rounding TEXT CHECK (tax_rounding IN ('PerLine', 'PerRate')) NOT NULL DEFAULT 'PerRate',
+        due_date TIMESTAMP NOT NULL,
+        status TEXT CHECK (status IN ('Draft', 'Paid', 'Pending', 'Overdue')) NOT NULL DEFAULT 'Pending',
+        event_id INTEGER,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL,
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE SET NULL
+    );
+    INSERT INTO invoices_new (id, client_id, subtotal, total, due_date, status, event_id, created_at)
+        SELECT id, client_id, CAST(round(amount * (SELECT factor FROM minor_unit)) AS INTEGER),
+               CAST(round(amount * (SELECT factor FROM minor_unit)) AS INTEGER), due_date, status, event_id, created_at
+        FROM invoices;
+    DROP TABLE invoices;
+    ALTER TABLE invoices_new RENAME TO invoices;
+
+    CREATE TABLE invoice_lines_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        invoice_id INTEGER NOT NULL,
+        position INTEGER NOT NULL DEFAULT 0,
+        description TEXT NOT NULL,
+        quantity REAL NOT NULL,
+        unit_price INTEGER NOT NULL,
+        discount_bp INTEGER NOT NULL DEFAULT 0 CHECK (discount_bp BETWEEN 0 AND 10000),
+        tax_rate_bp INTEGER NOT NULL DEFAULT 0 CHECK (tax_rate_bp >= 0),
+        net_amount INTEGER NOT NULL,
+        tax_amount INTEGER NOT NULL DEFAULT 0,
+        event_id INTEGER,
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE SET NULL
+    );
+    INSERT INTO invoice_lines_new (id, invoice_id, position, description, quantity, unit_price, net_amount, event_id)
+        SELECT id, invoice_id, position, description, quantity, CAST(round(unit_price * (SELECT factor FROM minor_unit)) AS INTEGER),
+               CAST(round(amount * (SELECT factor FROM minor_unit)) AS INTEGER), event_id
+        FROM invoice_lines;
+    DROP TABLE invoice_lines;
+    ALTER TABLE invoice_lines_new RENAME TO invoice_lines;
+    CREATE INDEX idx_invoice_lines_invoice ON invoice_lines(invoice_id, position);
+    DROP TABLE minor_unit;
+    ",
+    // 7: Gapless invoice numbers, immutable once finalized
+    "
+    CREATE TABLE number_sequences (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        scope TEXT NOT NULL,
+        period TEXT NOT NULL,
+        last_value INTEGER NOT NULL,
+        UNIQUE (scope, period)
+    );
+
+    ALTER TABLE invoices ADD COLUMN number TEXT;
+    ALTER TABLE invoices ADD COLUMN issued_at TIMESTAMP;
+    CREATE UNIQUE INDEX idx_invoices_number ON invoices(number);
+
+    CREATE TRIGGER invoices_finalized_update
+    BEFORE UPDATE OF client_id, number, subtotal, tax_total, total, tax_rounding, due_date, issued_at ON invoices
+    WHEN OLD.number IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+
+    CREATE TRIGGER invoices_finalized_delete
+    BEFORE DELETE ON invoices
+    WHEN OLD.number IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be deleted');
+    END;
+
+    CREATE TRIGGER invoice_lines_finalized_insert
+    BEFORE INSERT ON invoice_lines
+    WHEN (SELECT number FROM invoices WHERE id = NEW.invoice_id) IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+
+    CREATE TRIGGER invoice_lines_finalized_update
+    BEFORE UPDATE OF invoice_id, description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount, tax_amount ON invoice_lines
+    WHEN (SELECT number FROM invoices WHERE id = OLD.invoice_id) IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+
+    CREATE TRIGGER invoice_lines_finalized_delete
+    BEFORE DELETE ON invoice_lines
+    WHEN (SELECT number FROM invoices WHERE id = OLD.invoice_id) IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+    ",
+    // 8: Invoice lifecycle states and status history
+    "
+    DROP TRIGGER invoices_finalized_update;
+    DROP TRIGGER invoices_finalized_delete;
+    DROP TRIGGER invoice_lines_finalized_insert;
+    DROP TRIGGER invoice_lines_finalized_update;
+    DROP TRIGGER invoice_lines_finalized_delete;
+
+    CREATE TABLE invoices_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        number TEXT,
+        client_id INTEGER,
+        subtotal INTEGER NOT NULL DEFAULT 0,
+        tax_total INTEGER NOT NULL DEFAULT 0,
+        total INTEGER NOT NULL DEFAULT 0,
+        tax_rounding TEXT CHECK (tax_rounding IN ('PerLine', 'PerRate')) NOT NULL DEFAULT 'PerRate',
+        due_date TIMESTAMP NOT NULL,
+        status TEXT CHECK (status IN ('Draft', 'Issued', 'PartiallyPaid', 'Paid', 'Overdue', 'Void')) NOT NULL DEFAULT 'Draft',
+        event_id INTEGER,
+        issued_at TIMESTAMP,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL,
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE SET NULL
+    );
+    INSERT INTO invoices_new (id, number, client_id, subtotal, tax_total, total, tax_rounding, due_date, status, event_id, issued_at, created_at)
+        SELECT id, number, client_id, subtotal, tax_total, total, tax_rounding, due_date,
+               CASE status WHEN 'Pending' THEN 'Issued' ELSE status END,
+               event_id, issued_at, created_at
+        FROM invoices;
+    DROP TABLE invoices;
+    ALTER TABLE invoices_new RENAME TO invoices;
+    CREATE UNIQUE INDEX idx_invoices_number ON invoices(number);
+    CREATE INDEX idx_invoices_status_due ON invoices(status, due_date);
+
+    CREATE TABLE invoice_status_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        invoice_id INTEGER NOT NULL,
+        from_status TEXT,
+        to_status TEXT NOT NULL,
+        member_id INTEGER,
+        note TEXT,
+        changed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+        FOREIGN KEY (member_id) REFERENCES members(id) ON DELETE SET NULL
+    );
+    CREATE INDEX idx_invoice_status_log_invoice ON invoice_status_log(invoice_id, changed_at);
+
+    CREATE TRIGGER invoices_finalized_update
+    BEFORE UPDATE OF client_id, number, subtotal, tax_total, total, tax_rounding, due_date, issued_at ON invoices
+    WHEN OLD.number IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+
+    CREATE TRIGGER invoices_finalized_delete
+    BEFORE DELETE ON invoices
+    WHEN OLD.number IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be deleted');
+    END;
+
+    CREATE TRIGGER invoice_lines_finalized_insert
+    BEFORE INSERT ON invoice_lines
+    WHEN (SELECT number FROM invoices WHERE id = NEW.invoice_id) IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+
+    CREATE TRIGGER invoice_lines_finalized_update
+    BEFORE UPDATE OF invoice_id, description, quantity, unit_price, discount_bp, tax_rate_bp, net_amount, tax_amount ON invoice_lines
+    WHEN (SELECT number FROM invoices WHERE id = OLD.invoice_id) IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+
+    CREATE TRIGGER invoice_lines_finalized_delete
+    BEFORE DELETE ON invoice_lines
+    WHEN (SELECT number FROM invoices WHERE id = OLD.invoice_id) IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+    ",
+    // 9: Payments ledger
+    "
+    CREATE TABLE payments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        client_id INTEGER,
+        invoice_id INTEGER,
+        kind TEXT CHECK (kind IN ('Payment', 'Refund', 'Credit')) NOT NULL DEFAULT 'Payment',
+        amount INTEGER NOT NULL,
+        date TIMESTAMP NOT NULL,
+        method TEXT,
+        reference TEXT,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL,
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE RESTRICT
+    );
+    CREATE INDEX idx_payments_invoice ON payments(invoice_id);
+    CREATE INDEX idx_payments_client ON payments(client_id, date);
+    ",
+    // 10: Client billing addresses and file attachments
+    "
+    ALTER TABLE clients ADD COLUMN billing_address TEXT;
+
+    CREATE TABLE attachments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        owner_type TEXT NOT NULL,
+        owner_id INTEGER NOT NULL,
+        file_name TEXT NOT NULL,
+        mime_type TEXT NOT NULL,
+        path TEXT UNIQUE NOT NULL,
+        size INTEGER NOT NULL,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX idx_attachments_owner ON attachments(owner_type, owner_id);
+    ",
+    // 11: Quotes
+    "
+    CREATE TABLE quotes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        number TEXT,
+        client_id INTEGER,
+        title TEXT NOT NULL DEFAULT '',
+        subtotal INTEGER NOT NULL DEFAULT 0,
+        tax_total INTEGER NOT NULL DEFAULT 0,
+        total INTEGER NOT NULL DEFAULT 0,
+        tax_rounding TEXT CHECK (tax_rounding IN ('PerLine', 'PerRate')) NOT NULL DEFAULT 'PerRate',
+        valid_until TIMESTAMP NOT NULL,
+        status TEXT CHECK (status IN ('Draft', 'Sent', 'Accepted', 'Rejected', 'Expired')) NOT NULL DEFAULT 'Draft',
+        sent_at TIMESTAMP,
+        decided_at TIMESTAMP,
+        event_id INTEGER,
+        invoice_id INTEGER,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL,
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE SET NULL,
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE SET NULL
+    );
+    CREATE UNIQUE INDEX idx_quotes_number ON quotes(number);
+    CREATE INDEX idx_quotes_status_valid ON quotes(status, valid_until);
+
+    CREATE TABLE quote_lines (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        quote_id INTEGER NOT NULL,
+        position INTEGER NOT NULL DEFAULT 0,
+        description TEXT NOT NULL,
+        quantity REAL NOT NULL,
+        unit_price INTEGER NOT NULL,
+        discount_bp INTEGER NOT NULL DEFAULT 0 CHECK (discount_bp BETWEEN 0 AND 10000),
+        tax_rate_bp INTEGER NOT NULL DEFAULT 0 CHECK (tax_rate_bp >= 0),
+        net_amount INTEGER NOT NULL,
+        tax_amount INTEGER NOT NULL DEFAULT 0,
+        FOREIGN KEY (quote_id) REFERENCES quotes(id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_quote_lines_quote ON quote_lines(quote_id, position);
+
+    ALTER TABLE invoices ADD COLUMN quote_id INTEGER REFERENCES quotes(id) ON DELETE SET NULL;
+    ",
+    // 12: Recurring invoice profiles
+    "
+    CREATE TABLE recurring_profiles (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        client_id INTEGER NOT NULL,
+        title TEXT NOT NULL DEFAULT '',
+        interval_months INTEGER NOT NULL DEFAULT 1 CHECK (interval_months >= 1),
+        day_of_month INTEGER NOT NULL DEFAULT 1 CHECK (day_of_month BETWEEN 1 AND 31),
+        start_date DATE NOT NULL,
+        end_date DATE,
+        mode TEXT CHECK (mode IN ('Draft', 'AutoIssue')) NOT NULL DEFAULT 'Draft',
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE CASCADE
+    );
+
+    CREATE TABLE recurring_profile_lines (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        profile_id INTEGER NOT NULL,
+        position INTEGER NOT NULL DEFAULT 0,
+        description TEXT NOT NULL,
+        quantity REAL NOT NULL,
+        unit_price INTEGER NOT NULL,
+        discount_bp INTEGER NOT NULL DEFAULT 0 CHECK (discount_bp BETWEEN 0 AND 10000),
+        tax_rate_bp INTEGER NOT NULL DEFAULT 0 CHECK (tax_rate_bp >= 0),
+        FOREIGN KEY (profile_id) REFERENCES recurring_profiles(id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_recurring_profile_lines_profile ON recurring_profile_lines(profile_id, position);
+
+    CREATE TABLE recurring_runs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        profile_id INTEGER NOT NULL,
+        run_date DATE NOT NULL,
+        invoice_id INTEGER,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        UNIQUE (profile_id, run_date),
+        FOREIGN KEY (profile_id) REFERENCES recurring_profiles(id) ON DELETE CASCADE,
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE SET NULL
+    );
+
+    ALTER TABLE invoices ADD COLUMN recurring_profile_id INTEGER REFERENCES recurring_profiles(id) ON DELETE SET NULL;
+    ",
+    // 13: Payment reminder levels and sent reminders
+    "
+    CREATE TABLE dunning_levels (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        level INTEGER NOT NULL UNIQUE CHECK (level >= 1),
+        days_overdue INTEGER NOT NULL CHECK (days_overdue >= 0),
+        title TEXT NOT NULL,
+        template TEXT,
+        fee INTEGER NOT NULL DEFAULT 0 CHECK (fee >= 0),
+        interest_bp INTEGER NOT NULL DEFAULT 0 CHECK (interest_bp >= 0)
+    );
+    INSERT INTO dunning_levels (level, days_overdue, title) VALUES
+        (1, 7, 'Payment reminder'),
+        (2, 14, 'Second reminder'),
+        (3, 30, 'Final notice');
+
+    CREATE TABLE dunning_notices (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        invoice_id INTEGER NOT NULL,
+        level INTEGER NOT NULL,
+        days_overdue INTEGER NOT NULL,
+        outstanding INTEGER NOT NULL,
+        fee INTEGER NOT NULL DEFAULT 0,
+        interest INTEGER NOT NULL DEFAULT 0,
+        subject TEXT NOT NULL,
+        text TEXT NOT NULL,
+        member_id INTEGER,
+        sent_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        UNIQUE (invoice_id, level),
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+        FOREIGN KEY (member_id) REFERENCES members(id) ON DELETE SET NULL
+    );
+    ",
+    // 14: Credit notes against finalized invoices, immutable once issued
+    "
+    CREATE TABLE credit_notes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        number TEXT NOT NULL UNIQUE,
+        invoice_id INTEGER NOT NULL,
+        client_id INTEGER,
+        reason TEXT,
+        subtotal INTEGER NOT NULL,
+        tax_total INTEGER NOT NULL,
+        total INTEGER NOT NULL,
+        tax_rounding TEXT CHECK (tax_rounding IN ('PerLine', 'PerRate')) NOT NULL,
+        member_id INTEGER,
+        issued_at TIMESTAMP NOT NULL,
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id),
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL,
+        FOREIGN KEY (member_id) REFERENCES members(id) ON DELETE SET NULL
+    );
+    CREATE INDEX idx_credit_notes_invoice ON credit_notes(invoice_id);
+
+    CREATE TABLE credit_note_lines (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        credit_note_id INTEGER NOT NULL,
+        invoice_line_id INTEGER,
+        position INTEGER NOT NULL DEFAULT 0,
+        description TEXT NOT NULL,
+        quantity REAL NOT NULL,
+        unit_price INTEGER NOT NULL,
+        discount_bp INTEGER NOT NULL DEFAULT 0 CHECK (discount_bp BETWEEN 0 AND 10000),
+        tax_rate_bp INTEGER NOT NULL DEFAULT 0 CHECK (tax_rate_bp >= 0),
+        net_amount INTEGER NOT NULL,
+        tax_amount INTEGER NOT NULL DEFAULT 0,
+        FOREIGN KEY (credit_note_id) REFERENCES credit_notes(id),
+        FOREIGN KEY (invoice_line_id) REFERENCES invoice_lines(id)
+    );
+    CREATE INDEX idx_credit_note_lines_note ON credit_note_lines(credit_note_id, position);
+    CREATE INDEX idx_credit_note_lines_invoice_line ON credit_note_lines(invoice_line_id);
+
+    CREATE TRIGGER credit_notes_update
+    BEFORE UPDATE ON credit_notes
+    BEGIN
+        SELECT RAISE(ABORT, 'Credit notes cannot be changed');
+    END;
+
+    CREATE TRIGGER credit_notes_delete
+    BEFORE DELETE ON credit_notes
+    BEGIN
+        SELECT RAISE(ABORT, 'Credit notes cannot be deleted');
+    END;
+
+    CREATE TRIGGER credit_note_lines_update
+    BEFORE UPDATE ON credit_note_lines
+    BEGIN
+        SELECT RAISE(ABORT, 'Credit notes cannot be changed');
+    END;
+
+    CREATE TRIGGER credit_note_lines_delete
+    BEFORE DELETE ON credit_note_lines
+    BEGIN
+        SELECT RAISE(ABORT, 'Credit notes cannot be changed');
+    END;
+    ",
+    // 15: Currencies on monetary records and exchange rates
+    "
+    ALTER TABLE invoices ADD COLUMN currency TEXT NOT NULL DEFAULT 'CHF';
+    ALTER TABLE payments ADD COLUMN currency TEXT NOT NULL DEFAULT 'CHF';
+    ALTER TABLE expenses ADD COLUMN currency TEXT NOT NULL DEFAULT 'CHF';
+    ALTER TABLE quotes ADD COLUMN currency TEXT NOT NULL DEFAULT 'CHF';
+    ALTER TABLE recurring_profiles ADD COLUMN currency TEXT NOT NULL DEFAULT 'CHF';
+
+    CREATE TEMP TABLE base_currency AS
+        SELECT COALESCE(
+            (SELECT UPPER(TRIM(value)) FROM settings WHERE key = 'base_currency' AND LENGTH(TRIM(value)) = 3),
+            'CHF'
+        ) AS code;
+    UPDATE invoices SET currency = (SELECT code FROM base_currency);
+    UPDATE payments SET currency = (SELECT code FROM base_currency);
+    UPDATE expenses SET currency = (SELECT code FROM base_currency);
+    UPDATE quotes SET currency = (SELECT code FROM base_currency);
+    UPDATE recurring_profiles SET currency = (SELECT code FROM base_currency);
+    DROP TABLE base_currency;
+
+    DROP TRIGGER invoices_finalized_update;
+    CREATE TRIGGER invoices_finalized_update
+    BEFORE UPDATE OF client_id, number, subtotal, tax_total, total, tax_rounding, due_date, issued_at, currency ON invoices
+    WHEN OLD.number IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+
+    CREATE TABLE exchange_rates (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        date DATE NOT NULL,
+        base_currency TEXT NOT NULL,
+        quote_currency TEXT NOT NULL,
+        rate INTEGER NOT NULL CHECK (rate > 0),
+        source TEXT CHECK (source IN ('Manual', 'Csv', 'Ecb')) NOT NULL DEFAULT 'Manual',
+        UNIQUE (date, base_currency, quote_currency),
+        CHECK (base_currency <> quote_currency)
+    );
+    CREATE INDEX idx_exchange_rates_pair ON exchange_rates(base_currency, quote_currency, date);
+    ",
+    // 16: Expenses in minor units with managed categories and encrypted attachments
+    "
+    CREATE TABLE expense_categories (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    );
+    INSERT OR IGNORE INTO expense_categories (name)
+        SELECT DISTINCT TRIM(category) FROM expenses WHERE TRIM(category) <> '';
+    INSERT OR IGNORE INTO expense_categories (name)
+        VALUES ('Travel'), ('Office'), ('Software'), ('Equipment'), ('Marketing'), ('Meals'), ('Other');
+
+    CREATE TABLE expenses_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        date TIMESTAMP NOT NULL,
+        description TEXT NOT NULL DEFAULT '',
+        vendor TEXT,
+        category_id INTEGER,
+        amount INTEGER NOT NULL,
+        tax_rate_bp INTEGER NOT NULL DEFAULT 0 CHECK (tax_rate_bp >= 0),
+        tax_amount INTEGER NOT NULL DEFAULT 0,
+        currency TEXT NOT NULL DEFAULT 'CHF',
+        event_id INTEGER,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (category_id) REFERENCES expense_categories(id),
+        FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE SET NULL
+    );
+    INSERT INTO expenses_new (id, date, category_id, amount, currency, event_id, created_at)
+        SELECT e.id, e.date, c.id,
+               CAST(round(e.amount * CASE
+                        WHEN e.currency IN ('BIF', 'CLP', 'DJF', 'GNF', 'ISK', 'JPY', 'KMF', 'KRW', 'PYG', 'RWF', 'UGX', 'UYI', 'VND',
+                                     'VUV', 'XAF', 'XOF', 'XPF') THEN 1
+                        WHEN e.currency IN ('BHD', 'IQD', 'JOD', 'KWD', 'LYD', 'OMR', 'TND') THEN 1000
+                        WHEN e.currency IN ('CLF', 'UYW') THEN 10000
+                        ELSE 100
+                    END) AS INTEGER),
+               e.currency, e.event_id, e.created_at
+        FROM expenses e
+        LEFT JOIN expense_categories c ON c.name = TRIM(e.category);
+    DROP TABLE expenses;
+    ALTER TABLE expenses_new RENAME TO expenses;
+    CREATE INDEX idx_expenses_date ON expenses(date);
+    CREATE INDEX idx_expenses_category ON expenses(category_id, date);
+    CREATE INDEX idx_expenses_event ON expenses(event_id);
+
+    ALTER TABLE attachments ADD COLUMN encrypted INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 17: Bank accounts, imported statements and their transactions
+    "
+    CREATE TABLE bank_accounts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        identifier TEXT NOT NULL UNIQUE,
+        name TEXT,
+        currency TEXT NOT NULL,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE TABLE bank_statements (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        account_id INTEGER NOT NULL,
+        format TEXT CHECK (format IN ('Camt053', 'Mt940', 'Ofx', 'Csv')) NOT NULL,
+        statement_ref TEXT,
+        period_from DATE,
+        period_to DATE,
+        opening_balance INTEGER,
+        closing_balance INTEGER,
+        file_name TEXT,
+        imported_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (account_id) REFERENCES bank_accounts(id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_bank_statements_account ON bank_statements(account_id, period_to);
+
+    CREATE TABLE bank_transactions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        account_id INTEGER NOT NULL,
+        statement_id INTEGER,
+        booking_date DATE NOT NULL,
+        value_date DATE,
+        amount INTEGER NOT NULL,
+        currency TEXT NOT NULL,
+        reference TEXT NOT NULL,
+        remittance TEXT,
+        structured_reference TEXT,
+        counterparty_name TEXT,
+        counterparty_iban TEXT,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        UNIQUE (account_id, reference),
+        FOREIGN KEY (account_id) REFERENCES bank_accounts(id) ON DELETE CASCADE,
+        FOREIGN KEY (statement_id) REFERENCES bank_statements(id) ON DELETE SET NULL
+    );
+    CREATE INDEX idx_bank_transactions_date ON bank_transactions(account_id, booking_date);
+    ",
+    // 18: Proposed and confirmed matches of bank transactions to invoices and expenses
+    "
+    ALTER TABLE expenses ADD COLUMN paid_at TIMESTAMP;
+    ALTER TABLE expenses ADD COLUMN bank_transaction_id INTEGER REFERENCES bank_transactions(id) ON DELETE SET NULL;
+
+    CREATE TABLE bank_matches (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        transaction_id INTEGER NOT NULL,
+        invoice_id INTEGER,
+        expense_id INTEGER,
+        confidence INTEGER NOT NULL CHECK (confidence BETWEEN 0 AND 100),
+        reasons TEXT NOT NULL DEFAULT '',
+        status TEXT CHECK (status IN ('Proposed', 'Confirmed', 'Rejected')) NOT NULL DEFAULT 'Proposed',
+        payment_id INTEGER,
+        member_id INTEGER,
+        decided_at TIMESTAMP,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        CHECK ((invoice_id IS NULL) <> (expense_id IS NULL)),
+        FOREIGN KEY (transaction_id) REFERENCES bank_transactions(id) ON DELETE CASCADE,
+        FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+        FOREIGN KEY (expense_id) REFERENCES expenses(id) ON DELETE CASCADE,
+        FOREIGN KEY (payment_id) REFERENCES payments(id) ON DELETE SET NULL,
+        FOREIGN KEY (member_id) REFERENCES members(id) ON DELETE SET NULL
+    );
+    CREATE UNIQUE INDEX idx_bank_matches_invoice ON bank_matches(transaction_id, invoice_id) WHERE invoice_id IS NOT NULL;
+    CREATE UNIQUE INDEX idx_bank_matches_expense ON bank_matches(transaction_id, expense_id) WHERE expense_id IS NOT NULL;
+    CREATE INDEX idx_bank_matches_status ON bank_matches(status, transaction_id);
+    ",
+    // 19: Chart of accounts and the double-entry journal posted from invoices, payments and expenses
+    "
+    CREATE TABLE accounts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        code TEXT NOT NULL UNIQUE,
+        name TEXT NOT NULL,
+        kind TEXT CHECK (kind IN ('Asset', 'Liability', 'Equity', 'Revenue', 'Expense')) NOT NULL,
+        system_key TEXT UNIQUE,
+        active INTEGER NOT NULL DEFAULT 1,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    );
+    INSERT INTO accounts (code, name, kind, system_key) VALUES
+        ('1020', 'Bank', 'Asset', 'bank'),
+        ('1100', 'Accounts receivable', 'Asset', 'receivables'),
+        ('1170', 'Input VAT', 'Asset', 'input_vat'),
+        ('2000', 'Accounts payable', 'Liability', 'payables'),
+        ('2030', 'Customer credit', 'Liability', 'customer_credit'),
+        ('2200', 'VAT payable', 'Liability', 'output_vat'),
+        ('2800', 'Equity', 'Equity', NULL),
+        ('3400', 'Service revenue', 'Revenue', 'revenue'),
+        ('6100', 'Equipment and maintenance', 'Expense', NULL),
+        ('6500', 'Office expenses', 'Expense', NULL),
+        ('6570', 'IT and software', 'Expense', NULL),
+        ('6600', 'Advertising', 'Expense', NULL),
+        ('6640', 'Travel and meals', 'Expense', NULL),
+        ('6700', 'Other operating expenses', 'Expense', 'expenses'),
+        ('6950', 'Exchange differences', 'Expense', 'exchange_differences');
+
+    ALTER TABLE expense_categories ADD COLUMN account_id INTEGER REFERENCES accounts(id) ON DELETE SET NULL;
+    UPDATE expense_categories SET account_id = (
+        SELECT id FROM accounts WHERE code = CASE expense_categories.name
+            WHEN 'Equipment' THEN '6100'
+            WHEN 'Office' THEN '6500'
+            WHEN 'Software' THEN '6570'
+            WHEN 'Marketing' THEN '6600'
+            WHEN 'Travel' THEN '6640'
+            WHEN 'Meals' THEN '6640'
+        END
+    );
+
+    CREATE TABLE journal_entries (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        date DATE NOT NULL,
+        description TEXT NOT NULL DEFAULT '',
+        source_type TEXT CHECK (source_type IN ('Invoice', 'CreditNote', 'Payment', 'Expense', 'ExpensePayment')) NOT NULL,
+        source_id INTEGER NOT NULL,
+        currency TEXT NOT NULL,
+        reverses_id INTEGER,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (reverses_id) REFERENCES journal_entries(id)
+    );
+    CREATE INDEX idx_journal_entries_source ON journal_entries(source_type, source_id);
+    CREATE INDEX idx_journal_entries_date ON journal_entries(date);
+    CREATE UNIQUE INDEX idx_journal_entries_reverses ON journal_entries(reverses_id) WHERE reverses_id IS NOT NULL;
+
+    CREATE TABLE journal_lines (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        entry_id INTEGER NOT NULL,
+        account_id INTEGER NOT NULL,
+        debit INTEGER NOT NULL DEFAULT 0,
+        credit INTEGER NOT NULL DEFAULT 0,
+        CHECK (debit >= 0 AND credit >= 0 AND (debit = 0) <> (credit = 0)),
+        FOREIGN KEY (entry_id) REFERENCES journal_entries(id),
+        FOREIGN KEY (account_id) REFERENCES accounts(id)
+    );
+    CREATE INDEX idx_journal_lines_entry ON journal_lines(entry_id);
+    CREATE INDEX idx_journal_lines_account ON journal_lines(account_id);
+
+    CREATE TRIGGER journal_entries_update
+    BEFORE UPDATE ON journal_entries
+    BEGIN
+        SELECT RAISE(ABORT, 'Journal entries cannot be changed');
+    END;
+
+    CREATE TRIGGER journal_entries_delete
+    BEFORE DELETE ON journal_entries
+    BEGIN
+        SELECT RAISE(ABORT, 'Journal entries cannot be deleted');
+    END;
+
+    CREATE TRIGGER journal_lines_update
+    BEFORE UPDATE ON journal_lines
+    BEGIN
+        SELECT RAISE(ABORT, 'Journal lines cannot be changed');
+    END;
+
+    CREATE TRIGGER journal_lines_delete
+    BEFORE DELETE ON journal_lines
+    BEGIN
+        SELECT RAISE(ABORT, 'Journal lines cannot be deleted');
+    END;
+    ",
+    // 20: Products and services catalog with per-client prices, referenced by invoice and quote lines
+    "
+    CREATE TABLE products (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        sku TEXT UNIQUE,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL DEFAULT '',
+        unit TEXT NOT NULL DEFAULT '',
+        unit_price INTEGER NOT NULL DEFAULT 0,
+        currency TEXT NOT NULL DEFAULT 'CHF',
+        tax_rate_bp INTEGER NOT NULL DEFAULT 0 CHECK (tax_rate_bp >= 0),
+        active INTEGER NOT NULL DEFAULT 1,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX idx_products_name ON products(active, name);
+
+    CREATE TABLE product_client_prices (
+        product_id INTEGER NOT NULL,
+        client_id INTEGER NOT NULL,
+        unit_price INTEGER NOT NULL,
+        PRIMARY KEY (product_id, client_id),
+        FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE CASCADE,
+        FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE CASCADE
+    );
+
+    ALTER TABLE invoice_lines ADD COLUMN product_id INTEGER REFERENCES products(id) ON DELETE SET NULL;
+    ALTER TABLE quote_lines ADD COLUMN product_id INTEGER REFERENCES products(id) ON DELETE SET NULL;
+    ",
+    // 21: Monthly and yearly budgets per expense category and the alerts raised for them
+    "
+    CREATE TABLE budgets (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        category_id INTEGER NOT NULL,
+        period TEXT CHECK (period IN ('Monthly', 'Yearly')) NOT NULL,
+        amount INTEGER NOT NULL CHECK (amount > 0),
+        currency TEXT NOT NULL DEFAULT 'CHF',
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        UNIQUE (category_id, period),
+        FOREIGN KEY (category_id) REFERENCES expense_categories(id) ON DELETE CASCADE
+    );
+
+    CREATE TABLE budget_alerts (
+        budget_id INTEGER NOT NULL,
+        period_start TEXT NOT NULL,
+        threshold_bp INTEGER NOT NULL,
+        raised_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (budget_id, period_start, threshold_bp),
+        FOREIGN KEY (budget_id) REFERENCES budgets(id) ON DELETE CASCADE
+    );
+    ",
+    // 22: Number the legacy `Pending` invoices migration 8 made `Issued`, so issued always means numbered
+    "
+    UPDATE invoices
+    SET number = 'LEGACY-' || id, issued_at = COALESCE(issued_at, created_at, CURRENT_TIMESTAMP)
+    WHERE number IS NULL AND status <> 'Draft';
+    ",
+    // 23: Link the client-credit part of an overpayment to the payment it was split from
+    "
+    ALTER TABLE payments ADD COLUMN split_from_id INTEGER REFERENCES payments(id);
+    UPDATE payments
+    SET split_from_id = (
+        SELECT q.id FROM payments q
+        WHERE q.id = payments.id - 1 AND q.kind = 'Payment' AND q.invoice_id IS NOT NULL
+          AND q.client_id = payments.client_id AND q.date = payments.date AND q.currency = payments.currency
+          AND q.method IS payments.method AND q.reference IS payments.reference
+    )
+    WHERE kind = 'Payment' AND invoice_id IS NULL;
+    ",
+    // 24: Deduplicate bank transactions by an import key instead of the payer's reference
+    "
+    CREATE TABLE bank_transactions_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        account_id INTEGER NOT NULL,
+        statement_id INTEGER,
+        booking_date DATE NOT NULL,
+        value_date DATE,
+        amount INTEGER NOT NULL,
+        currency TEXT NOT NULL,
+        reference TEXT,
+        import_key TEXT NOT NULL,
+        remittance TEXT,
+        structured_reference TEXT,
+        counterparty_name TEXT,
+        counterparty_iban TEXT,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        UNIQUE (account_id, import_key),
+        FOREIGN KEY (account_id) REFERENCES bank_accounts(id) ON DELETE CASCADE,
+        FOREIGN KEY (statement_id) REFERENCES bank_statements(id) ON DELETE SET NULL
+    );
+    INSERT INTO bank_transactions_new (id, account_id, statement_id, booking_date, value_date, amount, currency, reference,
+                                       import_key, remittance, structured_reference, counterparty_name, counterparty_iban,
+                                       created_at)
+        SELECT id, account_id, statement_id, booking_date, value_date, amount, currency,
+               CASE WHEN reference LIKE 'h:%' THEN NULL ELSE reference END,
+               reference, remittance, structured_reference, counterparty_name, counterparty_iban, created_at
+        FROM bank_transactions;
+    DROP TABLE bank_transactions;
+    ALTER TABLE bank_transactions_new RENAME TO bank_transactions;
+    CREATE INDEX idx_bank_transactions_date ON bank_transactions(account_id, booking_date);
+    ",
+    // 25: Client hourly rates in integer minor units of the base currency
+    "
+    CREATE TEMP TABLE minor_unit AS
+        SELECT CASE
+            WHEN code IN ('BIF', 'CLP', 'DJF', 'GNF', 'ISK', 'JPY', 'KMF', 'KRW', 'PYG', 'RWF', 'UGX', 'UYI', 'VND',
+                         'VUV', 'XAF', 'XOF', 'XPF') THEN 1
+            WHEN code IN ('BHD', 'IQD', 'JOD', 'KWD', 'LYD', 'OMR', 'TND') THEN 1000
+            WHEN code IN ('CLF', 'UYW') THEN 10000
+            ELSE 100
+        END AS factor
+        FROM (SELECT COALESCE(
+            (SELECT UPPER(TRIM(value)) FROM settings WHERE key = 'base_currency' AND LENGTH(TRIM(value)) = 3),
+            'CHF'
+        ) AS code);
+
+    CREATE TABLE clients_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        email TEXT UNIQUE NOT NULL,
+        phone TEXT,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        hourly_rate INTEGER,
+        billing_address TEXT
+    );
+    INSERT INTO clients_new (id, name, email, phone, created_at, hourly_rate, billing_address)
+        SELECT id, name, email, phone, created_at,
+               CAST(round(hourly_rate * (SELECT factor FROM minor_unit)) AS INTEGER), billing_address
+        FROM clients;
+    DROP TABLE clients;
+    ALTER TABLE clients_new RENAME TO clients;
+    DROP TABLE minor_unit;
+    ",
+    // 26: Reminder fees in a currency of their own, reminder charges booked as invoices
+    "
+    ALTER TABLE dunning_levels ADD COLUMN currency TEXT NOT NULL DEFAULT 'CHF';
+    UPDATE dunning_levels SET currency = COALESCE(
+        (SELECT UPPER(TRIM(value)) FROM settings WHERE key = 'base_currency' AND LENGTH(TRIM(value)) = 3),
+        'CHF'
+    );
+    ALTER TABLE dunning_notices ADD COLUMN charge_invoice_id INTEGER REFERENCES invoices(id) ON DELETE SET NULL;
+    ",
+    // 27: One line carrying the amount of numbered invoices that have none, such as the legacy
+    // invoices numbered by migration 22, so they can be credited and voided like any other
+    "
+    DROP TRIGGER invoice_lines_finalized_insert;
+    INSERT INTO invoice_lines (invoice_id, position, description, quantity, unit_price, tax_rate_bp, net_amount, tax_amount)
+        SELECT i.id, 0, 'Invoiced amount', 1, i.subtotal, 0, i.subtotal, i.tax_total
+        FROM invoices i
+        WHERE i.number IS NOT NULL AND NOT EXISTS (SELECT 1 FROM invoice_lines l WHERE l.invoice_id = i.id);
+    CREATE TRIGGER invoice_lines_finalized_insert
+    BEFORE INSERT ON invoice_lines
+    WHEN (SELECT number FROM invoices WHERE id = NEW.invoice_id) IS NOT NULL
+    BEGIN
+        SELECT RAISE(ABORT, 'Finalized invoices cannot be changed');
+    END;
+    ",
+];
+
+fn run_migrations(conn: &mut Connection) -> Result<(), StorageError> {
+    let applied: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if applied as usize >= MIGRATIONS.len() {
+        return Ok(());
+    }
+
+    // Table rebuilds must not trigger ON DELETE actions; the pragma is a no-op inside a transaction
+    conn.pragma_update(None, "foreign_keys", false)?;
+    let result = apply_migrations(conn, applied as usize);
+    conn.pragma_update(None, "foreign_keys", true)?;
+    result
+}
+
+/// Applies the migrations after `applied`, each in its own transaction. With foreign keys
+/// off, a migration is only committed if every reference still resolves afterwards.
+fn apply_migrations(conn: &mut Connection, applied: usize) -> Result<(), StorageError> {
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        let violations: i64 = tx.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0))?;
+        if violations > 0 {
+            return Err(StorageError::ForeignKeyViolation(index + 1, violations));
+        }
+        tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+        tx.commit()?;
+        println!("✅ Migration {} applied.", index + 1);
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! Minimal XML tree used to read WebDAV responses, bank statements and Schematron schemas.
+
+use quick_xml::events::{BytesStart, Event as XmlEvent};
+use quick_xml::Reader;
+
+/// Element names are stored without their namespace prefix (`d:href` → `href`)
+#[derive(Debug, Default)]
+pub struct XmlNode {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<XmlNode>,
+}
+
+impl XmlNode {
+    /// Parses a document and returns its root element
+    pub fn parse(body: &str) -> Result<XmlNode, String> {
+        let mut reader = Reader::from_str(body);
+        reader.config_mut().trim_text(true);
+
+        let mut stack = vec![XmlNode::default()];
+        loop {
+            match reader.read_event().map_err(|e| e.to_string())? {
+                XmlEvent::Start(e) => stack.push(XmlNode::from_tag(&e)),
+                XmlEvent::Empty(e) => {
+                    let node = XmlNode::from_tag(&e);
+                    stack.last_mut().unwrap().children.push(node);
+                }
+                XmlEvent::Text(t) => {
+                    let text = t.unescape().map_err(|e| e.to_string())?;
+                    stack.last_mut().unwrap().text.push_str(&text);
+                }
+                XmlEvent::CData(c) => {
+                    stack.last_mut().unwrap().text.push_str(&String::from_utf8_lossy(&c.into_inner()));
+                }
+                XmlEvent::End(_) => {
+                    let node = stack.pop().unwrap();
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(node),
+                        None => return Err("unbalanced closing tag".into()),
+                    }
+                }
+                XmlEvent::Eof => break,
+                _ => {}
+            }
+        }
+        stack
+            .pop()
+            .and_then(|root| root.children.into_iter().next())
+            .ok_or_else(|| "empty document".into())
+    }
+
+    fn from_tag(tag: &BytesStart) -> XmlNode {
+        XmlNode {
+            name: String::from_utf8_lossy(tag.local_name().as_ref()).into_owned(),
+            attributes: tag
+                .attributes()
+                .flatten()
+                .map(|a| {
+                    let key = String::from_utf8_lossy(a.key.local_name().as_ref()).into_owned();
+                    let value = a.unescape_value().map(|v| v.into_owned());
+                    (key, value.unwrap_or_else(|_| String::from_utf8_lossy(&a.value).into_owned()))
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&XmlNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlNode> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// Follows a path of child names, e.g. `["Acct", "Id", "IBAN"]`
+    pub fn find(&self, path: &[&str]) -> Option<&XmlNode> {
+        path.iter().try_fold(self, |node, name| node.child(name))
+    }
+
+    /// Trimmed text at the end of `path`, if present and not empty
+    pub fn text_at(&self, path: &[&str]) -> Option<String> {
+        self.find(path).map(|node| node.text.trim().to_string()).filter(|text| !text.is_empty())
+    }
+}
@@ -9,6 +9,8 @@ export type Client = {
   name: string;
   email: string;
   phone?: string;
+  hourly_rate?: number | null;
+  billing_address?: string | null;
 };
 
 export default function ClientsMain() {
@@ -0,0 +1,78 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Trash2 } from "lucide-react";
+import { useToast } from "@/hooks/use-toast";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Dialog, DialogTrigger, DialogContent, DialogHeader, DialogTitle, DialogDescription } from "@/components/ui/dialog";
+import { ExpenseCategory } from "@/app/dashboard/modules/financials/financials-main";
+
+type ExpenseCategoriesDialogProps = {
+  categories: ExpenseCategory[];
+  refresh: () => void;
+};
+
+export default function ExpenseCategoriesDialog({ categories, refresh }: ExpenseCategoriesDialogProps) {
+  const [name, setName] = useState("");
+  const { toast } = useToast();
+
+  // ✅ Run a category command and report errors
+  const run = async (command: string, args: Record<string, unknown>) => {
+    try {
+      await invoke(command, args);
+      refresh();
+      return true;
+    } catch (error) {
+      toast({
+        title: "Error!",
+        description: error instanceof Error ? error.message : String(error),
+      });
+      return false;
+    }
+  };
+
+  const handleAdd = async () => {
+    if (name.trim() && (await run("create_expense_category", { name }))) {
+      setName("");
+    }
+  };
+
+  return (
+    <Dialog>
+      <DialogTrigger asChild>
+        <Button variant="outline">Categories</Button>
+      </DialogTrigger>
+
+      <DialogContent>
+        <DialogHeader>
+          <DialogTitle>Expense Categories</DialogTitle>
+          <DialogDescription>Categories in use cannot be deleted</DialogDescription>
+        </DialogHeader>
+
+        <ul className="space-y-2">
+          {categories.map((category) => (
+            <li key={category.id} className="flex items-center justify-between">
+              <span>
+                {category.name} <span className="text-muted-foreground">({category.expense_count})</span>
+              </span>
+              <Button
+                variant="ghost"
+                size="icon"
+                disabled={category.expense_count > 0}
+                onClick={() => run("delete_expense_category", { categoryId: category.id })}
+              >
+                <Trash2 />
+              </Button>
+            </li>
+          ))}
+        </ul>
+
+        {/* Add Category */}
+        <div className="flex gap-2">
+          <Input placeholder="New category" value={name} onChange={(e) => setName(e.target.value)} />
+          <Button onClick={handleAdd}>Add</Button>
+        </div>
+      </DialogContent>
+    </Dialog>
+  );
+}
@@ -0,0 +1,66 @@
+import { invoke } from "@tauri-apps/api/core";
+import { Paperclip, Trash2 } from "lucide-react";
+import { useToast } from "@/hooks/use-toast";
+import { Button } from "@/components/ui/button";
+import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from "@/components/ui/table";
+import { Expense, formatAmount } from "@/app/dashboard/modules/financials/financials-main";
+
+type ExpensesTableProps = {
+  expenses: Expense[];
+  refresh: () => void;
+};
+
+export default function ExpensesTable({ expenses, refresh }: ExpensesTableProps) {
+  const { toast } = useToast();
+
+  // ✅ Delete an expense together with its receipts
+  const handleDelete = async (expense: Expense) => {
+    try {
+      await invoke("delete_expense", { expenseId: expense.id });
+      refresh();
+    } catch (error) {
+      toast({
+        title: "Error!",
+        description: error instanceof Error ? error.message : String(error),
+      });
+    }
+  };
+
+  return (
+    <Table>
+      <TableHeader>
+        <TableRow>
+          <TableHead>Date</TableHead>
+          <TableHead>Description</TableHead>
+          <TableHead>Category</TableHead>
+          <TableHead className="text-right">Tax</TableHead>
+          <TableHead className="text-right">Amount</TableHead>
+          <TableHead />
+        </TableRow>
+      </TableHeader>
+      <TableBody>
+        {expenses.map((expense) => (
+          <TableRow key={expense.id}>
+            <TableCell>{expense.date.slice(0, 10)}</TableCell>
+            <TableCell className="font-medium">
+              {expense.description || "N/A"}
+              {expense.vendor && <span className="text-muted-foreground"> · {expense.vendor}</span>}
+              {expense.receipt_count > 0 && <Paperclip className="ml-2 inline h-4 w-4" />}
+            </TableCell>
+            <TableCell>
+              {expense.category || "Uncategorized"}
+              {expense.paid_at && <span className="text-muted-foreground"> · Paid {expense.paid_at.slice(0, 10)}</span>}
+            </TableCell>
+            <TableCell className="text-right">{formatAmount(expense.tax_amount, expense.currency)}</TableCell>
+            <TableCell className="text-right">{formatAmount(expense.amount, expense.currency)}</TableCell>
+            <TableCell className="text-right">
+              <Button variant="ghost" size="icon" onClick={() => handleDelete(expense)}>
+                <Trash2 />
+              </Button>
+            </TableCell>
+          </TableRow>
+        ))}
+      </TableBody>
+    </Table>
+  );
+}
@@ -0,0 +1,51 @@
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import NewExpenseDialog from "@/app/dashboard/modules/financials/financials-new-expense";
+import ExpenseCategoriesDialog from "@/app/dashboard/modules/financials/financials-categories";
+import { ExpenseCategory, ExpenseFilter } from "@/app/dashboard/modules/financials/financials-main";
+
+type FinancialsHeaderProps = {
+  filter: ExpenseFilter;
+  setFilter: (filter: ExpenseFilter) => void;
+  categories: ExpenseCategory[];
+  refresh: () => void;
+};
+
+export default function FinancialsHeader({ filter, setFilter, categories, refresh }: FinancialsHeaderProps) {
+  return (
+    <header className="flex items-end justify-between gap-4 bg-background px-4 py-2 border-b border-border">
+      {/* Filters */}
+      <div className="flex items-end gap-4">
+        <div className="space-y-1">
+          <Label>From</Label>
+          <Input type="date" value={filter.from ?? ""} onChange={(e) => setFilter({ ...filter, from: e.target.value || undefined })} />
+        </div>
+        <div className="space-y-1">
+          <Label>To</Label>
+          <Input type="date" value={filter.to ?? ""} onChange={(e) => setFilter({ ...filter, to: e.target.value || undefined })} />
+        </div>
+        <div className="space-y-1">
+          <Label>Category</Label>
+          <select
+            className="flex h-9 rounded-md border border-input bg-transparent px-3 py-1 text-sm shadow-sm"
+            value={filter.category_id ?? ""}
+            onChange={(e) => setFilter({ ...filter, category_id: e.target.value ? Number(e.target.value) : undefined })}
+          >
+            <option value="">All categories</option>
+            {categories.map((category) => (
+              <option key={category.id} value={category.id}>
+                {category.name}
+              </option>
+            ))}
+          </select>
+        </div>
+      </div>
+
+      {/* Categories and New Expense */}
+      <div className="flex gap-2">
+        <ExpenseCategoriesDialog categories={categories} refresh={refresh} />
+        <NewExpenseDialog categories={categories} refresh={refresh} />
+      </div>
+    </header>
+  );
+}
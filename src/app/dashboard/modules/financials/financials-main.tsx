@@ -1,3 +1,83 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import FinancialsHeader from "@/app/dashboard/modules/financials/financials-header";
+import ExpensesTable from "@/app/dashboard/modules/financials/financials-expenses-table";
+
+// ✅ Define the Expense Type (money in minor units, rates in basis points)
+export type Expense = {
+  id: number;
+  date: string;
+  description: string;
+  vendor?: string | null;
+  category_id?: number | null;
+  category?: string | null;
+  amount: number;
+  tax_rate_bp: number;
+  tax_amount: number;
+  currency: string;
+  event_id?: number | null;
+  receipt_count: number;
+  paid_at?: string | null;
+};
+
+// ✅ Define the Expense Category Type
+export type ExpenseCategory = {
+  id: number;
+  name: string;
+  account_id: number | null;
+  expense_count: number;
+};
+
+// ✅ Define the Expense Filter Type
+export type ExpenseFilter = {
+  from?: string;
+  to?: string;
+  category_id?: number;
+  event_id?: number;
+};
+
+// ✅ Format minor units for display
+export const formatAmount = (amount: number, currency: string) => `${currency} ${(amount / 100).toFixed(2)}`;
+
 export default function FinancialsMain() {
-  return <div>Financials Section</div>
+  const [expenses, setExpenses] = useState<Expense[]>([]);
+  const [categories, setCategories] = useState<ExpenseCategory[]>([]);
+  const [filter, setFilter] = useState<ExpenseFilter>({});
+
+  // ✅ Fetch Expenses from Rust API
+  const fetchExpenses = () => {
+    invoke<Expense[]>("list_expenses", { filter })
+      .then((data) => setExpenses(data))
+      .catch((error) => console.error("Error fetching expenses:", error));
+  };
+
+  // ✅ Fetch Expense Categories from Rust API
+  const fetchCategories = () => {
+    invoke<ExpenseCategory[]>("list_expense_categories")
+      .then((data) => setCategories(data))
+      .catch((error) => console.error("Error fetching expense categories:", error));
+  };
+
+  useEffect(() => {
+    fetchCategories();
+  }, []);
+
+  useEffect(() => {
+    fetchExpenses();
+  }, [filter]);
+
+  const refresh = () => {
+    fetchExpenses();
+    fetchCategories();
+  };
+
+  return (
+    <div className="space-y-6">
+      {/* Financials Header */}
+      <FinancialsHeader filter={filter} setFilter={setFilter} categories={categories} refresh={refresh} />
+
+      {/* Expenses Table */}
+      <ExpensesTable expenses={expenses} refresh={refresh} />
+    </div>
+  );
 }
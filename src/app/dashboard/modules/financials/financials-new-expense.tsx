@@ -0,0 +1,245 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { motion } from "framer-motion";
+import { useToast } from "@/hooks/use-toast";
+import { useForm } from "react-hook-form";
+import { z } from "zod";
+import { zodResolver } from "@hookform/resolvers/zod";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Dialog, DialogTrigger, DialogContent, DialogHeader, DialogTitle, DialogDescription } from "@/components/ui/dialog";
+import { Form, FormControl, FormField, FormItem, FormMessage } from "@/components/ui/form";
+import { Label } from "@/components/ui/label";
+import { ExpenseCategory } from "@/app/dashboard/modules/financials/financials-main";
+
+// ✅ Define validation schema using Zod
+const expenseSchema = z.object({
+  date: z.string().min(1, "Date is required"),
+  description: z.string().min(1, "Description is required"),
+  vendor: z.string().optional(),
+  category_id: z.string().optional(),
+  amount: z.string().regex(/^\d+([.,]\d{1,2})?$/, "Enter an amount like 12.50"),
+  tax_rate: z.string().regex(/^(\d+([.,]\d{1,2})?)?$/, "Enter a rate like 8.1"),
+  currency: z.string().optional(),
+  receipt_path: z.string().optional(),
+});
+
+type NewExpenseDialogProps = {
+  categories: ExpenseCategory[];
+  refresh: () => void;
+};
+
+// ✅ Convert a decimal input to minor units / basis points
+const toHundredths = (value: string) => Math.round(parseFloat(value.replace(",", ".") || "0") * 100);
+
+export default function NewExpenseDialog({ categories, refresh }: NewExpenseDialogProps) {
+  const [loading, setLoading] = useState(false);
+  const [isOpen, setIsOpen] = useState(false);
+  const { toast } = useToast();
+
+  // ✅ Initialize react-hook-form with validation
+  const form = useForm<z.infer<typeof expenseSchema>>({
+    resolver: zodResolver(expenseSchema),
+    defaultValues: {
+      date: new Date().toISOString().slice(0, 10),
+      description: "",
+      vendor: "",
+      category_id: "",
+      amount: "",
+      tax_rate: "",
+      currency: "",
+      receipt_path: "",
+    },
+  });
+
+  // ✅ Submit form to backend
+  const handleCreateExpense = async (values: z.infer<typeof expenseSchema>) => {
+    setLoading(true);
+
+    try {
+      const expenseId = await invoke<number>("create_expense", {
+        expense: {
+          id: null,
+          date: values.date,
+          description: values.description,
+          vendor: values.vendor || null,
+          category_id: values.category_id ? Number(values.category_id) : null,
+          amount: toHundredths(values.amount),
+          tax_rate_bp: toHundredths(values.tax_rate),
+          currency: values.currency || null,
+        },
+      });
+
+      // Attach the receipt, if one was given
+      if (values.receipt_path) {
+        await invoke("attach_expense_receipt", { expenseId, path: values.receipt_path });
+      }
+
+      // Successful creation
+      toast({
+        title: "Expense Added",
+        description: `Expense ${values.description} has been added successfully.`,
+      });
+
+      form.reset(); // Reset form fields
+      refresh(); // Refresh expense list
+      setIsOpen(false); // Close dialog
+    } catch (error) {
+      const errorMessage = error instanceof Error ? error.message : String(error);
+
+      // Show error notification
+      toast({
+        title: "Error!",
+        description: errorMessage,
+      });
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  return (
+    <Dialog open={isOpen} onOpenChange={setIsOpen}>
+      <DialogTrigger asChild>
+        <Button variant="outline">New Expense</Button>
+      </DialogTrigger>
+
+      <DialogContent>
+        <DialogHeader>
+          <DialogTitle>Create New Expense</DialogTitle>
+          <DialogDescription>Amounts include tax</DialogDescription>
+        </DialogHeader>
+
+        <motion.div initial={{ opacity: 0, y: -20 }} animate={{ opacity: 1, y: 0 }} transition={{ duration: 0.3 }}>
+          <Form {...form}>
+            <form onSubmit={form.handleSubmit(handleCreateExpense)} className="space-y-4">
+              <FormField
+                control={form.control}
+                name="date"
+                render={({ field }) => (
+                  <FormItem>
+                    <Label>Date</Label>
+                    <FormControl>
+                      <Input type="date" {...field} />
+                    </FormControl>
+                    <FormMessage />
+                  </FormItem>
+                )}
+              />
+
+              <FormField
+                control={form.control}
+                name="description"
+                render={({ field }) => (
+                  <FormItem>
+                    <Label>Description</Label>
+                    <FormControl>
+                      <Input placeholder="Enter a description" {...field} />
+                    </FormControl>
+                    <FormMessage />
+                  </FormItem>
+                )}
+              />
+
+              <FormField
+                control={form.control}
+                name="vendor"
+                render={({ field }) => (
+                  <FormItem>
+                    <Label>Vendor (Optional)</Label>
+                    <FormControl>
+                      <Input placeholder="Enter the vendor" {...field} />
+                    </FormControl>
+                    <FormMessage />
+                  </FormItem>
+                )}
+              />
+
+              <FormField
+                control={form.control}
+                name="category_id"
+                render={({ field }) => (
+                  <FormItem>
+                    <Label>Category</Label>
+                    <FormControl>
+                      <select className="flex h-9 w-full rounded-md border border-input bg-transparent px-3 py-1 text-sm shadow-sm" {...field}>
+                        <option value="">Uncategorized</option>
+                        {categories.map((category) => (
+                          <option key={category.id} value={category.id}>
+                            {category.name}
+                          </option>
+                        ))}
+                      </select>
+                    </FormControl>
+                    <FormMessage />
+                  </FormItem>
+                )}
+              />
+
+              <div className="grid grid-cols-3 gap-4">
+                <FormField
+                  control={form.control}
+                  name="amount"
+                  render={({ field }) => (
+                    <FormItem>
+                      <Label>Amount</Label>
+                      <FormControl>
+                        <Input inputMode="decimal" placeholder="0.00" {...field} />
+                      </FormControl>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
+
+                <FormField
+                  control={form.control}
+                  name="tax_rate"
+                  render={({ field }) => (
+                    <FormItem>
+                      <Label>Tax %</Label>
+                      <FormControl>
+                        <Input inputMode="decimal" placeholder="8.1" {...field} />
+                      </FormControl>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
+
+                <FormField
+                  control={form.control}
+                  name="currency"
+                  render={({ field }) => (
+                    <FormItem>
+                      <Label>Currency</Label>
+                      <FormControl>
+                        <Input maxLength={3} placeholder="Base" {...field} />
+                      </FormControl>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
+              </div>
+
+              <FormField
+                control={form.control}
+                name="receipt_path"
+                render={({ field }) => (
+                  <FormItem>
+                    <Label>Receipt File (Optional)</Label>
+                    <FormControl>
+                      <Input placeholder="Path to an image or PDF" {...field} />
+                    </FormControl>
+                    <FormMessage />
+                  </FormItem>
+                )}
+              />
+
+              <Button type="submit" className="w-full" disabled={loading}>
+                {loading ? "Saving..." : "Create Expense"}
+              </Button>
+            </form>
+          </Form>
+        </motion.div>
+      </DialogContent>
+    </Dialog>
+  );
+}